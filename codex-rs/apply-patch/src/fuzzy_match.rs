@@ -0,0 +1,380 @@
+//! Last-resort matching for chunks whose `old_lines` do not appear in the
+//! file, even after the whitespace and punctuation normalisation done by
+//! [`crate::seek_sequence`]. Models often reproduce a region with a line
+//! slightly off, an extra or missing line, or different indentation; rather
+//! than failing the whole patch, the chunk is applied to the most similar
+//! region if it is similar enough, keeping the region's own version of the
+//! lines the chunk does not change.
+//!
+//! Lines are compared after trimming them and collapsing runs of whitespace.
+//! A region's similarity comes from pairing its lines with the expected lines
+//! in order, scoring each pair by the ratio of matching characters (see
+//! [`similar::TextDiff::ratio`]).
+
+use std::path::Path;
+
+use similar::Algorithm;
+use similar::ChangeTag;
+use similar::TextDiff;
+
+/// Lines less similar than this are treated as different lines rather than
+/// as edited versions of each other.
+const MIN_LINE_SIMILARITY: f64 = 0.5;
+
+/// A region of the file that resembles the expected lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Candidate {
+    pub start: usize,
+    pub len: usize,
+    /// Between 0 (nothing in common) and 1 (identical after normalisation).
+    pub similarity: f64,
+}
+
+/// Finds the region at or after `start` that is most similar to `pattern`,
+/// returning it if its similarity is at least `threshold`. When `eof` is set,
+/// a region at the end of the file is preferred.
+pub(crate) fn fuzzy_seek(
+    lines: &[String],
+    pattern: &[String],
+    start: usize,
+    eof: bool,
+    threshold: f64,
+) -> Option<Candidate> {
+    if threshold >= 1.0 {
+        return None;
+    }
+    if eof
+        && let Some(candidate) = closest_match_at_end(lines, pattern)
+        && candidate.similarity >= threshold
+    {
+        return Some(candidate);
+    }
+    closest_match(lines, pattern, start).filter(|candidate| candidate.similarity >= threshold)
+}
+
+/// The region at or after `start` most similar to `pattern`, however
+/// dissimilar. Regions may be one line shorter or longer than `pattern` to
+/// allow for a line the model added or left out.
+pub(crate) fn closest_match(
+    lines: &[String],
+    pattern: &[String],
+    start: usize,
+) -> Option<Candidate> {
+    if pattern.is_empty() || start >= lines.len() {
+        return None;
+    }
+    let similarities = line_similarities(&lines[start..], pattern);
+
+    let mut lengths = vec![pattern.len()];
+    if pattern.len() > 1 {
+        lengths.extend([pattern.len() - 1, pattern.len() + 1]);
+    }
+    let mut best: Option<Candidate> = None;
+    for len in lengths {
+        if len > similarities.len() {
+            continue;
+        }
+        for offset in 0..=similarities.len() - len {
+            let candidate = Candidate {
+                start: start + offset,
+                len,
+                similarity: region_similarity(&similarities[offset..offset + len], pattern.len()),
+            };
+            if best.is_none_or(|best| {
+                candidate.similarity > best.similarity
+                    || (candidate.similarity == best.similarity && candidate.start < best.start)
+            }) {
+                best = Some(candidate);
+            }
+        }
+    }
+    best
+}
+
+fn closest_match_at_end(lines: &[String], pattern: &[String]) -> Option<Candidate> {
+    let len = pattern.len();
+    if len == 0 || len > lines.len() {
+        return None;
+    }
+    let start = lines.len() - len;
+    let similarities = line_similarities(&lines[start..], pattern);
+    Some(Candidate {
+        start,
+        len,
+        similarity: region_similarity(&similarities, pattern.len()),
+    })
+}
+
+/// The similarity of every line in `lines` to every line in `pattern`.
+fn line_similarities(lines: &[String], pattern: &[String]) -> Vec<Vec<f64>> {
+    let pattern: Vec<String> = pattern.iter().map(|line| normalise(line)).collect();
+    lines
+        .iter()
+        .map(|line| {
+            let line = normalise(line);
+            pattern
+                .iter()
+                .map(|expected| line_similarity(&line, expected))
+                .collect()
+        })
+        .collect()
+}
+
+fn line_similarity(actual: &str, expected: &str) -> f64 {
+    if actual == expected {
+        return 1.0;
+    }
+    // The ratio cannot reach the minimum when the lengths are too far apart.
+    let (short, long) = if actual.len() < expected.len() {
+        (actual.len(), expected.len())
+    } else {
+        (expected.len(), actual.len())
+    };
+    if (2 * short) as f64 / ((short + long) as f64) < MIN_LINE_SIMILARITY {
+        return 0.0;
+    }
+    let similarity = f64::from(
+        TextDiff::configure()
+            .algorithm(Algorithm::Myers)
+            .diff_chars(actual, expected)
+            .ratio(),
+    );
+    if similarity < MIN_LINE_SIMILARITY {
+        0.0
+    } else {
+        similarity
+    }
+}
+
+/// Aligns the lines of a region with the pattern's lines in order, the way a
+/// line diff would, pairing up lines that resemble each other. The result is
+/// the total similarity of the pairs relative to the number of lines on both
+/// sides, so extra, missing and edited lines all lower it.
+fn region_similarity(similarities: &[Vec<f64>], pattern_len: usize) -> f64 {
+    let mut previous = vec![0.0; pattern_len + 1];
+    let mut current = vec![0.0; pattern_len + 1];
+    for row in similarities {
+        for j in 1..=pattern_len {
+            current[j] = f64::max(
+                f64::max(previous[j], current[j - 1]),
+                previous[j - 1] + row[j - 1],
+            );
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    2.0 * previous[pattern_len] / (similarities.len() + pattern_len) as f64
+}
+
+/// Pairs each line of `pattern` with the line of `region` it was aligned with
+/// by [`region_similarity`], if any.
+fn align(region: &[String], pattern: &[String]) -> Vec<Option<usize>> {
+    let similarities = line_similarities(region, pattern);
+    let mut table = vec![vec![0.0; pattern.len() + 1]; region.len() + 1];
+    for i in 1..=region.len() {
+        for j in 1..=pattern.len() {
+            table[i][j] = f64::max(
+                f64::max(table[i - 1][j], table[i][j - 1]),
+                table[i - 1][j - 1] + similarities[i - 1][j - 1],
+            );
+        }
+    }
+
+    let mut aligned = vec![None; pattern.len()];
+    let (mut i, mut j) = (region.len(), pattern.len());
+    while i > 0 && j > 0 {
+        let similarity = similarities[i - 1][j - 1];
+        if similarity > 0.0 && table[i][j] == table[i - 1][j - 1] + similarity {
+            aligned[j - 1] = Some(i - 1);
+            i -= 1;
+            j -= 1;
+        } else if table[i][j] == table[i - 1][j] {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    aligned
+}
+
+/// Applies the change from `old_lines` to `new_lines` to `region`, a part of
+/// the file that only resembles `old_lines`. Context lines keep the file's
+/// version, removed lines are removed wherever they were matched, and lines
+/// the file has but `old_lines` lacks are kept in place.
+pub(crate) fn rebase(region: &[String], old_lines: &[String], new_lines: &[String]) -> Vec<String> {
+    let aligned = align(region, old_lines);
+    let old_refs: Vec<&str> = old_lines.iter().map(String::as_str).collect();
+    let new_refs: Vec<&str> = new_lines.iter().map(String::as_str).collect();
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Myers)
+        .diff_slices(&old_refs, &new_refs);
+
+    let mut rebased = Vec::with_capacity(region.len() + new_lines.len());
+    let mut next_region_line = 0;
+    for change in diff.iter_all_changes() {
+        let matched = change.old_index().and_then(|index| aligned[index]);
+        match change.tag() {
+            ChangeTag::Insert => rebased.push(change.value().to_string()),
+            ChangeTag::Equal | ChangeTag::Delete => {
+                let Some(line) = matched else {
+                    // Not in the file, so there is nothing to keep or remove.
+                    continue;
+                };
+                rebased.extend_from_slice(&region[next_region_line..line]);
+                if change.tag() == ChangeTag::Equal {
+                    rebased.push(region[line].clone());
+                }
+                next_region_line = line + 1;
+            }
+        }
+    }
+    rebased.extend_from_slice(&region[next_region_line..]);
+    rebased
+}
+
+/// Explains why `pattern` could not be found in `path`: the closest region,
+/// its line numbers and how it differs from the expected lines.
+pub(crate) fn describe_mismatch(
+    path: &Path,
+    lines: &[String],
+    pattern: &[String],
+    threshold: f64,
+) -> String {
+    let mut message = format!(
+        "Failed to find expected lines in {}:\n{}",
+        path.display(),
+        pattern.join("\n")
+    );
+    let Some(candidate) = closest_match(lines, pattern, 0) else {
+        return message;
+    };
+    let first_line = candidate.start + 1;
+    let last_line = candidate.start + candidate.len;
+    let actual = lines[candidate.start..candidate.start + candidate.len].join("\n") + "\n";
+    let expected = pattern.join("\n") + "\n";
+    let diff = TextDiff::from_lines(&actual, &expected)
+        .unified_diff()
+        .context_radius(candidate.len.max(pattern.len()))
+        .header(
+            &format!("{} (lines {first_line}-{last_line})", path.display()),
+            "expected lines",
+        )
+        .to_string();
+    message.push_str(&format!(
+        "\n\nThe closest match is lines {first_line}-{last_line}, which are {:.0}% similar (at least {:.0}% is needed to apply the change there):\n{diff}",
+        candidate.similarity * 100.0,
+        threshold * 100.0,
+    ));
+    message
+}
+
+/// Trims the line and collapses runs of whitespace, so that indentation and
+/// spacing differences do not count against a region.
+fn normalise(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn to_vec(strings: &[&str]) -> Vec<String> {
+        strings.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn tolerates_indentation_and_small_edits() {
+        let lines = to_vec(&[
+            "fn main() {",
+            "\tlet total = compute(1, 2);",
+            "\tprintln!(\"total: {}\", total);",
+            "}",
+        ]);
+        let pattern = to_vec(&[
+            "    let total = compute(1, 2);",
+            "    println!(\"total = {}\", total);",
+        ]);
+        let candidate = fuzzy_seek(&lines, &pattern, 0, false, 0.9).expect("close enough");
+        assert_eq!((candidate.start, candidate.len), (1, 2));
+        assert!(fuzzy_seek(&lines, &pattern, 0, false, 1.0).is_none());
+    }
+
+    #[test]
+    fn tolerates_a_missing_line() {
+        let lines = to_vec(&[
+            "use std::fs;",
+            "",
+            "fn load() {",
+            "    let text = fs::read_to_string(\"config.toml\").unwrap();",
+            "    // parse the configuration",
+            "    parse(&text);",
+            "}",
+        ]);
+        let pattern = to_vec(&[
+            "fn load() {",
+            "    let text = fs::read_to_string(\"config.toml\").unwrap();",
+            "    parse(&text);",
+            "}",
+        ]);
+        let candidate = fuzzy_seek(&lines, &pattern, 0, false, 0.8).expect("close enough");
+        assert_eq!((candidate.start, candidate.len), (2, 5));
+    }
+
+    #[test]
+    fn rebase_keeps_the_files_version_of_unchanged_lines() {
+        let region = to_vec(&[
+            "fn load() {",
+            "\tlet text = read(\"config.toml\");",
+            "\t// parse the configuration",
+            "\tparse(&text);",
+            "}",
+        ]);
+        let old_lines = to_vec(&[
+            "fn load() {",
+            "    let text = read(\"config.toml\")",
+            "    parse(&text);",
+            "}",
+        ]);
+        let new_lines = to_vec(&[
+            "fn load() {",
+            "    let text = read(\"config.toml\")",
+            "    parse(&text)?;",
+            "    Ok(())",
+            "}",
+        ]);
+        assert_eq!(
+            rebase(&region, &old_lines, &new_lines),
+            to_vec(&[
+                "fn load() {",
+                "\tlet text = read(\"config.toml\");",
+                "\t// parse the configuration",
+                "    parse(&text)?;",
+                "    Ok(())",
+                "}",
+            ])
+        );
+    }
+
+    #[test]
+    fn describes_the_closest_region() {
+        let lines = to_vec(&["a", "b", "c", "d"]);
+        let pattern = to_vec(&["b", "x", "d"]);
+        assert_eq!(
+            describe_mismatch(Path::new("f.txt"), &lines, &pattern, 0.9),
+            "Failed to find expected lines in f.txt:
+b
+x
+d
+
+The closest match is lines 2-4, which are 67% similar (at least 90% is needed to apply the change there):
+--- f.txt (lines 2-4)
++++ expected lines
+@@ -1,3 +1,3 @@
+ b
+-c
++x
+ d
+"
+        );
+    }
+}
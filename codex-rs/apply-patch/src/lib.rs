@@ -1,5 +1,8 @@
+mod fuzzy_match;
 mod parser;
 mod seek_sequence;
+mod transaction;
+mod unified_diff;
 
 use std::collections::HashMap;
 use std::path::Path;
@@ -11,10 +14,17 @@ use anyhow::Result;
 pub use parser::Hunk;
 pub use parser::ParseError;
 use parser::ParseError::*;
+pub use parser::RawContents;
 use parser::UpdateFileChunk;
 pub use parser::parse_patch;
 use similar::TextDiff;
 use thiserror::Error;
+pub use transaction::FileOutcome;
+pub use transaction::FileResult;
+use transaction::NewFile;
+use transaction::PatchFailure;
+use transaction::Transaction;
+pub use transaction::take_file_results;
 use tree_sitter::LanguageError;
 use tree_sitter::Parser;
 use tree_sitter_bash::LANGUAGE as BASH;
@@ -73,6 +83,41 @@ pub enum MaybeApplyPatch {
     NotApplyPatch,
 }
 
+/// Settings for how leniently chunks are located in the files they update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ApplyPatchOptions {
+    /// Similarity, between 0 and 1, that a region of a file must have with a
+    /// chunk's expected lines for the chunk to be applied there when the lines
+    /// cannot be found as written. `1.0`, the default, disables fuzzy
+    /// matching.
+    pub fuzzy_threshold: f64,
+    /// Also write the outcome of every file to stderr as a line of JSON, for
+    /// callers that run `apply_patch` as a process. See [`take_file_results`].
+    pub report_file_results: bool,
+}
+
+impl Default for ApplyPatchOptions {
+    fn default() -> Self {
+        Self {
+            fuzzy_threshold: 1.0,
+            report_file_results: false,
+        }
+    }
+}
+
+/// A chunk that was applied to a region of a file that only resembled the
+/// chunk's expected lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub path: PathBuf,
+    /// First line of the region, counted from 1.
+    pub start_line: usize,
+    /// Last line of the region, counted from 1.
+    pub end_line: usize,
+    /// Between 0 (nothing in common) and 1 (identical after normalisation).
+    pub similarity: f64,
+}
+
 /// Both the raw PATCH argument to `apply_patch` as well as the PATCH argument
 /// parsed into hunks.
 #[derive(Debug, PartialEq)]
@@ -109,6 +154,7 @@ pub fn maybe_parse_apply_patch(argv: &[String]) -> MaybeApplyPatch {
 pub enum ApplyPatchFileChange {
     Add {
         content: String,
+        executable: bool,
     },
     Delete,
     Update {
@@ -116,6 +162,17 @@ pub enum ApplyPatchFileChange {
         move_path: Option<PathBuf>,
         /// new_content that will result after the unified_diff is applied.
         new_content: String,
+        /// Sets or clears the executable bit; `None` leaves it as it is.
+        executable: Option<bool>,
+    },
+    /// Adds or replaces a file with contents that are not text.
+    Binary {
+        content: Vec<u8>,
+        executable: bool,
+    },
+    /// Adds or replaces a symlink.
+    Symlink {
+        target: PathBuf,
     },
 }
 
@@ -159,6 +216,38 @@ impl ApplyPatchAction {
         &self.changes
     }
 
+    /// Describes writing `new_content` to `path`, which is created when
+    /// `old_content` is `None`. This is for edits made without a patch, such
+    /// as by the `edit_file` tool, so `patch` is empty and the action cannot
+    /// be run with `apply_patch`.
+    pub fn new_write(
+        path: PathBuf,
+        old_content: Option<&str>,
+        new_content: String,
+        cwd: PathBuf,
+    ) -> Self {
+        let change = match old_content {
+            None => ApplyPatchFileChange::Add {
+                content: new_content,
+                executable: false,
+            },
+            Some(old_content) => ApplyPatchFileChange::Update {
+                unified_diff: TextDiff::from_lines(old_content, &new_content)
+                    .unified_diff()
+                    .context_radius(1)
+                    .to_string(),
+                move_path: None,
+                new_content,
+                executable: None,
+            },
+        };
+        Self {
+            changes: HashMap::from([(path, change)]),
+            patch: String::new(),
+            cwd,
+        }
+    }
+
     /// Should be used exclusively for testing. (Not worth the overhead of
     /// creating a feature flag for this.)
     pub fn new_add_for_test(path: &Path, content: String) -> Self {
@@ -178,7 +267,13 @@ impl ApplyPatchAction {
 + {content}
 *** End Patch"#,
         );
-        let changes = HashMap::from([(path.to_path_buf(), ApplyPatchFileChange::Add { content })]);
+        let changes = HashMap::from([(
+            path.to_path_buf(),
+            ApplyPatchFileChange::Add {
+                content,
+                executable: false,
+            },
+        )]);
         #[expect(clippy::expect_used)]
         Self {
             changes,
@@ -194,25 +289,47 @@ impl ApplyPatchAction {
 /// cwd must be an absolute path so that we can resolve relative paths in the
 /// patch.
 pub fn maybe_parse_apply_patch_verified(argv: &[String], cwd: &Path) -> MaybeApplyPatchVerified {
+    maybe_parse_apply_patch_verified_with_options(argv, cwd, &ApplyPatchOptions::default())
+}
+
+pub fn maybe_parse_apply_patch_verified_with_options(
+    argv: &[String],
+    cwd: &Path,
+    options: &ApplyPatchOptions,
+) -> MaybeApplyPatchVerified {
     match maybe_parse_apply_patch(argv) {
         MaybeApplyPatch::Body(ApplyPatchArgs { patch, hunks }) => {
             let mut changes = HashMap::new();
             for hunk in hunks {
                 let path = hunk.resolve_path(cwd);
                 match hunk {
-                    Hunk::AddFile { contents, .. } => {
-                        changes.insert(path, ApplyPatchFileChange::Add { content: contents });
+                    Hunk::AddFile {
+                        contents,
+                        executable,
+                        ..
+                    } => {
+                        changes.insert(
+                            path,
+                            ApplyPatchFileChange::Add {
+                                content: contents,
+                                executable,
+                            },
+                        );
                     }
                     Hunk::DeleteFile { .. } => {
                         changes.insert(path, ApplyPatchFileChange::Delete);
                     }
                     Hunk::UpdateFile {
-                        move_path, chunks, ..
+                        move_path,
+                        executable,
+                        chunks,
+                        ..
                     } => {
                         let ApplyPatchFileUpdate {
                             unified_diff,
                             content: contents,
-                        } = match unified_diff_from_chunks(&path, &chunks) {
+                        } = match unified_diff_from_chunks_with_context(&path, &chunks, 1, options)
+                        {
                             Ok(diff) => diff,
                             Err(e) => {
                                 return MaybeApplyPatchVerified::CorrectnessError(e);
@@ -224,9 +341,24 @@ pub fn maybe_parse_apply_patch_verified(argv: &[String], cwd: &Path) -> MaybeApp
                                 unified_diff,
                                 move_path: move_path.map(|p| cwd.join(p)),
                                 new_content: contents,
+                                executable,
                             },
                         );
                     }
+                    Hunk::WriteFile { contents, .. } => {
+                        let change = match contents {
+                            RawContents::Binary { data, executable } => {
+                                ApplyPatchFileChange::Binary {
+                                    content: data,
+                                    executable,
+                                }
+                            }
+                            RawContents::Symlink { target } => {
+                                ApplyPatchFileChange::Symlink { target }
+                            }
+                        };
+                        changes.insert(path, change);
+                    }
                 }
             }
             MaybeApplyPatchVerified::Body(ApplyPatchAction {
@@ -312,6 +444,15 @@ pub fn apply_patch(
     patch: &str,
     stdout: &mut impl std::io::Write,
     stderr: &mut impl std::io::Write,
+) -> Result<(), ApplyPatchError> {
+    apply_patch_with_options(patch, &ApplyPatchOptions::default(), stdout, stderr)
+}
+
+pub fn apply_patch_with_options(
+    patch: &str,
+    options: &ApplyPatchOptions,
+    stdout: &mut impl std::io::Write,
+    stderr: &mut impl std::io::Write,
 ) -> Result<(), ApplyPatchError> {
     let hunks = match parse_patch(patch) {
         Ok(source) => source.hunks,
@@ -335,7 +476,7 @@ pub fn apply_patch(
         }
     };
 
-    apply_hunks(&hunks, stdout, stderr)?;
+    apply_hunks(&hunks, options, stdout, stderr)?;
 
     Ok(())
 }
@@ -343,6 +484,7 @@ pub fn apply_patch(
 /// Applies hunks and continues to update stdout/stderr
 pub fn apply_hunks(
     hunks: &[Hunk],
+    options: &ApplyPatchOptions,
     stdout: &mut impl std::io::Write,
     stderr: &mut impl std::io::Write,
 ) -> Result<(), ApplyPatchError> {
@@ -353,7 +495,7 @@ pub fn apply_hunks(
                 // The file is being added, so it doesn't exist yet.
                 None
             }
-            Hunk::DeleteFile { path } => Some(path.as_path()),
+            Hunk::DeleteFile { path } | Hunk::WriteFile { path, .. } => Some(path.as_path()),
             Hunk::UpdateFile {
                 path, move_path, ..
             } => match move_path {
@@ -373,20 +515,37 @@ pub fn apply_hunks(
         .collect::<Vec<&Path>>();
 
     // Delegate to a helper that applies each hunk to the filesystem.
-    match apply_hunks_to_files(hunks) {
+    match apply_hunks_to_files(hunks, options) {
         Ok(affected) => {
             print_summary(&affected, stdout).map_err(ApplyPatchError::from)?;
+            if options.report_file_results {
+                let files = affected
+                    .added
+                    .iter()
+                    .chain(&affected.modified)
+                    .chain(&affected.deleted)
+                    .map(|path| FileResult {
+                        path: path.clone(),
+                        outcome: FileOutcome::Applied,
+                    })
+                    .collect::<Vec<_>>();
+                transaction::write_file_results(&files, stderr).map_err(ApplyPatchError::from)?;
+            }
             Ok(())
         }
-        Err(err) => {
-            let msg = err.to_string();
+        Err(PatchFailure { error, files }) => {
+            let msg = error.to_string();
             writeln!(stderr, "{msg}").map_err(ApplyPatchError::from)?;
-            if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            transaction::print_file_results(&files, stderr).map_err(ApplyPatchError::from)?;
+            if options.report_file_results {
+                transaction::write_file_results(&files, stderr).map_err(ApplyPatchError::from)?;
+            }
+            if let Some(io) = error.downcast_ref::<std::io::Error>() {
                 Err(ApplyPatchError::from(io))
             } else {
                 Err(ApplyPatchError::IoError(IoError {
                     context: msg,
-                    source: std::io::Error::other(err),
+                    source: std::io::Error::other(error),
                 }))
             }
         }
@@ -400,75 +559,137 @@ pub struct AffectedPaths {
     pub added: Vec<PathBuf>,
     pub modified: Vec<PathBuf>,
     pub deleted: Vec<PathBuf>,
+    /// Chunks applied where the file only resembled their expected lines.
+    pub fuzzy_matches: Vec<FuzzyMatch>,
 }
 
 /// Apply the hunks to the filesystem, returning which files were added, modified, or deleted.
-/// Returns an error if the patch could not be applied.
-fn apply_hunks_to_files(hunks: &[Hunk]) -> anyhow::Result<AffectedPaths> {
+/// Every hunk is checked before any file is written, and files written before
+/// a failed write are restored, so either all of the changes are made or none.
+fn apply_hunks_to_files(
+    hunks: &[Hunk],
+    options: &ApplyPatchOptions,
+) -> std::result::Result<AffectedPaths, PatchFailure> {
     if hunks.is_empty() {
-        anyhow::bail!("No files were modified.");
+        return Err(PatchFailure {
+            error: anyhow::anyhow!("No files were modified."),
+            files: Vec::new(),
+        });
     }
 
+    let mut transaction = Transaction::default();
     let mut added: Vec<PathBuf> = Vec::new();
     let mut modified: Vec<PathBuf> = Vec::new();
     let mut deleted: Vec<PathBuf> = Vec::new();
+    let mut fuzzy_matches: Vec<FuzzyMatch> = Vec::new();
     for hunk in hunks {
-        match hunk {
-            Hunk::AddFile { path, contents } => {
-                if let Some(parent) = path.parent()
-                    && !parent.as_os_str().is_empty()
-                {
-                    std::fs::create_dir_all(parent).with_context(|| {
-                        format!("Failed to create parent directories for {}", path.display())
-                    })?;
-                }
-                std::fs::write(path, contents)
-                    .with_context(|| format!("Failed to write file {}", path.display()))?;
+        let planned = match hunk {
+            Hunk::AddFile {
+                path,
+                contents,
+                executable,
+            } => {
+                transaction.write(
+                    path,
+                    NewFile::Contents {
+                        data: contents.clone().into_bytes(),
+                        executable: Some(*executable),
+                    },
+                );
                 added.push(path.clone());
+                Ok(())
             }
-            Hunk::DeleteFile { path } => {
-                std::fs::remove_file(path)
-                    .with_context(|| format!("Failed to delete file {}", path.display()))?;
-                deleted.push(path.clone());
+            Hunk::WriteFile { path, contents } => {
+                if transaction.exists(path) {
+                    modified.push(path.clone());
+                } else {
+                    added.push(path.clone());
+                }
+                let file = match contents {
+                    RawContents::Binary { data, executable } => NewFile::Contents {
+                        data: data.clone(),
+                        executable: Some(*executable),
+                    },
+                    RawContents::Symlink { target } => NewFile::Symlink {
+                        target: target.clone(),
+                    },
+                };
+                transaction.write(path, file);
+                Ok(())
             }
+            Hunk::DeleteFile { path } => transaction
+                .delete(path)
+                .with_context(|| format!("Failed to delete file {}", path.display()))
+                .map(|()| deleted.push(path.clone())),
             Hunk::UpdateFile {
                 path,
                 move_path,
+                executable,
                 chunks,
-            } => {
-                let AppliedPatch { new_contents, .. } =
-                    derive_new_contents_from_chunks(path, chunks)?;
-                if let Some(dest) = move_path {
-                    if let Some(parent) = dest.parent()
-                        && !parent.as_os_str().is_empty()
-                    {
-                        std::fs::create_dir_all(parent).with_context(|| {
-                            format!("Failed to create parent directories for {}", dest.display())
-                        })?;
+            } => transaction
+                .read(path)
+                .map_err(|err| {
+                    ApplyPatchError::IoError(IoError {
+                        context: format!("Failed to read file to update {}", path.display()),
+                        source: err,
+                    })
+                })
+                .and_then(|original_contents| {
+                    apply_chunks(path, original_contents, chunks, options)
+                })
+                .map(|applied| {
+                    fuzzy_matches.extend(applied.fuzzy_matches);
+                    let file = NewFile::Contents {
+                        data: applied.new_contents.into_bytes(),
+                        executable: *executable,
+                    };
+                    match move_path {
+                        Some(dest) => {
+                            transaction.write(dest, file);
+                            if dest != path {
+                                transaction.remove(path);
+                            }
+                            modified.push(dest.clone());
+                        }
+                        None => {
+                            transaction.write(path, file);
+                            modified.push(path.clone());
+                        }
                     }
-                    std::fs::write(dest, new_contents)
-                        .with_context(|| format!("Failed to write file {}", dest.display()))?;
-                    std::fs::remove_file(path)
-                        .with_context(|| format!("Failed to remove original {}", path.display()))?;
-                    modified.push(dest.clone());
-                } else {
-                    std::fs::write(path, new_contents)
-                        .with_context(|| format!("Failed to write file {}", path.display()))?;
-                    modified.push(path.clone());
+                })
+                .map_err(anyhow::Error::from),
+        };
+        if let Err(error) = planned {
+            // Nothing has been written yet.
+            let failed = hunk.path();
+            let mut files = vec![FileResult {
+                path: failed.to_path_buf(),
+                outcome: FileOutcome::Failed,
+            }];
+            for path in hunks.iter().flat_map(Hunk::paths) {
+                if !files.iter().any(|file| file.path == path) {
+                    files.push(FileResult {
+                        path: path.to_path_buf(),
+                        outcome: FileOutcome::NotApplied,
+                    });
                 }
             }
+            return Err(PatchFailure { error, files });
         }
     }
+    transaction.commit()?;
     Ok(AffectedPaths {
         added,
         modified,
         deleted,
+        fuzzy_matches,
     })
 }
 
 struct AppliedPatch {
     original_contents: String,
     new_contents: String,
+    fuzzy_matches: Vec<FuzzyMatch>,
 }
 
 /// Return *only* the new file contents (joined into a single `String`) after
@@ -476,6 +697,7 @@ struct AppliedPatch {
 fn derive_new_contents_from_chunks(
     path: &Path,
     chunks: &[UpdateFileChunk],
+    options: &ApplyPatchOptions,
 ) -> std::result::Result<AppliedPatch, ApplyPatchError> {
     let original_contents = match std::fs::read_to_string(path) {
         Ok(contents) => contents,
@@ -486,6 +708,25 @@ fn derive_new_contents_from_chunks(
             }));
         }
     };
+    apply_chunks(path, original_contents, chunks, options)
+}
+
+/// Applies the chunks to `original_contents`, the contents of the file at `path`.
+fn apply_chunks(
+    path: &Path,
+    original_contents: String,
+    chunks: &[UpdateFileChunk],
+    options: &ApplyPatchOptions,
+) -> std::result::Result<AppliedPatch, ApplyPatchError> {
+    // A hunk that only moves the file or changes its mode keeps the contents
+    // exactly as they are.
+    if chunks.is_empty() {
+        return Ok(AppliedPatch {
+            new_contents: original_contents.clone(),
+            original_contents,
+            fuzzy_matches: Vec::new(),
+        });
+    }
 
     let mut original_lines: Vec<String> = original_contents
         .split('\n')
@@ -498,7 +739,8 @@ fn derive_new_contents_from_chunks(
         original_lines.pop();
     }
 
-    let replacements = compute_replacements(&original_lines, path, chunks)?;
+    let (replacements, fuzzy_matches) =
+        compute_replacements(&original_lines, path, chunks, options)?;
     let new_lines = apply_replacements(original_lines, &replacements);
     let mut new_lines = new_lines;
     if !new_lines.last().is_some_and(|s| s.is_empty()) {
@@ -508,18 +750,25 @@ fn derive_new_contents_from_chunks(
     Ok(AppliedPatch {
         original_contents,
         new_contents,
+        fuzzy_matches,
     })
 }
 
+/// `(start_index, old_len, new_lines)`: replaces `old_len` lines starting at
+/// `start_index` with `new_lines`.
+type Replacement = (usize, usize, Vec<String>);
+
 /// Compute a list of replacements needed to transform `original_lines` into the
-/// new lines, given the patch `chunks`. Each replacement is returned as
-/// `(start_index, old_len, new_lines)`.
+/// new lines, given the patch `chunks`, along with the chunks that were only
+/// matched fuzzily.
 fn compute_replacements(
     original_lines: &[String],
     path: &Path,
     chunks: &[UpdateFileChunk],
-) -> std::result::Result<Vec<(usize, usize, Vec<String>)>, ApplyPatchError> {
-    let mut replacements: Vec<(usize, usize, Vec<String>)> = Vec::new();
+    options: &ApplyPatchOptions,
+) -> std::result::Result<(Vec<Replacement>, Vec<FuzzyMatch>), ApplyPatchError> {
+    let mut replacements: Vec<Replacement> = Vec::new();
+    let mut fuzzy_matches: Vec<FuzzyMatch> = Vec::new();
     let mut line_index: usize = 0;
 
     for chunk in chunks {
@@ -590,24 +839,44 @@ fn compute_replacements(
         if let Some(start_idx) = found {
             replacements.push((start_idx, pattern.len(), new_slice.to_vec()));
             line_index = start_idx + pattern.len();
+        } else if let Some(candidate) = fuzzy_match::fuzzy_seek(
+            original_lines,
+            pattern,
+            line_index,
+            chunk.is_end_of_file,
+            options.fuzzy_threshold,
+        ) {
+            let region = &original_lines[candidate.start..candidate.start + candidate.len];
+            replacements.push((
+                candidate.start,
+                candidate.len,
+                fuzzy_match::rebase(region, pattern, new_slice),
+            ));
+            fuzzy_matches.push(FuzzyMatch {
+                path: path.to_path_buf(),
+                start_line: candidate.start + 1,
+                end_line: candidate.start + candidate.len.max(1),
+                similarity: candidate.similarity,
+            });
+            line_index = candidate.start + candidate.len;
         } else {
-            return Err(ApplyPatchError::ComputeReplacements(format!(
-                "Failed to find expected lines {:?} in {}",
-                chunk.old_lines,
-                path.display()
-            )));
+            return Err(ApplyPatchError::ComputeReplacements(
+                fuzzy_match::describe_mismatch(
+                    path,
+                    original_lines,
+                    pattern,
+                    options.fuzzy_threshold,
+                ),
+            ));
         }
     }
 
-    Ok(replacements)
+    Ok((replacements, fuzzy_matches))
 }
 
 /// Apply the `(start_index, old_len, new_lines)` replacements to `original_lines`,
 /// returning the modified file contents as a vector of lines.
-fn apply_replacements(
-    mut lines: Vec<String>,
-    replacements: &[(usize, usize, Vec<String>)],
-) -> Vec<String> {
+fn apply_replacements(mut lines: Vec<String>, replacements: &[Replacement]) -> Vec<String> {
     // We must apply replacements in descending order so that earlier replacements
     // don't shift the positions of later ones.
     for (start_idx, old_len, new_segment) in replacements.iter().rev() {
@@ -641,18 +910,20 @@ pub fn unified_diff_from_chunks(
     path: &Path,
     chunks: &[UpdateFileChunk],
 ) -> std::result::Result<ApplyPatchFileUpdate, ApplyPatchError> {
-    unified_diff_from_chunks_with_context(path, chunks, 1)
+    unified_diff_from_chunks_with_context(path, chunks, 1, &ApplyPatchOptions::default())
 }
 
 pub fn unified_diff_from_chunks_with_context(
     path: &Path,
     chunks: &[UpdateFileChunk],
     context: usize,
+    options: &ApplyPatchOptions,
 ) -> std::result::Result<ApplyPatchFileUpdate, ApplyPatchError> {
     let AppliedPatch {
         original_contents,
         new_contents,
-    } = derive_new_contents_from_chunks(path, chunks)?;
+        ..
+    } = derive_new_contents_from_chunks(path, chunks, options)?;
     let text_diff = TextDiff::from_lines(&original_contents, &new_contents);
     let unified_diff = text_diff.unified_diff().context_radius(context).to_string();
     Ok(ApplyPatchFileUpdate {
@@ -677,6 +948,16 @@ pub fn print_summary(
     for path in &affected.deleted {
         writeln!(out, "D {}", path.display())?;
     }
+    for fuzzy in &affected.fuzzy_matches {
+        writeln!(
+            out,
+            "Note: the expected lines were not found in {}, so the change was applied to lines {}-{}, which are {:.0}% similar.",
+            fuzzy.path.display(),
+            fuzzy.start_line,
+            fuzzy.end_line,
+            fuzzy.similarity * 100.0
+        )?;
+    }
     Ok(())
 }
 
@@ -713,7 +994,8 @@ mod tests {
                     hunks,
                     vec![Hunk::AddFile {
                         path: PathBuf::from("foo"),
-                        contents: "hi\n".to_string()
+                        contents: "hi\n".to_string(),
+                        executable: false,
                     }]
                 );
             }
@@ -738,7 +1020,8 @@ mod tests {
                     hunks,
                     vec![Hunk::AddFile {
                         path: PathBuf::from("foo"),
-                        contents: "hi\n".to_string()
+                        contents: "hi\n".to_string(),
+                        executable: false,
                     }]
                 );
             }
@@ -765,7 +1048,8 @@ PATCH"#,
                     hunks,
                     vec![Hunk::AddFile {
                         path: PathBuf::from("foo"),
-                        contents: "hi\n".to_string()
+                        contents: "hi\n".to_string(),
+                        executable: false,
                     }]
                 );
             }
@@ -773,6 +1057,152 @@ PATCH"#,
         }
     }
 
+    #[test]
+    fn test_verified_accepts_git_diff() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("old.txt"), "one\ntwo\nthree\n").unwrap();
+        let args = strs_to_strings(&[
+            "bash",
+            "-lc",
+            r#"apply_patch <<'PATCH'
+diff --git a/old.txt b/new.txt
+similarity index 80%
+rename from old.txt
+rename to new.txt
+--- a/old.txt
++++ b/new.txt
+@@ -1,3 +1,3 @@
+ one
+-two
++TWO
+ three
+diff --git a/added.txt b/added.txt
+new file mode 100644
+--- /dev/null
++++ b/added.txt
+@@ -0,0 +1 @@
++hi
+PATCH"#,
+        ]);
+
+        match maybe_parse_apply_patch_verified(&args, dir.path()) {
+            MaybeApplyPatchVerified::Body(action) => {
+                assert_eq!(
+                    action.changes(),
+                    &HashMap::from([
+                        (
+                            dir.path().join("old.txt"),
+                            ApplyPatchFileChange::Update {
+                                unified_diff: "@@ -1,3 +1,3 @@\n one\n-two\n+TWO\n three\n"
+                                    .to_string(),
+                                move_path: Some(dir.path().join("new.txt")),
+                                new_content: "one\nTWO\nthree\n".to_string(),
+                                executable: None,
+                            }
+                        ),
+                        (
+                            dir.path().join("added.txt"),
+                            ApplyPatchFileChange::Add {
+                                content: "hi\n".to_string(),
+                                executable: false,
+                            }
+                        ),
+                    ])
+                );
+            }
+            result => panic!("expected MaybeApplyPatchVerified::Body got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_apply_patch_applies_unified_diff() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let deleted = dir.path().join("deleted.txt");
+        fs::write(&path, "foo\nbar\nbaz\n").unwrap();
+        fs::write(&deleted, "gone\n").unwrap();
+        let patch = format!(
+            r#"--- {path}
++++ {path}
+@@ -1,3 +1,4 @@
+ foo
+-bar
++BAR
++qux
+ baz
+--- {deleted}
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+"#,
+            path = path.display(),
+            deleted = deleted.display(),
+        );
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        apply_patch(&patch, &mut stdout, &mut stderr).unwrap();
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            format!(
+                "Success. Updated the following files:\nM {}\nD {}\n",
+                path.display(),
+                deleted.display()
+            )
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\nBAR\nqux\nbaz\n");
+        assert!(!deleted.exists());
+    }
+
+    #[test]
+    fn test_apply_patch_fuzzy_matches_drifted_context() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        fs::write(
+            &path,
+            "fn load() {\n\tlet text = read(\"config.toml\");\n\t// parse it\n\tparse(&text);\n}\n",
+        )
+        .unwrap();
+        let patch = wrap_patch(&format!(
+            r#"*** Update File: {}
+@@
+ fn load() {{
+     let text = read("config.toml");
+-    parse(&text);
++    parse(&text)?;
+ }}"#,
+            path.display()
+        ));
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        assert!(apply_patch(&patch, &mut stdout, &mut stderr).is_err());
+        let stderr = String::from_utf8(stderr).unwrap();
+        assert!(
+            stderr.contains("The closest match is lines 1-5"),
+            "unexpected error: {stderr}"
+        );
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let fuzzy = ApplyPatchOptions {
+            fuzzy_threshold: 0.85,
+            ..ApplyPatchOptions::default()
+        };
+        apply_patch_with_options(&patch, &fuzzy, &mut stdout, &mut stderr).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "fn load() {\n\tlet text = read(\"config.toml\");\n\t// parse it\n    parse(&text)?;\n}\n"
+        );
+        let stdout = String::from_utf8(stdout).unwrap();
+        assert!(
+            stdout.contains(&format!(
+                "Note: the expected lines were not found in {}, so the change was applied to lines 1-5, which are",
+                path.display()
+            )),
+            "unexpected output: {stdout}"
+        );
+    }
+
     #[test]
     fn test_add_file_hunk_creates_file_with_contents() {
         let dir = tempdir().unwrap();
@@ -1272,6 +1702,7 @@ g
                         .to_string(),
                         move_path: None,
                         new_content: "updated session directory content\n".to_string(),
+                        executable: None,
                     },
                 )]),
                 patch: argv[1].clone(),
@@ -1280,6 +1711,143 @@ g
         );
     }
 
+    #[test]
+    fn test_apply_patch_checks_every_hunk_before_writing() {
+        let dir = tempdir().unwrap();
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second.txt");
+        let added = dir.path().join("added.txt");
+        fs::write(&first, "one\n").unwrap();
+        fs::write(&second, "two\n").unwrap();
+        let patch = wrap_patch(&format!(
+            "*** Update File: {}\n@@\n-one\n+ONE\n*** Add File: {}\n+new\n*** Update File: {}\n@@\n-three\n+THREE",
+            first.display(),
+            added.display(),
+            second.display()
+        ));
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let options = ApplyPatchOptions {
+            report_file_results: true,
+            ..ApplyPatchOptions::default()
+        };
+        assert!(apply_patch_with_options(&patch, &options, &mut stdout, &mut stderr).is_err());
+        assert_eq!(fs::read_to_string(&first).unwrap(), "one\n");
+        assert!(!added.exists());
+        let mut stderr = String::from_utf8(stderr).unwrap();
+        assert_eq!(
+            take_file_results(&mut stderr),
+            vec![
+                FileResult {
+                    path: second,
+                    outcome: FileOutcome::Failed,
+                },
+                FileResult {
+                    path: first,
+                    outcome: FileOutcome::NotApplied,
+                },
+                FileResult {
+                    path: added,
+                    outcome: FileOutcome::NotApplied,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_patch_rolls_back_when_a_write_fails() {
+        let dir = tempdir().unwrap();
+        let updated = dir.path().join("updated.txt");
+        let deleted = dir.path().join("deleted.txt");
+        let added = dir.path().join("new/dir/added.txt");
+        // A file where the patch needs a directory makes the last write fail.
+        let blocked = dir.path().join("blocker/blocked.txt");
+        fs::write(&updated, "before\n").unwrap();
+        fs::write(&deleted, "keep me\n").unwrap();
+        fs::write(dir.path().join("blocker"), "").unwrap();
+        let patch = wrap_patch(&format!(
+            "*** Update File: {}\n@@\n-before\n+after\n*** Delete File: {}\n*** Add File: {}\n+added\n*** Add File: {}\n+blocked",
+            updated.display(),
+            deleted.display(),
+            added.display(),
+            blocked.display()
+        ));
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let options = ApplyPatchOptions {
+            report_file_results: true,
+            ..ApplyPatchOptions::default()
+        };
+        assert!(apply_patch_with_options(&patch, &options, &mut stdout, &mut stderr).is_err());
+        assert_eq!(fs::read_to_string(&updated).unwrap(), "before\n");
+        assert_eq!(fs::read_to_string(&deleted).unwrap(), "keep me\n");
+        assert!(!dir.path().join("new").exists());
+        let mut stderr = String::from_utf8(stderr).unwrap();
+        assert!(
+            stderr.contains("No files were changed:"),
+            "unexpected error: {stderr}"
+        );
+        assert_eq!(
+            take_file_results(&mut stderr),
+            vec![
+                FileResult {
+                    path: updated,
+                    outcome: FileOutcome::RolledBack,
+                },
+                FileResult {
+                    path: deleted,
+                    outcome: FileOutcome::RolledBack,
+                },
+                FileResult {
+                    path: added,
+                    outcome: FileOutcome::RolledBack,
+                },
+                FileResult {
+                    path: blocked,
+                    outcome: FileOutcome::Failed,
+                },
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_patch_writes_modes_binary_contents_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("run.sh");
+        let tool = dir.path().join("tool.sh");
+        let image = dir.path().join("logo.png");
+        let link = dir.path().join("latest");
+        fs::write(&tool, "echo tool\n").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o644)).unwrap();
+        let patch = wrap_patch(&format!(
+            "*** Add File: {}\n*** File Mode: 100755\n+echo hi\n\
+             *** Update File: {}\n*** File Mode: 100755\n\
+             *** Add File: {}\n*** Binary Contents\niVBORw==\n\
+             *** Add File: {}\n*** Symlink To: run.sh",
+            script.display(),
+            tool.display(),
+            image.display(),
+            link.display()
+        ));
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        apply_patch(&patch, &mut stdout, &mut stderr).unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(fs::read_to_string(&script).unwrap(), "echo hi\n");
+        assert_eq!(mode(&script), 0o755);
+        assert_eq!(fs::read_to_string(&tool).unwrap(), "echo tool\n");
+        assert_eq!(mode(&tool), 0o755);
+        assert_eq!(fs::read(&image).unwrap(), vec![0x89, b'P', b'N', b'G']);
+        assert_eq!(fs::read_link(&link).unwrap(), PathBuf::from("run.sh"));
+    }
+
     #[test]
     fn test_apply_patch_fails_on_write_error() {
         let dir = tempdir().unwrap();
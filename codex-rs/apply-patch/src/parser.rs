@@ -8,21 +8,33 @@
 //! end_patch: "*** End Patch" LF?
 //!
 //! hunk: add_hunk | delete_hunk | update_hunk
-//! add_hunk: "*** Add File: " filename LF add_line+
+//! add_hunk: "*** Add File: " filename LF (file_mode? (add_line+ | binary) | symlink)
 //! delete_hunk: "*** Delete File: " filename LF
-//! update_hunk: "*** Update File: " filename LF change_move? change?
+//! update_hunk: "*** Update File: " filename LF
+//!     (change_move? file_mode? change? | file_mode? binary | symlink)
 //! filename: /(.+)/
 //! add_line: "+" /(.+)/ LF -> line
 //!
+//! file_mode: "*** File Mode: " ("100644" | "100755") LF
+//! binary: "*** Binary Contents" LF base64_line+
+//! base64_line: /[A-Za-z0-9+\/=]+/ LF
+//! symlink: "*** Symlink To: " target LF
+//! target: /(.+)/
+//!
 //! change_move: "*** Move to: " filename LF
 //! change: (change_context | change_line)+ eof_line?
 //! change_context: ("@@" | "@@ " /(.+)/) LF
 //! change_line: ("+" | "-" | " ") /(.+)/ LF
 //! eof_line: "*** End of File" LF
 //!
+//! An update hunk must change something: its contents, its path or its mode.
+//!
 //! The parser below is a little more lenient than the explicit spec and allows for
 //! leading/trailing whitespace around patch markers.
 use crate::ApplyPatchArgs;
+use crate::unified_diff;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -35,6 +47,11 @@ const DELETE_FILE_MARKER: &str = "*** Delete File: ";
 const UPDATE_FILE_MARKER: &str = "*** Update File: ";
 const MOVE_TO_MARKER: &str = "*** Move to: ";
 const EOF_MARKER: &str = "*** End of File";
+const FILE_MODE_MARKER: &str = "*** File Mode: ";
+const BINARY_CONTENTS_MARKER: &str = "*** Binary Contents";
+const SYMLINK_TO_MARKER: &str = "*** Symlink To: ";
+const REGULAR_FILE_MODE: &str = "100644";
+const EXECUTABLE_FILE_MODE: &str = "100755";
 const CHANGE_CONTEXT_MARKER: &str = "@@ ";
 const EMPTY_CHANGE_CONTEXT_MARKER: &str = "@@";
 
@@ -61,6 +78,7 @@ pub enum Hunk {
     AddFile {
         path: PathBuf,
         contents: String,
+        executable: bool,
     },
     DeleteFile {
         path: PathBuf,
@@ -68,11 +86,26 @@ pub enum Hunk {
     UpdateFile {
         path: PathBuf,
         move_path: Option<PathBuf>,
+        /// Sets or clears the executable bit; `None` leaves it as it is.
+        executable: Option<bool>,
 
         /// Chunks should be in order, i.e. the `change_context` of one chunk
         /// should occur later in the file than the previous chunk.
         chunks: Vec<UpdateFileChunk>,
     },
+    /// Adds `path`, or replaces it if it exists, with contents that are not
+    /// lines of text.
+    WriteFile {
+        path: PathBuf,
+        contents: RawContents,
+    },
+}
+
+/// Contents that a patch cannot express as lines of text.
+#[derive(Debug, PartialEq, Clone)]
+pub enum RawContents {
+    Binary { data: Vec<u8>, executable: bool },
+    Symlink { target: PathBuf },
 }
 
 impl Hunk {
@@ -81,8 +114,28 @@ impl Hunk {
             Hunk::AddFile { path, .. } => cwd.join(path),
             Hunk::DeleteFile { path } => cwd.join(path),
             Hunk::UpdateFile { path, .. } => cwd.join(path),
+            Hunk::WriteFile { path, .. } => cwd.join(path),
         }
     }
+
+    /// The file the hunk adds, deletes or updates.
+    pub fn path(&self) -> &Path {
+        match self {
+            Hunk::AddFile { path, .. }
+            | Hunk::DeleteFile { path }
+            | Hunk::UpdateFile { path, .. }
+            | Hunk::WriteFile { path, .. } => path,
+        }
+    }
+
+    /// Every file the hunk touches, including the destination of a move.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        let move_path = match self {
+            Hunk::UpdateFile { move_path, .. } => move_path.as_deref(),
+            _ => None,
+        };
+        std::iter::once(self.path()).chain(move_path)
+    }
 }
 
 use Hunk::*;
@@ -103,7 +156,11 @@ pub struct UpdateFileChunk {
     pub is_end_of_file: bool,
 }
 
+/// Parses either a `*** Begin Patch` patch or a unified diff into hunks.
 pub fn parse_patch(patch: &str) -> Result<ApplyPatchArgs, ParseError> {
+    if unified_diff::is_unified_diff(patch) {
+        return unified_diff::parse_unified_diff(patch);
+    }
     let mode = if PARSE_IN_STRICT_MODE {
         ParseMode::Strict
     } else {
@@ -243,9 +300,20 @@ fn parse_one_hunk(lines: &[&str], line_number: usize) -> Result<(Hunk, usize), P
     let first_line = lines[0].trim();
     if let Some(path) = first_line.strip_prefix(ADD_FILE_MARKER) {
         // Add File
+        if let Some((contents, parsed_lines)) = parse_raw_contents(&lines[1..], line_number + 1)? {
+            return Ok((
+                WriteFile {
+                    path: PathBuf::from(path),
+                    contents,
+                },
+                parsed_lines + 1,
+            ));
+        }
+        let (executable, mode_lines) = parse_file_mode(&lines[1..], line_number + 1)?;
+        let executable = executable.unwrap_or(false);
         let mut contents = String::new();
-        let mut parsed_lines = 1;
-        for add_line in &lines[1..] {
+        let mut parsed_lines = 1 + mode_lines;
+        for add_line in &lines[parsed_lines..] {
             if let Some(line_to_add) = add_line.strip_prefix('+') {
                 contents.push_str(line_to_add);
                 contents.push('\n');
@@ -258,6 +326,7 @@ fn parse_one_hunk(lines: &[&str], line_number: usize) -> Result<(Hunk, usize), P
             AddFile {
                 path: PathBuf::from(path),
                 contents,
+                executable,
             },
             parsed_lines,
         ));
@@ -271,6 +340,15 @@ fn parse_one_hunk(lines: &[&str], line_number: usize) -> Result<(Hunk, usize), P
         ));
     } else if let Some(path) = first_line.strip_prefix(UPDATE_FILE_MARKER) {
         // Update File
+        if let Some((contents, parsed_lines)) = parse_raw_contents(&lines[1..], line_number + 1)? {
+            return Ok((
+                WriteFile {
+                    path: PathBuf::from(path),
+                    contents,
+                },
+                parsed_lines + 1,
+            ));
+        }
         let mut remaining_lines = &lines[1..];
         let mut parsed_lines = 1;
 
@@ -284,6 +362,11 @@ fn parse_one_hunk(lines: &[&str], line_number: usize) -> Result<(Hunk, usize), P
             parsed_lines += 1;
         }
 
+        let (executable, mode_lines) =
+            parse_file_mode(remaining_lines, line_number + parsed_lines)?;
+        remaining_lines = &remaining_lines[mode_lines..];
+        parsed_lines += mode_lines;
+
         let mut chunks = Vec::new();
         // NOTE: we need to know to stop once we reach the next special marker header.
         while !remaining_lines.is_empty() {
@@ -308,7 +391,7 @@ fn parse_one_hunk(lines: &[&str], line_number: usize) -> Result<(Hunk, usize), P
             remaining_lines = &remaining_lines[chunk_lines..]
         }
 
-        if chunks.is_empty() {
+        if chunks.is_empty() && move_path.is_none() && executable.is_none() {
             return Err(InvalidHunkError {
                 message: format!("Update file hunk for path '{path}' is empty"),
                 line_number,
@@ -319,6 +402,7 @@ fn parse_one_hunk(lines: &[&str], line_number: usize) -> Result<(Hunk, usize), P
             UpdateFile {
                 path: PathBuf::from(path),
                 move_path: move_path.map(PathBuf::from),
+                executable,
                 chunks,
             },
             parsed_lines,
@@ -333,6 +417,74 @@ fn parse_one_hunk(lines: &[&str], line_number: usize) -> Result<(Hunk, usize), P
     })
 }
 
+/// Parses an optional `*** File Mode:` line, returning whether it makes the
+/// file executable and the number of lines parsed.
+fn parse_file_mode(
+    lines: &[&str],
+    line_number: usize,
+) -> Result<(Option<bool>, usize), ParseError> {
+    let Some(mode) = lines
+        .first()
+        .and_then(|line| line.trim().strip_prefix(FILE_MODE_MARKER))
+    else {
+        return Ok((None, 0));
+    };
+    match mode.trim() {
+        REGULAR_FILE_MODE => Ok((Some(false), 1)),
+        EXECUTABLE_FILE_MODE => Ok((Some(true), 1)),
+        other => Err(InvalidHunkError {
+            message: format!(
+                "Unsupported file mode '{other}'. Valid modes: '{REGULAR_FILE_MODE}', '{EXECUTABLE_FILE_MODE}'"
+            ),
+            line_number,
+        }),
+    }
+}
+
+/// Parses the symlink target or binary contents that may follow an Add or
+/// Update header, returning `None` when the hunk has text contents instead.
+fn parse_raw_contents(
+    lines: &[&str],
+    line_number: usize,
+) -> Result<Option<(RawContents, usize)>, ParseError> {
+    if let Some(target) = lines
+        .first()
+        .and_then(|line| line.trim().strip_prefix(SYMLINK_TO_MARKER))
+    {
+        return Ok(Some((
+            RawContents::Symlink {
+                target: PathBuf::from(target),
+            },
+            1,
+        )));
+    }
+
+    let (executable, mode_lines) = parse_file_mode(lines, line_number)?;
+    if lines.get(mode_lines).map(|line| line.trim()) != Some(BINARY_CONTENTS_MARKER) {
+        return Ok(None);
+    }
+    let mut parsed_lines = mode_lines + 1;
+    let mut encoded = String::new();
+    for line in &lines[parsed_lines..] {
+        if line.starts_with("***") {
+            break;
+        }
+        encoded.push_str(line.trim());
+        parsed_lines += 1;
+    }
+    let data = BASE64.decode(&encoded).map_err(|err| InvalidHunkError {
+        message: format!("Binary contents are not valid base64: {err}"),
+        line_number: line_number + mode_lines,
+    })?;
+    Ok(Some((
+        RawContents::Binary {
+            data,
+            executable: executable.unwrap_or(false),
+        },
+        parsed_lines,
+    )))
+}
+
 fn parse_update_file_chunk(
     lines: &[&str],
     line_number: usize,
@@ -482,7 +634,8 @@ fn test_parse_patch() {
         vec![
             AddFile {
                 path: PathBuf::from("path/add.py"),
-                contents: "abc\ndef\n".to_string()
+                contents: "abc\ndef\n".to_string(),
+                executable: false,
             },
             DeleteFile {
                 path: PathBuf::from("path/delete.py")
@@ -490,6 +643,7 @@ fn test_parse_patch() {
             UpdateFile {
                 path: PathBuf::from("path/update.py"),
                 move_path: Some(PathBuf::from("path/update2.py")),
+                executable: None,
                 chunks: vec![UpdateFileChunk {
                     change_context: Some("def f():".to_string()),
                     old_lines: vec!["    pass".to_string()],
@@ -517,6 +671,7 @@ fn test_parse_patch() {
             UpdateFile {
                 path: PathBuf::from("file.py"),
                 move_path: None,
+                executable: None,
                 chunks: vec![UpdateFileChunk {
                     change_context: None,
                     old_lines: vec![],
@@ -526,7 +681,8 @@ fn test_parse_patch() {
             },
             AddFile {
                 path: PathBuf::from("other.py"),
-                contents: "content\n".to_string()
+                contents: "content\n".to_string(),
+                executable: false,
             }
         ]
     );
@@ -547,6 +703,7 @@ fn test_parse_patch() {
         vec![UpdateFile {
             path: PathBuf::from("file2.py"),
             move_path: None,
+            executable: None,
             chunks: vec![UpdateFileChunk {
                 change_context: None,
                 old_lines: vec!["import foo".to_string()],
@@ -567,6 +724,7 @@ fn test_parse_patch_lenient() {
     let expected_patch = vec![UpdateFile {
         path: PathBuf::from("file2.py"),
         move_path: None,
+        executable: None,
         chunks: vec![UpdateFileChunk {
             change_context: None,
             old_lines: vec!["import foo".to_string()],
@@ -653,6 +811,69 @@ fn test_parse_one_hunk() {
     // Other edge cases are already covered by tests above/below.
 }
 
+#[test]
+fn test_parse_file_modes_symlinks_and_binary_contents() {
+    assert_eq!(
+        parse_patch_text(
+            "*** Begin Patch\n\
+             *** Add File: run.sh\n\
+             *** File Mode: 100755\n\
+             +echo hi\n\
+             *** Update File: tool.sh\n\
+             *** File Mode: 100644\n\
+             *** Add File: logo.png\n\
+             *** Binary Contents\n\
+             iVBO\n\
+             Rw==\n\
+             *** Add File: latest\n\
+             *** Symlink To: releases/v2\n\
+             *** End Patch",
+            ParseMode::Strict
+        )
+        .unwrap()
+        .hunks,
+        vec![
+            AddFile {
+                path: PathBuf::from("run.sh"),
+                contents: "echo hi\n".to_string(),
+                executable: true,
+            },
+            UpdateFile {
+                path: PathBuf::from("tool.sh"),
+                move_path: None,
+                executable: Some(false),
+                chunks: Vec::new(),
+            },
+            WriteFile {
+                path: PathBuf::from("logo.png"),
+                contents: RawContents::Binary {
+                    data: vec![0x89, b'P', b'N', b'G'],
+                    executable: false,
+                },
+            },
+            WriteFile {
+                path: PathBuf::from("latest"),
+                contents: RawContents::Symlink {
+                    target: PathBuf::from("releases/v2"),
+                },
+            },
+        ]
+    );
+    assert_eq!(
+        parse_patch_text(
+            "*** Begin Patch\n\
+             *** Update File: tool.sh\n\
+             *** File Mode: 100600\n\
+             *** End Patch",
+            ParseMode::Strict
+        ),
+        Err(InvalidHunkError {
+            message: "Unsupported file mode '100600'. Valid modes: '100644', '100755'".to_string(),
+            line_number: 3,
+        })
+    );
+}
+
 #[test]
 fn test_update_file_chunk() {
     assert_eq!(
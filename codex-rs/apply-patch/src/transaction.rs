@@ -0,0 +1,488 @@
+//! Applies the changes a patch makes to several files as a unit.
+//!
+//! Every hunk is first applied in memory, so a hunk that does not match the
+//! file leaves the disk untouched. The resulting contents are then written to
+//! temporary files next to their targets and renamed into place one file at a
+//! time; if any write fails, the files already written are restored from the
+//! copies taken before they were replaced.
+
+use std::collections::HashMap;
+use std::fs::Permissions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use tempfile::NamedTempFile;
+
+/// Starts the line of stderr that lists the outcome of every file as JSON,
+/// written when [`crate::ApplyPatchOptions::report_file_results`] is set.
+const FILE_RESULTS_PREFIX: &str = "apply_patch file results: ";
+
+/// What happened to one of the files a patch touches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileOutcome {
+    /// The file was changed as the patch describes.
+    Applied,
+    /// The change to this file is what made the patch fail.
+    Failed,
+    /// The patch failed before this file was written.
+    NotApplied,
+    /// The file was written, then put back the way it was.
+    RolledBack,
+    /// The file was written, and putting it back the way it was failed.
+    RestoreFailed,
+}
+
+impl FileOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            FileOutcome::Applied => "applied",
+            FileOutcome::Failed => "failed",
+            FileOutcome::NotApplied => "not applied",
+            FileOutcome::RolledBack => "rolled back",
+            FileOutcome::RestoreFailed => "restore failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileResult {
+    pub path: PathBuf,
+    pub outcome: FileOutcome,
+}
+
+/// Why a patch was not applied, and what happened to each file it touches.
+#[derive(Debug)]
+pub(crate) struct PatchFailure {
+    pub error: anyhow::Error,
+    pub files: Vec<FileResult>,
+}
+
+/// What a file will be once the patch is applied.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NewFile {
+    /// A regular file. `executable: None` keeps the file's current mode.
+    Contents {
+        data: Vec<u8>,
+        executable: Option<bool>,
+    },
+    Symlink {
+        target: PathBuf,
+    },
+}
+
+/// The state every file touched by a patch will have once it is applied.
+/// `None` means the file is deleted.
+#[derive(Debug, Default)]
+pub(crate) struct Transaction {
+    writes: Vec<(PathBuf, Option<NewFile>)>,
+    index: HashMap<PathBuf, usize>,
+}
+
+impl Transaction {
+    fn planned(&self, path: &Path) -> Option<&Option<NewFile>> {
+        self.index.get(path).map(|&i| &self.writes[i].1)
+    }
+
+    /// The text of `path` as of the changes planned so far.
+    pub fn read(&self, path: &Path) -> std::io::Result<String> {
+        match self.planned(path) {
+            Some(Some(NewFile::Contents { data, .. })) => String::from_utf8(data.clone())
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+            Some(Some(NewFile::Symlink { .. })) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the patch turns it into a symlink",
+            )),
+            Some(None) => Err(std::io::ErrorKind::NotFound.into()),
+            None => std::fs::read_to_string(path),
+        }
+    }
+
+    /// Whether `path` exists as of the changes planned so far.
+    pub fn exists(&self, path: &Path) -> bool {
+        match self.planned(path) {
+            Some(file) => file.is_some(),
+            None => std::fs::symlink_metadata(path).is_ok(),
+        }
+    }
+
+    pub fn write(&mut self, path: &Path, mut file: NewFile) {
+        // A later hunk that leaves the mode alone keeps the one an earlier
+        // hunk set.
+        if let NewFile::Contents {
+            executable: executable @ None,
+            ..
+        } = &mut file
+            && let Some(Some(NewFile::Contents {
+                executable: planned,
+                ..
+            })) = self.planned(path)
+        {
+            *executable = *planned;
+        }
+        self.set(path, Some(file));
+    }
+
+    pub fn delete(&mut self, path: &Path) -> std::io::Result<()> {
+        let exists = match self.planned(path) {
+            Some(file) => file.is_some(),
+            None => {
+                let metadata = std::fs::symlink_metadata(path)?;
+                metadata.is_file() || metadata.file_type().is_symlink()
+            }
+        };
+        if !exists {
+            return Err(std::io::Error::other("not a file"));
+        }
+        self.set(path, None);
+        Ok(())
+    }
+
+    /// Deletes `path`, which the caller knows to be a file.
+    pub fn remove(&mut self, path: &Path) {
+        self.set(path, None);
+    }
+
+    fn set(&mut self, path: &Path, file: Option<NewFile>) {
+        match self.index.get(path) {
+            Some(&i) => self.writes[i].1 = file,
+            None => {
+                self.index.insert(path.to_path_buf(), self.writes.len());
+                self.writes.push((path.to_path_buf(), file));
+            }
+        }
+    }
+
+    /// Writes the planned contents to disk. If a write fails, every file
+    /// written before it is restored.
+    pub fn commit(self) -> Result<(), PatchFailure> {
+        let mut written: Vec<Original> = Vec::new();
+        for (i, (path, file)) in self.writes.iter().enumerate() {
+            let result = match file {
+                Some(NewFile::Contents { data, executable }) => Original::capture(path, true)
+                    .and_then(|mut original| {
+                        original.write(data, *executable)?;
+                        Ok(original)
+                    }),
+                Some(NewFile::Symlink { target }) => {
+                    Original::capture(path, false).and_then(|mut original| {
+                        original.symlink(target)?;
+                        Ok(original)
+                    })
+                }
+                None => Original::capture(path, false).and_then(|original| {
+                    std::fs::remove_file(path)
+                        .with_context(|| format!("Failed to delete file {}", path.display()))?;
+                    Ok(original)
+                }),
+            };
+            match result {
+                Ok(original) => written.push(original),
+                Err(error) => return Err(roll_back(written, &self.writes[i..], error)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Restores the files written so far, newest first so that directories
+/// created for them can be removed again.
+fn roll_back(
+    written: Vec<Original>,
+    remaining: &[(PathBuf, Option<NewFile>)],
+    error: anyhow::Error,
+) -> PatchFailure {
+    let mut files: Vec<FileResult> = written
+        .into_iter()
+        .rev()
+        .map(|original| FileResult {
+            outcome: if original.restore().is_ok() {
+                FileOutcome::RolledBack
+            } else {
+                FileOutcome::RestoreFailed
+            },
+            path: original.path,
+        })
+        .collect();
+    files.reverse();
+    for (i, (path, _)) in remaining.iter().enumerate() {
+        files.push(FileResult {
+            path: path.clone(),
+            outcome: if i == 0 {
+                FileOutcome::Failed
+            } else {
+                FileOutcome::NotApplied
+            },
+        });
+    }
+    PatchFailure { error, files }
+}
+
+/// A file as it was before the patch replaced or deleted it.
+struct Original {
+    /// The path named in the patch.
+    path: PathBuf,
+    /// The file actually written: `path`, or the file it links to.
+    target: PathBuf,
+    /// Contents and permissions, if the file existed.
+    contents: Option<(Vec<u8>, Permissions)>,
+    /// Where `target` pointed, if it was a symlink that the patch replaces.
+    link: Option<PathBuf>,
+    /// Directories created to hold the file, deepest first.
+    created_dirs: Vec<PathBuf>,
+}
+
+impl Original {
+    fn capture(path: &Path, follow_symlinks: bool) -> anyhow::Result<Self> {
+        // Write through symlinks rather than replacing them with a file.
+        let target = if follow_symlinks {
+            std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        } else {
+            path.to_path_buf()
+        };
+        let mut original = Self {
+            path: path.to_path_buf(),
+            target,
+            contents: None,
+            link: None,
+            created_dirs: Vec::new(),
+        };
+        match std::fs::symlink_metadata(&original.target) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                original.link = Some(
+                    std::fs::read_link(&original.target)
+                        .with_context(|| format!("Failed to read symlink {}", path.display()))?,
+                );
+            }
+            Ok(metadata) => {
+                original.contents = Some((
+                    std::fs::read(&original.target)
+                        .with_context(|| format!("Failed to read file {}", path.display()))?,
+                    metadata.permissions(),
+                ));
+            }
+            Err(_) => {}
+        }
+        Ok(original)
+    }
+
+    fn write(&mut self, contents: &[u8], executable: Option<bool>) -> anyhow::Result<()> {
+        // Renaming over a read-only file would succeed where writing to it
+        // does not.
+        if let Some((_, permissions)) = &self.contents
+            && permissions.readonly()
+        {
+            return Err(anyhow::Error::new(std::io::Error::from(
+                std::io::ErrorKind::PermissionDenied,
+            ))
+            .context(format!("Failed to write file {}", self.path.display())));
+        }
+        self.record_created_dirs();
+        let permissions = new_permissions(
+            self.contents.as_ref().map(|(_, permissions)| permissions),
+            executable,
+        );
+        let result = replace_file(&self.target, contents, permissions)
+            .with_context(|| format!("Failed to write file {}", self.path.display()));
+        if result.is_err() {
+            self.remove_created_dirs();
+        }
+        result
+    }
+
+    fn symlink(&mut self, target: &Path) -> anyhow::Result<()> {
+        self.record_created_dirs();
+        let result = replace_with_symlink(&self.target, target)
+            .with_context(|| format!("Failed to create symlink {}", self.path.display()));
+        if result.is_err() {
+            self.remove_created_dirs();
+        }
+        result
+    }
+
+    fn record_created_dirs(&mut self) {
+        let mut dir = self.target.parent();
+        while let Some(parent) = dir
+            && !parent.as_os_str().is_empty()
+            && !parent.exists()
+        {
+            self.created_dirs.push(parent.to_path_buf());
+            dir = parent.parent();
+        }
+    }
+
+    fn restore(&self) -> std::io::Result<()> {
+        match (&self.contents, &self.link) {
+            (Some((contents, permissions)), _) => {
+                replace_file(&self.target, contents, Some(permissions.clone()))?;
+            }
+            (None, Some(link)) => replace_with_symlink(&self.target, link)?,
+            (None, None) => {
+                std::fs::remove_file(&self.target)?;
+                self.remove_created_dirs();
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_created_dirs(&self) {
+        for dir in &self.created_dirs {
+            if std::fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// The permissions for a file written by the patch: those of the file it
+/// replaces, or the usual ones for a new file, with the executable bit set or
+/// cleared as the patch asks.
+#[cfg(unix)]
+fn new_permissions(
+    original: Option<&Permissions>,
+    executable: Option<bool>,
+) -> Option<Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = original.map_or(0o644, PermissionsExt::mode);
+    let mode = match executable {
+        // Executable by whoever can read it, like `chmod +x`.
+        Some(true) => mode | ((mode & 0o444) >> 2),
+        Some(false) => mode & !0o111,
+        None => mode,
+    };
+    Some(Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn new_permissions(
+    original: Option<&Permissions>,
+    _executable: Option<bool>,
+) -> Option<Permissions> {
+    original.cloned()
+}
+
+fn temp_dir_for(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Writes `contents` to a temporary file in the same directory as `path`, then
+/// renames it over `path`, creating parent directories as needed.
+fn replace_file(
+    path: &Path,
+    contents: &[u8],
+    permissions: Option<Permissions>,
+) -> std::io::Result<()> {
+    let parent = temp_dir_for(path);
+    std::fs::create_dir_all(parent)?;
+    let mut file = NamedTempFile::new_in(parent)?;
+    file.write_all(contents)?;
+    if let Some(permissions) = permissions {
+        file.as_file().set_permissions(permissions)?;
+    }
+    file.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+/// Creates a symlink to `target` next to `path`, then renames it over `path`.
+#[cfg(unix)]
+fn replace_with_symlink(path: &Path, target: &Path) -> std::io::Result<()> {
+    let parent = temp_dir_for(path);
+    std::fs::create_dir_all(parent)?;
+    let link = tempfile::Builder::new()
+        .make_in(parent, |link| std::os::unix::fs::symlink(target, link))?;
+    link.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn replace_with_symlink(_path: &Path, _target: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "symlinks are only supported on Unix",
+    ))
+}
+
+/// Prints the outcome of every file a failed patch touches.
+pub(crate) fn print_file_results(
+    files: &[FileResult],
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    if files
+        .iter()
+        .any(|file| file.outcome == FileOutcome::RestoreFailed)
+    {
+        writeln!(
+            out,
+            "Some files could not be restored, so the patch is partially applied:"
+        )?;
+    } else {
+        writeln!(out, "No files were changed:")?;
+    }
+    for file in files {
+        writeln!(out, "{}: {}", file.outcome.label(), file.path.display())?;
+    }
+    Ok(())
+}
+
+/// Writes the outcome of every file as a single JSON line.
+pub(crate) fn write_file_results(
+    files: &[FileResult],
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let json = serde_json::to_string(files).map_err(std::io::Error::other)?;
+    writeln!(out, "{FILE_RESULTS_PREFIX}{json}")
+}
+
+/// Removes the line written by [`write_file_results`] from the stderr of an
+/// `apply_patch` process and returns the results it lists, or none when
+/// the process did not write one.
+pub fn take_file_results(stderr: &mut String) -> Vec<FileResult> {
+    let Some(start) = stderr
+        .match_indices(FILE_RESULTS_PREFIX)
+        .map(|(start, _)| start)
+        .find(|start| *start == 0 || stderr[..*start].ends_with('\n'))
+    else {
+        return Vec::new();
+    };
+    let end = stderr[start..]
+        .find('\n')
+        .map_or(stderr.len(), |newline| start + newline + 1);
+    let files = serde_json::from_str(stderr[start + FILE_RESULTS_PREFIX.len()..end].trim_end())
+        .unwrap_or_default();
+    stderr.replace_range(start..end, "");
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn file_results_round_trip_through_stderr() {
+        let files = vec![
+            FileResult {
+                path: PathBuf::from("new file.txt"),
+                outcome: FileOutcome::RolledBack,
+            },
+            FileResult {
+                path: PathBuf::from("src/lib.rs"),
+                outcome: FileOutcome::Failed,
+            },
+        ];
+        let mut stderr = b"Failed to find expected lines\n".to_vec();
+        write_file_results(&files, &mut stderr).unwrap();
+        let mut stderr = String::from_utf8(stderr).unwrap();
+
+        assert_eq!(take_file_results(&mut stderr), files);
+        assert_eq!(stderr, "Failed to find expected lines\n");
+        assert_eq!(take_file_results(&mut stderr), Vec::new());
+    }
+}
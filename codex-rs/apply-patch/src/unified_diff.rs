@@ -0,0 +1,657 @@
+//! Front end for standard unified diffs, as produced by `diff -u` and
+//! `git diff`, that yields the same [`Hunk`]s as the `*** Begin Patch`
+//! grammar in [`crate::parser`].
+//!
+//! Supported:
+//!
+//! - `---`/`+++` file headers, with or without git's `a/` and `b/` prefixes
+//!   and with optional timestamps after a tab.
+//! - `@@ -a,b +c,d @@` hunks. The line counts are not trusted, because models
+//!   routinely get them wrong; a hunk ends at the next hunk or file header.
+//! - New files (`--- /dev/null`) and deleted files (`+++ /dev/null`),
+//!   including git's `new file mode`/`deleted file mode` for empty files.
+//! - Renames via git's `rename from`/`rename to` headers.
+//! - git's file modes: `new file mode 100755` and `old mode`/`new mode` set
+//!   the executable bit, and a new file with mode `120000` is a symlink.
+//! - `\ No newline at end of file`. New files honor it; like the rest of
+//!   `apply_patch`, updated files always end with a newline.
+//!
+//! Each hunk becomes one [`UpdateFileChunk`] whose `old_lines` and
+//! `new_lines` are located in the file by content, not by line number, so
+//! hunks that add lines must carry at least one line of context.
+
+use std::path::PathBuf;
+
+use crate::ApplyPatchArgs;
+use crate::parser::Hunk;
+use crate::parser::ParseError;
+use crate::parser::ParseError::*;
+use crate::parser::RawContents;
+use crate::parser::UpdateFileChunk;
+
+const DEV_NULL: &str = "/dev/null";
+const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+const EXECUTABLE_MODE: &str = "100755";
+const SYMLINK_MODE: &str = "120000";
+
+/// Whether `patch` looks like a unified diff rather than a `*** Begin Patch`
+/// patch.
+pub(crate) fn is_unified_diff(patch: &str) -> bool {
+    let lines = strip_heredoc(patch.trim().lines().collect());
+    if lines
+        .first()
+        .is_some_and(|line| line.trim() == "*** Begin Patch")
+    {
+        return false;
+    }
+    lines.iter().enumerate().any(|(i, line)| {
+        line.starts_with("diff --git ")
+            || (line.starts_with("--- ")
+                && lines
+                    .get(i + 1)
+                    .is_some_and(|next| next.starts_with("+++ ")))
+    })
+}
+
+pub(crate) fn parse_unified_diff(patch: &str) -> Result<ApplyPatchArgs, ParseError> {
+    let lines = strip_heredoc(patch.trim().lines().collect());
+    let mut hunks = Vec::new();
+    let mut file: Option<FileDiff> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let line_number = i + 1;
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            finish_file(file.take(), &mut hunks, line_number)?;
+            file = Some(FileDiff::from_git_header(rest));
+            i += 1;
+        } else if line.starts_with("--- ")
+            && let Some(next) = lines.get(i + 1)
+            && let Some(new_header) = next.strip_prefix("+++ ")
+        {
+            // A plain `diff -u` starts a new file with its `---` header; in
+            // git's output the `---` header follows `diff --git`.
+            let continues_git_file = file
+                .as_ref()
+                .is_some_and(|file| file.git && file.chunks.is_empty() && !file.has_headers);
+            if !continues_git_file {
+                finish_file(file.take(), &mut hunks, line_number)?;
+            }
+            let file = file.get_or_insert_with(FileDiff::default);
+            (file.old_path, file.new_path) = parse_header_paths(&line[4..], new_header, file.git);
+            file.has_headers = true;
+            i += 2;
+        } else if line.starts_with("@@") {
+            let Some(file) = file.as_mut().filter(|file| file.has_headers) else {
+                return Err(InvalidHunkError {
+                    message: format!(
+                        "hunk header '{line}' is not preceded by ---/+++ file headers"
+                    ),
+                    line_number,
+                });
+            };
+            let hunk_lines = parse_hunk(&lines[i + 1..], file, line_number)?;
+            i += 1 + hunk_lines;
+        } else {
+            if let Some(file) = file.as_mut() {
+                file.parse_extended_header(line, line_number)?;
+            }
+            // Anything else outside a hunk, such as `index` lines or text
+            // before the first file, carries no changes.
+            i += 1;
+        }
+    }
+    finish_file(file, &mut hunks, lines.len())?;
+
+    if hunks.is_empty() {
+        return Err(InvalidPatchError(
+            "The unified diff does not change any files".to_string(),
+        ));
+    }
+    Ok(ApplyPatchArgs {
+        hunks,
+        patch: lines.join("\n"),
+    })
+}
+
+#[derive(Default)]
+struct FileDiff {
+    /// Started by a `diff --git` header, so paths carry `a/` and `b/`.
+    git: bool,
+    /// Whether the `---`/`+++` headers have been seen.
+    has_headers: bool,
+    /// Paths from `---`/`+++`, `None` for `/dev/null`.
+    old_path: Option<String>,
+    new_path: Option<String>,
+    /// Paths from the `diff --git` header, used when a git diff has no
+    /// `---`/`+++` headers because no lines changed.
+    git_old_path: Option<String>,
+    git_new_path: Option<String>,
+    rename_from: Option<String>,
+    rename_to: Option<String>,
+    new_file: bool,
+    deleted_file: bool,
+    /// Mode from `new file mode` or `new mode`.
+    new_mode: Option<String>,
+    chunks: Vec<UpdateFileChunk>,
+    /// Whether the last line on the new side lacks a trailing newline.
+    new_missing_newline: bool,
+}
+
+impl FileDiff {
+    fn from_git_header(rest: &str) -> Self {
+        let (git_old_path, git_new_path) = split_git_header_paths(rest);
+        Self {
+            git: true,
+            git_old_path,
+            git_new_path,
+            ..Default::default()
+        }
+    }
+
+    fn parse_extended_header(&mut self, line: &str, line_number: usize) -> Result<(), ParseError> {
+        if !self.git {
+            return Ok(());
+        }
+        if let Some(path) = line.strip_prefix("rename from ") {
+            self.rename_from = Some(unquote(path));
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            self.rename_to = Some(unquote(path));
+        } else if let Some(mode) = line.strip_prefix("new file mode ") {
+            self.new_file = true;
+            self.new_mode = Some(mode.trim().to_string());
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            self.new_mode = Some(mode.trim().to_string());
+        } else if line.starts_with("deleted file mode ") {
+            self.deleted_file = true;
+        } else if line.starts_with("copy from ") || line.starts_with("copy to ") {
+            return Err(InvalidHunkError {
+                message: "copies are not supported; add the new file instead".to_string(),
+                line_number,
+            });
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            return Err(InvalidHunkError {
+                message: "binary diffs are not supported; use '*** Binary Contents' in a '*** Begin Patch' patch instead"
+                    .to_string(),
+                line_number,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn finish_file(
+    file: Option<FileDiff>,
+    hunks: &mut Vec<Hunk>,
+    line_number: usize,
+) -> Result<(), ParseError> {
+    let Some(file) = file else {
+        return Ok(());
+    };
+
+    let old_path = if file.has_headers {
+        file.old_path.clone()
+    } else if file.new_file {
+        None
+    } else {
+        file.rename_from.clone().or(file.git_old_path.clone())
+    };
+    let new_path = if file.has_headers {
+        file.new_path.clone()
+    } else if file.deleted_file {
+        None
+    } else {
+        file.rename_to.clone().or(file.git_new_path.clone())
+    };
+
+    match (old_path, new_path) {
+        (None, Some(path)) => {
+            let mut contents = String::new();
+            for chunk in &file.chunks {
+                for line in &chunk.new_lines {
+                    contents.push_str(line);
+                    contents.push('\n');
+                }
+            }
+            if file.new_missing_newline {
+                contents.pop();
+            }
+            if file.new_mode.as_deref() == Some(SYMLINK_MODE) {
+                hunks.push(Hunk::WriteFile {
+                    path: PathBuf::from(path),
+                    contents: RawContents::Symlink {
+                        target: PathBuf::from(contents.trim_end_matches('\n')),
+                    },
+                });
+            } else {
+                hunks.push(Hunk::AddFile {
+                    path: PathBuf::from(path),
+                    contents,
+                    executable: file.new_mode.as_deref() == Some(EXECUTABLE_MODE),
+                });
+            }
+        }
+        (Some(path), None) => hunks.push(Hunk::DeleteFile {
+            path: PathBuf::from(path),
+        }),
+        (Some(old_path), Some(new_path)) => {
+            let old_path = file.rename_from.unwrap_or(old_path);
+            let new_path = file.rename_to.unwrap_or(new_path);
+            let move_path = (old_path != new_path).then(|| PathBuf::from(new_path));
+            if file.new_mode.as_deref() == Some(SYMLINK_MODE) {
+                return Err(InvalidHunkError {
+                    message:
+                        "changes to symlinks are not supported; use '*** Symlink To: ' instead"
+                            .to_string(),
+                    line_number,
+                });
+            }
+            let executable = file.new_mode.as_deref().map(|mode| mode == EXECUTABLE_MODE);
+            if file.chunks.is_empty() && move_path.is_none() && executable.is_none() {
+                return Ok(());
+            }
+            hunks.push(Hunk::UpdateFile {
+                path: PathBuf::from(old_path),
+                move_path,
+                executable,
+                chunks: file.chunks,
+            });
+        }
+        (None, None) => {
+            return Err(InvalidHunkError {
+                message: "file diff has no path on either side".to_string(),
+                line_number,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Parses the lines of one hunk, after its `@@` header, into a chunk of
+/// `file`. Returns the number of lines consumed.
+fn parse_hunk(
+    lines: &[&str],
+    file: &mut FileDiff,
+    line_number: usize,
+) -> Result<usize, ParseError> {
+    let mut chunk = UpdateFileChunk {
+        change_context: None,
+        old_lines: Vec::new(),
+        new_lines: Vec::new(),
+        is_end_of_file: false,
+    };
+    // Which side the previous line belonged to, for the no-newline marker.
+    let mut last_side = ' ';
+    let mut consumed = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with("@@")
+            || line.starts_with("diff --git ")
+            || (line.starts_with("--- ")
+                && lines
+                    .get(i + 1)
+                    .is_some_and(|next| next.starts_with("+++ ")))
+        {
+            break;
+        }
+        if *line == NO_NEWLINE_MARKER {
+            chunk.is_end_of_file = true;
+            if last_side != '-' {
+                file.new_missing_newline = true;
+            }
+            consumed += 1;
+            continue;
+        }
+        match line.chars().next() {
+            // Some tools and most models drop the space of blank context lines.
+            None => {
+                chunk.old_lines.push(String::new());
+                chunk.new_lines.push(String::new());
+                last_side = ' ';
+            }
+            Some(' ') => {
+                chunk.old_lines.push(line[1..].to_string());
+                chunk.new_lines.push(line[1..].to_string());
+                last_side = ' ';
+            }
+            Some('-') => {
+                chunk.old_lines.push(line[1..].to_string());
+                last_side = '-';
+            }
+            Some('+') => {
+                chunk.new_lines.push(line[1..].to_string());
+                last_side = '+';
+                file.new_missing_newline = false;
+            }
+            _ => break,
+        }
+        consumed += 1;
+    }
+
+    // Blank lines that separate one file's diff from the next are not part
+    // of the hunk.
+    let mut hunk_len = consumed;
+    while hunk_len > 0 && lines[hunk_len - 1].is_empty() {
+        hunk_len -= 1;
+        chunk.old_lines.pop();
+        chunk.new_lines.pop();
+    }
+
+    if chunk.old_lines.is_empty() && chunk.new_lines.is_empty() {
+        return Err(InvalidHunkError {
+            message: "hunk does not contain any lines".to_string(),
+            line_number,
+        });
+    }
+    let adds_to_existing_file = file.old_path.is_some() && file.new_path.is_some();
+    if adds_to_existing_file && chunk.old_lines.is_empty() {
+        return Err(InvalidHunkError {
+            message: "hunk adds lines without any context; include at least one unchanged line around the added lines so they can be placed".to_string(),
+            line_number,
+        });
+    }
+    file.chunks.push(chunk);
+    Ok(consumed)
+}
+
+/// Extracts the paths from the `---` and `+++` headers, dropping trailing
+/// timestamps. `None` stands for `/dev/null`. Git's `a/` and `b/` prefixes
+/// are dropped in git diffs, and in other diffs when every path has them.
+fn parse_header_paths(old: &str, new: &str, git: bool) -> (Option<String>, Option<String>) {
+    let path = |header: &str| {
+        let path = unquote(header.split('\t').next().unwrap_or(header).trim_end());
+        (path != DEV_NULL).then_some(path)
+    };
+    let (old, new) = (path(old), path(new));
+    let strip = git
+        || (old.as_ref().is_none_or(|old| old.starts_with("a/"))
+            && new.as_ref().is_none_or(|new| new.starts_with("b/")));
+    if !strip {
+        return (old, new);
+    }
+    let strip_prefix = |path: Option<String>, prefix: &str| {
+        path.map(|path| match path.strip_prefix(prefix) {
+            Some(stripped) => stripped.to_string(),
+            None => path,
+        })
+    };
+    (strip_prefix(old, "a/"), strip_prefix(new, "b/"))
+}
+
+/// Splits `a/<old> b/<new>` from a `diff --git` header. Paths containing
+/// spaces are only recovered when both sides are the same.
+fn split_git_header_paths(rest: &str) -> (Option<String>, Option<String>) {
+    let rest = rest.trim_end();
+    if rest.len() >= 5 && rest.len() % 2 == 1 {
+        let half = (rest.len() - 1) / 2;
+        if let (Some(old), Some(new)) = (rest.get(..half), rest.get(half + 1..))
+            && let (Some(old), Some(new)) = (old.strip_prefix("a/"), new.strip_prefix("b/"))
+            && old == new
+        {
+            return (Some(old.to_string()), Some(new.to_string()));
+        }
+    }
+    match rest.split_once(" b/") {
+        Some((old, new)) => (
+            old.strip_prefix("a/").map(str::to_string),
+            Some(new.to_string()),
+        ),
+        None => (None, None),
+    }
+}
+
+/// Removes the double quotes git puts around paths with unusual characters.
+fn unquote(path: &str) -> String {
+    let Some(inner) = path
+        .strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+    else {
+        return path.to_string();
+    };
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unquoted.push('\t'),
+            Some('n') => unquoted.push('\n'),
+            Some(other) => unquoted.push(other),
+            None => unquoted.push('\\'),
+        }
+    }
+    unquoted
+}
+
+/// Drops a `<<EOF` ... `EOF` heredoc wrapper around the diff, which models
+/// sometimes pass verbatim (see `ParseMode::Lenient` in the parser).
+fn strip_heredoc(lines: Vec<&str>) -> Vec<&str> {
+    match lines.as_slice() {
+        [first, inner @ .., last]
+            if (*first == "<<EOF" || *first == "<<'EOF'" || *first == "<<\"EOF\"")
+                && last.ends_with("EOF") =>
+        {
+            inner.to_vec()
+        }
+        _ => lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn parse(patch: &str) -> Vec<Hunk> {
+        parse_unified_diff(patch).unwrap().hunks
+    }
+
+    fn strings(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_plain_unified_diff() {
+        let patch = "\
+--- src/lib.rs\t2025-01-01 00:00:00
++++ src/lib.rs\t2025-01-02 00:00:00
+@@ -1,3 +1,3 @@
+ fn main() {
+-    println!(\"hi\");
++    println!(\"hello\");
+ }
+@@ -10,2 +10,3 @@ fn other() {
+ a
+
++b
+";
+        assert_eq!(
+            parse(patch),
+            vec![Hunk::UpdateFile {
+                path: PathBuf::from("src/lib.rs"),
+                move_path: None,
+                executable: None,
+                chunks: vec![
+                    UpdateFileChunk {
+                        change_context: None,
+                        old_lines: strings(&["fn main() {", "    println!(\"hi\");", "}"]),
+                        new_lines: strings(&["fn main() {", "    println!(\"hello\");", "}"]),
+                        is_end_of_file: false,
+                    },
+                    UpdateFileChunk {
+                        change_context: None,
+                        old_lines: strings(&["a", ""]),
+                        new_lines: strings(&["a", "", "b"]),
+                        is_end_of_file: false,
+                    },
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_git_diff_with_new_deleted_and_renamed_files() {
+        let patch = "\
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..3b18e51
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++hello
++world
+\\ No newline at end of file
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+index 3b18e51..0000000
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+diff --git a/empty.txt b/empty.txt
+new file mode 100644
+index 0000000..e69de29
+diff --git a/src/a.rs b/src/b.rs
+similarity index 90%
+rename from src/a.rs
+rename to src/b.rs
+index 1111111..2222222 100644
+--- a/src/a.rs
++++ b/src/b.rs
+@@ -1,2 +1,2 @@
+ use foo;
+-use bar;
++use baz;
+diff --git a/moved.txt b/dir/moved.txt
+similarity index 100%
+rename from moved.txt
+rename to dir/moved.txt
+diff --git a/script.sh b/script.sh
+old mode 100644
+new mode 100755
+";
+        assert_eq!(
+            parse(patch),
+            vec![
+                Hunk::AddFile {
+                    path: PathBuf::from("new.txt"),
+                    contents: "hello\nworld".to_string(),
+                    executable: false,
+                },
+                Hunk::DeleteFile {
+                    path: PathBuf::from("old.txt"),
+                },
+                Hunk::AddFile {
+                    path: PathBuf::from("empty.txt"),
+                    contents: String::new(),
+                    executable: false,
+                },
+                Hunk::UpdateFile {
+                    path: PathBuf::from("src/a.rs"),
+                    move_path: Some(PathBuf::from("src/b.rs")),
+                    executable: None,
+                    chunks: vec![UpdateFileChunk {
+                        change_context: None,
+                        old_lines: strings(&["use foo;", "use bar;"]),
+                        new_lines: strings(&["use foo;", "use baz;"]),
+                        is_end_of_file: false,
+                    }],
+                },
+                Hunk::UpdateFile {
+                    path: PathBuf::from("moved.txt"),
+                    move_path: Some(PathBuf::from("dir/moved.txt")),
+                    executable: None,
+                    chunks: Vec::new(),
+                },
+                Hunk::UpdateFile {
+                    path: PathBuf::from("script.sh"),
+                    move_path: None,
+                    executable: Some(true),
+                    chunks: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn no_newline_marker_anchors_hunk_at_end_of_file() {
+        let patch = "\
+--- a/f.txt
++++ b/f.txt
+@@ -1,2 +1,2 @@
+ one
+-two
+\\ No newline at end of file
++two
+";
+        assert_eq!(
+            parse(patch),
+            vec![Hunk::UpdateFile {
+                path: PathBuf::from("f.txt"),
+                move_path: None,
+                executable: None,
+                chunks: vec![UpdateFileChunk {
+                    change_context: None,
+                    old_lines: strings(&["one", "two"]),
+                    new_lines: strings(&["one", "two"]),
+                    is_end_of_file: true,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn removed_lines_that_look_like_headers_stay_in_the_hunk() {
+        let patch = "\
+--- a/notes.md
++++ b/notes.md
+@@ -1,3 +1,2 @@
+ # Notes
+--- draft
+ end
+";
+        assert_eq!(
+            parse(patch),
+            vec![Hunk::UpdateFile {
+                path: PathBuf::from("notes.md"),
+                move_path: None,
+                executable: None,
+                chunks: vec![UpdateFileChunk {
+                    change_context: None,
+                    old_lines: strings(&["# Notes", "-- draft", "end"]),
+                    new_lines: strings(&["# Notes", "end"]),
+                    is_end_of_file: false,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_diffs() {
+        let no_context = "--- a/f\n+++ b/f\n@@ -3,0 +4 @@\n+new line\n";
+        assert!(matches!(
+            parse_unified_diff(no_context),
+            Err(InvalidHunkError { line_number: 3, .. })
+        ));
+
+        let binary =
+            "diff --git a/img.png b/img.png\nBinary files a/img.png and b/img.png differ\n";
+        assert!(matches!(
+            parse_unified_diff(binary),
+            Err(InvalidHunkError { line_number: 2, .. })
+        ));
+
+        let headerless_hunk = "diff --git a/f b/f\n@@ -1 +1 @@\n-a\n+b\n";
+        assert!(parse_unified_diff(headerless_hunk).is_err());
+    }
+
+    #[test]
+    fn detects_unified_diffs() {
+        assert!(is_unified_diff("--- a/f\n+++ b/f\n@@ -1 +1 @@\n-a\n+b"));
+        assert!(is_unified_diff("<<'EOF'\ndiff --git a/f b/f\nEOF"));
+        assert!(!is_unified_diff(
+            "*** Begin Patch\n*** Update File: f\n@@\n--- a\n+++ b\n*** End Patch"
+        ));
+    }
+}
@@ -3,6 +3,8 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use codex_core::CODEX_APPLY_PATCH_ARG1;
+use codex_core::CODEX_APPLY_PATCH_FUZZY_THRESHOLD_ENV_VAR;
+use codex_core::CODEX_APPLY_PATCH_REPORT_RESULTS_ENV_VAR;
 
 /// While we want to deploy the Codex CLI as a single executable for simplicity,
 /// we also want to expose some of its functionality as distinct CLIs, so we use
@@ -51,7 +53,23 @@ where
             Some(patch_arg) => {
                 let mut stdout = std::io::stdout();
                 let mut stderr = std::io::stderr();
-                match codex_apply_patch::apply_patch(&patch_arg, &mut stdout, &mut stderr) {
+                let mut options = codex_apply_patch::ApplyPatchOptions {
+                    report_file_results: std::env::var_os(CODEX_APPLY_PATCH_REPORT_RESULTS_ENV_VAR)
+                        .is_some(),
+                    ..Default::default()
+                };
+                if let Some(threshold) = std::env::var(CODEX_APPLY_PATCH_FUZZY_THRESHOLD_ENV_VAR)
+                    .ok()
+                    .and_then(|threshold| threshold.parse().ok())
+                {
+                    options.fuzzy_threshold = threshold;
+                }
+                match codex_apply_patch::apply_patch_with_options(
+                    &patch_arg,
+                    &options,
+                    &mut stdout,
+                    &mut stderr,
+                ) {
                     Ok(()) => 0,
                     Err(_) => 1,
                 }
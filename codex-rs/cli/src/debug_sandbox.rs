@@ -5,6 +5,9 @@ use codex_core::config::Config;
 use codex_core::config::ConfigOverrides;
 use codex_core::exec_env::create_env;
 use codex_core::landlock::spawn_command_under_linux_sandbox;
+use codex_core::network_proxy::NetworkProxy;
+use codex_core::resource_limits::exec_cgroup_path;
+use codex_core::resource_limits::remove_exec_cgroup;
 use codex_core::seatbelt::spawn_command_under_seatbelt;
 use codex_core::spawn::StdioPolicy;
 use codex_protocol::config_types::SandboxMode;
@@ -77,11 +80,23 @@ async fn run_command_under_sandbox(
     )?;
     let stdio_policy = StdioPolicy::Inherit;
     let env = create_env(&config.shell_environment_policy);
+    // Denied connections are logged rather than reported as events.
+    let network_proxy = match config.sandbox_policy.get_proxied_domains() {
+        Some(allowed_domains) => Some(NetworkProxy::start(allowed_domains.to_vec(), None).await?),
+        None => None,
+    };
 
     let mut child = match sandbox_type {
         SandboxType::Seatbelt => {
-            spawn_command_under_seatbelt(command, &config.sandbox_policy, cwd, stdio_policy, env)
-                .await?
+            spawn_command_under_seatbelt(
+                command,
+                &config.sandbox_policy,
+                network_proxy.as_ref(),
+                cwd,
+                stdio_policy,
+                env,
+            )
+            .await?
         }
         SandboxType::Landlock => {
             #[expect(clippy::expect_used)]
@@ -92,6 +107,8 @@ async fn run_command_under_sandbox(
                 codex_linux_sandbox_exe,
                 command,
                 &config.sandbox_policy,
+                network_proxy.as_ref(),
+                &config.resource_limits,
                 cwd,
                 stdio_policy,
                 env,
@@ -99,7 +116,14 @@ async fn run_command_under_sandbox(
             .await?
         }
     };
+    let memory_cgroup = match (config.resource_limits.memory_max_mb, &sandbox_type) {
+        (Some(_), SandboxType::Landlock) => child.id().and_then(exec_cgroup_path),
+        _ => None,
+    };
     let status = child.wait().await?;
+    if let Some(memory_cgroup) = memory_cgroup {
+        remove_exec_cgroup(&memory_cgroup);
+    }
 
     handle_exit_status(status);
 }
@@ -149,9 +149,9 @@ async fn cli_main(codex_linux_sandbox_exe: Option<PathBuf>) -> anyhow::Result<()
         None => {
             let mut tui_cli = cli.interactive;
             prepend_config_flags(&mut tui_cli.config_overrides, cli.config_overrides);
-            let usage = codex_tui::run_main(tui_cli, codex_linux_sandbox_exe).await?;
-            if !usage.is_zero() {
-                println!("{}", codex_core::protocol::FinalOutput::from(usage));
+            let output = codex_tui::run_main(tui_cli, codex_linux_sandbox_exe).await?;
+            if !output.token_usage.is_zero() {
+                println!("{output}");
             }
         }
         Some(Subcommand::Exec(mut exec_cli)) => {
@@ -3,16 +3,23 @@ use codex_common::CliConfigOverrides;
 use codex_core::ConversationManager;
 use codex_core::NewConversation;
 use codex_core::agents;
+use codex_core::budget::Spend;
 use codex_core::config::Config;
 use codex_core::config::ConfigOverrides;
 use codex_core::config::{self};
+use codex_core::output_schema::DEFAULT_OUTPUT_SCHEMA_RETRIES;
+use codex_core::output_schema::OutputSchema;
+use codex_core::protocol::BackgroundEventEvent;
 use codex_core::protocol::Event;
 use codex_core::protocol::EventMsg;
 use codex_core::protocol::InputItem;
 use codex_core::protocol::Op;
 use codex_core::protocol::TaskCompleteEvent;
+use codex_core::protocol::TurnAbortReason;
+use codex_core::protocol::TurnAbortedEvent;
 use codex_core::workflows::StepKind;
 use codex_core::workflows::{self};
+use serde_json::Value;
 use std::path::PathBuf;
 use tracing::error;
 
@@ -135,6 +142,11 @@ async fn run_workflow(
         .map_err(|e| anyhow::anyhow!("Error parsing -c overrides: {e}"))?;
     let base_config = Config::load_with_cli_overrides(cli_kv_overrides, overrides)?;
 
+    // Tokens and cost consumed by all steps so far.
+    let mut workflow_spend = Spend::default();
+    // Validated output of the steps that declare an `output_schema`.
+    let mut step_outputs: Vec<(String, Value)> = Vec::new();
+
     // Run each step sequentially as a clean session.
     for (idx, step) in wf.steps.iter().enumerate() {
         println!(
@@ -157,6 +169,7 @@ async fn run_workflow(
             include_plan,
             include_apply,
             mcp_servers,
+            agent_budget,
         ) = match step.kind {
             StepKind::Agent => {
                 let def = agents::load_agent(&project_dir, &step.id, &project_cfg_toml)?;
@@ -171,6 +184,7 @@ async fn run_workflow(
                     def.config.include_plan_tool,
                     def.config.include_apply_patch_tool,
                     def.mcp_servers.clone(),
+                    def.config.budget(),
                 )
             }
             StepKind::Team => {
@@ -201,6 +215,7 @@ async fn run_workflow(
                     agent.config.include_plan_tool,
                     agent.config.include_apply_patch_tool,
                     agent.mcp_servers.clone(),
+                    agent.config.budget(),
                 )
             }
         };
@@ -225,27 +240,61 @@ async fn run_workflow(
         }
         step_config.base_instructions = Some(combined_prompt.clone());
         step_config.mcp_servers = mcp_servers;
+        // A step may spend no more than its agent allows nor more than what
+        // the workflow has left.
+        step_config.budget = step_config
+            .budget
+            .min(agent_budget)
+            .min(wf.budget.remaining(&workflow_spend));
+        step_config.output_schema = step.output_schema.clone();
+        let output_schema = step
+            .output_schema
+            .clone()
+            .map(OutputSchema::new)
+            .transpose()?;
 
         // Run this step as a clean session using a minimal inline runner.
-        run_step_with_config(
+        let output = run_step_with_config(
             step_config,
-            combined_prompt,
+            workflows::prompt_with_step_outputs(&combined_prompt, &step_outputs),
+            output_schema.as_ref(),
             json,
             last_message_file.clone(),
+            &mut workflow_spend,
         )
         .await?;
+        if let Some(value) = output {
+            step_outputs.push((step.id.clone(), value));
+        }
+    }
+
+    if !json {
+        match workflow_spend.cost_usd {
+            Some(cost_usd) => println!(
+                "Workflow '{}' used {} tokens (${cost_usd:.4})",
+                wf.name, workflow_spend.total_tokens
+            ),
+            None => println!(
+                "Workflow '{}' used {} tokens",
+                wf.name, workflow_spend.total_tokens
+            ),
+        }
     }
 
     Ok(())
 }
 
 /// Minimal non-interactive runner for a single step using a pre-built Config.
+/// Usage reported by the step is added to `spend`. Returns the step's final
+/// message as JSON when it has an `output_schema`.
 async fn run_step_with_config(
     config: Config,
     prompt: String,
+    output_schema: Option<&OutputSchema>,
     json_mode: bool,
     last_message_file: Option<PathBuf>,
-) -> anyhow::Result<()> {
+    spend: &mut Spend,
+) -> anyhow::Result<Option<Value>> {
     // Create conversation
     let conversation_manager = ConversationManager::default();
     let NewConversation {
@@ -299,20 +348,65 @@ async fn run_step_with_config(
         })
         .await?;
 
-    // Drain until TaskComplete, then Shutdown
+    // Drain until TaskComplete (or a budget abort), then Shutdown
     let mut last_message: Option<String> = None;
+    let mut budget_exceeded = false;
+    let mut structured_output: Option<Value> = None;
+    let mut corrections_sent = 0;
+    let mut schema_errors: Option<Vec<String>> = None;
     while let Some(event) = rx.recv().await {
-        if let EventMsg::TaskComplete(TaskCompleteEvent {
-            ref last_agent_message,
-        }) = event.msg
-        {
-            last_message = last_agent_message.clone();
-            conversation.submit(Op::Shutdown).await?;
-        }
-        if matches!(event.msg, EventMsg::ShutdownComplete) {
-            break;
+        match &event.msg {
+            EventMsg::TaskComplete(TaskCompleteEvent { last_agent_message }) => {
+                if let Some(schema) = output_schema {
+                    match schema.validate(last_agent_message.as_deref()) {
+                        Ok(value) => structured_output = Some(value),
+                        Err(errors) if corrections_sent < DEFAULT_OUTPUT_SCHEMA_RETRIES => {
+                            corrections_sent += 1;
+                            if !json_mode {
+                                eprintln!(
+                                    "Step output does not match its output schema; asking for a correction ({corrections_sent}/{DEFAULT_OUTPUT_SCHEMA_RETRIES})."
+                                );
+                            }
+                            let items = vec![InputItem::Text {
+                                text: schema.correction_prompt(&errors),
+                            }];
+                            conversation.submit(Op::UserInput { items }).await?;
+                            continue;
+                        }
+                        Err(errors) => schema_errors = Some(errors),
+                    }
+                }
+                last_message = last_agent_message.clone();
+                conversation.submit(Op::Shutdown).await?;
+            }
+            EventMsg::TokenCount(token_count) => {
+                spend.add(&token_count.usage, token_count.cost_usd)
+            }
+            EventMsg::BackgroundEvent(BackgroundEventEvent { message }) if !json_mode => {
+                eprintln!("{message}");
+            }
+            EventMsg::TurnAborted(TurnAbortedEvent {
+                reason: TurnAbortReason::BudgetExceeded,
+            }) => {
+                budget_exceeded = true;
+                conversation.submit(Op::Shutdown).await?;
+            }
+            EventMsg::ShutdownComplete => break,
+            _ => {}
         }
     }
+    if budget_exceeded {
+        anyhow::bail!("workflow step aborted: budget exceeded");
+    }
+    if let Some(errors) = schema_errors {
+        anyhow::bail!(
+            "workflow step output does not match its output schema:\n- {}",
+            errors.join("\n- ")
+        );
+    }
+    if let Some(value) = &structured_output {
+        last_message = Some(serde_json::to_string_pretty(value)?);
+    }
 
     // Output last message
     if let Some(text) = last_message {
@@ -329,5 +423,5 @@ async fn run_step_with_config(
         }
     }
 
-    Ok(())
+    Ok(structured_output)
 }
@@ -1,14 +1,21 @@
 use codex_core::protocol::SandboxPolicy;
+use std::path::PathBuf;
 
 pub fn summarize_sandbox_policy(sandbox_policy: &SandboxPolicy) -> String {
     match sandbox_policy {
         SandboxPolicy::DangerFullAccess => "danger-full-access".to_string(),
-        SandboxPolicy::ReadOnly => "read-only".to_string(),
+        SandboxPolicy::ReadOnly { readable_roots } => {
+            let mut summary = "read-only".to_string();
+            push_readable_roots(&mut summary, readable_roots);
+            summary
+        }
         SandboxPolicy::WorkspaceWrite {
             writable_roots,
             network_access,
             exclude_tmpdir_env_var,
             exclude_slash_tmp,
+            readable_roots,
+            allowed_domains,
         } => {
             let mut summary = "workspace-write".to_string();
 
@@ -27,10 +34,27 @@ pub fn summarize_sandbox_policy(sandbox_policy: &SandboxPolicy) -> String {
             );
 
             summary.push_str(&format!(" [{}]", writable_entries.join(", ")));
+            push_readable_roots(&mut summary, readable_roots);
             if *network_access {
                 summary.push_str(" (network access enabled)");
+            } else if !allowed_domains.is_empty() {
+                summary.push_str(&format!(
+                    " (network access limited to [{}])",
+                    allowed_domains.join(", ")
+                ));
             }
             summary
         }
     }
 }
+
+fn push_readable_roots(summary: &mut String, readable_roots: &[PathBuf]) {
+    if readable_roots.is_empty() {
+        return;
+    }
+    let entries: Vec<String> = readable_roots
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    summary.push_str(&format!(" (reads limited to [{}])", entries.join(", ")));
+}
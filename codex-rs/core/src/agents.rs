@@ -1,3 +1,4 @@
+use crate::budget::Budget;
 use crate::config::ConfigToml;
 use crate::config::find_project_codex_dir;
 use crate::config::resolve_preliminary_cwd;
@@ -36,6 +37,21 @@ pub struct AgentConfigToml {
     /// Inline MCP servers for this agent (alternative to `mcp.toml`).
     #[serde(default)]
     pub mcp_servers: HashMap<String, McpServerConfig>,
+    /// Spend cap for a session run as this agent, in US dollars.
+    pub max_cost_usd: Option<f64>,
+    /// Token cap for a session run as this agent.
+    pub max_total_tokens: Option<u64>,
+}
+
+impl AgentConfigToml {
+    /// Limits declared by this agent; combine with the session budget via
+    /// [`Budget::min`].
+    pub fn budget(&self) -> Budget {
+        Budget {
+            max_cost_usd: self.max_cost_usd,
+            max_total_tokens: self.max_total_tokens,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
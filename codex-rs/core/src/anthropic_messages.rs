@@ -0,0 +1,944 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use eventsource_stream::Eventsource;
+use futures::Stream;
+use futures::StreamExt;
+use futures::TryStreamExt;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use tracing::debug;
+use tracing::trace;
+
+use crate::ModelProviderInfo;
+use crate::client_common::Prompt;
+use crate::client_common::ResponseEvent;
+use crate::client_common::ResponseStream;
+use crate::error::CodexErr;
+use crate::error::Result;
+use crate::model_family::ModelFamily;
+use crate::models::ContentItem;
+use crate::models::ReasoningItemContent;
+use crate::models::ResponseItem;
+use crate::openai_tools::create_tools_json_for_messages_api;
+use crate::protocol::TokenUsage;
+use crate::util::backoff;
+use codex_protocol::config_types::ReasoningEffort as ReasoningEffortConfig;
+
+/// Value sent in the `anthropic-version` header unless the provider config
+/// already sets one through `http_headers`.
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// `max_tokens` is mandatory for the Messages API. Used when neither the
+/// config nor the model info table provides a value.
+const DEFAULT_MAX_OUTPUT_TOKENS: u64 = 8_192;
+
+/// Smallest thinking budget accepted by the Messages API.
+const MIN_THINKING_BUDGET_TOKENS: u64 = 1_024;
+
+/// Implementation for the Anthropic Messages API (`/v1/messages`).
+pub(crate) async fn stream_messages(
+    prompt: &Prompt,
+    model_family: &ModelFamily,
+    effort: ReasoningEffortConfig,
+    max_output_tokens: Option<u64>,
+    client: &reqwest::Client,
+    provider: &ModelProviderInfo,
+) -> Result<ResponseStream> {
+    let payload = create_messages_request(prompt, model_family, effort, max_output_tokens)?;
+
+    debug!(
+        "POST to {}: {}",
+        provider.get_full_url(&None),
+        serde_json::to_string_pretty(&payload).unwrap_or_default()
+    );
+
+    let sets_version_header = provider.http_headers.as_ref().is_some_and(|h| {
+        h.keys()
+            .any(|k| k.eq_ignore_ascii_case("anthropic-version"))
+    });
+
+    let mut attempt = 0;
+    let max_retries = provider.request_max_retries();
+    loop {
+        attempt += 1;
+
+        let mut req_builder = provider.create_request_builder(client, &None).await?;
+        if !sets_version_header {
+            req_builder = req_builder.header("anthropic-version", ANTHROPIC_VERSION);
+        }
+
+        let res = req_builder
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .json(&payload)
+            .send()
+            .await;
+
+        match res {
+            Ok(resp) if resp.status().is_success() => {
+                let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(1600);
+                let stream = resp.bytes_stream().map_err(CodexErr::Reqwest);
+                tokio::spawn(process_messages_sse(
+                    stream,
+                    tx_event,
+                    provider.stream_idle_timeout(),
+                ));
+                return Ok(ResponseStream { rx_event });
+            }
+            Ok(res) => {
+                let status = res.status();
+                // 529 is Anthropic's "overloaded" status and is worth retrying
+                // just like a 5xx from any other provider.
+                if !(status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()) {
+                    let body = (res.text().await).unwrap_or_default();
+                    return Err(CodexErr::UnexpectedStatus(status, body));
+                }
+
+                if attempt > max_retries {
+                    return Err(CodexErr::RetryLimit(status));
+                }
+
+                let retry_after_secs = res
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|s| s.parse::<u64>().ok());
+
+                let delay = retry_after_secs
+                    .map(|s| Duration::from_millis(s * 1_000))
+                    .unwrap_or_else(|| backoff(attempt));
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                if attempt > max_retries {
+                    return Err(e.into());
+                }
+                let delay = backoff(attempt);
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// Builds the JSON body for a streaming `/v1/messages` request.
+///
+/// Prompt caching is requested by placing `cache_control` breakpoints on the
+/// system prompt, the last tool definition and the last content block of the
+/// two most recent user turns. Everything before a breakpoint is cached, so
+/// each turn reuses the prefix written by the previous one.
+fn create_messages_request(
+    prompt: &Prompt,
+    model_family: &ModelFamily,
+    effort: ReasoningEffortConfig,
+    max_output_tokens: Option<u64>,
+) -> Result<Value> {
+    let full_instructions = prompt.get_full_instructions(model_family);
+    let system = json!([{
+        "type": "text",
+        "text": full_instructions,
+        "cache_control": {"type": "ephemeral"},
+    }]);
+
+    let mut messages = build_messages(&prompt.get_formatted_input());
+    add_message_cache_breakpoints(&mut messages);
+
+    let mut tools_json = create_tools_json_for_messages_api(&prompt.tools)?;
+    if let Some(Value::Object(last)) = tools_json.last_mut() {
+        last.insert("cache_control".to_string(), json!({"type": "ephemeral"}));
+    }
+
+    let max_tokens = max_output_tokens.unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS);
+    let mut payload = json!({
+        "model": model_family.slug,
+        "system": system,
+        "messages": messages,
+        "max_tokens": max_tokens,
+        "stream": true,
+    });
+
+    if !tools_json.is_empty() {
+        payload["tools"] = Value::Array(tools_json);
+    }
+
+    // Extended thinking is opt-in through the same switch that enables the
+    // `reasoning` parameter for the Responses API.
+    if model_family.supports_reasoning_summaries
+        && let Some(budget_tokens) = thinking_budget_tokens(effort, max_tokens)
+    {
+        payload["thinking"] = json!({
+            "type": "enabled",
+            "budget_tokens": budget_tokens,
+        });
+    }
+
+    Ok(payload)
+}
+
+/// Maps the reasoning effort onto a thinking budget. The budget must be at
+/// least [`MIN_THINKING_BUDGET_TOKENS`] and strictly below `max_tokens`, so
+/// `None` is returned when the output limit is too small to fit one.
+fn thinking_budget_tokens(effort: ReasoningEffortConfig, max_tokens: u64) -> Option<u64> {
+    let requested = match effort {
+        ReasoningEffortConfig::Minimal => MIN_THINKING_BUDGET_TOKENS,
+        ReasoningEffortConfig::Low => 4_096,
+        ReasoningEffortConfig::Medium => 10_000,
+        ReasoningEffortConfig::High => 32_000,
+    };
+    let budget = requested.min(max_tokens / 2);
+    (budget >= MIN_THINKING_BUDGET_TOKENS).then_some(budget)
+}
+
+/// Converts the conversation history into Messages API turns. Adjacent items
+/// that map to the same role are merged into one message because the API
+/// requires user and assistant turns to alternate, and a `tool_result` must
+/// live in the user turn directly after its `tool_use`.
+fn build_messages(input: &[ResponseItem]) -> Vec<Value> {
+    let mut messages: Vec<(&'static str, Vec<Value>)> = Vec::new();
+
+    for item in input {
+        let (role, blocks) = match item {
+            ResponseItem::Message { role, content, .. } => {
+                let role = if role == "assistant" {
+                    "assistant"
+                } else {
+                    "user"
+                };
+                let blocks = content.iter().filter_map(content_item_to_block).collect();
+                (role, blocks)
+            }
+            ResponseItem::FunctionCall {
+                name,
+                arguments,
+                call_id,
+                ..
+            } => {
+                // The Messages API takes tool input as an object whereas our
+                // history keeps the raw JSON string the model produced.
+                let input = serde_json::from_str::<Value>(arguments)
+                    .ok()
+                    .filter(Value::is_object)
+                    .unwrap_or_else(|| json!({}));
+                (
+                    "assistant",
+                    vec![json!({
+                        "type": "tool_use",
+                        "id": call_id,
+                        "name": name,
+                        "input": input,
+                    })],
+                )
+            }
+            ResponseItem::FunctionCallOutput { call_id, output } => {
+                let mut block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": call_id,
+                    "content": output.content,
+                });
+                if output.success == Some(false) {
+                    block["is_error"] = Value::Bool(true);
+                }
+                ("user", vec![block])
+            }
+            ResponseItem::Reasoning {
+                content,
+                encrypted_content,
+                ..
+            } => {
+                // Thinking blocks can only be replayed with the signature the
+                // API attached to them, which we keep in `encrypted_content`.
+                let Some(signature) = encrypted_content else {
+                    continue;
+                };
+                let thinking = content.as_ref().map(|content| {
+                    content
+                        .iter()
+                        .map(|c| match c {
+                            ReasoningItemContent::ReasoningText { text }
+                            | ReasoningItemContent::Text { text } => text.as_str(),
+                        })
+                        .collect::<String>()
+                });
+                let block = match thinking {
+                    Some(thinking) => json!({
+                        "type": "thinking",
+                        "thinking": thinking,
+                        "signature": signature,
+                    }),
+                    None => json!({
+                        "type": "redacted_thinking",
+                        "data": signature,
+                    }),
+                };
+                ("assistant", vec![block])
+            }
+            ResponseItem::LocalShellCall { .. } | ResponseItem::Other => continue,
+        };
+
+        if blocks.is_empty() {
+            continue;
+        }
+
+        match messages.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => messages.push((role, blocks)),
+        }
+    }
+
+    messages
+        .into_iter()
+        .map(|(role, content)| json!({"role": role, "content": content}))
+        .collect()
+}
+
+fn content_item_to_block(item: &ContentItem) -> Option<Value> {
+    match item {
+        ContentItem::InputText { text } | ContentItem::OutputText { text } => {
+            // Empty text blocks are rejected by the API.
+            (!text.is_empty()).then(|| json!({"type": "text", "text": text}))
+        }
+        ContentItem::InputImage { image_url } => {
+            let source = match image_url
+                .strip_prefix("data:")
+                .and_then(|rest| rest.split_once(";base64,"))
+            {
+                Some((media_type, data)) => json!({
+                    "type": "base64",
+                    "media_type": media_type,
+                    "data": data,
+                }),
+                None => json!({"type": "url", "url": image_url}),
+            };
+            Some(json!({"type": "image", "source": source}))
+        }
+    }
+}
+
+/// Marks the last content block of the two most recent user turns as cache
+/// breakpoints. Together with the system prompt and tools this stays within
+/// the API limit of four breakpoints per request.
+fn add_message_cache_breakpoints(messages: &mut [Value]) {
+    messages
+        .iter_mut()
+        .rev()
+        .filter(|m| m["role"] == "user")
+        .take(2)
+        .filter_map(|m| m["content"].as_array_mut().and_then(|c| c.last_mut()))
+        .for_each(|block| block["cache_control"] = json!({"type": "ephemeral"}));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MessagesSseEvent {
+    MessageStart {
+        message: MessageStart,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: ContentBlockDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        usage: Option<MessagesUsage>,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: MessagesError,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageStart {
+    id: String,
+    usage: Option<MessagesUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text,
+    ToolUse {
+        id: String,
+        name: String,
+    },
+    Thinking,
+    RedactedThinking {
+        data: String,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlockDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    SignatureDelta {
+        signature: String,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MessagesUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u64>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u64>,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesError {
+    message: Option<String>,
+}
+
+/// A content block that is still being streamed, keyed by its `index`.
+enum PendingBlock {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    Thinking {
+        text: String,
+        signature: String,
+    },
+    RedactedThinking(String),
+}
+
+impl PendingBlock {
+    fn into_response_item(self) -> Option<ResponseItem> {
+        match self {
+            PendingBlock::Text(text) if text.is_empty() => None,
+            PendingBlock::Text(text) => Some(ResponseItem::Message {
+                id: None,
+                role: "assistant".to_string(),
+                content: vec![ContentItem::OutputText { text }],
+            }),
+            PendingBlock::ToolUse {
+                id,
+                name,
+                arguments,
+            } => Some(ResponseItem::FunctionCall {
+                id: None,
+                name,
+                // A tool without parameters streams no `input_json_delta`.
+                arguments: if arguments.is_empty() {
+                    "{}".to_string()
+                } else {
+                    arguments
+                },
+                call_id: id,
+            }),
+            PendingBlock::Thinking { text, signature } => Some(ResponseItem::Reasoning {
+                id: String::new(),
+                summary: Vec::new(),
+                content: Some(vec![ReasoningItemContent::ReasoningText { text }]),
+                encrypted_content: (!signature.is_empty()).then_some(signature),
+            }),
+            PendingBlock::RedactedThinking(data) => Some(ResponseItem::Reasoning {
+                id: String::new(),
+                summary: Vec::new(),
+                content: None,
+                encrypted_content: Some(data),
+            }),
+        }
+    }
+}
+
+/// SSE processor for the Messages streaming format. Each completed content
+/// block is forwarded as an `OutputItemDone` so that downstream consumers see
+/// the same event sequence as with the Responses API.
+async fn process_messages_sse<S>(
+    stream: S,
+    tx_event: mpsc::Sender<Result<ResponseEvent>>,
+    idle_timeout: Duration,
+) where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+{
+    let mut stream = stream.eventsource();
+
+    let mut response_id = String::new();
+    let mut usage = MessagesUsage::default();
+    let mut blocks: Vec<(usize, PendingBlock)> = Vec::new();
+
+    loop {
+        let sse = match timeout(idle_timeout, stream.next()).await {
+            Ok(Some(Ok(sse))) => sse,
+            Ok(Some(Err(e))) => {
+                let _ = tx_event
+                    .send(Err(CodexErr::Stream(e.to_string(), None)))
+                    .await;
+                return;
+            }
+            Ok(None) => {
+                let _ = tx_event
+                    .send(Err(CodexErr::Stream(
+                        "stream closed before message_stop".into(),
+                        None,
+                    )))
+                    .await;
+                return;
+            }
+            Err(_) => {
+                let _ = tx_event
+                    .send(Err(CodexErr::Stream(
+                        "idle timeout waiting for SSE".into(),
+                        None,
+                    )))
+                    .await;
+                return;
+            }
+        };
+
+        trace!("messages SSE event: {}", sse.data);
+
+        let event: MessagesSseEvent = match serde_json::from_str(&sse.data) {
+            Ok(event) => event,
+            Err(e) => {
+                debug!(
+                    "Failed to parse Messages SSE event: {e}, data: {}",
+                    &sse.data
+                );
+                continue;
+            }
+        };
+
+        match event {
+            MessagesSseEvent::MessageStart { message } => {
+                response_id = message.id;
+                if let Some(start_usage) = message.usage {
+                    usage = start_usage;
+                }
+                if tx_event.send(Ok(ResponseEvent::Created)).await.is_err() {
+                    return;
+                }
+            }
+            MessagesSseEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                let block = match content_block {
+                    ContentBlock::Text => PendingBlock::Text(String::new()),
+                    ContentBlock::ToolUse { id, name } => PendingBlock::ToolUse {
+                        id,
+                        name,
+                        arguments: String::new(),
+                    },
+                    ContentBlock::Thinking => PendingBlock::Thinking {
+                        text: String::new(),
+                        signature: String::new(),
+                    },
+                    ContentBlock::RedactedThinking { data } => PendingBlock::RedactedThinking(data),
+                    ContentBlock::Unknown => continue,
+                };
+                blocks.push((index, block));
+            }
+            MessagesSseEvent::ContentBlockDelta { index, delta } => {
+                let Some((_, block)) = blocks.iter_mut().find(|(i, _)| *i == index) else {
+                    continue;
+                };
+                let event = match (block, delta) {
+                    (PendingBlock::Text(text), ContentBlockDelta::TextDelta { text: delta }) => {
+                        text.push_str(&delta);
+                        Some(ResponseEvent::OutputTextDelta(delta))
+                    }
+                    (
+                        PendingBlock::ToolUse { arguments, .. },
+                        ContentBlockDelta::InputJsonDelta { partial_json },
+                    ) => {
+                        arguments.push_str(&partial_json);
+                        None
+                    }
+                    (
+                        PendingBlock::Thinking { text, .. },
+                        ContentBlockDelta::ThinkingDelta { thinking },
+                    ) => {
+                        text.push_str(&thinking);
+                        Some(ResponseEvent::ReasoningContentDelta(thinking))
+                    }
+                    (
+                        PendingBlock::Thinking { signature, .. },
+                        ContentBlockDelta::SignatureDelta { signature: delta },
+                    ) => {
+                        signature.push_str(&delta);
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(event) = event
+                    && tx_event.send(Ok(event)).await.is_err()
+                {
+                    return;
+                }
+            }
+            MessagesSseEvent::ContentBlockStop { index } => {
+                let Some(pos) = blocks.iter().position(|(i, _)| *i == index) else {
+                    continue;
+                };
+                let (_, block) = blocks.remove(pos);
+                if let Some(item) = block.into_response_item()
+                    && tx_event
+                        .send(Ok(ResponseEvent::OutputItemDone(item)))
+                        .await
+                        .is_err()
+                {
+                    return;
+                }
+            }
+            MessagesSseEvent::MessageDelta { usage: delta_usage } => {
+                // `message_delta` carries the cumulative output token count.
+                if let Some(delta_usage) = delta_usage {
+                    usage.output_tokens = delta_usage.output_tokens;
+                }
+            }
+            MessagesSseEvent::MessageStop => {
+                let _ = tx_event
+                    .send(Ok(ResponseEvent::Completed {
+                        response_id,
+                        token_usage: Some(usage.into()),
+                    }))
+                    .await;
+                return;
+            }
+            MessagesSseEvent::Ping => {}
+            MessagesSseEvent::Error { error } => {
+                let message = error
+                    .message
+                    .unwrap_or_else(|| "error event received".to_string());
+                let _ = tx_event.send(Err(CodexErr::Stream(message, None))).await;
+                return;
+            }
+        }
+    }
+}
+
+impl From<MessagesUsage> for TokenUsage {
+    fn from(val: MessagesUsage) -> Self {
+        // `input_tokens` excludes tokens read from or written to the prompt
+        // cache, so add them back to report the full prompt size.
+        let cache_read = val.cache_read_input_tokens.unwrap_or(0);
+        let input_tokens =
+            val.input_tokens + val.cache_creation_input_tokens.unwrap_or(0) + cache_read;
+        TokenUsage {
+            input_tokens,
+            cached_input_tokens: val.cache_read_input_tokens,
+            output_tokens: val.output_tokens,
+            reasoning_output_tokens: None,
+            total_tokens: input_tokens + val.output_tokens,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_family::find_family_for_model;
+    use crate::models::FunctionCallOutputPayload;
+    use pretty_assertions::assert_eq;
+    use tokio_util::io::ReaderStream;
+
+    /// Feeds `data:` payloads through [`process_messages_sse`] and collects
+    /// every event, including a terminal `Err`.
+    async fn run_sse(events: Vec<Value>) -> Vec<Result<ResponseEvent>> {
+        let mut body = String::new();
+        for e in events {
+            let kind = e["type"].as_str().unwrap_or_default().to_string();
+            body.push_str(&format!("event: {kind}\ndata: {e}\n\n"));
+        }
+
+        let (tx, mut rx) = mpsc::channel::<Result<ResponseEvent>>(16);
+        let stream = ReaderStream::new(std::io::Cursor::new(body)).map_err(CodexErr::Io);
+        tokio::spawn(process_messages_sse(stream, tx, Duration::from_secs(1)));
+
+        let mut out = Vec::new();
+        while let Some(ev) = rx.recv().await {
+            out.push(ev);
+        }
+        out
+    }
+
+    fn message_start() -> Value {
+        json!({
+            "type": "message_start",
+            "message": {
+                "id": "msg_1",
+                "usage": {
+                    "input_tokens": 10,
+                    "cache_creation_input_tokens": 5,
+                    "cache_read_input_tokens": 100,
+                    "output_tokens": 1
+                }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn maps_text_tool_use_and_usage() {
+        let events = run_sse(vec![
+            message_start(),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "ping"}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hel"}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "lo"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "shell", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"command\": "}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "[\"ls\"]}"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 42}}),
+            json!({"type": "message_stop"}),
+        ])
+        .await;
+
+        let events: Vec<ResponseEvent> = events
+            .into_iter()
+            .map(|e| e.expect("unexpected error event"))
+            .collect();
+        assert_eq!(events.len(), 6, "{events:?}");
+        assert!(matches!(events[0], ResponseEvent::Created));
+        assert!(matches!(&events[1], ResponseEvent::OutputTextDelta(d) if d == "Hel"));
+        assert!(matches!(&events[2], ResponseEvent::OutputTextDelta(d) if d == "lo"));
+        match &events[3] {
+            ResponseEvent::OutputItemDone(ResponseItem::Message { role, content, .. }) => {
+                assert_eq!(role, "assistant");
+                assert_eq!(
+                    content,
+                    &vec![ContentItem::OutputText {
+                        text: "Hello".to_string()
+                    }]
+                );
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        match &events[4] {
+            ResponseEvent::OutputItemDone(ResponseItem::FunctionCall {
+                name,
+                arguments,
+                call_id,
+                ..
+            }) => {
+                assert_eq!(name, "shell");
+                assert_eq!(arguments, "{\"command\": [\"ls\"]}");
+                assert_eq!(call_id, "toolu_1");
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        match &events[5] {
+            ResponseEvent::Completed {
+                response_id,
+                token_usage,
+            } => {
+                assert_eq!(response_id, "msg_1");
+                let usage = token_usage.as_ref().expect("usage");
+                assert_eq!(usage.input_tokens, 115);
+                assert_eq!(usage.cached_input_tokens, Some(100));
+                assert_eq!(usage.output_tokens, 42);
+                assert_eq!(usage.total_tokens, 157);
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn maps_thinking_blocks_to_reasoning() {
+        let events = run_sse(vec![
+            message_start(),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "Let me think"}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "signature_delta", "signature": "sig=="}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "redacted_thinking", "data": "opaque"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "message_stop"}),
+        ])
+        .await;
+
+        let events: Vec<ResponseEvent> = events
+            .into_iter()
+            .map(|e| e.expect("unexpected error event"))
+            .collect();
+        assert!(
+            matches!(&events[1], ResponseEvent::ReasoningContentDelta(d) if d == "Let me think")
+        );
+        match &events[2] {
+            ResponseEvent::OutputItemDone(ResponseItem::Reasoning {
+                content,
+                encrypted_content,
+                ..
+            }) => {
+                assert_eq!(
+                    content,
+                    &Some(vec![ReasoningItemContent::ReasoningText {
+                        text: "Let me think".to_string()
+                    }])
+                );
+                assert_eq!(encrypted_content.as_deref(), Some("sig=="));
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        match &events[3] {
+            ResponseEvent::OutputItemDone(ResponseItem::Reasoning {
+                content,
+                encrypted_content,
+                ..
+            }) => {
+                assert_eq!(content, &None);
+                assert_eq!(encrypted_content.as_deref(), Some("opaque"));
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        assert!(matches!(events[4], ResponseEvent::Completed { .. }));
+    }
+
+    #[tokio::test]
+    async fn surfaces_error_events_and_early_close() {
+        let events = run_sse(vec![
+            message_start(),
+            json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}),
+        ])
+        .await;
+        match events.last() {
+            Some(Err(CodexErr::Stream(msg, None))) => assert_eq!(msg, "Overloaded"),
+            other => panic!("unexpected event: {other:?}"),
+        }
+
+        let events = run_sse(vec![message_start()]).await;
+        match events.last() {
+            Some(Err(CodexErr::Stream(msg, None))) => {
+                assert_eq!(msg, "stream closed before message_stop")
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn builds_alternating_turns_with_tool_results() {
+        let input = vec![
+            ResponseItem::Message {
+                id: None,
+                role: "user".to_string(),
+                content: vec![
+                    ContentItem::InputText {
+                        text: "list files".to_string(),
+                    },
+                    ContentItem::InputImage {
+                        image_url: "data:image/png;base64,AAAA".to_string(),
+                    },
+                ],
+            },
+            ResponseItem::Reasoning {
+                id: String::new(),
+                summary: Vec::new(),
+                content: Some(vec![ReasoningItemContent::ReasoningText {
+                    text: "thinking".to_string(),
+                }]),
+                encrypted_content: Some("sig".to_string()),
+            },
+            ResponseItem::Message {
+                id: None,
+                role: "assistant".to_string(),
+                content: vec![ContentItem::OutputText {
+                    text: "Sure.".to_string(),
+                }],
+            },
+            ResponseItem::FunctionCall {
+                id: None,
+                name: "shell".to_string(),
+                arguments: r#"{"command":["ls"]}"#.to_string(),
+                call_id: "toolu_1".to_string(),
+            },
+            ResponseItem::FunctionCallOutput {
+                call_id: "toolu_1".to_string(),
+                output: FunctionCallOutputPayload {
+                    content: "boom".to_string(),
+                    success: Some(false),
+                },
+            },
+        ];
+
+        let mut messages = build_messages(&input);
+        add_message_cache_breakpoints(&mut messages);
+
+        assert_eq!(
+            messages,
+            vec![
+                json!({"role": "user", "content": [
+                    {"type": "text", "text": "list files"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"},
+                     "cache_control": {"type": "ephemeral"}},
+                ]}),
+                json!({"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": "thinking", "signature": "sig"},
+                    {"type": "text", "text": "Sure."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "shell", "input": {"command": ["ls"]}},
+                ]}),
+                json!({"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "boom", "is_error": true,
+                     "cache_control": {"type": "ephemeral"}},
+                ]}),
+            ]
+        );
+    }
+
+    #[test]
+    fn requests_thinking_only_for_reasoning_models() {
+        let prompt = Prompt::default();
+        let mut family = find_family_for_model("gpt-4.1").expect("known model slug");
+
+        let payload = create_messages_request(
+            &prompt,
+            &family,
+            ReasoningEffortConfig::Medium,
+            Some(64_000),
+        )
+        .expect("payload");
+        assert_eq!(payload["max_tokens"], 64_000);
+        assert!(payload.get("thinking").is_none());
+        assert!(payload.get("tools").is_none());
+        assert_eq!(payload["system"][0]["cache_control"]["type"], "ephemeral");
+
+        family.supports_reasoning_summaries = true;
+        let payload = create_messages_request(
+            &prompt,
+            &family,
+            ReasoningEffortConfig::Medium,
+            Some(64_000),
+        )
+        .expect("payload");
+        assert_eq!(payload["thinking"]["budget_tokens"], 10_000);
+
+        assert_eq!(
+            thinking_budget_tokens(ReasoningEffortConfig::High, 8_192),
+            Some(4_096)
+        );
+        assert_eq!(
+            thinking_budget_tokens(ReasoningEffortConfig::Low, 1_024),
+            None
+        );
+    }
+}
@@ -3,16 +3,29 @@ use crate::codex::TurnContext;
 use crate::models::FunctionCallOutputPayload;
 use crate::models::ResponseInputItem;
 use crate::protocol::FileChange;
+use crate::protocol::PatchApplyFileResult;
+use crate::protocol::PatchApplyFileStatus;
 use crate::protocol::ReviewDecision;
 use crate::safety::SafetyCheck;
 use crate::safety::assess_patch_safety;
+use base64::Engine;
 use codex_apply_patch::ApplyPatchAction;
 use codex_apply_patch::ApplyPatchFileChange;
+use codex_apply_patch::FileOutcome;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
 pub const CODEX_APPLY_PATCH_ARG1: &str = "--codex-run-as-apply-patch";
 
+/// Environment variable that passes the fuzzy matching threshold to the
+/// process started with [`CODEX_APPLY_PATCH_ARG1`].
+pub const CODEX_APPLY_PATCH_FUZZY_THRESHOLD_ENV_VAR: &str = "CODEX_APPLY_PATCH_FUZZY_THRESHOLD";
+
+/// Environment variable that asks the process started with
+/// [`CODEX_APPLY_PATCH_ARG1`] to write its per-file results to stderr, so
+/// they can be read back with [`codex_apply_patch::take_file_results`].
+pub const CODEX_APPLY_PATCH_REPORT_RESULTS_ENV_VAR: &str = "CODEX_APPLY_PATCH_REPORT_RESULTS";
+
 pub(crate) enum InternalApplyPatchInvocation {
     /// The `apply_patch` call was handled programmatically, without any sort
     /// of sandbox, because the user explicitly approved it. This is the
@@ -70,7 +83,9 @@ pub(crate) async fn apply_patch(
                 .request_patch_approval(sub_id.to_owned(), call_id.to_owned(), &action, None, None)
                 .await;
             match rx_approve.await.unwrap_or_default() {
-                ReviewDecision::Approved | ReviewDecision::ApprovedForSession => {
+                ReviewDecision::Approved
+                | ReviewDecision::ApprovedForSession
+                | ReviewDecision::ApprovedForProject => {
                     InternalApplyPatchInvocation::DelegateToExec(ApplyPatchExec {
                         action,
                         user_explicitly_approved_this_action: true,
@@ -106,20 +121,54 @@ pub(crate) fn convert_apply_patch_to_protocol(
     let mut result = HashMap::with_capacity(changes.len());
     for (path, change) in changes {
         let protocol_change = match change {
-            ApplyPatchFileChange::Add { content } => FileChange::Add {
+            ApplyPatchFileChange::Add {
+                content,
+                executable,
+            } => FileChange::Add {
                 content: content.clone(),
+                executable: *executable,
             },
             ApplyPatchFileChange::Delete => FileChange::Delete,
             ApplyPatchFileChange::Update {
                 unified_diff,
                 move_path,
                 new_content: _new_content,
+                executable,
             } => FileChange::Update {
                 unified_diff: unified_diff.clone(),
                 move_path: move_path.clone(),
+                executable: *executable,
+            },
+            ApplyPatchFileChange::Binary {
+                content,
+                executable,
+            } => FileChange::Binary {
+                content_base64: base64::engine::general_purpose::STANDARD.encode(content),
+                executable: *executable,
+            },
+            ApplyPatchFileChange::Symlink { target } => FileChange::Symlink {
+                target: target.clone(),
             },
         };
         result.insert(path.clone(), protocol_change);
     }
     result
 }
+
+/// Takes what happened to each file out of the stderr of the `apply_patch`
+/// process, so that the model does not see the machine-readable line.
+pub(crate) fn take_file_results(stderr: &mut String) -> Vec<PatchApplyFileResult> {
+    codex_apply_patch::take_file_results(stderr)
+        .into_iter()
+        .map(|file| PatchApplyFileResult {
+            path: file.path,
+            status: match file.outcome {
+                FileOutcome::Applied => PatchApplyFileStatus::Applied,
+                FileOutcome::Failed => PatchApplyFileStatus::Failed,
+                FileOutcome::NotApplied => PatchApplyFileStatus::NotApplied,
+                FileOutcome::RolledBack => PatchApplyFileStatus::RolledBack,
+                FileOutcome::RestoreFailed => PatchApplyFileStatus::RestoreFailed,
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,627 @@
+//! Commands that run in the background while the model keeps working.
+//!
+//! `background_job_start` spawns a command in the sandbox a `shell` call would
+//! use and returns immediately with a job id. The command's stdout and stderr
+//! go to a log file under `$CODEX_HOME/jobs/<session id>/`, which the model
+//! reads through `background_job_tail` (or directly, since the path is
+//! returned). Clients are told which jobs are running through
+//! `BackgroundJobsUpdate` events, and when a job exits through a
+//! `BackgroundEvent`.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::error::CodexErr;
+use crate::exec::ExecParams;
+use crate::exec::SandboxType;
+use crate::exec::StdoutStream;
+use crate::exec::exit_code_of;
+use crate::exec::spawn_command_for_sandbox_type;
+use crate::file_tools::function_output;
+use crate::file_tools::parse_error;
+use crate::models::ResponseInputItem;
+use crate::network_proxy::NetworkProxy;
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::create_function_tool;
+use crate::protocol::BackgroundEventEvent;
+use crate::protocol::BackgroundJobsUpdateEvent;
+use crate::protocol::Event;
+use crate::protocol::EventMsg;
+use crate::protocol::RunningBackgroundJob;
+use crate::protocol::SandboxPolicy;
+use crate::resource_limits::exec_cgroup_path;
+use crate::resource_limits::remove_exec_cgroup;
+
+/// Lines `background_job_tail` returns by default.
+const DEFAULT_TAIL_LINES: usize = 50;
+
+/// Most output returned by a single `background_job_tail` call.
+const MAX_TAIL_BYTES: u64 = 10 * 1024;
+
+/// How long `background_job_kill` waits for the job to exit.
+const KILL_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct BackgroundJobStartParams {
+    pub(crate) command: Vec<String>,
+    #[serde(default)]
+    pub(crate) workdir: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BackgroundJobStatusParams {
+    #[serde(default)]
+    job_id: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BackgroundJobTailParams {
+    job_id: u32,
+    #[serde(default)]
+    lines: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BackgroundJobKillParams {
+    job_id: u32,
+}
+
+fn job_id_schema() -> JsonSchema {
+    JsonSchema::Number {
+        description: Some("The id returned by background_job_start.".to_string()),
+    }
+}
+
+pub(crate) static BACKGROUND_JOB_TOOLS: LazyLock<Vec<OpenAiTool>> = LazyLock::new(|| {
+    let mut start_properties = BTreeMap::new();
+    start_properties.insert(
+        "command".to_string(),
+        JsonSchema::Array {
+            items: Box::new(JsonSchema::String { description: None }),
+            description: Some("The command to execute".to_string()),
+        },
+    );
+    start_properties.insert(
+        "workdir".to_string(),
+        JsonSchema::String {
+            description: Some("The working directory to execute the command in".to_string()),
+        },
+    );
+
+    let mut status_properties = BTreeMap::new();
+    status_properties.insert(
+        "job_id".to_string(),
+        JsonSchema::Number {
+            description: Some("The job to report on. Omit to list every job.".to_string()),
+        },
+    );
+
+    let mut tail_properties = BTreeMap::new();
+    tail_properties.insert("job_id".to_string(), job_id_schema());
+    tail_properties.insert(
+        "lines".to_string(),
+        JsonSchema::Number {
+            description: Some(format!(
+                "How many lines to return from the end of the output. Defaults to {DEFAULT_TAIL_LINES}."
+            )),
+        },
+    );
+
+    let mut kill_properties = BTreeMap::new();
+    kill_properties.insert("job_id".to_string(), job_id_schema());
+
+    vec![
+        create_function_tool(
+            "background_job_start",
+            "Starts a command in the background and returns right away with a job_id, so you can keep working while it runs. Use it for long builds and test suites; poll with background_job_status and background_job_tail. The command has no timeout and its output goes to the returned log_path.",
+            start_properties,
+            &["command"],
+        ),
+        create_function_tool(
+            "background_job_status",
+            "Reports whether background jobs are still running, and the exit codes of those that finished.",
+            status_properties,
+            &[],
+        ),
+        create_function_tool(
+            "background_job_tail",
+            "Returns the last lines a background job printed to stdout and stderr.",
+            tail_properties,
+            &["job_id"],
+        ),
+        create_function_tool(
+            "background_job_kill",
+            "Kills a background job along with everything it started.",
+            kill_properties,
+            &["job_id"],
+        ),
+    ]
+});
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum BackgroundJobError {
+    #[error("no background job with id {0}")]
+    UnknownJob(u32),
+
+    #[error("failed to start background job: {0}")]
+    Start(#[from] CodexErr),
+
+    #[error("failed to read the output of background job {0}: {1}")]
+    Tail(u32, io::Error),
+}
+
+/// What the model is told about a job.
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct BackgroundJobStatus {
+    pub(crate) job_id: u32,
+    pub(crate) command: Vec<String>,
+    pub(crate) running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) log_path: PathBuf,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct BackgroundJobTail {
+    pub(crate) output: String,
+    pub(crate) metadata: BackgroundJobStatus,
+}
+
+struct BackgroundJob {
+    command: Vec<String>,
+    log_path: PathBuf,
+    /// Leader of the process group the command runs in.
+    pid: Option<u32>,
+    exit_code: Mutex<Option<i32>>,
+    exited: Notify,
+    /// Kept alive for the command's network access.
+    _network_proxy: Option<NetworkProxy>,
+}
+
+impl BackgroundJob {
+    fn exit_code(&self) -> Option<i32> {
+        self.exit_code.lock().ok().and_then(|exit_code| *exit_code)
+    }
+
+    fn kill(&self) {
+        if let Some(pid) = self.pid {
+            // The command is a session leader, so this also reaches whatever
+            // it started, including what outlived it: the group id is not
+            // handed out again while the group has members.
+            unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+        }
+    }
+
+    fn status(&self, job_id: u32) -> BackgroundJobStatus {
+        let exit_code = self.exit_code();
+        BackgroundJobStatus {
+            job_id,
+            command: self.command.clone(),
+            running: exit_code.is_none(),
+            exit_code,
+            log_path: self.log_path.clone(),
+        }
+    }
+}
+
+impl Drop for BackgroundJob {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+type JobTable = Arc<Mutex<BTreeMap<u32, Arc<BackgroundJob>>>>;
+
+/// The background jobs of a Codex session, addressed by id. Finished jobs
+/// are kept so that their status and output remain available.
+pub(crate) struct BackgroundJobManager {
+    log_dir: PathBuf,
+    next_job_id: AtomicU32,
+    jobs: JobTable,
+}
+
+impl BackgroundJobManager {
+    /// `log_dir` receives one `<job id>.log` file per job.
+    pub(crate) fn new(log_dir: PathBuf) -> Self {
+        Self {
+            log_dir,
+            next_job_id: AtomicU32::new(0),
+            jobs: Arc::default(),
+        }
+    }
+
+    /// Starts `params.command` without waiting for it. `events` receives the
+    /// job updates and notices about blocked network requests.
+    pub(crate) async fn start(
+        &self,
+        params: ExecParams,
+        sandbox_type: SandboxType,
+        sandbox_policy: &SandboxPolicy,
+        codex_linux_sandbox_exe: &Option<PathBuf>,
+        events: StdoutStream,
+    ) -> Result<BackgroundJobStatus, BackgroundJobError> {
+        let network_proxy = match sandbox_policy.get_proxied_domains() {
+            Some(allowed_domains) if sandbox_type != SandboxType::None => Some(
+                NetworkProxy::start(allowed_domains.to_vec(), Some(events.clone()))
+                    .await
+                    .map_err(CodexErr::Io)?,
+            ),
+            _ => None,
+        };
+
+        let job_id = self.next_job_id.fetch_add(1, Ordering::Relaxed) + 1;
+        std::fs::create_dir_all(&self.log_dir).map_err(CodexErr::Io)?;
+        let log_path = self.log_dir.join(format!("{job_id}.log"));
+        let log = std::fs::File::create(&log_path).map_err(CodexErr::Io)?;
+        let mut child = spawn_command_for_sandbox_type(
+            params.command.clone(),
+            params.cwd,
+            params.env,
+            sandbox_type,
+            sandbox_policy,
+            network_proxy.as_ref(),
+            &params.resource_limits,
+            codex_linux_sandbox_exe,
+            spawn_stdio_policy(&log).map_err(CodexErr::Io)?,
+        )
+        .await?;
+        drop(log);
+
+        let pid = child.id();
+        let exec_cgroup = match (sandbox_type, pid) {
+            (SandboxType::LinuxSeccomp, Some(pid)) if params.resource_limits.uses_cgroup() => {
+                exec_cgroup_path(pid)
+            }
+            _ => None,
+        };
+        let job = Arc::new(BackgroundJob {
+            command: params.command,
+            log_path,
+            pid,
+            exit_code: Mutex::new(None),
+            exited: Notify::new(),
+            _network_proxy: network_proxy,
+        });
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.insert(job_id, job.clone());
+        }
+        send_jobs_update(&self.jobs, &events);
+
+        let jobs = self.jobs.clone();
+        let wait_job = job.clone();
+        tokio::spawn(async move {
+            let exit_code = match child.wait().await {
+                Ok(status) => exit_code_of(status),
+                Err(_) => -1,
+            };
+            if let Some(exec_cgroup) = exec_cgroup {
+                remove_exec_cgroup(&exec_cgroup);
+            }
+            if let Ok(mut job_exit_code) = wait_job.exit_code.lock() {
+                *job_exit_code = Some(exit_code);
+            }
+            wait_job.exited.notify_waiters();
+
+            let command = shlex::try_join(wait_job.command.iter().map(String::as_str))
+                .unwrap_or_else(|_| wait_job.command.join(" "));
+            let _ = events
+                .tx_event
+                .send(Event {
+                    id: events.sub_id.clone(),
+                    msg: EventMsg::BackgroundEvent(BackgroundEventEvent {
+                        message: format!(
+                            "background job {job_id} (`{command}`) exited with code {exit_code}"
+                        ),
+                    }),
+                })
+                .await;
+            send_jobs_update(&jobs, &events);
+        });
+
+        Ok(job.status(job_id))
+    }
+
+    /// Returns the status of `job_id`, or of every job in start order.
+    fn status(&self, job_id: Option<u32>) -> Result<Vec<BackgroundJobStatus>, BackgroundJobError> {
+        match job_id {
+            Some(job_id) => Ok(vec![self.get(job_id)?.status(job_id)]),
+            None => Ok(self
+                .jobs
+                .lock()
+                .map(|jobs| jobs.iter().map(|(id, job)| job.status(*id)).collect())
+                .unwrap_or_default()),
+        }
+    }
+
+    async fn tail(
+        &self,
+        job_id: u32,
+        lines: usize,
+    ) -> Result<BackgroundJobTail, BackgroundJobError> {
+        let job = self.get(job_id)?;
+        let log_path = job.log_path.clone();
+        let output = tokio::task::spawn_blocking(move || read_tail(&log_path, lines))
+            .await
+            .map_err(|e| BackgroundJobError::Tail(job_id, io::Error::other(e)))?
+            .map_err(|e| BackgroundJobError::Tail(job_id, e))?;
+        Ok(BackgroundJobTail {
+            output,
+            metadata: job.status(job_id),
+        })
+    }
+
+    async fn kill(&self, job_id: u32) -> Result<BackgroundJobStatus, BackgroundJobError> {
+        let job = self.get(job_id)?;
+        let exited = job.exited.notified();
+        job.kill();
+        if job.exit_code().is_none() {
+            let _ = tokio::time::timeout(KILL_TIMEOUT, exited).await;
+        }
+        Ok(job.status(job_id))
+    }
+
+    /// Kills every job and whatever it left running, e.g. when Codex shuts
+    /// down.
+    pub(crate) fn kill_all(&self) {
+        if let Ok(jobs) = self.jobs.lock() {
+            for job in jobs.values() {
+                job.kill();
+            }
+        }
+    }
+
+    fn get(&self, job_id: u32) -> Result<Arc<BackgroundJob>, BackgroundJobError> {
+        self.jobs
+            .lock()
+            .ok()
+            .and_then(|jobs| jobs.get(&job_id).cloned())
+            .ok_or(BackgroundJobError::UnknownJob(job_id))
+    }
+}
+
+fn send_jobs_update(jobs: &JobTable, events: &StdoutStream) {
+    let running = jobs
+        .lock()
+        .map(|jobs| {
+            jobs.iter()
+                .filter(|(_, job)| job.exit_code().is_none())
+                .map(|(job_id, job)| RunningBackgroundJob {
+                    job_id: *job_id,
+                    command: job.command.clone(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let _ = events.tx_event.try_send(Event {
+        id: events.sub_id.clone(),
+        msg: EventMsg::BackgroundJobsUpdate(BackgroundJobsUpdateEvent { running }),
+    });
+}
+
+/// Returns up to the last `lines` lines of `path`, reading at most
+/// `MAX_TAIL_BYTES` of it.
+fn read_tail(path: &Path, lines: usize) -> io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(MAX_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    let text = String::from_utf8_lossy(&buf);
+
+    let mut all_lines: Vec<&str> = text.lines().collect();
+    // A partial first line is an artifact of where reading started.
+    if start > 0 && !all_lines.is_empty() {
+        all_lines.remove(0);
+    }
+    let skip = all_lines.len().saturating_sub(lines);
+    let mut output = String::new();
+    if start > 0 || skip > 0 {
+        output.push_str("[earlier output omitted]\n");
+    }
+    output.push_str(&all_lines[skip..].join("\n"));
+    Ok(output)
+}
+
+/// Handles the job tools other than `background_job_start`, which needs the
+/// approval flow in `codex.rs`.
+pub(crate) async fn handle_background_job_call(
+    manager: &BackgroundJobManager,
+    name: &str,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let result = match name {
+        "background_job_status" => {
+            match serde_json::from_str::<BackgroundJobStatusParams>(&arguments) {
+                Ok(params) => Ok(manager.status(params.job_id).map(|jobs| to_json(&jobs))),
+                Err(e) => Err(e),
+            }
+        }
+        "background_job_tail" => {
+            match serde_json::from_str::<BackgroundJobTailParams>(&arguments) {
+                Ok(params) => Ok(manager
+                    .tail(params.job_id, params.lines.unwrap_or(DEFAULT_TAIL_LINES))
+                    .await
+                    .map(|tail| to_json(&tail))),
+                Err(e) => Err(e),
+            }
+        }
+        _ => match serde_json::from_str::<BackgroundJobKillParams>(&arguments) {
+            Ok(params) => Ok(manager
+                .kill(params.job_id)
+                .await
+                .map(|status| to_json(&status))),
+            Err(e) => Err(e),
+        },
+    };
+
+    match result {
+        Ok(result) => job_call_output(call_id, result),
+        Err(e) => parse_error(call_id, e),
+    }
+}
+
+pub(crate) fn job_call_output(
+    call_id: String,
+    result: Result<String, BackgroundJobError>,
+) -> ResponseInputItem {
+    function_output(call_id, result.map_err(|e| e.to_string()))
+}
+
+pub(crate) fn to_json(value: &impl Serialize) -> String {
+    #[expect(clippy::expect_used)]
+    serde_json::to_string(value).expect("serialize background job output")
+}
+
+#[cfg(unix)]
+fn spawn_stdio_policy(log: &std::fs::File) -> io::Result<crate::spawn::StdioPolicy> {
+    use std::os::fd::AsRawFd;
+
+    Ok(crate::spawn::StdioPolicy::Background(log.as_raw_fd()))
+}
+
+#[cfg(not(unix))]
+fn spawn_stdio_policy(_log: &std::fs::File) -> io::Result<crate::spawn::StdioPolicy> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "background jobs are only supported on Unix",
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    #[expect(clippy::unwrap_used)]
+    async fn start(
+        manager: &BackgroundJobManager,
+        script: &str,
+    ) -> (BackgroundJobStatus, async_channel::Receiver<Event>) {
+        let (tx_event, rx_event) = async_channel::unbounded();
+        let params = ExecParams {
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            cwd: std::env::temp_dir(),
+            timeout_ms: None,
+            env: HashMap::from([("PATH".to_string(), "/usr/bin:/bin".to_string())]),
+            with_escalated_permissions: None,
+            justification: None,
+            resource_limits: Default::default(),
+            output_limits: Default::default(),
+        };
+        let status = manager
+            .start(
+                params,
+                SandboxType::None,
+                &SandboxPolicy::DangerFullAccess,
+                &None,
+                StdoutStream {
+                    sub_id: "sub".to_string(),
+                    call_id: "call".to_string(),
+                    tx_event,
+                },
+            )
+            .await
+            .unwrap();
+        (status, rx_event)
+    }
+
+    #[expect(clippy::expect_used)]
+    async fn next_jobs_update(rx_event: &async_channel::Receiver<Event>) -> Vec<u32> {
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(10), rx_event.recv())
+                .await
+                .expect("timed out waiting for a jobs update")
+                .expect("event channel closed");
+            if let EventMsg::BackgroundJobsUpdate(update) = event.msg {
+                return update.running.iter().map(|job| job.job_id).collect();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn job_output_is_logged_and_exit_is_reported() {
+        let codex_home = tempfile::TempDir::new().unwrap();
+        let manager = BackgroundJobManager::new(codex_home.path().join("jobs"));
+        let (status, rx_event) = start(&manager, "echo one; echo two >&2; echo three").await;
+        assert_eq!(status.job_id, 1);
+        assert!(status.running);
+        assert_eq!(
+            status.log_path,
+            codex_home.path().join("jobs").join("1.log")
+        );
+
+        assert_eq!(next_jobs_update(&rx_event).await, vec![1]);
+        assert_eq!(next_jobs_update(&rx_event).await, Vec::<u32>::new());
+
+        let tail = manager.tail(1, 2).await.unwrap();
+        assert_eq!(tail.output, "[earlier output omitted]\ntwo\nthree");
+        assert_eq!(tail.metadata.exit_code, Some(0));
+        assert!(!tail.metadata.running);
+        assert_eq!(
+            std::fs::read_to_string(&status.log_path).unwrap(),
+            "one\ntwo\nthree\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn running_job_can_be_killed() {
+        let codex_home = tempfile::TempDir::new().unwrap();
+        let manager = BackgroundJobManager::new(codex_home.path().to_path_buf());
+        let (status, _rx_event) = start(&manager, "sleep 60 & wait").await;
+
+        let statuses = manager.status(None).unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert!(statuses[0].running);
+
+        let killed = manager.kill(status.job_id).await.unwrap();
+        assert_eq!(killed.exit_code, Some(128 + libc::SIGKILL));
+        assert!(matches!(
+            manager.status(Some(2)),
+            Err(BackgroundJobError::UnknownJob(2))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn kill_all_reaches_processes_that_outlived_the_job() {
+        let codex_home = tempfile::TempDir::new().unwrap();
+        let manager = BackgroundJobManager::new(codex_home.path().to_path_buf());
+        let (status, rx_event) = start(&manager, "sleep 60 & echo $!").await;
+        assert_eq!(next_jobs_update(&rx_event).await, vec![1]);
+        assert_eq!(next_jobs_update(&rx_event).await, Vec::<u32>::new());
+        let sleep_pid = std::fs::read_to_string(&status.log_path).unwrap();
+        let proc_stat = format!("/proc/{}/stat", sleep_pid.trim());
+        // A killed orphan may linger as a zombie until it is reaped.
+        let alive = || {
+            std::fs::read_to_string(&proc_stat)
+                .is_ok_and(|stat| !stat.rsplit(')').next().unwrap().trim().starts_with('Z'))
+        };
+        assert!(alive());
+
+        manager.kill_all();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while alive() && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!alive());
+    }
+}
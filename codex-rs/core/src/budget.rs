@@ -0,0 +1,198 @@
+//! Token pricing and spend limits.
+//!
+//! Prices are declared per model under a provider's `pricing` table; limits
+//! come from `max_cost_usd` / `max_total_tokens` at the session, agent and
+//! workflow level and are folded into a single [`Budget`] per session.
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::protocol::TokenUsage;
+
+/// Prices for a single model, in US dollars per million tokens.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    /// Price for input tokens served from the prompt cache. Defaults to
+    /// `input` when the provider does not discount cached tokens.
+    pub cached_input: Option<f64>,
+    /// Price for output tokens, reasoning tokens included.
+    pub output: f64,
+}
+
+impl ModelPricing {
+    pub fn cost_usd(&self, usage: &TokenUsage) -> f64 {
+        let cached_input = self.cached_input.unwrap_or(self.input);
+        (usage.non_cached_input() as f64 * self.input
+            + usage.cached_input() as f64 * cached_input
+            + usage.output_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// Spend limits for a session. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    pub max_cost_usd: Option<f64>,
+    pub max_total_tokens: Option<u64>,
+}
+
+impl Budget {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_cost_usd.is_none() && self.max_total_tokens.is_none()
+    }
+
+    /// Combines two budgets, keeping the stricter of each limit.
+    pub fn min(self, other: Budget) -> Budget {
+        fn stricter<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+                (a, b) => a.or(b),
+            }
+        }
+        Budget {
+            max_cost_usd: stricter(self.max_cost_usd, other.max_cost_usd),
+            max_total_tokens: stricter(self.max_total_tokens, other.max_total_tokens),
+        }
+    }
+
+    /// What is left of this budget once `spend` has been used, e.g. to carry a
+    /// workflow budget over into its next step.
+    pub fn remaining(self, spend: &Spend) -> Budget {
+        Budget {
+            max_cost_usd: self
+                .max_cost_usd
+                .map(|max| (max - spend.cost_usd.unwrap_or(0.0)).max(0.0)),
+            max_total_tokens: self
+                .max_total_tokens
+                .map(|max| max.saturating_sub(spend.total_tokens)),
+        }
+    }
+
+    /// Returns a description of the limit `spend` has reached, if any.
+    pub fn exceeded_by(&self, spend: &Spend) -> Option<String> {
+        if let Some(max) = self.max_total_tokens
+            && spend.total_tokens >= max
+        {
+            return Some(format!(
+                "token budget exhausted: {} of {max} tokens used",
+                spend.total_tokens
+            ));
+        }
+        if let Some(max) = self.max_cost_usd
+            && let Some(cost) = spend.cost_usd
+            && cost >= max
+        {
+            return Some(format!(
+                "cost budget exhausted: ${cost:.4} of ${max:.4} spent"
+            ));
+        }
+        None
+    }
+}
+
+/// Running totals of what a session has consumed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spend {
+    pub total_tokens: u64,
+    /// `None` until a response from a model with known pricing is recorded.
+    pub cost_usd: Option<f64>,
+}
+
+impl Spend {
+    /// Records a response's usage and, when its pricing is known, its cost.
+    pub fn add(&mut self, usage: &TokenUsage, cost_usd: Option<f64>) {
+        self.total_tokens += usage.total_tokens;
+        if let Some(cost_usd) = cost_usd {
+            *self.cost_usd.get_or_insert(0.0) += cost_usd;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn usage(input: u64, cached: u64, output: u64) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            cached_input_tokens: Some(cached),
+            output_tokens: output,
+            reasoning_output_tokens: None,
+            total_tokens: input + output,
+        }
+    }
+
+    #[test]
+    fn cost_discounts_cached_input() {
+        let pricing = ModelPricing {
+            input: 2.0,
+            cached_input: Some(0.5),
+            output: 8.0,
+        };
+        let cost = pricing.cost_usd(&usage(1_000_000, 400_000, 500_000));
+        assert!((cost - (1.2 + 0.2 + 4.0)).abs() < 1e-9, "cost = {cost}");
+    }
+
+    #[test]
+    fn budget_reports_first_exhausted_limit() {
+        let budget = Budget {
+            max_cost_usd: Some(1.0),
+            max_total_tokens: Some(2_000),
+        };
+        let pricing = ModelPricing {
+            input: 1_000.0,
+            cached_input: None,
+            output: 1_000.0,
+        };
+
+        let mut spend = Spend::default();
+        let response = usage(400, 0, 100);
+        spend.add(&response, Some(pricing.cost_usd(&response)));
+        assert_eq!(None, budget.exceeded_by(&spend));
+
+        spend.add(&response, Some(pricing.cost_usd(&response)));
+        assert_eq!(
+            Some("cost budget exhausted: $1.0000 of $1.0000 spent".to_string()),
+            budget.exceeded_by(&spend)
+        );
+
+        spend.add(&usage(1_000, 0, 0), None);
+        assert_eq!(
+            Some("token budget exhausted: 2000 of 2000 tokens used".to_string()),
+            budget.exceeded_by(&spend)
+        );
+    }
+
+    #[test]
+    fn min_and_remaining_combine_budgets() {
+        let workflow = Budget {
+            max_cost_usd: Some(5.0),
+            max_total_tokens: None,
+        };
+        let agent = Budget {
+            max_cost_usd: Some(10.0),
+            max_total_tokens: Some(2_000),
+        };
+        assert_eq!(
+            Budget {
+                max_cost_usd: Some(5.0),
+                max_total_tokens: Some(2_000),
+            },
+            workflow.min(agent)
+        );
+
+        let spend = Spend {
+            total_tokens: 3_000,
+            cost_usd: Some(6.0),
+        };
+        assert_eq!(
+            Budget {
+                max_cost_usd: Some(0.0),
+                max_total_tokens: Some(0),
+            },
+            agent.min(workflow).remaining(&spend)
+        );
+    }
+}
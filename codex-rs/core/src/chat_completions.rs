@@ -26,6 +26,7 @@ use crate::models::ContentItem;
 use crate::models::ReasoningItemContent;
 use crate::models::ResponseItem;
 use crate::openai_tools::create_tools_json_for_chat_completions_api;
+use crate::output_schema::OUTPUT_SCHEMA_NAME;
 use crate::util::backoff;
 
 /// Implementation for the classic Chat Completions API.
@@ -110,12 +111,22 @@ pub(crate) async fn stream_chat_completions(
     }
 
     let tools_json = create_tools_json_for_chat_completions_api(&prompt.tools)?;
-    let payload = json!({
+    let mut payload = json!({
         "model": model_family.slug,
         "messages": messages,
         "stream": true,
         "tools": tools_json,
     });
+    if let Some(schema) = &prompt.output_schema {
+        payload["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": OUTPUT_SCHEMA_NAME,
+                "schema": schema,
+                "strict": false,
+            },
+        });
+    }
 
     debug!(
         "POST to {}: {}",
@@ -592,6 +603,9 @@ where
                 Poll::Ready(Some(Ok(ResponseEvent::ReasoningSummaryPartAdded))) => {
                     continue;
                 }
+                Poll::Ready(Some(Ok(ResponseEvent::ProviderFallback(message)))) => {
+                    return Poll::Ready(Some(Ok(ResponseEvent::ProviderFallback(message))));
+                }
             }
         }
     }
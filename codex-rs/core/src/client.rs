@@ -1,6 +1,8 @@
 use std::io::BufRead;
 use std::path::Path;
 use std::sync::OnceLock;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 
 use bytes::Bytes;
@@ -21,12 +23,15 @@ use tracing::trace;
 use tracing::warn;
 use uuid::Uuid;
 
+use crate::anthropic_messages::stream_messages;
+use crate::budget::ModelPricing;
 use crate::chat_completions::AggregateStreamExt;
 use crate::chat_completions::stream_chat_completions;
 use crate::client_common::Prompt;
 use crate::client_common::ResponseEvent;
 use crate::client_common::ResponseStream;
 use crate::client_common::ResponsesApiRequest;
+use crate::client_common::TextControls;
 use crate::client_common::create_reasoning_param_for_request;
 use crate::config::Config;
 use crate::error::CodexErr;
@@ -34,9 +39,11 @@ use crate::error::Result;
 use crate::error::UsageLimitReachedError;
 use crate::flags::CODEX_RS_SSE_FIXTURE;
 use crate::model_family::ModelFamily;
+use crate::model_family::find_family_for_model_with_metadata;
 use crate::model_provider_info::ModelProviderInfo;
 use crate::model_provider_info::WireApi;
 use crate::models::ResponseItem;
+use crate::openai_model_info::get_model_limits;
 use crate::openai_tools::create_tools_json_for_responses_api;
 use crate::protocol::TokenUsage;
 use crate::user_agent::get_codex_user_agent;
@@ -66,6 +73,12 @@ pub struct ModelClient {
     session_id: Uuid,
     effort: ReasoningEffortConfig,
     summary: ReasoningSummaryConfig,
+    /// Clients for `config.model_fallbacks`, tried in order once the active
+    /// one gives up.
+    fallbacks: Arc<Vec<ModelClient>>,
+    /// Which client serves requests: `0` is `self`, `n` is `fallbacks[n - 1]`.
+    /// Shared between clones so that a switch sticks for the whole session.
+    active: Arc<AtomicUsize>,
 }
 
 impl ModelClient {
@@ -77,6 +90,44 @@ impl ModelClient {
         summary: ReasoningSummaryConfig,
         session_id: Uuid,
     ) -> Self {
+        let fallbacks = config
+            .model_fallbacks
+            .iter()
+            .map(|fallback| {
+                let mut fallback_config = (*config).clone();
+                fallback_config.model_family =
+                    find_family_for_model_with_metadata(&fallback.model, &config.models)
+                        .unwrap_or_else(|| ModelFamily {
+                            slug: fallback.model.clone(),
+                            family: fallback.model.clone(),
+                            ..config.model_family.clone()
+                        });
+                (
+                    fallback_config.model_context_window,
+                    fallback_config.model_max_output_tokens,
+                ) = get_model_limits(&fallback_config.model_family, &config.models);
+                fallback_config.model = fallback.model.clone();
+                fallback_config.model_provider_id = fallback.provider_id.clone();
+                fallback_config.model_provider = fallback.provider.clone();
+                // The chain is flat: a fallback never has fallbacks of its own.
+                fallback_config.model_fallbacks = Vec::new();
+                // The OpenAI credentials must not reach a third-party provider.
+                let fallback_auth = if fallback.provider.requires_openai_auth {
+                    auth.clone()
+                } else {
+                    None
+                };
+                Self::new(
+                    Arc::new(fallback_config),
+                    fallback_auth,
+                    fallback.provider.clone(),
+                    effort,
+                    summary,
+                    session_id,
+                )
+            })
+            .collect();
+
         Self {
             config,
             auth,
@@ -85,13 +136,81 @@ impl ModelClient {
             session_id,
             effort,
             summary,
+            fallbacks: Arc::new(fallbacks),
+            active: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    /// Dispatches to either the Responses or Chat implementation depending on
-    /// the provider config.  Public callers always invoke `stream()` – the
-    /// specialised helpers are private to avoid accidental misuse.
+    /// Builds a client for `config` with new reasoning settings, keeping this
+    /// client's provider, auth and session. The position in the fallback chain
+    /// is shared with this client, so a provider that was given up on is not
+    /// contacted again after the model or settings change.
+    pub fn with_config(
+        &self,
+        config: Arc<Config>,
+        effort: ReasoningEffortConfig,
+        summary: ReasoningSummaryConfig,
+    ) -> Self {
+        let mut client = Self::new(
+            config,
+            self.auth.clone(),
+            self.provider.clone(),
+            effort,
+            summary,
+            self.session_id,
+        );
+        client.active = Arc::clone(&self.active);
+        client
+    }
+
+    /// Streams a response from the active provider. When it fails with a 5xx
+    /// or rate-limit error after exhausting `request_max_retries`, the same
+    /// prompt (and therefore the same history) is sent to the next entry of
+    /// the fallback chain. Each switch is reported to the caller as a
+    /// [`ResponseEvent::ProviderFallback`] at the start of the stream.
     pub async fn stream(&self, prompt: &Prompt) -> Result<ResponseStream> {
+        let mut index = self.active.load(Ordering::Relaxed);
+        let mut notices = Vec::new();
+        loop {
+            let client = match index.checked_sub(1) {
+                None => self,
+                Some(i) => &self.fallbacks[i],
+            };
+            match client.stream_from_provider(prompt).await {
+                Ok(stream) if notices.is_empty() => return Ok(stream),
+                Ok(stream) => return Ok(prepend_events(stream, notices)),
+                Err(e @ (CodexErr::RetryLimit(_) | CodexErr::InternalServerError))
+                    if index < self.fallbacks.len() =>
+                {
+                    let next = &self.fallbacks[index];
+                    let message = format!(
+                        "{} ({}) unavailable: {e}; falling back to {} ({})",
+                        client.provider.name,
+                        client.config.model,
+                        next.provider.name,
+                        next.config.model,
+                    );
+                    warn!("{message}");
+                    notices.push(ResponseEvent::ProviderFallback(message));
+                    index += 1;
+                    self.active.store(index, Ordering::Relaxed);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Dispatches to the Responses, Chat or Messages implementation depending
+    /// on the provider config.  Public callers always invoke `stream()` – the
+    /// specialised helpers are private to avoid accidental misuse.
+    async fn stream_from_provider(&self, prompt: &Prompt) -> Result<ResponseStream> {
+        let schema_prompt;
+        let prompt = if prompt.output_schema.is_some() && !self.supports_output_schema() {
+            schema_prompt = prompt.with_output_schema_in_instructions();
+            &schema_prompt
+        } else {
+            prompt
+        };
         match self.provider.wire_api {
             WireApi::Responses => self.stream_responses(prompt).await,
             WireApi::Chat => {
@@ -129,6 +248,17 @@ impl ModelClient {
 
                 Ok(ResponseStream { rx_event: rx })
             }
+            WireApi::Messages => {
+                stream_messages(
+                    prompt,
+                    &self.config.model_family,
+                    self.effort,
+                    self.config.model_max_output_tokens,
+                    &self.client,
+                    &self.provider,
+                )
+                .await
+            }
         }
     }
 
@@ -176,6 +306,7 @@ impl ModelClient {
             stream: true,
             include,
             prompt_cache_key: Some(self.session_id.to_string()),
+            text: prompt.output_schema.as_ref().map(TextControls::json_schema),
         };
 
         let mut attempt = 0;
@@ -337,8 +468,33 @@ impl ModelClient {
         self.summary
     }
 
-    pub fn get_auth(&self) -> Option<CodexAuth> {
-        self.auth.clone()
+    /// Returns the JSON Schema the final message should conform to, if any.
+    pub fn get_output_schema(&self) -> Option<serde_json::Value> {
+        self.config.output_schema.clone()
+    }
+
+    /// Whether the output schema can be sent as a response format, per the
+    /// model's `[models.<slug>]` table, then the provider, then the wire API.
+    fn supports_output_schema(&self) -> bool {
+        if self.provider.wire_api == WireApi::Messages {
+            return false;
+        }
+        self.config
+            .models
+            .get(&self.config.model)
+            .and_then(|metadata| metadata.supports_output_schema)
+            .or(self.provider.supports_output_schema)
+            .unwrap_or(self.provider.wire_api == WireApi::Responses)
+    }
+
+    /// Returns the price of the model currently serving requests, taking a
+    /// provider fallback into account.
+    pub fn get_pricing(&self) -> Option<ModelPricing> {
+        let client = match self.active.load(Ordering::Relaxed).checked_sub(1) {
+            None => self,
+            Some(i) => &self.fallbacks[i],
+        };
+        client.provider.pricing_for(&client.config.model)
     }
 }
 
@@ -576,6 +732,24 @@ async fn process_sse<S>(
     }
 }
 
+/// Returns a stream that yields `events` before everything from `stream`.
+fn prepend_events(mut stream: ResponseStream, events: Vec<ResponseEvent>) -> ResponseStream {
+    let (tx, rx) = mpsc::channel::<Result<ResponseEvent>>(1600);
+    tokio::spawn(async move {
+        for event in events {
+            if tx.send(Ok(event)).await.is_err() {
+                return;
+            }
+        }
+        while let Some(ev) = stream.next().await {
+            if tx.send(ev).await.is_err() {
+                break;
+            }
+        }
+    });
+    ResponseStream { rx_event: rx }
+}
+
 /// used in tests to stream from a text SSE file
 async fn stream_from_fixture(
     path: impl AsRef<Path>,
@@ -750,6 +924,9 @@ mod tests {
             stream_max_retries: Some(0),
             stream_idle_timeout_ms: Some(1000),
             requires_openai_auth: false,
+            fallback: None,
+            pricing: None,
+            supports_output_schema: None,
         };
 
         let events = collect_events(
@@ -810,6 +987,9 @@ mod tests {
             stream_max_retries: Some(0),
             stream_idle_timeout_ms: Some(1000),
             requires_openai_auth: false,
+            fallback: None,
+            pricing: None,
+            supports_output_schema: None,
         };
 
         let events = collect_events(&[sse1.as_bytes()], provider).await;
@@ -844,6 +1024,9 @@ mod tests {
             stream_max_retries: Some(0),
             stream_idle_timeout_ms: Some(1000),
             requires_openai_auth: false,
+            fallback: None,
+            pricing: None,
+            supports_output_schema: None,
         };
 
         let events = collect_events(&[sse1.as_bytes()], provider).await;
@@ -949,6 +1132,9 @@ mod tests {
                 stream_max_retries: Some(0),
                 stream_idle_timeout_ms: Some(1000),
                 requires_openai_auth: false,
+                fallback: None,
+                pricing: None,
+                supports_output_schema: None,
             };
 
             let out = run_sse(evs, provider).await;
@@ -3,6 +3,7 @@ use crate::model_family::ModelFamily;
 use crate::models::ContentItem;
 use crate::models::ResponseItem;
 use crate::openai_tools::OpenAiTool;
+use crate::output_schema::OUTPUT_SCHEMA_NAME;
 use crate::protocol::TokenUsage;
 use codex_apply_patch::APPLY_PATCH_TOOL_INSTRUCTIONS;
 use codex_protocol::config_types::ReasoningEffort as ReasoningEffortConfig;
@@ -38,6 +39,13 @@ pub struct Prompt {
 
     /// Optional override for the built-in BASE_INSTRUCTIONS.
     pub base_instructions_override: Option<String>,
+
+    /// JSON Schema to request as the response format for the final message.
+    pub output_schema: Option<serde_json::Value>,
+
+    /// JSON Schema the final message is asked to follow in the instructions,
+    /// for providers and models that do not accept a response format.
+    pub output_schema_instructions: Option<serde_json::Value>,
 }
 
 impl Prompt {
@@ -46,13 +54,29 @@ impl Prompt {
             .base_instructions_override
             .as_deref()
             .unwrap_or(BASE_INSTRUCTIONS);
-        let mut sections: Vec<&str> = vec![base];
+        let mut sections: Vec<Cow<'_, str>> = vec![Cow::Borrowed(base)];
         if model.needs_special_apply_patch_instructions {
-            sections.push(APPLY_PATCH_TOOL_INSTRUCTIONS);
+            sections.push(Cow::Borrowed(APPLY_PATCH_TOOL_INSTRUCTIONS));
+        }
+        if let Some(schema) = &self.output_schema_instructions {
+            sections.push(Cow::Owned(format!(
+                "Your final message must be a single JSON document, with no other text, that conforms to this JSON Schema:\n{}",
+                serde_json::to_string_pretty(schema).unwrap_or_default()
+            )));
         }
         Cow::Owned(sections.join("\n"))
     }
 
+    /// Moves the output schema from the response format into the
+    /// instructions. The final message is validated by the caller either way.
+    pub(crate) fn with_output_schema_in_instructions(&self) -> Prompt {
+        Prompt {
+            output_schema: None,
+            output_schema_instructions: self.output_schema.clone(),
+            ..self.clone()
+        }
+    }
+
     pub(crate) fn get_formatted_input(&self) -> Vec<ResponseItem> {
         self.input.clone()
     }
@@ -81,6 +105,9 @@ pub enum ResponseEvent {
     ReasoningSummaryDelta(String),
     ReasoningContentDelta(String),
     ReasoningSummaryPartAdded,
+    /// Emitted first on a stream served by a fallback provider, describing
+    /// why [`crate::client::ModelClient`] switched away from the previous one.
+    ProviderFallback(String),
 }
 
 #[derive(Debug, Serialize)]
@@ -109,6 +136,38 @@ pub(crate) struct ResponsesApiRequest<'a> {
     pub(crate) include: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) prompt_cache_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) text: Option<TextControls<'a>>,
+}
+
+/// `text` parameter of a Responses API request, used to constrain the final
+/// message to a JSON Schema.
+#[derive(Debug, Serialize)]
+pub(crate) struct TextControls<'a> {
+    pub(crate) format: TextFormat<'a>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct TextFormat<'a> {
+    pub(crate) r#type: &'static str,
+    pub(crate) name: &'static str,
+    pub(crate) schema: &'a serde_json::Value,
+    /// Strict mode only accepts a subset of JSON Schema, so it is left off
+    /// and the final message is validated by the caller instead.
+    pub(crate) strict: bool,
+}
+
+impl<'a> TextControls<'a> {
+    pub(crate) fn json_schema(schema: &'a serde_json::Value) -> Self {
+        Self {
+            format: TextFormat {
+                r#type: "json_schema",
+                name: OUTPUT_SCHEMA_NAME,
+                schema,
+                strict: false,
+            },
+        }
+    }
 }
 
 pub(crate) fn create_reasoning_param_for_request(
@@ -151,4 +210,21 @@ mod tests {
         let full = prompt.get_full_instructions(&model_family);
         assert_eq!(full, expected);
     }
+
+    #[test]
+    fn output_schema_moves_into_instructions() {
+        let schema = serde_json::json!({"type": "object"});
+        let prompt = Prompt {
+            output_schema: Some(schema.clone()),
+            ..Default::default()
+        }
+        .with_output_schema_in_instructions();
+        assert_eq!(prompt.output_schema, None);
+        assert_eq!(prompt.output_schema_instructions, Some(schema));
+
+        let model_family = find_family_for_model("o3").expect("known model slug");
+        let full = prompt.get_full_instructions(&model_family);
+        assert!(full.starts_with(BASE_INSTRUCTIONS));
+        assert!(full.ends_with("conforms to this JSON Schema:\n{\n  \"type\": \"object\"\n}"));
+    }
 }
@@ -1,6 +1,8 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -11,14 +13,17 @@ use std::time::Duration;
 use async_channel::Receiver;
 use async_channel::Sender;
 use codex_apply_patch::ApplyPatchAction;
+use codex_apply_patch::ApplyPatchOptions;
 use codex_apply_patch::MaybeApplyPatchVerified;
-use codex_apply_patch::maybe_parse_apply_patch_verified;
+use codex_apply_patch::maybe_parse_apply_patch_verified_with_options;
 use codex_login::CodexAuth;
 use codex_protocol::protocol::TurnAbortReason;
 use codex_protocol::protocol::TurnAbortedEvent;
 use futures::prelude::*;
 use mcp_types::CallToolResult;
+use reqwest::Url;
 use serde::Serialize;
+use serde_bytes::ByteBuf;
 use serde_json;
 use tokio::sync::oneshot;
 use tokio::task::AbortHandle;
@@ -33,29 +38,63 @@ use crate::ModelProviderInfo;
 use crate::apply_patch;
 use crate::apply_patch::ApplyPatchExec;
 use crate::apply_patch::CODEX_APPLY_PATCH_ARG1;
+use crate::apply_patch::CODEX_APPLY_PATCH_FUZZY_THRESHOLD_ENV_VAR;
+use crate::apply_patch::CODEX_APPLY_PATCH_REPORT_RESULTS_ENV_VAR;
 use crate::apply_patch::InternalApplyPatchInvocation;
 use crate::apply_patch::convert_apply_patch_to_protocol;
+use crate::background_jobs;
+use crate::background_jobs::BackgroundJobManager;
+use crate::background_jobs::BackgroundJobStartParams;
+use crate::background_jobs::handle_background_job_call;
+use crate::background_jobs::job_call_output;
+use crate::budget::Budget;
+use crate::budget::ModelPricing;
+use crate::budget::Spend;
 use crate::client::ModelClient;
 use crate::client_common::Prompt;
 use crate::client_common::ResponseEvent;
+use crate::command_approvals::ApprovedCommands;
+use crate::command_approvals::CommandPattern;
 use crate::config::Config;
+use crate::config_types::Redaction;
+use crate::config_types::ResourceLimits;
 use crate::config_types::ShellEnvironmentPolicy;
+use crate::config_types::WebSearchConfig;
+use crate::content_search::handle_search_file_contents;
 use crate::conversation_history::ConversationHistory;
 use crate::environment_context::EnvironmentContext;
 use crate::error::CodexErr;
 use crate::error::Result as CodexResult;
 use crate::error::SandboxErr;
 use crate::error::get_error_message_ui;
+use crate::exec::ExecOutputLimits;
 use crate::exec::ExecParams;
 use crate::exec::ExecToolCallOutput;
 use crate::exec::SandboxType;
 use crate::exec::StdoutStream;
 use crate::exec::StreamOutput;
 use crate::exec::process_exec_tool_call;
+use crate::exec::prune_stale_output_dirs;
+use crate::exec::session_output_dir;
 use crate::exec_env::create_env;
+use crate::exec_policy::ExecPolicy;
+use crate::exec_session;
+use crate::exec_session::DEFAULT_START_YIELD_TIME_MS;
+use crate::exec_session::ExecSessionManager;
+use crate::exec_session::ExecSessionStartParams;
+use crate::exec_session::handle_exec_session_call;
+use crate::exec_session::session_call_output;
+use crate::file_tools::EditFileParams;
+use crate::file_tools::FileEdit;
+use crate::file_tools::ReadScope;
+use crate::file_tools::function_output;
+use crate::file_tools::handle_list_dir;
+use crate::file_tools::handle_read_file;
+use crate::file_tools::prepare_edit;
+use crate::git_snapshot::GitSnapshot;
 use crate::mcp_connection_manager::McpConnectionManager;
 use crate::mcp_tool_call::handle_mcp_tool_call;
-use crate::model_family::find_family_for_model;
+use crate::model_family::find_family_for_model_with_metadata;
 use crate::models::ContentItem;
 use crate::models::FunctionCallOutputPayload;
 use crate::models::LocalShellAction;
@@ -64,6 +103,9 @@ use crate::models::ReasoningItemReasoningSummary;
 use crate::models::ResponseInputItem;
 use crate::models::ResponseItem;
 use crate::models::ShellToolCallParams;
+use crate::notebook::EditNotebookParams;
+use crate::notebook::handle_read_notebook;
+use crate::notebook::prepare_notebook_edit;
 use crate::openai_tools::ApplyPatchToolArgs;
 use crate::openai_tools::ToolsConfig;
 use crate::openai_tools::get_openai_tools;
@@ -91,20 +133,38 @@ use crate::protocol::InputItem;
 use crate::protocol::Op;
 use crate::protocol::PatchApplyBeginEvent;
 use crate::protocol::PatchApplyEndEvent;
+use crate::protocol::PatchApplyFileResult;
+use crate::protocol::PatchApplyFileStatus;
 use crate::protocol::ReviewDecision;
 use crate::protocol::SandboxPolicy;
 use crate::protocol::SessionConfiguredEvent;
 use crate::protocol::Submission;
 use crate::protocol::TaskCompleteEvent;
+use crate::protocol::TokenCountEvent;
+use crate::protocol::TokenUsage;
 use crate::protocol::TurnDiffEvent;
+use crate::redact::RedactionCounts;
+use crate::redact::Redactor;
+use crate::repo_map::handle_repo_map;
+use crate::repo_map::repo_map_summary;
 use crate::rollout::RolloutRecorder;
 use crate::safety::SafetyCheck;
 use crate::safety::assess_command_safety;
+use crate::safety::assess_direct_write_safety;
 use crate::safety::assess_safety_for_untrusted_command;
 use crate::shell;
 use crate::turn_diff_tracker::TurnDiffTracker;
 use crate::user_notification::UserNotification;
 use crate::util::backoff;
+use crate::view_image::ViewImageParams;
+use crate::view_image::load_image;
+use crate::web_tools::WebFetchParams;
+use crate::web_tools::WebSearchParams;
+use crate::web_tools::parse_web_url;
+use crate::web_tools::sandbox_allows_host;
+use crate::web_tools::search_endpoint;
+use crate::web_tools::web_fetch;
+use crate::web_tools::web_search;
 use codex_protocol::config_types::ReasoningEffort as ReasoningEffortConfig;
 use codex_protocol::config_types::ReasoningSummary as ReasoningSummaryConfig;
 
@@ -129,6 +189,12 @@ pub struct Codex {
     next_id: AtomicU64,
     tx_sub: Sender<Submission>,
     rx_event: Receiver<Event>,
+    redactor: Arc<Redactor>,
+    /// Secrets redacted so far from the streamed output of each running
+    /// command, by call id.
+    streamed_redactions: Mutex<HashMap<String, RedactionCounts>>,
+    /// Events to return before receiving more, e.g. redaction notices.
+    pending_events: Mutex<VecDeque<Event>>,
 }
 
 /// Wrapper returned by [`Codex::spawn`] containing the spawned [`Codex`],
@@ -176,6 +242,7 @@ impl Codex {
                     CodexErr::InternalAgentDied
                 })?;
         let session_id = session.session_id;
+        let redactor = session.redactor.clone();
 
         // This task will run until Op::Shutdown is received.
         tokio::spawn(submission_loop(session, turn_context, config, rx_sub));
@@ -183,6 +250,9 @@ impl Codex {
             next_id: AtomicU64::new(0),
             tx_sub,
             rx_event,
+            redactor,
+            streamed_redactions: Mutex::new(HashMap::new()),
+            pending_events: Mutex::new(VecDeque::new()),
         };
 
         Ok(CodexSpawnOk { codex, session_id })
@@ -210,23 +280,88 @@ impl Codex {
     }
 
     pub async fn next_event(&self) -> CodexResult<Event> {
-        let event = self
+        if let Some(event) = self.pending_events.lock_unchecked().pop_front() {
+            return Ok(event);
+        }
+        let mut event = self
             .rx_event
             .recv()
             .await
             .map_err(|_| CodexErr::InternalAgentDied)?;
+        if let Some(message) = self.redact_command_output(&mut event.msg) {
+            self.pending_events.lock_unchecked().push_back(Event {
+                id: event.id.clone(),
+                msg: EventMsg::BackgroundEvent(BackgroundEventEvent { message }),
+            });
+        }
         Ok(event)
     }
+
+    /// Command output reaches clients as it streams, before it is turned into
+    /// a tool output, so it is redacted here. Secrets split across two chunks
+    /// are missed; the tool output the model sees is redacted as a whole.
+    ///
+    /// Returns a notice of what was redacted once the command ends.
+    fn redact_command_output(&self, msg: &mut EventMsg) -> Option<String> {
+        let mut counts = RedactionCounts::default();
+        match msg {
+            EventMsg::ExecCommandOutputDelta(delta) => {
+                let mut chunk = std::mem::take(&mut delta.chunk).into_vec();
+                self.redactor.redact_bytes(&mut chunk, &mut counts);
+                delta.chunk = ByteBuf::from(chunk);
+                if !counts.is_empty() {
+                    self.streamed_redactions
+                        .lock_unchecked()
+                        .entry(delta.call_id.clone())
+                        .or_default()
+                        .add_all(&counts);
+                }
+                None
+            }
+            EventMsg::ExecCommandEnd(end) => {
+                for text in [&mut end.stdout, &mut end.stderr] {
+                    if let Cow::Owned(redacted) = self.redactor.redact(text, &mut counts) {
+                        *text = redacted;
+                    }
+                }
+                // The streamed output and the final output are the same text
+                // shown twice, but either may hold secrets the other lacks:
+                // the final output can be truncated, and streaming misses
+                // secrets split across chunks.
+                if let Some(streamed) = self
+                    .streamed_redactions
+                    .lock_unchecked()
+                    .remove(&end.call_id)
+                {
+                    counts.max_with(&streamed);
+                }
+                (!counts.is_empty()).then(|| {
+                    format!(
+                        "redacted {} secret(s) from the output of a command ({counts})",
+                        counts.total()
+                    )
+                })
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Mutable state of the agent
 #[derive(Default)]
 struct State {
-    approved_commands: HashSet<Vec<String>>,
+    approved_commands: ApprovedCommands,
+    /// Hosts the web tools may reach even when the sandbox blocks the network.
+    approved_hosts: HashSet<String>,
     current_task: Option<AgentTask>,
     pending_approvals: HashMap<String, oneshot::Sender<ReviewDecision>>,
     pending_input: Vec<ResponseInputItem>,
     history: ConversationHistory,
+    spend: Spend,
+    /// Working tree snapshots taken around each turn, keyed by the turn's
+    /// submission id, most recent last.
+    turn_snapshots: Vec<(String, GitSnapshot)>,
+    turns_snapshotted: u64,
 }
 
 /// Context for an initialized model agent
@@ -250,6 +385,21 @@ pub(crate) struct Session {
     codex_linux_sandbox_exe: Option<PathBuf>,
     user_shell: shell::Shell,
     show_raw_agent_reasoning: bool,
+    budget: Budget,
+    /// User and project exec policies, loaded once per session.
+    exec_policy: ExecPolicy,
+    /// Scrubs secrets from tool output, streamed command output and rollouts.
+    redactor: Arc<Redactor>,
+    apply_patch_options: ApplyPatchOptions,
+    codex_home: PathBuf,
+    /// Key under `[projects]` in `config.toml` that project approvals are
+    /// read from and saved to.
+    project_dir: PathBuf,
+    /// Commands started with `exec_session_start`, killed on shutdown.
+    exec_sessions: ExecSessionManager,
+    /// Commands started with `background_job_start`, killed on shutdown.
+    background_jobs: BackgroundJobManager,
+    web_search: Option<WebSearchConfig>,
 }
 
 /// The context needed for a single turn of the conversation.
@@ -265,6 +415,8 @@ pub(crate) struct TurnContext {
     pub(crate) approval_policy: AskForApproval,
     pub(crate) sandbox_policy: SandboxPolicy,
     pub(crate) shell_environment_policy: ShellEnvironmentPolicy,
+    pub(crate) resource_limits: ResourceLimits,
+    pub(crate) exec_output_limits: ExecOutputLimits,
     pub(crate) disable_response_storage: bool,
     pub(crate) tools_config: ToolsConfig,
 }
@@ -347,6 +499,24 @@ impl Session {
         // Error messages to dispatch after SessionConfigured is sent.
         let mut post_session_configured_error_events = Vec::<Event>::new();
 
+        let redactor = Arc::new(match Redactor::new(&config.redaction) {
+            Ok(redactor) => redactor,
+            Err(e) => {
+                let message =
+                    format!("Invalid redaction pattern, redacting built-in secrets only: {e}");
+                error!("{message}");
+                post_session_configured_error_events.push(Event {
+                    id: INITIAL_SUBMIT_ID.to_owned(),
+                    msg: EventMsg::Error(ErrorEvent { message }),
+                });
+                Redactor::new(&Redaction {
+                    patterns: Vec::new(),
+                    ..config.redaction.clone()
+                })
+                .unwrap_or_else(|_| Redactor::disabled())
+            }
+        });
+
         // Kick off independent async setup tasks in parallel to reduce startup latency.
         //
         // - initialize RolloutRecorder with new or resumed session info
@@ -355,14 +525,19 @@ impl Session {
         // - load history metadata
         let rollout_fut = async {
             match resume_path.as_ref() {
-                Some(path) => RolloutRecorder::resume(path, cwd.clone())
+                Some(path) => RolloutRecorder::resume(path, cwd.clone(), redactor.clone())
                     .await
                     .map(|(rec, saved)| (saved.session_id, Some(saved), rec)),
                 None => {
                     let session_id = Uuid::new_v4();
-                    RolloutRecorder::new(&config, session_id, user_instructions.clone())
-                        .await
-                        .map(|rec| (session_id, None, rec))
+                    RolloutRecorder::new(
+                        &config,
+                        session_id,
+                        user_instructions.clone(),
+                        redactor.clone(),
+                    )
+                    .await
+                    .map(|rec| (session_id, None, rec))
                 }
             }
         };
@@ -429,6 +604,8 @@ impl Session {
 
         // Create the mutable state for the Session.
         let mut state = State {
+            approved_commands: ApprovedCommands::new(&config.project_approved_commands),
+            approved_hosts: config.project_approved_hosts.iter().cloned().collect(),
             history: ConversationHistory::new(),
             ..Default::default()
         };
@@ -462,6 +639,29 @@ impl Session {
             }
         }
 
+        prune_stale_output_dirs(&config.codex_home);
+        {
+            let cwd = cwd.clone();
+            tokio::spawn(async move {
+                if let Err(e) = GitSnapshot::prune_stale(&cwd).await {
+                    warn!("failed to prune stale snapshots: {e}");
+                }
+            });
+        }
+
+        let exec_policy = match ExecPolicy::load(&config.codex_home, &cwd) {
+            Ok(exec_policy) => exec_policy,
+            Err(e) => {
+                let message = format!("Failed to load exec policy: {e}");
+                error!("{message}");
+                post_session_configured_error_events.push(Event {
+                    id: INITIAL_SUBMIT_ID.to_owned(),
+                    msg: EventMsg::Error(ErrorEvent { message }),
+                });
+                ExecPolicy::default()
+            }
+        };
+
         // Now that `session_id` is final (may have been updated by resume),
         // construct the model client.
         let client = ModelClient::new(
@@ -480,12 +680,23 @@ impl Session {
                 sandbox_policy.clone(),
                 config.include_plan_tool,
                 config.include_apply_patch_tool,
+                config.include_exec_session_tools,
+                config.include_background_job_tools,
+                config.include_content_search_tool,
+                config.include_repo_map_tool,
+                config.include_file_tools,
+                config.include_web_fetch_tool,
+                config.web_search.is_some(),
+                config.include_view_image_tool,
+                config.include_notebook_tools,
             ),
             user_instructions,
             base_instructions,
             approval_policy,
             sandbox_policy,
             shell_environment_policy: config.shell_environment_policy.clone(),
+            resource_limits: config.resource_limits.clone(),
+            exec_output_limits: model_exec_output_limits(&config, &config.model, session_id),
             cwd,
             disable_response_storage,
         };
@@ -499,6 +710,20 @@ impl Session {
             codex_linux_sandbox_exe: config.codex_linux_sandbox_exe.clone(),
             user_shell: default_shell,
             show_raw_agent_reasoning: config.show_raw_agent_reasoning,
+            budget: config.budget,
+            exec_policy,
+            redactor,
+            apply_patch_options: ApplyPatchOptions {
+                fuzzy_threshold: config.apply_patch_fuzzy_threshold,
+                ..ApplyPatchOptions::default()
+            },
+            codex_home: config.codex_home.clone(),
+            project_dir: config.cwd.clone(),
+            exec_sessions: ExecSessionManager::default(),
+            background_jobs: BackgroundJobManager::new(
+                config.codex_home.join("jobs").join(session_id.to_string()),
+            ),
+            web_search: config.web_search.clone(),
         });
 
         // record the initial user instructions and environment context,
@@ -507,12 +732,30 @@ impl Session {
         if let Some(user_instructions) = turn_context.user_instructions.as_deref() {
             conversation_items.push(Prompt::format_user_instructions_message(user_instructions));
         }
-        conversation_items.push(ResponseItem::from(EnvironmentContext::new(
+        let mut environment_context = EnvironmentContext::new(
             turn_context.cwd.to_path_buf(),
             turn_context.approval_policy,
             turn_context.sandbox_policy.clone(),
-        )));
-        sess.record_conversation_items(&conversation_items).await;
+        );
+        if let Some(max_tokens) = config.repo_map_context_tokens {
+            environment_context.repo_map =
+                repo_map_summary(&config.codex_home, &turn_context.cwd, max_tokens).await;
+        }
+        conversation_items.push(ResponseItem::from(environment_context));
+        // Reported after `SessionConfigured`, like the errors below.
+        let counts = sess.record_state_snapshot(&conversation_items).await;
+        sess.state
+            .lock_unchecked()
+            .history
+            .record_items(&conversation_items);
+        if !counts.is_empty() {
+            post_session_configured_error_events.push(Event {
+                id: INITIAL_SUBMIT_ID.to_owned(),
+                msg: EventMsg::BackgroundEvent(BackgroundEventEvent {
+                    message: rollout_redaction_message(&counts),
+                }),
+            });
+        }
 
         // Dispatch the SessionConfiguredEvent first and then report any errors.
         let events = std::iter::once(Event {
@@ -566,6 +809,7 @@ impl Session {
         command: Vec<String>,
         cwd: PathBuf,
         reason: Option<String>,
+        matched_rule: Option<String>,
     ) -> oneshot::Receiver<ReviewDecision> {
         let (tx_approve, rx_approve) = oneshot::channel();
         let event = Event {
@@ -575,6 +819,7 @@ impl Session {
                 command,
                 cwd,
                 reason,
+                matched_rule,
             }),
         };
         let _ = self.tx_event.send(event).await;
@@ -620,19 +865,97 @@ impl Session {
 
     pub fn add_approved_command(&self, cmd: Vec<String>) {
         let mut state = self.state.lock_unchecked();
-        state.approved_commands.insert(cmd);
+        state.approved_commands.add_for_session(cmd);
+    }
+
+    /// Approves `cmd` for the project and saves it to `config.toml`. Scripts
+    /// that cannot be expressed as a [`CommandPattern`] are approved for the
+    /// session only.
+    async fn add_project_approved_command(&self, sub_id: &str, cmd: Vec<String>) {
+        let Some(pattern) = CommandPattern::for_command(&cmd) else {
+            self.add_approved_command(cmd);
+            self.notify_background_event(
+                sub_id,
+                "command is too complex to save as a project approval; approved for this session only",
+            )
+            .await;
+            return;
+        };
+        let pattern_str = pattern.to_string();
+        self.state
+            .lock_unchecked()
+            .approved_commands
+            .add_for_project(pattern);
+        if let Err(e) = crate::config::add_project_approved_command(
+            &self.codex_home,
+            &self.project_dir,
+            &pattern_str,
+        ) {
+            warn!("failed to save project approval `{pattern_str}`: {e:#}");
+            self.notify_background_event(
+                sub_id,
+                format!("failed to save project approval `{pattern_str}`: {e:#}"),
+            )
+            .await;
+        }
+    }
+
+    /// Approves network access to `host` for the project and saves it to
+    /// `config.toml`.
+    async fn add_project_approved_host(&self, sub_id: &str, host: &str) {
+        self.state
+            .lock_unchecked()
+            .approved_hosts
+            .insert(host.to_string());
+        if let Err(e) =
+            crate::config::add_project_approved_host(&self.codex_home, &self.project_dir, host)
+        {
+            warn!("failed to save project approval for host `{host}`: {e:#}");
+            self.notify_background_event(
+                sub_id,
+                format!("failed to save project approval for host `{host}`: {e:#}"),
+            )
+            .await;
+        }
+    }
+
+    async fn revoke_project_approval(&self, sub_id: &str, pattern: &str) {
+        if let Some(parsed) = CommandPattern::parse(pattern) {
+            self.state
+                .lock_unchecked()
+                .approved_commands
+                .remove_for_project(&parsed);
+        }
+        let message = match crate::config::remove_project_approved_command(
+            &self.codex_home,
+            &self.project_dir,
+            pattern,
+        ) {
+            Ok(()) => format!("revoked project approval `{pattern}`"),
+            Err(e) => {
+                warn!("failed to revoke project approval `{pattern}`: {e:#}");
+                format!("failed to revoke project approval `{pattern}`: {e:#}")
+            }
+        };
+        self.notify_background_event(sub_id, message).await;
     }
 
     /// Records items to both the rollout and the chat completions/ZDR
     /// transcript, if enabled.
-    async fn record_conversation_items(&self, items: &[ResponseItem]) {
+    async fn record_conversation_items(&self, sub_id: &str, items: &[ResponseItem]) {
         debug!("Recording items for conversation: {items:?}");
-        self.record_state_snapshot(items).await;
+        let counts = self.record_state_snapshot(items).await;
 
         self.state.lock_unchecked().history.record_items(items);
+        if !counts.is_empty() {
+            self.notify_background_event(sub_id, rollout_redaction_message(&counts))
+                .await;
+        }
     }
 
-    async fn record_state_snapshot(&self, items: &[ResponseItem]) {
+    /// Returns the secrets that were redacted from the rollout copy of
+    /// `items`.
+    async fn record_state_snapshot(&self, items: &[ResponseItem]) -> RedactionCounts {
         let snapshot = { crate::rollout::SessionStateSnapshot {} };
 
         let recorder = {
@@ -640,14 +963,16 @@ impl Session {
             guard.as_ref().cloned()
         };
 
-        if let Some(rec) = recorder {
-            if let Err(e) = rec.record_state(snapshot).await {
-                error!("failed to record rollout state: {e:#}");
-            }
-            if let Err(e) = rec.record_items(items).await {
-                error!("failed to record rollout items: {e:#}");
-            }
+        let Some(rec) = recorder else {
+            return RedactionCounts::default();
+        };
+        if let Err(e) = rec.record_state(snapshot).await {
+            error!("failed to record rollout state: {e:#}");
         }
+        rec.record_items(items).await.unwrap_or_else(|e| {
+            error!("failed to record rollout items: {e:#}");
+            RedactionCounts::default()
+        })
     }
 
     async fn on_exec_command_begin(
@@ -699,23 +1024,29 @@ impl Session {
         sub_id: &str,
         call_id: &str,
         output: &ExecToolCallOutput,
-        is_apply_patch: bool,
+        apply_patch_files: Option<Vec<PatchApplyFileResult>>,
     ) {
         let ExecToolCallOutput {
             stdout,
             stderr,
             duration,
             exit_code,
+            resource_limit_exceeded,
         } = output;
         // Because stdout and stderr could each be up to 100 KiB, we send
         // truncated versions.
         const MAX_STREAM_OUTPUT: usize = 5 * 1024; // 5KiB
         let stdout = stdout.text.chars().take(MAX_STREAM_OUTPUT).collect();
-        let stderr = stderr.text.chars().take(MAX_STREAM_OUTPUT).collect();
+        let mut stderr: String = stderr.text.chars().take(MAX_STREAM_OUTPUT).collect();
+        if let Some(message) = resource_limit_exceeded {
+            stderr.push_str(&format!("\n{message}"));
+        }
 
-        let msg = if is_apply_patch {
+        let is_apply_patch = apply_patch_files.is_some();
+        let msg = if let Some(files) = apply_patch_files {
             EventMsg::PatchApplyEnd(PatchApplyEndEvent {
                 call_id: call_id.to_string(),
+                files,
                 stdout,
                 stderr,
                 success: *exit_code == 0,
@@ -739,15 +1070,54 @@ impl Session {
         // If this is an apply_patch, after we emit the end patch, emit a second event
         // with the full turn diff if there is one.
         if is_apply_patch {
-            let unified_diff = turn_diff_tracker.get_unified_diff();
-            if let Ok(Some(unified_diff)) = unified_diff {
-                let msg = EventMsg::TurnDiff(TurnDiffEvent { unified_diff });
-                let event = Event {
-                    id: sub_id.into(),
-                    msg,
-                };
-                let _ = self.tx_event.send(event).await;
-            }
+            self.send_turn_diff(turn_diff_tracker, sub_id).await;
+        }
+    }
+
+    /// Emits the end of an `edit_file` edit, which Codex writes itself rather
+    /// than running `apply_patch`, followed by the turn diff.
+    async fn on_file_edit_end(
+        &self,
+        turn_diff_tracker: &mut TurnDiffTracker,
+        sub_id: &str,
+        call_id: &str,
+        path: &Path,
+        result: &Result<String, String>,
+    ) {
+        let (stdout, stderr, status) = match result {
+            Ok(summary) => (
+                summary.clone(),
+                String::new(),
+                PatchApplyFileStatus::Applied,
+            ),
+            Err(message) => (String::new(), message.clone(), PatchApplyFileStatus::Failed),
+        };
+        let event = Event {
+            id: sub_id.to_string(),
+            msg: EventMsg::PatchApplyEnd(PatchApplyEndEvent {
+                call_id: call_id.to_string(),
+                stdout,
+                stderr,
+                success: result.is_ok(),
+                files: vec![PatchApplyFileResult {
+                    path: path.to_path_buf(),
+                    status,
+                }],
+            }),
+        };
+        let _ = self.tx_event.send(event).await;
+        self.send_turn_diff(turn_diff_tracker, sub_id).await;
+    }
+
+    async fn send_turn_diff(&self, turn_diff_tracker: &mut TurnDiffTracker, sub_id: &str) {
+        let unified_diff = turn_diff_tracker.get_unified_diff();
+        if let Ok(Some(unified_diff)) = unified_diff {
+            let msg = EventMsg::TurnDiff(TurnDiffEvent { unified_diff });
+            let event = Event {
+                id: sub_id.into(),
+                msg,
+            };
+            let _ = self.tx_event.send(event).await;
         }
     }
     /// Runs the exec tool call and emits events for the begin and end of the
@@ -767,7 +1137,7 @@ impl Session {
         self.on_exec_command_begin(turn_diff_tracker, begin_ctx.clone())
             .await;
 
-        let result = process_exec_tool_call(
+        let mut result = process_exec_tool_call(
             exec_args.params,
             exec_args.sandbox_type,
             exec_args.sandbox_policy,
@@ -775,6 +1145,10 @@ impl Session {
             exec_args.stdout_stream,
         )
         .await;
+        let apply_patch_files = is_apply_patch.then(|| match &mut result {
+            Ok(output) => apply_patch::take_file_results(&mut output.stderr.text),
+            Err(_) => Vec::new(),
+        });
 
         let output_stderr;
         let borrowed: &ExecToolCallOutput = match &result {
@@ -785,6 +1159,7 @@ impl Session {
                     stdout: StreamOutput::new(String::new()),
                     stderr: StreamOutput::new(get_error_message_ui(e)),
                     duration: Duration::default(),
+                    resource_limit_exceeded: None,
                 };
                 &output_stderr
             }
@@ -794,13 +1169,65 @@ impl Session {
             &sub_id,
             &call_id,
             borrowed,
-            is_apply_patch,
+            apply_patch_files,
         )
         .await;
 
         result
     }
 
+    /// Redacts secrets from a tool's output before it is sent to the model,
+    /// telling the user how many were removed.
+    async fn redact_tool_output(
+        &self,
+        sub_id: &str,
+        mut response: ResponseInputItem,
+    ) -> ResponseInputItem {
+        let mut counts = RedactionCounts::default();
+        match &mut response {
+            ResponseInputItem::FunctionCallOutput { output, .. } => {
+                if let Cow::Owned(content) = self.redactor.redact(&output.content, &mut counts) {
+                    output.content = content;
+                }
+            }
+            ResponseInputItem::McpToolCallOutput {
+                result: Ok(result), ..
+            } => {
+                if let Ok(mut value) = serde_json::to_value(&*result) {
+                    self.redactor.redact_json(&mut value, &mut counts);
+                    if !counts.is_empty()
+                        && let Ok(redacted) = serde_json::from_value(value)
+                    {
+                        *result = redacted;
+                    }
+                }
+            }
+            ResponseInputItem::McpToolCallOutput { result: Err(_), .. }
+            | ResponseInputItem::Message { .. } => {}
+        }
+        if !counts.is_empty() {
+            self.notify_background_event(
+                sub_id,
+                format!(
+                    "redacted {} secret(s) from tool output ({counts})",
+                    counts.total()
+                ),
+            )
+            .await;
+        }
+        response
+    }
+
+    async fn notify_error(&self, sub_id: &str, message: impl Into<String>) {
+        let event = Event {
+            id: sub_id.to_string(),
+            msg: EventMsg::Error(ErrorEvent {
+                message: message.into(),
+            }),
+        };
+        let _ = self.tx_event.send(event).await;
+    }
+
     /// Helper that emits a BackgroundEvent with the given message. This keeps
     /// the call‑sites terse so adding more diagnostics does not clutter the
     /// core agent logic.
@@ -814,6 +1241,25 @@ impl Session {
         let _ = self.tx_event.send(event).await;
     }
 
+    /// Adds the usage reported for a completed response to the session's
+    /// spend, priced with `pricing`, and returns the event that reports it to
+    /// clients, so that they show the same cost the budget is enforced on.
+    fn record_token_usage(
+        &self,
+        usage: TokenUsage,
+        pricing: Option<ModelPricing>,
+    ) -> TokenCountEvent {
+        let cost_usd = pricing.map(|pricing| pricing.cost_usd(&usage));
+        self.state.lock_unchecked().spend.add(&usage, cost_usd);
+        TokenCountEvent { usage, cost_usd }
+    }
+
+    /// Returns why no further model requests may be made, if the session's
+    /// budget has been used up.
+    fn budget_exceeded(&self) -> Option<String> {
+        self.budget.exceeded_by(&self.state.lock_unchecked().spend)
+    }
+
     /// Build the full turn input by concatenating the current conversation
     /// history with additional items for this turn.
     pub fn turn_input_with_history(&self, extra: Vec<ResponseItem>) -> Vec<ResponseItem> {
@@ -831,6 +1277,12 @@ impl Session {
         }
     }
 
+    /// Queues `item` to be sent to the model at the start of the next turn of
+    /// the running task, after the outputs of this turn's tool calls.
+    fn queue_pending_input(&self, item: ResponseInputItem) {
+        self.state.lock_unchecked().pending_input.push(item);
+    }
+
     pub fn get_pending_input(&self) -> Vec<ResponseInputItem> {
         let mut state = self.state.lock_unchecked();
         if state.pending_input.is_empty() {
@@ -854,6 +1306,138 @@ impl Session {
             .await
     }
 
+    /// What the tools that read without asking for approval may read.
+    fn read_scope(&self, turn_context: &TurnContext) -> ReadScope {
+        ReadScope {
+            cwd: turn_context.cwd.clone(),
+            sandbox_policy: turn_context.sandbox_policy.clone(),
+            output_dir: session_output_dir(&self.codex_home, self.session_id),
+        }
+    }
+
+    /// Snapshots the working tree so that `Op::UndoTurn` can put it back.
+    async fn snapshot_working_tree(&self, sub_id: &str, cwd: &Path) {
+        let turn = {
+            let mut state = self.state.lock_unchecked();
+            state.turns_snapshotted += 1;
+            state.turns_snapshotted
+        };
+        match GitSnapshot::take(cwd, self.session_id, turn).await {
+            Ok(Some(snapshot)) => self
+                .state
+                .lock_unchecked()
+                .turn_snapshots
+                .push((sub_id.to_string(), snapshot)),
+            Ok(None) => {}
+            Err(e) => {
+                warn!("failed to snapshot working tree: {e}");
+                self.notify_background_event(
+                    sub_id,
+                    format!("failed to snapshot the working tree, this turn cannot be undone: {e}"),
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Snapshots the working tree again once the turn `sub_id` has ended, so
+    /// that undoing it leaves later edits alone.
+    async fn finish_turn_snapshot(&self, sub_id: &str) {
+        let snapshot = self
+            .state
+            .lock_unchecked()
+            .turn_snapshots
+            .iter()
+            .find(|(id, snapshot)| id == sub_id && !snapshot.is_finished())
+            .map(|(_, snapshot)| snapshot.clone());
+        let Some(mut snapshot) = snapshot else {
+            return;
+        };
+        match snapshot.finish().await {
+            Ok(()) => {
+                let mut state = self.state.lock_unchecked();
+                if let Some(entry) = state.turn_snapshots.iter_mut().find(|(id, _)| id == sub_id) {
+                    entry.1 = snapshot;
+                }
+            }
+            Err(e) => {
+                warn!("failed to snapshot working tree after the turn: {e}");
+                self.notify_background_event(
+                    sub_id,
+                    format!(
+                        "failed to snapshot the working tree after the turn, this turn cannot be undone: {e}"
+                    ),
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Puts back the files the most recent turn changed and tells the model
+    /// that its changes are gone.
+    async fn undo_turn(&self, sub_id: &str) {
+        let snapshot = {
+            let mut state = self.state.lock_unchecked();
+            if state.current_task.is_some() {
+                Err("Cannot undo while a turn is running.")
+            } else {
+                state
+                    .turn_snapshots
+                    .pop()
+                    .ok_or("There is no turn to undo.")
+            }
+        };
+        let (snapshot_sub_id, snapshot) = match snapshot {
+            Ok(entry) => entry,
+            Err(message) => {
+                self.notify_error(sub_id, message).await;
+                return;
+            }
+        };
+
+        match snapshot.restore().await {
+            Ok(files) => {
+                let message = if files.is_empty() {
+                    "Undid the last turn; it did not change any files.".to_string()
+                } else {
+                    format!(
+                        "Undid the last turn: restored {} file(s) and removed {} file(s) it created.",
+                        files.restored.len(),
+                        files.removed.len()
+                    )
+                };
+                self.record_conversation_items(sub_id, &[ResponseItem::Message {
+                    id: None,
+                    role: "user".to_string(),
+                    content: vec![ContentItem::InputText {
+                        text: "I undid your previous turn: every file it changed, created or deleted is back to how it was before the turn.".to_string(),
+                    }],
+                }])
+                .await;
+                self.notify_background_event(sub_id, message).await;
+            }
+            Err(e) => {
+                warn!("failed to undo turn: {e}");
+                self.state
+                    .lock_unchecked()
+                    .turn_snapshots
+                    .push((snapshot_sub_id, snapshot));
+                self.notify_error(sub_id, format!("Failed to undo the last turn: {e}"))
+                    .await;
+            }
+        }
+    }
+
+    /// Deletes the refs of snapshots that can no longer be undone.
+    async fn delete_turn_snapshots(&self) {
+        let snapshots = std::mem::take(&mut self.state.lock_unchecked().turn_snapshots);
+        for (_, snapshot) in snapshots {
+            if let Err(e) = snapshot.delete().await {
+                warn!("failed to delete snapshot {}: {e}", snapshot.ref_name());
+            }
+        }
+    }
+
     fn interrupt_task(&self) {
         info!("interrupt received: abort current task, if any");
         let mut state = self.state.lock_unchecked();
@@ -974,9 +1558,10 @@ impl AgentTask {
                 id: self.sub_id,
                 msg: EventMsg::TurnAborted(TurnAbortedEvent { reason }),
             };
-            let tx_event = self.sess.tx_event.clone();
+            let sess = self.sess;
             tokio::spawn(async move {
-                tx_event.send(event).await.ok();
+                sess.finish_turn_snapshot(&event.id).await;
+                sess.tx_event.send(event).await.ok();
             });
         }
     }
@@ -1007,12 +1592,11 @@ async fn submission_loop(
             } => {
                 // Recalculate the persistent turn context with provided overrides.
                 let prev = Arc::clone(&turn_context);
-                let provider = prev.client.get_provider();
 
                 // Effective model + family
                 let (effective_model, effective_family) = if let Some(m) = model {
-                    let fam =
-                        find_family_for_model(&m).unwrap_or_else(|| config.model_family.clone());
+                    let fam = find_family_for_model_with_metadata(&m, &config.models)
+                        .unwrap_or_else(|| config.model_family.clone());
                     (m, fam)
                 } else {
                     (prev.client.get_model(), prev.client.get_model_family())
@@ -1022,19 +1606,15 @@ async fn submission_loop(
                 let effective_effort = effort.unwrap_or(prev.client.get_reasoning_effort());
                 let effective_summary = summary.unwrap_or(prev.client.get_reasoning_summary());
 
-                let auth = prev.client.get_auth();
                 // Build updated config for the client
                 let mut updated_config = (*config).clone();
                 updated_config.model = effective_model.clone();
                 updated_config.model_family = effective_family.clone();
 
-                let client = ModelClient::new(
+                let client = prev.client.with_config(
                     Arc::new(updated_config),
-                    auth,
-                    provider,
                     effective_effort,
                     effective_summary,
-                    sess.session_id,
                 );
 
                 let new_approval_policy = approval_policy.unwrap_or(prev.approval_policy);
@@ -1049,16 +1629,32 @@ async fn submission_loop(
                     new_sandbox_policy.clone(),
                     config.include_plan_tool,
                     config.include_apply_patch_tool,
+                    config.include_exec_session_tools,
+                    config.include_background_job_tools,
+                    config.include_content_search_tool,
+                    config.include_repo_map_tool,
+                    config.include_file_tools,
+                    config.include_web_fetch_tool,
+                    config.web_search.is_some(),
+                    config.include_view_image_tool,
+                    config.include_notebook_tools,
                 );
 
                 let new_turn_context = TurnContext {
                     client,
                     tools_config,
                     user_instructions: prev.user_instructions.clone(),
-                    base_instructions: prev.base_instructions.clone(),
+                    base_instructions: model_base_instructions(&config, &effective_model)
+                        .or_else(|| prev.base_instructions.clone()),
                     approval_policy: new_approval_policy,
                     sandbox_policy: new_sandbox_policy.clone(),
                     shell_environment_policy: prev.shell_environment_policy.clone(),
+                    resource_limits: prev.resource_limits.clone(),
+                    exec_output_limits: model_exec_output_limits(
+                        &config,
+                        &effective_model,
+                        sess.session_id,
+                    ),
                     cwd: new_cwd.clone(),
                     disable_response_storage: prev.disable_response_storage,
                 };
@@ -1066,11 +1662,14 @@ async fn submission_loop(
                 // Install the new persistent context for subsequent tasks/turns.
                 turn_context = Arc::new(new_turn_context);
                 if cwd.is_some() || approval_policy.is_some() || sandbox_policy.is_some() {
-                    sess.record_conversation_items(&[ResponseItem::from(EnvironmentContext::new(
-                        new_cwd,
-                        new_approval_policy,
-                        new_sandbox_policy,
-                    ))])
+                    sess.record_conversation_items(
+                        &sub.id,
+                        &[ResponseItem::from(EnvironmentContext::new(
+                            new_cwd,
+                            new_approval_policy,
+                            new_sandbox_policy,
+                        ))],
+                    )
                     .await;
                 }
             }
@@ -1095,10 +1694,8 @@ async fn submission_loop(
                 // attempt to inject input into current task
                 if let Err(items) = sess.inject_input(items) {
                     // Derive a fresh TurnContext for this turn using the provided overrides.
-                    let provider = turn_context.client.get_provider();
-
                     // Derive a model family for the requested model; fall back to the session's.
-                    let model_family = find_family_for_model(&model)
+                    let model_family = find_family_for_model_with_metadata(&model, &config.models)
                         .unwrap_or_else(|| config.model_family.clone());
 
                     // Create a per‑turn Config clone with the requested model/family.
@@ -1107,15 +1704,11 @@ async fn submission_loop(
                     per_turn_config.model_family = model_family.clone();
 
                     // Build a new client with per‑turn reasoning settings.
-                    // Reuse the same provider and session id; auth defaults to env/API key.
-                    let client = ModelClient::new(
-                        Arc::new(per_turn_config),
-                        None,
-                        provider,
-                        effort,
-                        summary,
-                        sess.session_id,
-                    );
+                    // Reuse the same provider, auth, session id and fallback position.
+                    let client =
+                        turn_context
+                            .client
+                            .with_config(Arc::new(per_turn_config), effort, summary);
 
                     let fresh_turn_context = TurnContext {
                         client,
@@ -1125,12 +1718,28 @@ async fn submission_loop(
                             sandbox_policy.clone(),
                             config.include_plan_tool,
                             config.include_apply_patch_tool,
+                            config.include_exec_session_tools,
+                            config.include_background_job_tools,
+                            config.include_content_search_tool,
+                            config.include_repo_map_tool,
+                            config.include_file_tools,
+                            config.include_web_fetch_tool,
+                            config.web_search.is_some(),
+                            config.include_view_image_tool,
+                            config.include_notebook_tools,
                         ),
                         user_instructions: turn_context.user_instructions.clone(),
-                        base_instructions: turn_context.base_instructions.clone(),
+                        base_instructions: model_base_instructions(&config, &model)
+                            .or_else(|| turn_context.base_instructions.clone()),
                         approval_policy,
                         sandbox_policy,
                         shell_environment_policy: turn_context.shell_environment_policy.clone(),
+                        resource_limits: turn_context.resource_limits.clone(),
+                        exec_output_limits: model_exec_output_limits(
+                            &config,
+                            &model,
+                            sess.session_id,
+                        ),
                         cwd,
                         disable_response_storage: turn_context.disable_response_storage,
                     };
@@ -1153,6 +1762,9 @@ async fn submission_loop(
                 }
                 other => sess.notify_approval(&id, other),
             },
+            Op::RevokeProjectApproval { pattern } => {
+                sess.revoke_project_approval(&sub.id, &pattern).await;
+            }
             Op::AddToHistory { text } => {
                 let id = sess.session_id;
                 let config = config.clone();
@@ -1233,8 +1845,20 @@ async fn submission_loop(
                     sess.set_task(task);
                 }
             }
+            Op::UndoTurn => {
+                sess.undo_turn(&sub.id).await;
+            }
             Op::Shutdown => {
                 info!("Shutting down Codex instance");
+                sess.exec_sessions.kill_all();
+                sess.background_jobs.kill_all();
+                sess.delete_turn_snapshots().await;
+                let output_dir = session_output_dir(&sess.codex_home, sess.session_id);
+                if let Err(e) = std::fs::remove_dir_all(&output_dir)
+                    && e.kind() != std::io::ErrorKind::NotFound
+                {
+                    warn!("failed to remove {}: {e}", output_dir.display());
+                }
 
                 // Gracefully flush and shutdown rollout recorder on session end so tests
                 // that inspect the rollout file do not race with the background writer.
@@ -1271,6 +1895,37 @@ async fn submission_loop(
     debug!("Agent loop exited");
 }
 
+/// Base instructions declared for `model` in `[models.<slug>]`, which take
+/// effect when the model is switched mid-session.
+fn rollout_redaction_message(counts: &RedactionCounts) -> String {
+    format!(
+        "redacted {} secret(s) from the session transcript ({counts})",
+        counts.total()
+    )
+}
+
+fn model_base_instructions(config: &Config, model: &str) -> Option<String> {
+    config
+        .models
+        .get(model)
+        .and_then(|metadata| metadata.base_instructions.clone())
+}
+
+fn model_exec_output_limits(config: &Config, model: &str, session_id: Uuid) -> ExecOutputLimits {
+    let defaults = ExecOutputLimits::default();
+    let metadata = config.models.get(model);
+    ExecOutputLimits {
+        max_bytes: metadata
+            .and_then(|metadata| metadata.exec_output_max_bytes)
+            .unwrap_or(defaults.max_bytes),
+        max_lines: metadata
+            .and_then(|metadata| metadata.exec_output_max_lines)
+            .unwrap_or(defaults.max_lines),
+        spill_dir: Some(session_output_dir(&config.codex_home, session_id)),
+        ..defaults
+    }
+}
+
 /// Takes a user message as input and runs a loop where, at each turn, the model
 /// replies with either:
 ///
@@ -1301,8 +1956,10 @@ async fn run_task(
         return;
     }
 
+    sess.snapshot_working_tree(&sub_id, &turn_context.cwd).await;
+
     let initial_input_for_turn: ResponseInputItem = ResponseInputItem::from(input);
-    sess.record_conversation_items(&[initial_input_for_turn.clone().into()])
+    sess.record_conversation_items(&sub_id, &[initial_input_for_turn.clone().into()])
         .await;
 
     let mut last_agent_message: Option<String> = None;
@@ -1319,7 +1976,8 @@ async fn run_task(
             .into_iter()
             .map(ResponseItem::from)
             .collect::<Vec<ResponseItem>>();
-        sess.record_conversation_items(&pending_input).await;
+        sess.record_conversation_items(&sub_id, &pending_input)
+            .await;
 
         // Construct the input that we will send to the model. When using the
         // Chat completions API (or ZDR clients), the model needs the full
@@ -1341,6 +1999,23 @@ async fn run_task(
                 })
             })
             .collect();
+
+        if let Some(reason) = sess.budget_exceeded() {
+            info!("Aborting turn: {reason}");
+            sess.notify_background_event(&sub_id, format!("Budget exceeded: {reason}"))
+                .await;
+            sess.finish_turn_snapshot(&sub_id).await;
+            sess.remove_task(&sub_id);
+            let event = Event {
+                id: sub_id,
+                msg: EventMsg::TurnAborted(TurnAbortedEvent {
+                    reason: TurnAbortReason::BudgetExceeded,
+                }),
+            };
+            sess.tx_event.send(event).await.ok();
+            return;
+        }
+
         match run_turn(
             &sess,
             turn_context,
@@ -1437,8 +2112,11 @@ async fn run_task(
 
                 // Only attempt to take the lock if there is something to record.
                 if !items_to_record_in_conversation_history.is_empty() {
-                    sess.record_conversation_items(&items_to_record_in_conversation_history)
-                        .await;
+                    sess.record_conversation_items(
+                        &sub_id,
+                        &items_to_record_in_conversation_history,
+                    )
+                    .await;
                 }
 
                 if responses.is_empty() {
@@ -1468,6 +2146,7 @@ async fn run_task(
             }
         }
     }
+    sess.finish_turn_snapshot(&sub_id).await;
     sess.remove_task(&sub_id);
     let event = Event {
         id: sub_id,
@@ -1493,6 +2172,8 @@ async fn run_turn(
         store: !turn_context.disable_response_storage,
         tools,
         base_instructions_override: turn_context.base_instructions.clone(),
+        output_schema: turn_context.client.get_output_schema(),
+        output_schema_instructions: None,
     };
 
     let mut retries = 0;
@@ -1647,6 +2328,10 @@ async fn try_run_turn(
                     item.clone(),
                 )
                 .await?;
+                let response = match response {
+                    Some(response) => Some(sess.redact_tool_output(sub_id, response).await),
+                    None => None,
+                };
                 output.push(ProcessedResponseItem { item, response });
             }
             ResponseEvent::Completed {
@@ -1654,10 +2339,12 @@ async fn try_run_turn(
                 token_usage,
             } => {
                 if let Some(token_usage) = token_usage {
+                    let token_count =
+                        sess.record_token_usage(token_usage, turn_context.client.get_pricing());
                     sess.tx_event
                         .send(Event {
                             id: sub_id.to_string(),
-                            msg: EventMsg::TokenCount(token_usage),
+                            msg: EventMsg::TokenCount(token_count),
                         })
                         .await
                         .ok();
@@ -1701,6 +2388,9 @@ async fn try_run_turn(
                 };
                 sess.tx_event.send(event).await.ok();
             }
+            ResponseEvent::ProviderFallback(message) => {
+                sess.notify_background_event(sub_id, message).await;
+            }
             ResponseEvent::ReasoningContentDelta(delta) => {
                 if sess.show_raw_agent_reasoning {
                     let event = Event {
@@ -1740,6 +2430,8 @@ async fn run_compact_task(
         store: !turn_context.disable_response_storage,
         tools: Vec::new(),
         base_instructions_override: Some(compact_instructions.clone()),
+        output_schema: None,
+        output_schema_instructions: None,
     };
 
     let max_retries = turn_context.client.get_provider().stream_max_retries();
@@ -1973,6 +2665,8 @@ async fn handle_function_call(
                 env: HashMap::new(),
                 with_escalated_permissions: None,
                 justification: None,
+                resource_limits: ResourceLimits::default(),
+                output_limits: turn_context.exec_output_limits.clone(),
             };
             handle_container_exec_with_params(
                 exec_params,
@@ -1985,6 +2679,54 @@ async fn handle_function_call(
             .await
         }
         "update_plan" => handle_update_plan(sess, arguments, sub_id, call_id).await,
+        "exec_session_start" => {
+            handle_exec_session_start(sess, turn_context, sub_id, arguments, call_id).await
+        }
+        "exec_session_write" | "exec_session_read" | "exec_session_kill" => {
+            handle_exec_session_call(&sess.exec_sessions, &name, arguments, call_id).await
+        }
+        "background_job_start" => {
+            handle_background_job_start(sess, turn_context, sub_id, arguments, call_id).await
+        }
+        "background_job_status" | "background_job_tail" | "background_job_kill" => {
+            handle_background_job_call(&sess.background_jobs, &name, arguments, call_id).await
+        }
+        "search_file_contents" => {
+            handle_search_file_contents(&sess.read_scope(turn_context), arguments, call_id).await
+        }
+        "repo_map" => {
+            handle_repo_map(&sess.codex_home, &turn_context.cwd, arguments, call_id).await
+        }
+        "read_file" => handle_read_file(&sess.read_scope(turn_context), arguments, call_id).await,
+        "list_dir" => handle_list_dir(&sess.read_scope(turn_context), arguments, call_id).await,
+        "web_fetch" => handle_web_fetch(sess, turn_context, &sub_id, arguments, call_id).await,
+        "web_search" => handle_web_search(sess, turn_context, &sub_id, arguments, call_id).await,
+        "view_image" => handle_view_image(sess, turn_context, arguments, call_id).await,
+        "edit_file" => {
+            handle_edit_file(
+                sess,
+                turn_context,
+                turn_diff_tracker,
+                sub_id,
+                arguments,
+                call_id,
+            )
+            .await
+        }
+        "read_notebook" => {
+            handle_read_notebook(&sess.read_scope(turn_context), arguments, call_id).await
+        }
+        "edit_notebook" => {
+            handle_edit_notebook(
+                sess,
+                turn_context,
+                turn_diff_tracker,
+                sub_id,
+                arguments,
+                call_id,
+            )
+            .await
+        }
         _ => {
             match sess.mcp_connection_manager.parse_tool_name(&name) {
                 Some((server, tool_name)) => {
@@ -2010,17 +2752,486 @@ async fn handle_function_call(
     }
 }
 
-fn to_exec_params(params: ShellToolCallParams, turn_context: &TurnContext) -> ExecParams {
-    ExecParams {
-        command: params.command,
-        cwd: turn_context.resolve_path(params.workdir.clone()),
-        timeout_ms: params.timeout_ms,
+/// Handles `edit_file`. The edit goes through the same approval flow as
+/// `apply_patch`, but since Codex writes the file itself instead of running
+/// `apply_patch` in the sandbox, it is only made without asking when the file
+/// is inside the writable roots.
+async fn handle_edit_file(
+    sess: &Session,
+    turn_context: &TurnContext,
+    turn_diff_tracker: &mut TurnDiffTracker,
+    sub_id: String,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<EditFileParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+    let edit = prepare_edit(&turn_context.cwd, params);
+    apply_file_edit(
+        sess,
+        turn_context,
+        turn_diff_tracker,
+        sub_id,
+        call_id,
+        "edit_file",
+        edit,
+    )
+    .await
+}
+
+/// Handles `edit_notebook`, which is approved and written like `edit_file`.
+async fn handle_edit_notebook(
+    sess: &Session,
+    turn_context: &TurnContext,
+    turn_diff_tracker: &mut TurnDiffTracker,
+    sub_id: String,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<EditNotebookParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+    let edit = prepare_notebook_edit(&turn_context.cwd, params);
+    apply_file_edit(
+        sess,
+        turn_context,
+        turn_diff_tracker,
+        sub_id,
+        call_id,
+        "edit_notebook",
+        edit,
+    )
+    .await
+}
+
+/// Asks for approval of a prepared edit when it is outside the writable
+/// roots, then writes it and reports it like a patch.
+async fn apply_file_edit(
+    sess: &Session,
+    turn_context: &TurnContext,
+    turn_diff_tracker: &mut TurnDiffTracker,
+    sub_id: String,
+    call_id: String,
+    tool_name: &str,
+    edit: Result<FileEdit, String>,
+) -> ResponseInputItem {
+    let failure = |call_id, content| ResponseInputItem::FunctionCallOutput {
+        call_id,
+        output: FunctionCallOutputPayload {
+            content,
+            success: Some(false),
+        },
+    };
+    let edit = match edit {
+        Ok(edit) => edit,
+        Err(message) => return failure(call_id, message),
+    };
+    let user_explicitly_approved_this_action = match assess_direct_write_safety(
+        &edit.action,
+        turn_context.approval_policy,
+        &turn_context.sandbox_policy,
+        &turn_context.cwd,
+    ) {
+        SafetyCheck::AutoApprove { .. } => false,
+        SafetyCheck::AskUser => {
+            let rx_approve = sess
+                .request_patch_approval(sub_id.clone(), call_id.clone(), &edit.action, None, None)
+                .await;
+            match rx_approve.await.unwrap_or_default() {
+                ReviewDecision::Approved
+                | ReviewDecision::ApprovedForSession
+                | ReviewDecision::ApprovedForProject => true,
+                ReviewDecision::Denied | ReviewDecision::Abort => {
+                    return failure(call_id, "edit rejected by user".to_string());
+                }
+            }
+        }
+        SafetyCheck::Reject { reason } => {
+            return failure(call_id, format!("edit rejected: {reason}"));
+        }
+    };
+
+    sess.on_exec_command_begin(
+        turn_diff_tracker,
+        ExecCommandContext {
+            sub_id: sub_id.clone(),
+            call_id: call_id.clone(),
+            command_for_display: vec![tool_name.to_string(), edit.path.display().to_string()],
+            cwd: turn_context.cwd.clone(),
+            apply_patch: Some(ApplyPatchCommandContext {
+                user_explicitly_approved_this_action,
+                changes: convert_apply_patch_to_protocol(&edit.action),
+            }),
+        },
+    )
+    .await;
+    let result = edit
+        .write()
+        .map(|()| edit.summary())
+        .map_err(|e| format!("failed to write {}: {e}", edit.path.display()));
+    sess.on_file_edit_end(turn_diff_tracker, &sub_id, &call_id, &edit.path, &result)
+        .await;
+
+    match result {
+        Ok(summary) => ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: summary,
+                success: Some(true),
+            },
+        },
+        Err(message) => failure(call_id, message),
+    }
+}
+
+/// Handles `view_image`. The image itself is queued as user input, since a
+/// function call output cannot hold one.
+async fn handle_view_image(
+    sess: &Session,
+    turn_context: &TurnContext,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<ViewImageParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+    let scope = sess.read_scope(turn_context);
+    let result = tokio::task::spawn_blocking(move || load_image(&scope, &params))
+        .await
+        .unwrap_or_else(|e| Err(format!("failed to load image: {e}")));
+
+    let output = match result {
+        Ok(image) => {
+            let summary = image.summary();
+            sess.queue_pending_input(image.into_input_item());
+            FunctionCallOutputPayload {
+                content: summary,
+                success: Some(true),
+            }
+        }
+        Err(message) => FunctionCallOutputPayload {
+            content: message,
+            success: Some(false),
+        },
+    };
+    ResponseInputItem::FunctionCallOutput { call_id, output }
+}
+
+async fn handle_web_fetch(
+    sess: &Session,
+    turn_context: &TurnContext,
+    sub_id: &str,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<WebFetchParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+    let result = match parse_web_url(&params.url) {
+        Ok(url) => {
+            match authorize_network_access(sess, turn_context, sub_id, &call_id, "web_fetch", &url)
+                .await
+            {
+                Ok(()) => web_fetch(url, &params, turn_context.sandbox_policy.clone()).await,
+                Err(message) => Err(message),
+            }
+        }
+        Err(message) => Err(message),
+    };
+    function_output(call_id, result)
+}
+
+async fn handle_web_search(
+    sess: &Session,
+    turn_context: &TurnContext,
+    sub_id: &str,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<WebSearchParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+    let Some(config) = &sess.web_search else {
+        return function_output(call_id, Err("web search is not configured".to_string()));
+    };
+    let result = match search_endpoint(config) {
+        Ok(endpoint) => match authorize_network_access(
+            sess,
+            turn_context,
+            sub_id,
+            &call_id,
+            "web_search",
+            &endpoint,
+        )
+        .await
+        {
+            Ok(()) => web_search(config, endpoint, &params).await,
+            Err(message) => Err(message),
+        },
+        Err(message) => Err(message),
+    };
+    function_output(call_id, result)
+}
+
+/// Lets `tool` reach the host of `url` when the sandbox policy allows it, the
+/// user approved it earlier, or the user approves it now. Approvals are kept
+/// per host, apart from approved commands, and are shared by all web tools.
+async fn authorize_network_access(
+    sess: &Session,
+    turn_context: &TurnContext,
+    sub_id: &str,
+    call_id: &str,
+    tool: &str,
+    url: &Url,
+) -> Result<(), String> {
+    let host = url.host_str().unwrap_or_default();
+    if sandbox_allows_host(&turn_context.sandbox_policy, host) {
+        return Ok(());
+    }
+    if sess.state.lock_unchecked().approved_hosts.contains(host) {
+        return Ok(());
+    }
+    if turn_context.approval_policy == AskForApproval::Never {
+        return Err(format!(
+            "network access to {host} is blocked by the sandbox policy"
+        ));
+    }
+
+    let rx_approve = sess
+        .request_command_approval(
+            sub_id.to_string(),
+            call_id.to_string(),
+            vec![tool.to_string(), host.to_string()],
+            turn_context.cwd.clone(),
+            Some(format!(
+                "{tool} wants to reach {url}, which the sandbox does not allow network access to"
+            )),
+            None,
+        )
+        .await;
+    match rx_approve.await.unwrap_or_default() {
+        ReviewDecision::Approved => Ok(()),
+        ReviewDecision::ApprovedForSession => {
+            sess.state
+                .lock_unchecked()
+                .approved_hosts
+                .insert(host.to_string());
+            Ok(())
+        }
+        ReviewDecision::ApprovedForProject => {
+            sess.add_project_approved_host(sub_id, host).await;
+            Ok(())
+        }
+        ReviewDecision::Denied | ReviewDecision::Abort => {
+            Err(format!("network access to {host} was rejected by the user"))
+        }
+    }
+}
+
+fn to_exec_params(params: ShellToolCallParams, turn_context: &TurnContext) -> ExecParams {
+    ExecParams {
+        command: params.command,
+        cwd: turn_context.resolve_path(params.workdir.clone()),
+        timeout_ms: params.timeout_ms,
         env: create_env(&turn_context.shell_environment_policy),
         with_escalated_permissions: params.with_escalated_permissions,
         justification: params.justification,
+        resource_limits: turn_context.resource_limits.clone(),
+        output_limits: turn_context.exec_output_limits.clone(),
     }
 }
 
+async fn handle_exec_session_start(
+    sess: &Session,
+    turn_context: &TurnContext,
+    sub_id: String,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let args = match serde_json::from_str::<ExecSessionStartParams>(&arguments) {
+        Ok(args) => args,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+    let (params, sandbox_type) = match approve_detached_command(
+        sess,
+        turn_context,
+        &sub_id,
+        &call_id,
+        args.command,
+        args.workdir,
+    )
+    .await
+    {
+        Ok(approved) => approved,
+        Err(output) => return *output,
+    };
+
+    let result = sess
+        .exec_sessions
+        .start(
+            params,
+            sandbox_type,
+            &turn_context.sandbox_policy,
+            &sess.codex_linux_sandbox_exe,
+            StdoutStream {
+                sub_id,
+                call_id: call_id.clone(),
+                tx_event: sess.tx_event.clone(),
+            },
+            exec_session::yield_time(args.yield_time_ms, DEFAULT_START_YIELD_TIME_MS),
+        )
+        .await;
+    session_call_output(call_id, result)
+}
+
+async fn handle_background_job_start(
+    sess: &Session,
+    turn_context: &TurnContext,
+    sub_id: String,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let args = match serde_json::from_str::<BackgroundJobStartParams>(&arguments) {
+        Ok(args) => args,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+    let (params, sandbox_type) = match approve_detached_command(
+        sess,
+        turn_context,
+        &sub_id,
+        &call_id,
+        args.command,
+        args.workdir,
+    )
+    .await
+    {
+        Ok(approved) => approved,
+        Err(output) => return *output,
+    };
+
+    let result = sess
+        .background_jobs
+        .start(
+            params,
+            sandbox_type,
+            &turn_context.sandbox_policy,
+            &sess.codex_linux_sandbox_exe,
+            StdoutStream {
+                sub_id,
+                call_id: call_id.clone(),
+                tx_event: sess.tx_event.clone(),
+            },
+        )
+        .await
+        .map(|status| background_jobs::to_json(&status));
+    job_call_output(call_id, result)
+}
+
+/// Runs the approval flow for a command that outlives the tool call that
+/// starts it. Such commands are approved and sandboxed exactly like `shell`
+/// calls, but get no timeout.
+async fn approve_detached_command(
+    sess: &Session,
+    turn_context: &TurnContext,
+    sub_id: &str,
+    call_id: &str,
+    command: Vec<String>,
+    workdir: Option<String>,
+) -> Result<(ExecParams, SandboxType), Box<ResponseInputItem>> {
+    let params = ExecParams {
+        command,
+        cwd: turn_context.resolve_path(workdir),
+        timeout_ms: None,
+        env: create_env(&turn_context.shell_environment_policy),
+        with_escalated_permissions: None,
+        justification: None,
+        resource_limits: turn_context.resource_limits.clone(),
+        output_limits: turn_context.exec_output_limits.clone(),
+    };
+
+    let policy_match = sess.exec_policy.evaluate(&params.command);
+    let safety = {
+        let state = sess.state.lock_unchecked();
+        assess_command_safety(
+            &params.command,
+            turn_context.approval_policy,
+            &turn_context.sandbox_policy,
+            &state.approved_commands,
+            policy_match.as_ref(),
+            false,
+        )
+    };
+    let matched_rule = policy_match.map(|m| m.rule);
+    let sandbox_type =
+        sandbox_type_after_approval(sess, safety, sub_id, call_id, &params, matched_rule).await?;
+    Ok((
+        maybe_run_with_user_profile(params, sess, turn_context),
+        sandbox_type,
+    ))
+}
+
 fn parse_container_exec_arguments(
     arguments: String,
     turn_context: &TurnContext,
@@ -2076,7 +3287,11 @@ async fn handle_container_exec_with_params(
     call_id: String,
 ) -> ResponseInputItem {
     // check if this was a patch, and apply it if so
-    let apply_patch_exec = match maybe_parse_apply_patch_verified(&params.command, &params.cwd) {
+    let apply_patch_exec = match maybe_parse_apply_patch_verified_with_options(
+        &params.command,
+        &params.cwd,
+        &sess.apply_patch_options,
+    ) {
         MaybeApplyPatchVerified::Body(changes) => {
             match apply_patch::apply_patch(sess, turn_context, &sub_id, &call_id, changes).await {
                 InternalApplyPatchInvocation::Output(item) => return item,
@@ -2104,7 +3319,7 @@ async fn handle_container_exec_with_params(
         MaybeApplyPatchVerified::NotApplyPatch => None,
     };
 
-    let (params, safety, command_for_display) = match &apply_patch_exec {
+    let (params, safety, command_for_display, matched_rule) = match &apply_patch_exec {
         Some(ApplyPatchExec {
             action: ApplyPatchAction { patch, cwd, .. },
             user_explicitly_approved_this_action,
@@ -2130,9 +3345,20 @@ async fn handle_container_exec_with_params(
                 ],
                 cwd: cwd.clone(),
                 timeout_ms: params.timeout_ms,
-                env: HashMap::new(),
+                env: HashMap::from([
+                    (
+                        CODEX_APPLY_PATCH_FUZZY_THRESHOLD_ENV_VAR.to_string(),
+                        sess.apply_patch_options.fuzzy_threshold.to_string(),
+                    ),
+                    (
+                        CODEX_APPLY_PATCH_REPORT_RESULTS_ENV_VAR.to_string(),
+                        "1".to_string(),
+                    ),
+                ]),
                 with_escalated_permissions: params.with_escalated_permissions,
                 justification: params.justification.clone(),
+                resource_limits: ResourceLimits::default(),
+                output_limits: params.output_limits.clone(),
             };
             let safety = if *user_explicitly_approved_this_action {
                 SafetyCheck::AutoApprove {
@@ -2149,9 +3375,11 @@ async fn handle_container_exec_with_params(
                 params,
                 safety,
                 vec!["apply_patch".to_string(), patch.clone()],
+                None,
             )
         }
         None => {
+            let policy_match = sess.exec_policy.evaluate(&params.command);
             let safety = {
                 let state = sess.state.lock_unchecked();
                 assess_command_safety(
@@ -2159,57 +3387,27 @@ async fn handle_container_exec_with_params(
                     turn_context.approval_policy,
                     &turn_context.sandbox_policy,
                     &state.approved_commands,
+                    policy_match.as_ref(),
                     params.with_escalated_permissions.unwrap_or(false),
                 )
             };
             let command_for_display = params.command.clone();
-            (params, safety, command_for_display)
+            (
+                params,
+                safety,
+                command_for_display,
+                policy_match.map(|m| m.rule),
+            )
         }
     };
 
-    let sandbox_type = match safety {
-        SafetyCheck::AutoApprove { sandbox_type } => sandbox_type,
-        SafetyCheck::AskUser => {
-            let rx_approve = sess
-                .request_command_approval(
-                    sub_id.clone(),
-                    call_id.clone(),
-                    params.command.clone(),
-                    params.cwd.clone(),
-                    params.justification.clone(),
-                )
-                .await;
-            match rx_approve.await.unwrap_or_default() {
-                ReviewDecision::Approved => (),
-                ReviewDecision::ApprovedForSession => {
-                    sess.add_approved_command(params.command.clone());
-                }
-                ReviewDecision::Denied | ReviewDecision::Abort => {
-                    return ResponseInputItem::FunctionCallOutput {
-                        call_id,
-                        output: FunctionCallOutputPayload {
-                            content: "exec command rejected by user".to_string(),
-                            success: None,
-                        },
-                    };
-                }
-            }
-            // No sandboxing is applied because the user has given
-            // explicit approval. Often, we end up in this case because
-            // the command cannot be run in a sandbox, such as
-            // installing a new dependency that requires network access.
-            SandboxType::None
-        }
-        SafetyCheck::Reject { reason } => {
-            return ResponseInputItem::FunctionCallOutput {
-                call_id,
-                output: FunctionCallOutputPayload {
-                    content: format!("exec command rejected: {reason}"),
-                    success: None,
-                },
-            };
-        }
-    };
+    let sandbox_type =
+        match sandbox_type_after_approval(sess, safety, &sub_id, &call_id, &params, matched_rule)
+            .await
+        {
+            Ok(sandbox_type) => sandbox_type,
+            Err(output) => return *output,
+        };
 
     let exec_command_context = ExecCommandContext {
         sub_id: sub_id.clone(),
@@ -2282,6 +3480,64 @@ async fn handle_container_exec_with_params(
     }
 }
 
+/// Runs the approval flow `safety` calls for. Returns the sandbox to run the
+/// command in, or the output telling the model why the command may not run.
+async fn sandbox_type_after_approval(
+    sess: &Session,
+    safety: SafetyCheck,
+    sub_id: &str,
+    call_id: &str,
+    params: &ExecParams,
+    matched_rule: Option<String>,
+) -> Result<SandboxType, Box<ResponseInputItem>> {
+    match safety {
+        SafetyCheck::AutoApprove { sandbox_type } => Ok(sandbox_type),
+        SafetyCheck::AskUser => {
+            let rx_approve = sess
+                .request_command_approval(
+                    sub_id.to_string(),
+                    call_id.to_string(),
+                    params.command.clone(),
+                    params.cwd.clone(),
+                    params.justification.clone(),
+                    matched_rule,
+                )
+                .await;
+            match rx_approve.await.unwrap_or_default() {
+                ReviewDecision::Approved => (),
+                ReviewDecision::ApprovedForSession => {
+                    sess.add_approved_command(params.command.clone());
+                }
+                ReviewDecision::ApprovedForProject => {
+                    sess.add_project_approved_command(sub_id, params.command.clone())
+                        .await;
+                }
+                ReviewDecision::Denied | ReviewDecision::Abort => {
+                    return Err(Box::new(ResponseInputItem::FunctionCallOutput {
+                        call_id: call_id.to_string(),
+                        output: FunctionCallOutputPayload {
+                            content: "exec command rejected by user".to_string(),
+                            success: None,
+                        },
+                    }));
+                }
+            }
+            // No sandboxing is applied because the user has given
+            // explicit approval. Often, we end up in this case because
+            // the command cannot be run in a sandbox, such as
+            // installing a new dependency that requires network access.
+            Ok(SandboxType::None)
+        }
+        SafetyCheck::Reject { reason } => Err(Box::new(ResponseInputItem::FunctionCallOutput {
+            call_id: call_id.to_string(),
+            output: FunctionCallOutputPayload {
+                content: format!("exec command rejected: {reason}"),
+                success: None,
+            },
+        })),
+    }
+}
+
 async fn handle_sandbox_error(
     turn_diff_tracker: &mut TurnDiffTracker,
     params: ExecParams,
@@ -2345,16 +3601,24 @@ async fn handle_sandbox_error(
             params.command.clone(),
             cwd.clone(),
             Some("command failed; retry without sandbox?".to_string()),
+            None,
         )
         .await;
 
     match rx_approve.await.unwrap_or_default() {
-        ReviewDecision::Approved | ReviewDecision::ApprovedForSession => {
-            // Persist this command as pre‑approved for the
-            // remainder of the session so future
-            // executions skip the sandbox directly.
-            // TODO(ragona): Isn't this a bug? It always saves the command in an | fork?
-            sess.add_approved_command(params.command.clone());
+        decision @ (ReviewDecision::Approved
+        | ReviewDecision::ApprovedForSession
+        | ReviewDecision::ApprovedForProject) => {
+            if decision == ReviewDecision::ApprovedForProject {
+                sess.add_project_approved_command(&sub_id, params.command.clone())
+                    .await;
+            } else {
+                // Persist this command as pre‑approved for the
+                // remainder of the session so future
+                // executions skip the sandbox directly.
+                // TODO(ragona): Isn't this a bug? It always saves the command in an | fork?
+                sess.add_approved_command(params.command.clone());
+            }
             // Inform UI we are retrying without sandbox.
             sess.notify_background_event(&sub_id, "retrying command without sandbox")
                 .await;
@@ -2423,12 +3687,15 @@ fn format_exec_output(exec_output: ExecToolCallOutput) -> String {
         stdout,
         stderr,
         duration,
+        resource_limit_exceeded,
     } = exec_output;
 
     #[derive(Serialize)]
     struct ExecMetadata {
         exit_code: i32,
         duration_seconds: f32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        resource_limit_exceeded: Option<String>,
     }
 
     #[derive(Serialize)]
@@ -2443,18 +3710,13 @@ fn format_exec_output(exec_output: ExecToolCallOutput) -> String {
     let is_success = exit_code == 0;
     let output = if is_success { stdout } else { stderr };
 
-    let mut formatted_output = output.text;
-    if let Some(truncated_after_lines) = output.truncated_after_lines {
-        formatted_output.push_str(&format!(
-            "\n\n[Output truncated after {truncated_after_lines} lines: too many lines or bytes.]",
-        ));
-    }
-
+    // Truncated output already carries a marker where lines were left out.
     let payload = ExecOutput {
-        output: &formatted_output,
+        output: &output.text,
         metadata: ExecMetadata {
             exit_code,
             duration_seconds,
+            resource_limit_exceeded,
         },
     };
 
@@ -2516,15 +3778,20 @@ async fn drain_to_completed(
                         ));
                     }
                 };
+                let token_count =
+                    sess.record_token_usage(token_usage, turn_context.client.get_pricing());
                 sess.tx_event
                     .send(Event {
                         id: sub_id.to_string(),
-                        msg: EventMsg::TokenCount(token_usage),
+                        msg: EventMsg::TokenCount(token_count),
                     })
                     .await
                     .ok();
                 return Ok(());
             }
+            Ok(ResponseEvent::ProviderFallback(message)) => {
+                sess.notify_background_event(sub_id, message).await;
+            }
             Ok(_) => continue,
             Err(e) => return Err(e),
         }
@@ -0,0 +1,211 @@
+//! Commands the user has approved to run without a sandbox.
+//!
+//! Approvals come in two flavours:
+//!
+//! - *session* approvals remember an exact argv until the session ends
+//!   ([`crate::protocol::ReviewDecision::ApprovedForSession`]);
+//! - *project* approvals are [`CommandPattern`]s persisted under
+//!   `[projects."<path>"].approved_commands` in `config.toml` so they survive
+//!   across sessions ([`crate::protocol::ReviewDecision::ApprovedForProject`]).
+
+use std::collections::HashSet;
+use std::fmt;
+
+use tracing::warn;
+
+use crate::bash::try_parse_bash;
+use crate::bash::try_parse_word_only_commands_sequence;
+
+/// Trailing word that turns a pattern into a prefix match.
+const WILDCARD: &str = "*";
+
+/// A command approved for a project, written as shell words, e.g.
+/// `npm run lint` (matches exactly that argv) or `cargo test *` (matches
+/// `cargo test` followed by any arguments).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CommandPattern {
+    words: Vec<String>,
+    prefix: bool,
+}
+
+impl CommandPattern {
+    /// Parses a pattern as written in `config.toml`. Returns `None` for
+    /// patterns that do not name a program (empty, or a bare `*`).
+    pub(crate) fn parse(pattern: &str) -> Option<Self> {
+        let mut words = shlex::split(pattern)?;
+        let prefix = words.last().is_some_and(|w| w == WILDCARD);
+        if prefix {
+            words.pop();
+        }
+        if words.is_empty() {
+            return None;
+        }
+        Some(Self { words, prefix })
+    }
+
+    /// The exact-match pattern to persist when the user approves `command`
+    /// for the project. `bash -lc` scripts are unwrapped when they consist of
+    /// a single plain command; anything more complex cannot be expressed as a
+    /// pattern and yields `None`.
+    pub(crate) fn for_command(command: &[String]) -> Option<Self> {
+        let words = match parse_bash_lc_commands(command) {
+            Some(mut commands) if commands.len() == 1 => commands.pop()?,
+            Some(_) => return None,
+            None => command.to_vec(),
+        };
+        if words.is_empty() {
+            return None;
+        }
+        Some(Self {
+            words,
+            prefix: false,
+        })
+    }
+
+    pub(crate) fn matches(&self, command: &[String]) -> bool {
+        if self.prefix {
+            command.starts_with(&self.words)
+        } else {
+            command == self.words.as_slice()
+        }
+    }
+}
+
+impl fmt::Display for CommandPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let joined = shlex::try_join(self.words.iter().map(String::as_str))
+            .unwrap_or_else(|_| self.words.join(" "));
+        if self.prefix {
+            write!(f, "{joined} {WILDCARD}")
+        } else {
+            f.write_str(&joined)
+        }
+    }
+}
+
+/// Everything the user has approved so far in this session.
+#[derive(Debug, Default)]
+pub(crate) struct ApprovedCommands {
+    session: HashSet<Vec<String>>,
+    project: Vec<CommandPattern>,
+}
+
+impl ApprovedCommands {
+    /// Seeds the project approvals from `config.toml`. Malformed patterns are
+    /// logged and ignored.
+    pub(crate) fn new(project_patterns: &[String]) -> Self {
+        let project = project_patterns
+            .iter()
+            .filter_map(|p| {
+                let parsed = CommandPattern::parse(p);
+                if parsed.is_none() {
+                    warn!("ignoring invalid approved command pattern `{p}`");
+                }
+                parsed
+            })
+            .collect();
+        Self {
+            session: HashSet::new(),
+            project,
+        }
+    }
+
+    pub(crate) fn add_for_session(&mut self, command: Vec<String>) {
+        self.session.insert(command);
+    }
+
+    pub(crate) fn add_for_project(&mut self, pattern: CommandPattern) {
+        if !self.project.contains(&pattern) {
+            self.project.push(pattern);
+        }
+    }
+
+    pub(crate) fn remove_for_project(&mut self, pattern: &CommandPattern) {
+        self.project.retain(|p| p != pattern);
+    }
+
+    /// Whether `command` was approved for the session, or matches a project
+    /// pattern. A `bash -lc` script made only of plain commands matches when
+    /// every command in it matches some pattern.
+    pub(crate) fn contains(&self, command: &[String]) -> bool {
+        if self.session.contains(command) {
+            return true;
+        }
+        if self.project.is_empty() {
+            return false;
+        }
+        if let Some(commands) = parse_bash_lc_commands(command) {
+            return !commands.is_empty() && commands.iter().all(|cmd| self.matches_project(cmd));
+        }
+        self.matches_project(command)
+    }
+
+    fn matches_project(&self, command: &[String]) -> bool {
+        self.project.iter().any(|p| p.matches(command))
+    }
+}
+
+/// Splits `bash -lc "<script>"` into its plain commands. Returns `None` if
+/// `command` is not a `bash -lc` invocation, and `Some(vec![])` if the script
+/// uses anything beyond words and the `&&`, `||`, `;` and `|` operators.
+fn parse_bash_lc_commands(command: &[String]) -> Option<Vec<Vec<String>>> {
+    let [bash, flag, script] = command else {
+        return None;
+    };
+    if bash != "bash" || flag != "-lc" {
+        return None;
+    }
+    Some(
+        try_parse_bash(script)
+            .and_then(|tree| try_parse_word_only_commands_sequence(&tree, script))
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vec_str(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_and_match_patterns() {
+        let prefix = CommandPattern::parse("cargo test *").unwrap();
+        assert!(prefix.matches(&vec_str(&["cargo", "test"])));
+        assert!(prefix.matches(&vec_str(&["cargo", "test", "-p", "foo"])));
+        assert!(!prefix.matches(&vec_str(&["cargo", "build"])));
+        assert_eq!(prefix.to_string(), "cargo test *");
+
+        let exact = CommandPattern::parse("npm run 'lint'").unwrap();
+        assert!(exact.matches(&vec_str(&["npm", "run", "lint"])));
+        assert!(!exact.matches(&vec_str(&["npm", "run", "lint", "--fix"])));
+        assert_eq!(exact.to_string(), "npm run lint");
+
+        assert_eq!(CommandPattern::parse("*"), None);
+        assert_eq!(CommandPattern::parse("  "), None);
+    }
+
+    #[test]
+    fn bash_scripts_match_only_when_every_command_is_approved() {
+        let approved = ApprovedCommands::new(&["cargo test *".to_string(), "cd *".to_string()]);
+        let script = |s: &str| vec_str(&["bash", "-lc", s]);
+
+        assert!(approved.contains(&script("cd core && cargo test -p foo")));
+        assert!(!approved.contains(&script("cargo test && rm -rf target")));
+        assert!(!approved.contains(&script("cargo test > out.txt")));
+        assert!(!approved.contains(&script("cargo test $(whoami)")));
+    }
+
+    #[test]
+    fn for_command_unwraps_single_command_scripts() {
+        let pattern =
+            CommandPattern::for_command(&vec_str(&["bash", "-lc", "cargo test -p foo"])).unwrap();
+        assert_eq!(pattern.to_string(), "cargo test -p foo");
+        assert_eq!(
+            CommandPattern::for_command(&vec_str(&["bash", "-lc", "make && make install"])),
+            None
+        );
+    }
+}
@@ -1,18 +1,26 @@
+use crate::budget::Budget;
 use crate::config_profile::ConfigProfile;
 use crate::config_types::History;
 use crate::config_types::McpServerConfig;
+use crate::config_types::ModelMetadata;
+use crate::config_types::Redaction;
+use crate::config_types::ResourceLimits;
+use crate::config_types::SandboxReadOnly;
 use crate::config_types::SandboxWorkspaceWrite;
 use crate::config_types::ShellEnvironmentPolicy;
 use crate::config_types::ShellEnvironmentPolicyToml;
 use crate::config_types::Tui;
 use crate::config_types::UriBasedFileOpener;
+use crate::config_types::WebSearchConfig;
 use crate::model_family::ModelFamily;
-use crate::model_family::find_family_for_model;
+use crate::model_family::find_family_for_model_with_metadata;
+use crate::model_provider_info::ModelFallback;
 use crate::model_provider_info::ModelProviderInfo;
 use crate::model_provider_info::built_in_model_providers;
-use crate::openai_model_info::get_model_info;
+use crate::openai_model_info::get_model_limits;
 use crate::protocol::AskForApproval;
 use crate::protocol::SandboxPolicy;
+use codex_apply_patch::ApplyPatchOptions;
 use codex_login::AuthMode;
 use codex_protocol::config_types::ReasoningEffort;
 use codex_protocol::config_types::ReasoningSummary;
@@ -57,6 +65,15 @@ pub struct Config {
     /// Info needed to make an API request to the model.
     pub model_provider: ModelProviderInfo,
 
+    /// Providers (and models) to switch to, in order, when `model_provider`
+    /// keeps failing. Resolved from the profile's or the provider's
+    /// `fallback` list.
+    pub model_fallbacks: Vec<ModelFallback>,
+
+    /// Cost and token limits for the session. A turn that finds the budget
+    /// exhausted is aborted with `TurnAbortReason::BudgetExceeded`.
+    pub budget: Budget,
+
     /// Approval policy for executing commands.
     pub approval_policy: AskForApproval,
 
@@ -64,6 +81,9 @@ pub struct Config {
 
     pub shell_environment_policy: ShellEnvironmentPolicy,
 
+    /// Limits applied to shell commands run under the Linux sandbox.
+    pub resource_limits: ResourceLimits,
+
     /// When `true`, `AgentReasoning` events emitted by the backend will be
     /// suppressed from the frontend output. This can reduce visual noise when
     /// users are only interested in the final agent responses.
@@ -117,6 +137,10 @@ pub struct Config {
     /// Combined provider map (defaults merged with user-defined overrides).
     pub model_providers: HashMap<String, ModelProviderInfo>,
 
+    /// User-declared model metadata from `[models.<slug>]`, consulted whenever
+    /// the model changes.
+    pub models: HashMap<String, ModelMetadata>,
+
     /// Maximum number of bytes to include from an AGENTS.md project doc file.
     pub project_doc_max_bytes: usize,
 
@@ -127,6 +151,9 @@ pub struct Config {
     /// Settings that govern if and what will be written to `~/.codex/history.jsonl`.
     pub history: History,
 
+    /// Secrets to scrub from command output, from the `[redaction]` table.
+    pub redaction: Redaction,
+
     /// Optional URI-based file opener. If set, citations to files in the model
     /// output will be hyperlinked using the specified URI scheme.
     pub file_opener: UriBasedFileOpener,
@@ -164,11 +191,64 @@ pub struct Config {
     /// model family's default preference.
     pub include_apply_patch_tool: bool,
 
+    /// Include the `exec_session_*` tools for interactive, long-running
+    /// commands.
+    pub include_exec_session_tools: bool,
+
+    /// Include the `background_job_*` tools for commands that keep running
+    /// while the model works.
+    pub include_background_job_tools: bool,
+
+    /// Include the read-only `search_file_contents` tool.
+    pub include_content_search_tool: bool,
+
+    /// Include the read-only `repo_map` tool.
+    pub include_repo_map_tool: bool,
+
+    /// Include the `read_file`, `list_dir` and `edit_file` tools.
+    pub include_file_tools: bool,
+
+    /// Include the `web_fetch` tool.
+    pub include_web_fetch_tool: bool,
+
+    /// Search engine for the `web_search` tool, which is only included when
+    /// this is set.
+    pub web_search: Option<WebSearchConfig>,
+
+    /// Include the `view_image` tool.
+    pub include_view_image_tool: bool,
+
+    /// Include the `read_notebook` and `edit_notebook` tools.
+    pub include_notebook_tools: bool,
+
+    /// When set, an outline of the working directory of about this many
+    /// tokens is included in the environment context.
+    pub repo_map_context_tokens: Option<usize>,
+
+    /// How similar a region of a file must be to the lines an `apply_patch`
+    /// hunk expects for the hunk to be applied there when they do not match
+    /// exactly. `1.0`, the default, disables fuzzy matching.
+    pub apply_patch_fuzzy_threshold: f64,
+
     /// The value for the `originator` header included with Responses API requests.
     pub responses_originator_header: String,
 
     /// If set to `true`, the API key will be signed with the `originator` header.
     pub preferred_auth_method: AuthMode,
+
+    /// JSON Schema the agent's final message must conform to. Not read from
+    /// `config.toml`: set by `codex exec --output-schema` and by workflow
+    /// steps with an `output_schema`.
+    pub output_schema: Option<serde_json::Value>,
+
+    /// Commands the user approved for the project in `cwd`, as read from
+    /// `[projects."<cwd>"].approved_commands` in `CODEX_HOME/config.toml`.
+    pub project_approved_commands: Vec<String>,
+
+    /// Hosts the user approved network access to for the project in `cwd`,
+    /// as read from `[projects."<cwd>"].approved_hosts` in
+    /// `CODEX_HOME/config.toml`.
+    pub project_approved_hosts: Vec<String>,
 }
 
 impl Config {
@@ -272,19 +352,145 @@ pub fn load_config_as_toml(codex_home: &Path) -> std::io::Result<TomlValue> {
 /// Patch `CODEX_HOME/config.toml` project state.
 /// Use with caution.
 pub fn set_project_trusted(codex_home: &Path, project_path: &Path) -> anyhow::Result<()> {
-    let config_path = codex_home.join(CONFIG_TOML_FILE);
-    // Parse existing config if present; otherwise start a new document.
-    let mut doc = match std::fs::read_to_string(config_path.clone()) {
-        Ok(s) => s.parse::<DocumentMut>()?,
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => DocumentMut::new(),
-        Err(e) => return Err(e.into()),
-    };
+    let mut doc = read_config_document(codex_home)?;
 
     // Mark the project as trusted. toml_edit is very good at handling
     // missing properties
     let project_key = project_path.to_string_lossy().to_string();
     doc["projects"][project_key.as_str()]["trust_level"] = toml_edit::value("trusted");
 
+    write_config_document(codex_home, &doc)
+}
+
+/// Whether `[projects."<project_path>"].trust_level` in
+/// `CODEX_HOME/config.toml` is `"trusted"`.
+pub fn is_project_trusted(codex_home: &Path, project_path: &Path) -> bool {
+    let Ok(doc) = read_config_document(codex_home) else {
+        return false;
+    };
+    let project_key = project_path.to_string_lossy().to_string();
+    doc.get("projects")
+        .and_then(|projects| projects.get(project_key.as_str()))
+        .and_then(|project| project.get("trust_level"))
+        .and_then(|item| item.as_str())
+        == Some("trusted")
+}
+
+/// Read `[projects."<project_path>"].approved_commands` from
+/// `CODEX_HOME/config.toml`.
+pub fn load_project_approved_commands(
+    codex_home: &Path,
+    project_path: &Path,
+) -> anyhow::Result<Vec<String>> {
+    load_project_array(codex_home, project_path, "approved_commands")
+}
+
+/// Read `[projects."<project_path>"].approved_hosts` from
+/// `CODEX_HOME/config.toml`.
+pub fn load_project_approved_hosts(
+    codex_home: &Path,
+    project_path: &Path,
+) -> anyhow::Result<Vec<String>> {
+    load_project_array(codex_home, project_path, "approved_hosts")
+}
+
+fn load_project_array(
+    codex_home: &Path,
+    project_path: &Path,
+    key: &str,
+) -> anyhow::Result<Vec<String>> {
+    let doc = read_config_document(codex_home)?;
+    let project_key = project_path.to_string_lossy().to_string();
+    let values = doc
+        .get("projects")
+        .and_then(|projects| projects.get(project_key.as_str()))
+        .and_then(|project| project.get(key))
+        .and_then(|item| item.as_array())
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(values)
+}
+
+/// Append `pattern` to `[projects."<project_path>"].approved_commands` in
+/// `CODEX_HOME/config.toml`, unless it is already present.
+pub fn add_project_approved_command(
+    codex_home: &Path,
+    project_path: &Path,
+    pattern: &str,
+) -> anyhow::Result<()> {
+    add_to_project_array(codex_home, project_path, "approved_commands", pattern)
+}
+
+/// Append `host` to `[projects."<project_path>"].approved_hosts` in
+/// `CODEX_HOME/config.toml`, unless it is already present.
+pub fn add_project_approved_host(
+    codex_home: &Path,
+    project_path: &Path,
+    host: &str,
+) -> anyhow::Result<()> {
+    add_to_project_array(codex_home, project_path, "approved_hosts", host)
+}
+
+fn add_to_project_array(
+    codex_home: &Path,
+    project_path: &Path,
+    key: &str,
+    value: &str,
+) -> anyhow::Result<()> {
+    let mut doc = read_config_document(codex_home)?;
+    let project_key = project_path.to_string_lossy().to_string();
+    let item = &mut doc["projects"][project_key.as_str()][key];
+    if item.is_none() {
+        *item = toml_edit::value(toml_edit::Array::new());
+    }
+    let Some(array) = item.as_array_mut() else {
+        anyhow::bail!("`{key}` for project {project_key} is not an array");
+    };
+    if !array.iter().any(|v| v.as_str() == Some(value)) {
+        array.push(value);
+    }
+    write_config_document(codex_home, &doc)
+}
+
+/// Remove `pattern` from `[projects."<project_path>"].approved_commands` in
+/// `CODEX_HOME/config.toml`.
+pub fn remove_project_approved_command(
+    codex_home: &Path,
+    project_path: &Path,
+    pattern: &str,
+) -> anyhow::Result<()> {
+    let mut doc = read_config_document(codex_home)?;
+    let project_key = project_path.to_string_lossy().to_string();
+    let array = doc
+        .get_mut("projects")
+        .and_then(|projects| projects.get_mut(project_key.as_str()))
+        .and_then(|project| project.get_mut("approved_commands"))
+        .and_then(|item| item.as_array_mut());
+    if let Some(array) = array {
+        array.retain(|v| v.as_str() != Some(pattern));
+        write_config_document(codex_home, &doc)?;
+    }
+    Ok(())
+}
+
+/// Parse `CODEX_HOME/config.toml` for editing; a missing file yields an empty
+/// document.
+fn read_config_document(codex_home: &Path) -> anyhow::Result<DocumentMut> {
+    let config_path = codex_home.join(CONFIG_TOML_FILE);
+    match std::fs::read_to_string(config_path) {
+        Ok(s) => Ok(s.parse::<DocumentMut>()?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(DocumentMut::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Atomically replace `CODEX_HOME/config.toml` with `doc`.
+fn write_config_document(codex_home: &Path, doc: &DocumentMut) -> anyhow::Result<()> {
     // ensure codex_home exists
     std::fs::create_dir_all(codex_home)?;
 
@@ -293,7 +499,7 @@ pub fn set_project_trusted(codex_home: &Path, project_path: &Path) -> anyhow::Re
     std::fs::write(tmp_file.path(), doc.to_string())?;
 
     // atomically move the tmp file into config.toml
-    tmp_file.persist(config_path)?;
+    tmp_file.persist(codex_home.join(CONFIG_TOML_FILE))?;
 
     Ok(())
 }
@@ -356,6 +562,13 @@ pub struct ConfigToml {
     /// Maximum number of output tokens.
     pub model_max_output_tokens: Option<u64>,
 
+    /// Maximum spend for a session in US dollars, computed from the
+    /// provider's `pricing` table.
+    pub max_cost_usd: Option<f64>,
+
+    /// Maximum number of tokens (input + output) for a session.
+    pub max_total_tokens: Option<u64>,
+
     /// Default approval policy for executing commands.
     pub approval_policy: Option<AskForApproval>,
 
@@ -368,6 +581,12 @@ pub struct ConfigToml {
     /// Sandbox configuration to apply if `sandbox` is `WorkspaceWrite`.
     pub sandbox_workspace_write: Option<SandboxWorkspaceWrite>,
 
+    /// Sandbox configuration to apply if `sandbox` is `ReadOnly`.
+    pub sandbox_read_only: Option<SandboxReadOnly>,
+
+    /// Limits applied to shell commands run under the Linux sandbox.
+    pub resource_limits: Option<ResourceLimits>,
+
     /// Disable server-side response storage (sends the full conversation
     /// context with every request). Currently necessary for OpenAI customers
     /// who have opted into Zero Data Retention (ZDR).
@@ -388,6 +607,11 @@ pub struct ConfigToml {
     #[serde(default)]
     pub model_providers: HashMap<String, ModelProviderInfo>,
 
+    /// Metadata for models that are missing from (or wrong in) the built-in
+    /// tables, keyed by model slug.
+    #[serde(default)]
+    pub models: HashMap<String, ModelMetadata>,
+
     /// Maximum number of bytes to include from an AGENTS.md project doc file.
     pub project_doc_max_bytes: Option<usize>,
 
@@ -402,6 +626,11 @@ pub struct ConfigToml {
     #[serde(default)]
     pub history: Option<History>,
 
+    /// Secret redaction for command output sent to the model, clients and
+    /// rollouts.
+    #[serde(default)]
+    pub redaction: Option<Redaction>,
+
     /// Optional URI-based file opener. If set, citations to files in the model
     /// output will be hyperlinked using the specified URI scheme.
     pub file_opener: Option<UriBasedFileOpener>,
@@ -432,6 +661,48 @@ pub struct ConfigToml {
     /// Experimental path to a file whose contents replace the built-in BASE_INSTRUCTIONS.
     pub experimental_instructions_file: Option<PathBuf>,
 
+    /// When set to `true`, the model gets tools to run commands in a
+    /// pseudo-terminal and interact with them across tool calls.
+    pub include_exec_session_tools: Option<bool>,
+
+    /// When set to `true`, the model gets tools to start commands in the
+    /// background and poll them later.
+    pub include_background_job_tools: Option<bool>,
+
+    /// Set to `false` to remove the `search_file_contents` tool, which is
+    /// included by default.
+    pub include_content_search_tool: Option<bool>,
+
+    /// Set to `false` to remove the `repo_map` tool, which is included by
+    /// default.
+    pub include_repo_map_tool: Option<bool>,
+
+    /// Set to `true` to add the `read_file`, `list_dir` and `edit_file`
+    /// tools, for models that are unreliable with `apply_patch`.
+    pub include_file_tools: Option<bool>,
+
+    /// Set to `true` to add the `web_fetch` tool.
+    pub include_web_fetch_tool: Option<bool>,
+
+    /// Adds the `web_search` tool, backed by this search engine.
+    pub web_search: Option<WebSearchConfig>,
+
+    /// Set to `true` to add the `view_image` tool, for models that accept
+    /// image input.
+    pub include_view_image_tool: Option<bool>,
+
+    /// Set to `true` to add the `read_notebook` and `edit_notebook` tools,
+    /// which edit Jupyter notebooks cell by cell.
+    pub include_notebook_tools: Option<bool>,
+
+    /// Size in tokens of the repository outline to include in the
+    /// environment context. No outline is included when unset.
+    pub repo_map_context_tokens: Option<usize>,
+
+    /// Minimum similarity, between 0 and 1, for `apply_patch` to apply a hunk
+    /// to a region that does not match its context exactly.
+    pub apply_patch_fuzzy_threshold: Option<f64>,
+
     /// The value for the `originator` header included with Responses API requests.
     pub responses_originator_header_internal_override: Option<String>,
 
@@ -453,18 +724,27 @@ impl ConfigToml {
             .or(self.sandbox_mode)
             .unwrap_or_default();
         match resolved_sandbox_mode {
-            SandboxMode::ReadOnly => SandboxPolicy::new_read_only_policy(),
+            SandboxMode::ReadOnly => match self.sandbox_read_only.as_ref() {
+                Some(SandboxReadOnly { readable_roots }) => SandboxPolicy::ReadOnly {
+                    readable_roots: readable_roots.clone(),
+                },
+                None => SandboxPolicy::new_read_only_policy(),
+            },
             SandboxMode::WorkspaceWrite => match self.sandbox_workspace_write.as_ref() {
                 Some(SandboxWorkspaceWrite {
                     writable_roots,
                     network_access,
                     exclude_tmpdir_env_var,
                     exclude_slash_tmp,
+                    readable_roots,
+                    allowed_domains,
                 }) => SandboxPolicy::WorkspaceWrite {
                     writable_roots: writable_roots.clone(),
                     network_access: *network_access,
                     exclude_tmpdir_env_var: *exclude_tmpdir_env_var,
                     exclude_slash_tmp: *exclude_slash_tmp,
+                    readable_roots: readable_roots.clone(),
+                    allowed_domains: allowed_domains.clone(),
                 },
                 None => SandboxPolicy::new_workspace_write_policy(),
             },
@@ -611,34 +891,54 @@ impl Config {
             }
         };
 
+        // Approvals are only read from `CODEX_HOME/config.toml`: `cfg` has the
+        // project's own `.codex/config.toml` merged in, and a repository must
+        // not be able to approve its own commands or hosts.
+        let invalid_data =
+            |e: anyhow::Error| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string());
+        let project_approved_commands =
+            load_project_approved_commands(&codex_home, &resolved_cwd).map_err(invalid_data)?;
+        let project_approved_hosts =
+            load_project_approved_hosts(&codex_home, &resolved_cwd).map_err(invalid_data)?;
+
         let history = cfg.history.unwrap_or_default();
 
         let model = model
             .or(config_profile.model)
             .or(cfg.model)
             .unwrap_or_else(default_model);
-        let model_family = find_family_for_model(&model).unwrap_or_else(|| {
-            let supports_reasoning_summaries =
-                cfg.model_supports_reasoning_summaries.unwrap_or(false);
-            ModelFamily {
-                slug: model.clone(),
-                family: model.clone(),
-                needs_special_apply_patch_instructions: false,
-                supports_reasoning_summaries,
-                uses_local_shell_tool: false,
-                uses_apply_patch_tool: false,
-            }
-        });
+        let model_family =
+            find_family_for_model_with_metadata(&model, &cfg.models).unwrap_or_else(|| {
+                let supports_reasoning_summaries =
+                    cfg.model_supports_reasoning_summaries.unwrap_or(false);
+                ModelFamily {
+                    slug: model.clone(),
+                    family: model.clone(),
+                    needs_special_apply_patch_instructions: false,
+                    supports_reasoning_summaries,
+                    uses_local_shell_tool: false,
+                    uses_apply_patch_tool: false,
+                }
+            });
 
-        let openai_model_info = get_model_info(&model_family);
-        let model_context_window = cfg
-            .model_context_window
-            .or_else(|| openai_model_info.as_ref().map(|info| info.context_window));
-        let model_max_output_tokens = cfg.model_max_output_tokens.or_else(|| {
-            openai_model_info
-                .as_ref()
-                .map(|info| info.max_output_tokens)
-        });
+        let (known_context_window, known_max_output_tokens) =
+            get_model_limits(&model_family, &cfg.models);
+        let model_context_window = cfg.model_context_window.or(known_context_window);
+        let model_max_output_tokens = cfg.model_max_output_tokens.or(known_max_output_tokens);
+
+        let model_fallbacks = match config_profile
+            .fallback
+            .as_ref()
+            .or(model_provider.fallback.as_ref())
+        {
+            Some(entries) => ModelFallback::resolve_chain(entries, &model_providers, &model)?,
+            None => Vec::new(),
+        };
+
+        let budget = Budget {
+            max_cost_usd: config_profile.max_cost_usd.or(cfg.max_cost_usd),
+            max_total_tokens: config_profile.max_total_tokens.or(cfg.max_total_tokens),
+        };
 
         let experimental_resume = cfg.experimental_resume;
 
@@ -651,7 +951,11 @@ impl Config {
             .or(cfg.experimental_instructions_file.as_ref());
         let file_base_instructions =
             Self::get_base_instructions(experimental_instructions_path, &resolved_cwd)?;
-        let base_instructions = base_instructions.or(file_base_instructions);
+        let base_instructions = base_instructions.or(file_base_instructions).or_else(|| {
+            cfg.models
+                .get(&model)
+                .and_then(|metadata| metadata.base_instructions.clone())
+        });
 
         let include_apply_patch_tool_val =
             include_apply_patch_tool.unwrap_or(model_family.uses_apply_patch_tool);
@@ -667,6 +971,8 @@ impl Config {
             model_max_output_tokens,
             model_provider_id,
             model_provider,
+            model_fallbacks,
+            budget,
             cwd: resolved_cwd,
             approval_policy: approval_policy
                 .or(config_profile.approval_policy)
@@ -674,6 +980,7 @@ impl Config {
                 .unwrap_or_else(AskForApproval::default),
             sandbox_policy,
             shell_environment_policy,
+            resource_limits: cfg.resource_limits.unwrap_or_default(),
             disable_response_storage: config_profile
                 .disable_response_storage
                 .or(cfg.disable_response_storage)
@@ -684,9 +991,11 @@ impl Config {
             base_instructions,
             mcp_servers: cfg.mcp_servers,
             model_providers,
+            models: cfg.models,
             project_doc_max_bytes: cfg.project_doc_max_bytes.unwrap_or(PROJECT_DOC_MAX_BYTES),
             codex_home,
             history,
+            redaction: cfg.redaction.unwrap_or_default(),
             file_opener: cfg.file_opener.unwrap_or(UriBasedFileOpener::VsCode),
             tui: cfg.tui.unwrap_or_default(),
             codex_linux_sandbox_exe,
@@ -713,8 +1022,25 @@ impl Config {
             experimental_resume,
             include_plan_tool: include_plan_tool.unwrap_or(false),
             include_apply_patch_tool: include_apply_patch_tool_val,
+            include_exec_session_tools: cfg.include_exec_session_tools.unwrap_or(false),
+            include_background_job_tools: cfg.include_background_job_tools.unwrap_or(false),
+            include_content_search_tool: cfg.include_content_search_tool.unwrap_or(true),
+            include_repo_map_tool: cfg.include_repo_map_tool.unwrap_or(true),
+            include_file_tools: cfg.include_file_tools.unwrap_or(false),
+            include_web_fetch_tool: cfg.include_web_fetch_tool.unwrap_or(false),
+            web_search: cfg.web_search,
+            include_view_image_tool: cfg.include_view_image_tool.unwrap_or(false),
+            include_notebook_tools: cfg.include_notebook_tools.unwrap_or(false),
+            repo_map_context_tokens: cfg.repo_map_context_tokens,
+            apply_patch_fuzzy_threshold: cfg
+                .apply_patch_fuzzy_threshold
+                .unwrap_or(ApplyPatchOptions::default().fuzzy_threshold)
+                .clamp(0.0, 1.0),
             responses_originator_header,
             preferred_auth_method: cfg.preferred_auth_method.unwrap_or(AuthMode::ChatGPT),
+            output_schema: None,
+            project_approved_commands,
+            project_approved_hosts,
         };
         Ok(config)
     }
@@ -867,6 +1193,7 @@ pub fn log_dir(cfg: &Config) -> std::io::Result<PathBuf> {
 #[cfg(test)]
 mod tests {
     use crate::config_types::HistoryPersistence;
+    use crate::model_family::find_family_for_model;
 
     use super::*;
     use pretty_assertions::assert_eq;
@@ -931,10 +1258,26 @@ network_access = true  # This should be ignored.
             .expect("TOML deserialization should succeed");
         let sandbox_mode_override = None;
         assert_eq!(
-            SandboxPolicy::ReadOnly,
+            SandboxPolicy::new_read_only_policy(),
             sandbox_read_only_cfg.derive_sandbox_policy(sandbox_mode_override)
         );
 
+        let sandbox_read_only_restricted = r#"
+sandbox_mode = "read-only"
+
+[sandbox_read_only]
+readable_roots = ["/my/workspace"]
+"#;
+        let sandbox_read_only_restricted_cfg =
+            toml::from_str::<ConfigToml>(sandbox_read_only_restricted)
+                .expect("TOML deserialization should succeed");
+        assert_eq!(
+            SandboxPolicy::ReadOnly {
+                readable_roots: vec![PathBuf::from("/my/workspace")],
+            },
+            sandbox_read_only_restricted_cfg.derive_sandbox_policy(None)
+        );
+
         let sandbox_workspace_write = r#"
 sandbox_mode = "workspace-write"
 
@@ -944,6 +1287,8 @@ writable_roots = [
 ]
 exclude_tmpdir_env_var = true
 exclude_slash_tmp = true
+readable_roots = ["/usr/local/share"]
+allowed_domains = ["crates.io"]
 "#;
 
         let sandbox_workspace_write_cfg = toml::from_str::<ConfigToml>(sandbox_workspace_write)
@@ -955,6 +1300,8 @@ exclude_slash_tmp = true
                 network_access: false,
                 exclude_tmpdir_env_var: true,
                 exclude_slash_tmp: true,
+                readable_roots: vec![PathBuf::from("/usr/local/share")],
+                allowed_domains: vec!["crates.io".to_string()],
             },
             sandbox_workspace_write_cfg.derive_sandbox_policy(sandbox_mode_override)
         );
@@ -1041,6 +1388,9 @@ disable_response_storage = true
             stream_max_retries: Some(10),
             stream_idle_timeout_ms: Some(300_000),
             requires_openai_auth: false,
+            fallback: None,
+            pricing: None,
+            supports_output_schema: None,
         };
         let model_provider_map = {
             let mut model_provider_map = built_in_model_providers();
@@ -1066,6 +1416,115 @@ disable_response_storage = true
         })
     }
 
+    #[test]
+    fn test_user_model_metadata_is_merged() -> std::io::Result<()> {
+        let cfg = toml::from_str::<ConfigToml>(
+            r#"
+model = "my-local-oss"
+
+[models.my-local-oss]
+family = "gpt-oss"
+context_window = 32000
+supports_reasoning_summaries = true
+base_instructions = "You are a local model."
+"#,
+        )
+        .expect("TOML deserialization should succeed");
+        let codex_home = TempDir::new()?;
+        let config = Config::load_from_base_config_with_overrides(
+            cfg,
+            ConfigOverrides::default(),
+            codex_home.path().to_path_buf(),
+            None,
+        )?;
+
+        assert_eq!(
+            ModelFamily {
+                slug: "my-local-oss".to_string(),
+                family: "gpt-oss".to_string(),
+                needs_special_apply_patch_instructions: false,
+                supports_reasoning_summaries: true,
+                uses_local_shell_tool: false,
+                uses_apply_patch_tool: true,
+            },
+            config.model_family
+        );
+        // The declared context window wins; the output limit is inherited
+        // from the built-in `gpt-oss` entries via the declared family.
+        assert_eq!(Some(32_000), config.model_context_window);
+        assert_eq!(Some(32_000), config.model_max_output_tokens);
+        assert_eq!(
+            Some("You are a local model.".to_string()),
+            config.base_instructions
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_approved_commands_round_trip() -> anyhow::Result<()> {
+        let codex_home = TempDir::new()?;
+        let project = TempDir::new()?;
+        set_project_trusted(codex_home.path(), project.path())?;
+        add_project_approved_command(codex_home.path(), project.path(), "cargo test *")?;
+        add_project_approved_command(codex_home.path(), project.path(), "npm run lint")?;
+        add_project_approved_command(codex_home.path(), project.path(), "cargo test *")?;
+        remove_project_approved_command(codex_home.path(), project.path(), "npm run lint")?;
+        add_project_approved_host(codex_home.path(), project.path(), "docs.rs")?;
+
+        assert_eq!(
+            vec!["cargo test *".to_string()],
+            load_project_approved_commands(codex_home.path(), project.path())?
+        );
+
+        let cfg = load_config_as_toml(codex_home.path())?.try_into::<ConfigToml>()?;
+        assert!(cfg.is_cwd_trusted(project.path()));
+        let config = Config::load_from_base_config_with_overrides(
+            cfg,
+            ConfigOverrides {
+                cwd: Some(project.path().to_path_buf()),
+                ..Default::default()
+            },
+            codex_home.path().to_path_buf(),
+            None,
+        )?;
+        assert_eq!(
+            vec!["cargo test *".to_string()],
+            config.project_approved_commands
+        );
+        assert_eq!(vec!["docs.rs".to_string()], config.project_approved_hosts);
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_local_config_cannot_grant_approvals() -> anyhow::Result<()> {
+        let codex_home = TempDir::new()?;
+        let project = TempDir::new()?;
+
+        // Stands in for the global config with a cloned repository's
+        // `.codex/config.toml` merged on top.
+        let cfg: ConfigToml = toml::from_str(&format!(
+            r#"
+[projects."{}"]
+approved_commands = ["python *"]
+approved_hosts = ["attacker.example"]
+"#,
+            project.path().display()
+        ))?;
+
+        let config = Config::load_from_base_config_with_overrides(
+            cfg,
+            ConfigOverrides {
+                cwd: Some(project.path().to_path_buf()),
+                ..Default::default()
+            },
+            codex_home.path().to_path_buf(),
+            None,
+        )?;
+        assert!(config.project_approved_commands.is_empty());
+        assert!(config.project_approved_hosts.is_empty());
+        Ok(())
+    }
+
     /// Users can specify config values at multiple levels that have the
     /// following precedence:
     ///
@@ -1101,18 +1560,23 @@ disable_response_storage = true
                 model_max_output_tokens: Some(100_000),
                 model_provider_id: "openai".to_string(),
                 model_provider: fixture.openai_provider.clone(),
+                model_fallbacks: Vec::new(),
+                budget: Budget::default(),
                 approval_policy: AskForApproval::Never,
                 sandbox_policy: SandboxPolicy::new_read_only_policy(),
                 shell_environment_policy: ShellEnvironmentPolicy::default(),
+                resource_limits: ResourceLimits::default(),
                 disable_response_storage: false,
                 user_instructions: None,
                 notify: None,
                 cwd: fixture.cwd(),
                 mcp_servers: HashMap::new(),
                 model_providers: fixture.model_provider_map.clone(),
+                models: HashMap::new(),
                 project_doc_max_bytes: PROJECT_DOC_MAX_BYTES,
                 codex_home: fixture.codex_home(),
                 history: History::default(),
+                redaction: Redaction::default(),
                 file_opener: UriBasedFileOpener::VsCode,
                 tui: Tui::default(),
                 codex_linux_sandbox_exe: None,
@@ -1125,8 +1589,22 @@ disable_response_storage = true
                 base_instructions: None,
                 include_plan_tool: false,
                 include_apply_patch_tool: false,
+                include_exec_session_tools: false,
+                include_background_job_tools: false,
+                include_content_search_tool: true,
+                include_repo_map_tool: true,
+                include_file_tools: false,
+                include_web_fetch_tool: false,
+                web_search: None,
+                include_view_image_tool: false,
+                include_notebook_tools: false,
+                repo_map_context_tokens: None,
+                apply_patch_fuzzy_threshold: 1.0,
                 responses_originator_header: "codex_cli_rs".to_string(),
                 preferred_auth_method: AuthMode::ChatGPT,
+                output_schema: None,
+                project_approved_commands: Vec::new(),
+                project_approved_hosts: Vec::new(),
             },
             o3_profile_config
         );
@@ -1155,18 +1633,23 @@ disable_response_storage = true
             model_max_output_tokens: Some(4_096),
             model_provider_id: "openai-chat-completions".to_string(),
             model_provider: fixture.openai_chat_completions_provider.clone(),
+            model_fallbacks: Vec::new(),
+            budget: Budget::default(),
             approval_policy: AskForApproval::UnlessTrusted,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            resource_limits: ResourceLimits::default(),
             disable_response_storage: false,
             user_instructions: None,
             notify: None,
             cwd: fixture.cwd(),
             mcp_servers: HashMap::new(),
             model_providers: fixture.model_provider_map.clone(),
+            models: HashMap::new(),
             project_doc_max_bytes: PROJECT_DOC_MAX_BYTES,
             codex_home: fixture.codex_home(),
             history: History::default(),
+            redaction: Redaction::default(),
             file_opener: UriBasedFileOpener::VsCode,
             tui: Tui::default(),
             codex_linux_sandbox_exe: None,
@@ -1179,8 +1662,22 @@ disable_response_storage = true
             base_instructions: None,
             include_plan_tool: false,
             include_apply_patch_tool: false,
+            include_exec_session_tools: false,
+            include_background_job_tools: false,
+            include_content_search_tool: true,
+            include_repo_map_tool: true,
+            include_file_tools: false,
+            include_web_fetch_tool: false,
+            web_search: None,
+            include_view_image_tool: false,
+            include_notebook_tools: false,
+            repo_map_context_tokens: None,
+            apply_patch_fuzzy_threshold: 1.0,
             responses_originator_header: "codex_cli_rs".to_string(),
             preferred_auth_method: AuthMode::ChatGPT,
+            output_schema: None,
+            project_approved_commands: Vec::new(),
+            project_approved_hosts: Vec::new(),
         };
 
         assert_eq!(expected_gpt3_profile_config, gpt3_profile_config);
@@ -1225,18 +1722,23 @@ disable_response_storage = true
             model_max_output_tokens: Some(100_000),
             model_provider_id: "openai".to_string(),
             model_provider: fixture.openai_provider.clone(),
+            model_fallbacks: Vec::new(),
+            budget: Budget::default(),
             approval_policy: AskForApproval::OnFailure,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            resource_limits: ResourceLimits::default(),
             disable_response_storage: true,
             user_instructions: None,
             notify: None,
             cwd: fixture.cwd(),
             mcp_servers: HashMap::new(),
             model_providers: fixture.model_provider_map.clone(),
+            models: HashMap::new(),
             project_doc_max_bytes: PROJECT_DOC_MAX_BYTES,
             codex_home: fixture.codex_home(),
             history: History::default(),
+            redaction: Redaction::default(),
             file_opener: UriBasedFileOpener::VsCode,
             tui: Tui::default(),
             codex_linux_sandbox_exe: None,
@@ -1249,8 +1751,22 @@ disable_response_storage = true
             base_instructions: None,
             include_plan_tool: false,
             include_apply_patch_tool: false,
+            include_exec_session_tools: false,
+            include_background_job_tools: false,
+            include_content_search_tool: true,
+            include_repo_map_tool: true,
+            include_file_tools: false,
+            include_web_fetch_tool: false,
+            web_search: None,
+            include_view_image_tool: false,
+            include_notebook_tools: false,
+            repo_map_context_tokens: None,
+            apply_patch_fuzzy_threshold: 1.0,
             responses_originator_header: "codex_cli_rs".to_string(),
             preferred_auth_method: AuthMode::ChatGPT,
+            output_schema: None,
+            project_approved_commands: Vec::new(),
+            project_approved_hosts: Vec::new(),
         };
 
         assert_eq!(expected_zdr_profile_config, zdr_profile_config);
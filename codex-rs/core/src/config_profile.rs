@@ -19,4 +19,8 @@ pub struct ConfigProfile {
     pub model_reasoning_summary: Option<ReasoningSummary>,
     pub chatgpt_base_url: Option<String>,
     pub experimental_instructions_file: Option<PathBuf>,
+    /// Overrides the provider's `fallback` chain while this profile is active.
+    pub fallback: Option<Vec<String>>,
+    pub max_cost_usd: Option<f64>,
+    pub max_total_tokens: Option<u64>,
 }
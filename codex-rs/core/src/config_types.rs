@@ -8,6 +8,7 @@ use std::path::PathBuf;
 use wildmatch::WildMatchPattern;
 
 use serde::Deserialize;
+use serde::Serialize;
 
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct McpServerConfig {
@@ -72,10 +73,83 @@ pub enum HistoryPersistence {
     None,
 }
 
+/// Settings for scrubbing secrets from command output before it is sent to
+/// the model, shown to clients or written to rollouts.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Redaction {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Also redact long random-looking tokens that no specific detector
+    /// recognizes.
+    #[serde(default = "default_true")]
+    pub high_entropy: bool,
+
+    /// Additional regular expressions to redact. When a pattern has a capture
+    /// group, only the text of the first group is redacted.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            high_entropy: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Search engine queried by the `web_search` tool, from the `[web_search]`
+/// table.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum WebSearchConfig {
+    /// A SearXNG instance with the `json` output format enabled.
+    Searxng { url: String },
+    /// The Brave Search API, with the subscription token read from the
+    /// environment variable `api_key_env`.
+    Brave {
+        #[serde(default = "default_brave_api_key_env")]
+        api_key_env: String,
+    },
+}
+
+fn default_brave_api_key_env() -> String {
+    "BRAVE_SEARCH_API_KEY".to_string()
+}
+
 /// Collection of settings that are specific to the TUI.
 #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct Tui {}
 
+/// User-declared metadata for a model, from a `[models.<slug>]` table. Any
+/// field left unset falls back to the built-in tables.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ModelMetadata {
+    /// Built-in family whose traits the model shares, e.g. `"gpt-oss"`.
+    pub family: Option<String>,
+    /// Size of the context window, in tokens.
+    pub context_window: Option<u64>,
+    /// Maximum number of output tokens.
+    pub max_output_tokens: Option<u64>,
+    pub uses_apply_patch_tool: Option<bool>,
+    pub supports_reasoning_summaries: Option<bool>,
+    /// Replaces the built-in base instructions when this model is used.
+    pub base_instructions: Option<String>,
+    /// Most bytes of each command output stream shown to the model.
+    pub exec_output_max_bytes: Option<usize>,
+    /// Most lines of each command output stream shown to the model.
+    pub exec_output_max_lines: Option<usize>,
+    /// Overrides the provider's `supports_output_schema` for this model.
+    pub supports_output_schema: Option<bool>,
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct SandboxWorkspaceWrite {
     #[serde(default)]
@@ -86,6 +160,39 @@ pub struct SandboxWorkspaceWrite {
     pub exclude_tmpdir_env_var: bool,
     #[serde(default)]
     pub exclude_slash_tmp: bool,
+    /// When non-empty, reads are limited to these folders, the writable
+    /// roots and the system folders needed to run programs.
+    #[serde(default)]
+    pub readable_roots: Vec<PathBuf>,
+    /// When `network_access` is `false`, HTTP(S) requests to these domains
+    /// and their subdomains are still allowed through a local proxy.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SandboxReadOnly {
+    /// When non-empty, reads are limited to these folders, the current
+    /// working directory and the system folders needed to run programs.
+    #[serde(default)]
+    pub readable_roots: Vec<PathBuf>,
+}
+
+/// Limits applied by the Linux sandbox to each shell command. Unset limits
+/// are inherited from Codex.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ResourceLimits {
+    /// CPU time in seconds (`RLIMIT_CPU`).
+    pub cpu_time_seconds: Option<u64>,
+    /// Virtual address space in MiB (`RLIMIT_AS`).
+    pub address_space_mb: Option<u64>,
+    /// Processes and threads of the command and its descendants together,
+    /// enforced through a cgroup v2 `pids.max`.
+    pub max_processes: Option<u64>,
+    /// Open file descriptors (`RLIMIT_NOFILE`).
+    pub max_open_files: Option<u64>,
+    /// Memory cap in MiB, enforced through a cgroup v2 `memory.max`.
+    pub memory_max_mb: Option<u64>,
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
@@ -0,0 +1,378 @@
+//! The `search_file_contents` tool: a regex search over the files under a
+//! directory that honors `.gitignore`, so the model does not have to shell
+//! out to `rg`, which may not be installed in the sandbox. It only reads
+//! files, so it never asks for approval, and searches only folders its
+//! [`ReadScope`] allows.
+
+use std::collections::BTreeMap;
+use std::num::NonZero;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::atomic::AtomicBool;
+
+use codex_file_search::ContentMatch;
+use codex_file_search::ContentSearchOptions;
+use codex_file_search::ContentSearchResults;
+use codex_file_search::run_content_search;
+use serde::Deserialize;
+
+use crate::file_tools::ReadScope;
+use crate::models::FunctionCallOutputPayload;
+use crate::models::ResponseInputItem;
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+
+/// Matching lines returned when the model does not ask for a number.
+const DEFAULT_MAX_RESULTS: usize = 100;
+
+/// Most matching lines a single call can return.
+const MAX_RESULTS_LIMIT: usize = 1000;
+
+/// Most context lines a single call can ask for around each match.
+const MAX_CONTEXT_LINES: usize = 10;
+
+/// The walk is I/O bound, so more threads than this rarely help; see the
+/// comment on `codex_file_search::Cli::threads`.
+const SEARCH_THREADS: usize = 2;
+
+#[derive(Deserialize, Debug, Clone)]
+struct SearchFileContentsParams {
+    pattern: String,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    glob: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    context_lines: Option<usize>,
+    #[serde(default)]
+    case_insensitive: bool,
+    #[serde(default)]
+    max_results: Option<usize>,
+}
+
+pub(crate) static SEARCH_FILE_CONTENTS_TOOL: LazyLock<OpenAiTool> = LazyLock::new(|| {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "pattern".to_string(),
+        JsonSchema::String {
+            description: Some("Regular expression matched against each line.".to_string()),
+        },
+    );
+    properties.insert(
+        "path".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Directory to search, relative to the working directory. Defaults to the working directory."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "glob".to_string(),
+        JsonSchema::Array {
+            items: Box::new(JsonSchema::String { description: None }),
+            description: Some(
+                "Only search files matching one of these globs, e.g. [\"*.rs\", \"docs/**\"]."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "exclude".to_string(),
+        JsonSchema::Array {
+            items: Box::new(JsonSchema::String { description: None }),
+            description: Some("Skip files matching any of these globs.".to_string()),
+        },
+    );
+    properties.insert(
+        "context_lines".to_string(),
+        JsonSchema::Number {
+            description: Some(format!(
+                "Lines to show before and after each match, at most {MAX_CONTEXT_LINES}. Defaults to 0."
+            )),
+        },
+    );
+    properties.insert(
+        "case_insensitive".to_string(),
+        JsonSchema::Boolean {
+            description: Some("Match without regard to case.".to_string()),
+        },
+    );
+    properties.insert(
+        "max_results".to_string(),
+        JsonSchema::Number {
+            description: Some(format!(
+                "Most matching lines to return, at most {MAX_RESULTS_LIMIT}. Defaults to {DEFAULT_MAX_RESULTS}."
+            )),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "search_file_contents".to_string(),
+        description: "Searches the contents of the files under a directory for lines matching a regular expression, like `rg`. Files ignored by .gitignore, hidden files and binary files are skipped. Results are printed as `path:line:text`, with context lines as `path-line-text`. This tool only reads files and never needs approval; prefer it to running grep or rg through the shell.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(vec!["pattern".to_string()]),
+            additional_properties: Some(false),
+        },
+    })
+});
+
+pub(crate) async fn handle_search_file_contents(
+    scope: &ReadScope,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<SearchFileContentsParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+
+    let search_directory = match scope.resolve(Path::new(params.path.as_deref().unwrap_or("."))) {
+        Ok(search_directory) => search_directory,
+        Err(message) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: message,
+                    success: Some(false),
+                },
+            };
+        }
+    };
+    let context_lines = params.context_lines.unwrap_or(0).min(MAX_CONTEXT_LINES);
+    let limit = params
+        .max_results
+        .unwrap_or(DEFAULT_MAX_RESULTS)
+        .clamp(1, MAX_RESULTS_LIMIT);
+    let options = ContentSearchOptions {
+        pattern: params.pattern,
+        case_insensitive: params.case_insensitive,
+        include: params.glob,
+        exclude: params.exclude,
+        context_lines,
+        limit: NonZero::new(limit).unwrap_or(NonZero::<usize>::MIN),
+        threads: NonZero::new(SEARCH_THREADS).unwrap_or(NonZero::<usize>::MIN),
+    };
+
+    let result = if search_directory.is_dir() {
+        tokio::task::spawn_blocking(move || {
+            run_content_search(
+                &options,
+                &search_directory,
+                Arc::new(AtomicBool::new(false)),
+            )
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result)
+    } else {
+        Err(anyhow::anyhow!(
+            "{} is not a directory",
+            search_directory.display()
+        ))
+    };
+
+    let output = match result {
+        Ok(results) => FunctionCallOutputPayload {
+            content: format_results(&results, context_lines),
+            success: Some(true),
+        },
+        Err(e) => FunctionCallOutputPayload {
+            content: format!("search failed: {e}"),
+            success: Some(false),
+        },
+    };
+    ResponseInputItem::FunctionCallOutput { call_id, output }
+}
+
+/// Formats matches the way `rg -n` does: `path:line:text` for matching lines,
+/// `path-line-text` for context lines, and `--` between runs of lines that
+/// are not adjacent when context was requested.
+fn format_results(results: &ContentSearchResults, context_lines: usize) -> String {
+    if results.matches.is_empty() {
+        return "No matches found.".to_string();
+    }
+
+    let mut out = String::new();
+    let mut last_printed: Option<(&str, usize)> = None;
+    for file_matches in results.matches.chunk_by(|a, b| a.path == b.path) {
+        // Context around nearby matches overlaps, so collect every line of the
+        // file first and print each one once.
+        let mut lines: BTreeMap<usize, (&str, bool)> = BTreeMap::new();
+        for m in file_matches {
+            for (line_number, text) in context_lines_of(m) {
+                lines.entry(line_number).or_insert((text, false));
+            }
+            lines.insert(m.line_number, (&m.line, true));
+        }
+
+        let path = file_matches[0].path.as_str();
+        for (line_number, (text, is_match)) in lines {
+            if context_lines > 0
+                && let Some(last) = last_printed
+                && last != (path, line_number - 1)
+            {
+                out.push_str("--\n");
+            }
+            let separator = if is_match { ':' } else { '-' };
+            out.push_str(&format!(
+                "{path}{separator}{line_number}{separator}{text}\n"
+            ));
+            last_printed = Some((path, line_number));
+        }
+    }
+
+    let shown = results.matches.len();
+    if results.total_match_count > shown {
+        out.push_str(&format!(
+            "\n(showing the first {shown} of {} matching lines; narrow the pattern or glob, or raise max_results)\n",
+            results.total_match_count
+        ));
+    }
+    out
+}
+
+fn context_lines_of(m: &ContentMatch) -> impl Iterator<Item = (usize, &str)> {
+    let before_start = m.line_number - m.before.len();
+    let before = m
+        .before
+        .iter()
+        .enumerate()
+        .map(move |(i, text)| (before_start + i, text.as_str()));
+    let after = m
+        .after
+        .iter()
+        .enumerate()
+        .map(move |(i, text)| (m.line_number + 1 + i, text.as_str()));
+    before.chain(after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SandboxPolicy;
+    use pretty_assertions::assert_eq;
+
+    fn content_match(
+        path: &str,
+        line_number: usize,
+        before: &[&str],
+        after: &[&str],
+    ) -> ContentMatch {
+        ContentMatch {
+            path: path.to_string(),
+            line_number,
+            line: format!("match {line_number}"),
+            before: before.iter().map(|s| s.to_string()).collect(),
+            after: after.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn formats_matches_like_rg_and_merges_overlapping_context() {
+        let results = ContentSearchResults {
+            matches: vec![
+                content_match("a.rs", 2, &["one"], &["three"]),
+                content_match("a.rs", 4, &["three"], &["five"]),
+                content_match("a.rs", 9, &["eight"], &[]),
+                content_match("b.rs", 1, &[], &["two"]),
+            ],
+            total_match_count: 5,
+        };
+
+        assert_eq!(
+            format_results(&results, 1),
+            "a.rs-1-one\n\
+             a.rs:2:match 2\n\
+             a.rs-3-three\n\
+             a.rs:4:match 4\n\
+             a.rs-5-five\n\
+             --\n\
+             a.rs-8-eight\n\
+             a.rs:9:match 9\n\
+             --\n\
+             b.rs:1:match 1\n\
+             b.rs-2-two\n\
+             \n\
+             (showing the first 4 of 5 matching lines; narrow the pattern or glob, or raise max_results)\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn searches_relative_to_the_working_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/main.rs"),
+            "fn main() {\n    todo!()\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("README.md"), "todo: write docs\n").unwrap();
+
+        let scope = ReadScope {
+            cwd: dir.path().to_path_buf(),
+            sandbox_policy: SandboxPolicy::new_read_only_policy(),
+            output_dir: dir.path().join("output"),
+        };
+
+        let output = handle_search_file_contents(
+            &scope,
+            r#"{"pattern": "TODO", "path": "src", "case_insensitive": true}"#.to_string(),
+            "call".to_string(),
+        )
+        .await;
+
+        let ResponseInputItem::FunctionCallOutput { output, .. } = output else {
+            panic!("expected a function call output");
+        };
+        assert_eq!(output.content, "main.rs:2:    todo!()\n");
+        assert_eq!(output.success, Some(true));
+    }
+
+    #[tokio::test]
+    async fn refuses_folders_the_sandbox_cannot_read() {
+        let readable = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "TODO\n").unwrap();
+        let scope = ReadScope {
+            cwd: readable.path().to_path_buf(),
+            sandbox_policy: SandboxPolicy::ReadOnly {
+                readable_roots: vec![readable.path().to_path_buf()],
+            },
+            output_dir: readable.path().join("output"),
+        };
+        let arguments = serde_json::json!({
+            "pattern": "TODO",
+            "path": outside.path(),
+        });
+
+        let output =
+            handle_search_file_contents(&scope, arguments.to_string(), "call".to_string()).await;
+
+        let ResponseInputItem::FunctionCallOutput { output, .. } = output else {
+            panic!("expected a function call output");
+        };
+        assert!(
+            output
+                .content
+                .contains("outside the folders the sandbox can read"),
+            "{}",
+            output.content
+        );
+        assert_eq!(output.success, Some(false));
+    }
+}
@@ -28,6 +28,10 @@ pub(crate) struct EnvironmentContext {
     pub approval_policy: AskForApproval,
     pub sandbox_mode: SandboxMode,
     pub network_access: NetworkAccess,
+    /// Outline of the working directory, when `repo_map_context_tokens` is
+    /// set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_map: Option<String>,
 }
 
 impl EnvironmentContext {
@@ -41,12 +45,12 @@ impl EnvironmentContext {
             approval_policy,
             sandbox_mode: match sandbox_policy {
                 SandboxPolicy::DangerFullAccess => SandboxMode::DangerFullAccess,
-                SandboxPolicy::ReadOnly => SandboxMode::ReadOnly,
+                SandboxPolicy::ReadOnly { .. } => SandboxMode::ReadOnly,
                 SandboxPolicy::WorkspaceWrite { .. } => SandboxMode::WorkspaceWrite,
             },
             network_access: match sandbox_policy {
                 SandboxPolicy::DangerFullAccess => NetworkAccess::Enabled,
-                SandboxPolicy::ReadOnly => NetworkAccess::Restricted,
+                SandboxPolicy::ReadOnly { .. } => NetworkAccess::Restricted,
                 SandboxPolicy::WorkspaceWrite { network_access, .. } => {
                     if network_access {
                         NetworkAccess::Enabled
@@ -55,6 +59,7 @@ impl EnvironmentContext {
                     }
                 }
             },
+            repo_map: None,
         }
     }
 }
@@ -69,6 +74,13 @@ impl Display for EnvironmentContext {
         writeln!(f, "Approval policy: {}", self.approval_policy)?;
         writeln!(f, "Sandbox mode: {}", self.sandbox_mode)?;
         writeln!(f, "Network access: {}", self.network_access)?;
+        if let Some(repo_map) = &self.repo_map {
+            writeln!(
+                f,
+                "Repository map (files whose symbols are used most come first):"
+            )?;
+            write!(f, "{repo_map}")?;
+        }
         Ok(())
     }
 }
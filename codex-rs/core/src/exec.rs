@@ -3,6 +3,7 @@ use std::os::unix::process::ExitStatusExt;
 
 use std::collections::HashMap;
 use std::io;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::ExitStatus;
 use std::time::Duration;
@@ -11,31 +12,45 @@ use std::time::Instant;
 use async_channel::Sender;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
 use tokio::io::BufReader;
 use tokio::process::Child;
 
 use crate::error::CodexErr;
 use crate::error::Result;
 use crate::error::SandboxErr;
+use crate::exec_output::HeadTailBuffer;
 use crate::landlock::spawn_command_under_linux_sandbox;
+use crate::network_proxy::NetworkProxy;
 use crate::protocol::Event;
 use crate::protocol::EventMsg;
 use crate::protocol::ExecCommandOutputDeltaEvent;
 use crate::protocol::ExecOutputStream;
 use crate::protocol::SandboxPolicy;
+use crate::resource_limits::LimitedExit;
+use crate::resource_limits::ResourceLimits;
+use crate::resource_limits::describe_exceeded_limit;
+use crate::resource_limits::exec_cgroup_path;
+use crate::resource_limits::remove_exec_cgroup;
 use crate::seatbelt::spawn_command_under_seatbelt;
 use crate::spawn::StdioPolicy;
 use crate::spawn::spawn_child_async;
 use serde_bytes::ByteBuf;
+use uuid::Uuid;
 
-// Maximum we send for each stream, which is either:
+// Default maximum we send for each stream, which is either:
 // - 10KiB OR
 // - 256 lines
 const MAX_STREAM_OUTPUT: usize = 10 * 1024;
 const MAX_STREAM_OUTPUT_LINES: usize = 256;
+const MAX_SPILL_BYTES: usize = 64 * 1024 * 1024;
 
 const DEFAULT_TIMEOUT_MS: u64 = 10_000;
 
+/// Output folders of sessions that did not shut down cleanly are removed once
+/// they are this old.
+const STALE_OUTPUT_DIR_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 // Hardcode these since it does not seem worth including the libc crate just
 // for these.
 const SIGKILL_CODE: i32 = 9;
@@ -49,6 +64,9 @@ pub struct ExecParams {
     pub env: HashMap<String, String>,
     pub with_escalated_permissions: Option<bool>,
     pub justification: Option<String>,
+    /// Only enforced under the Linux sandbox.
+    pub resource_limits: ResourceLimits,
+    pub output_limits: ExecOutputLimits,
 }
 
 impl ExecParams {
@@ -57,6 +75,56 @@ impl ExecParams {
     }
 }
 
+/// How much of each output stream of a command is kept for the model. Longer
+/// output keeps its first and last lines, and is written to a file in
+/// `spill_dir`, up to `max_spill_bytes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecOutputLimits {
+    pub max_bytes: usize,
+    pub max_lines: usize,
+    /// Without it, the omitted output is not kept anywhere.
+    pub spill_dir: Option<PathBuf>,
+    /// The file in `spill_dir` stops growing at this size, so that a command
+    /// that prints without end cannot fill the disk.
+    pub max_spill_bytes: usize,
+}
+
+impl Default for ExecOutputLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: MAX_STREAM_OUTPUT,
+            max_lines: MAX_STREAM_OUTPUT_LINES,
+            spill_dir: None,
+            max_spill_bytes: MAX_SPILL_BYTES,
+        }
+    }
+}
+
+/// The folder that receives the full output of the truncated commands of a
+/// session. It is removed when the session shuts down.
+pub(crate) fn session_output_dir(codex_home: &Path, session_id: Uuid) -> PathBuf {
+    codex_home.join("output").join(session_id.to_string())
+}
+
+/// Removes the output folders that sessions which did not shut down cleanly
+/// left behind, once they are old enough that the session cannot be running.
+pub(crate) fn prune_stale_output_dirs(codex_home: &Path) {
+    let Ok(entries) = std::fs::read_dir(codex_home.join("output")) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_OUTPUT_DIR_AGE);
+        if stale && let Err(e) = std::fs::remove_dir_all(entry.path()) {
+            tracing::warn!("failed to remove {}: {e}", entry.path().display());
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SandboxType {
     None,
@@ -84,46 +152,53 @@ pub async fn process_exec_tool_call(
 ) -> Result<ExecToolCallOutput> {
     let start = Instant::now();
 
-    let raw_output_result: std::result::Result<RawExecToolCallOutput, CodexErr> = match sandbox_type
-    {
-        SandboxType::None => exec(params, sandbox_policy, stdout_stream.clone()).await,
-        SandboxType::MacosSeatbelt => {
-            let timeout = params.timeout_duration();
-            let ExecParams {
-                command, cwd, env, ..
-            } = params;
-            let child = spawn_command_under_seatbelt(
-                command,
-                sandbox_policy,
-                cwd,
-                StdioPolicy::RedirectForShellTool,
-                env,
-            )
-            .await?;
-            consume_truncated_output(child, timeout, stdout_stream.clone()).await
+    // Commands that may only reach some domains talk to the network through a
+    // proxy that lives as long as the command does.
+    let network_proxy = match sandbox_policy.get_proxied_domains() {
+        Some(allowed_domains) if sandbox_type != SandboxType::None => {
+            Some(NetworkProxy::start(allowed_domains.to_vec(), stdout_stream.clone()).await?)
         }
-        SandboxType::LinuxSeccomp => {
-            let timeout = params.timeout_duration();
-            let ExecParams {
-                command, cwd, env, ..
-            } = params;
-
-            let codex_linux_sandbox_exe = codex_linux_sandbox_exe
-                .as_ref()
-                .ok_or(CodexErr::LandlockSandboxExecutableNotProvided)?;
-            let child = spawn_command_under_linux_sandbox(
-                codex_linux_sandbox_exe,
-                command,
-                sandbox_policy,
-                cwd,
-                StdioPolicy::RedirectForShellTool,
-                env,
-            )
-            .await?;
+        _ => None,
+    };
 
-            consume_truncated_output(child, timeout, stdout_stream).await
+    let timeout = params.timeout_duration();
+    let ExecParams {
+        command,
+        cwd,
+        env,
+        resource_limits,
+        output_limits,
+        ..
+    } = params;
+    let child = spawn_command_for_sandbox_type(
+        command,
+        cwd,
+        env,
+        sandbox_type,
+        sandbox_policy,
+        network_proxy.as_ref(),
+        &resource_limits,
+        codex_linux_sandbox_exe,
+        StdioPolicy::RedirectForShellTool,
+    )
+    .await?;
+    // The Linux sandbox helper puts itself in a cgroup named after its pid to
+    // apply the memory and process caps; it is ours to clean up once the
+    // command exits.
+    let exec_cgroup = match (sandbox_type, child.id()) {
+        (SandboxType::LinuxSeccomp, Some(pid)) if resource_limits.uses_cgroup() => {
+            exec_cgroup_path(pid)
         }
+        _ => None,
     };
+
+    let raw_output_result =
+        consume_truncated_output(child, timeout, output_limits, stdout_stream).await;
+    let memory_cap_exceeded = exec_cgroup.as_deref().is_some_and(remove_exec_cgroup);
+    // Limits the command ran under, and whether the memory cap was hit.
+    let enforced_limits = (sandbox_type == SandboxType::LinuxSeccomp)
+        .then_some((resource_limits, memory_cap_exceeded));
+    drop(network_proxy);
     let duration = start.elapsed();
     match raw_output_result {
         Ok(raw_output) => {
@@ -131,16 +206,42 @@ pub async fn process_exec_tool_call(
             let stderr = raw_output.stderr.from_utf8_lossy();
 
             #[cfg(target_family = "unix")]
-            match raw_output.exit_status.signal() {
-                Some(TIMEOUT_CODE) => return Err(CodexErr::Sandbox(SandboxErr::Timeout)),
-                Some(signal) => {
-                    return Err(CodexErr::Sandbox(SandboxErr::Signal(signal)));
-                }
-                None => {}
+            let signal = raw_output.exit_status.signal();
+            #[cfg(not(target_family = "unix"))]
+            let signal = None;
+
+            if signal == Some(TIMEOUT_CODE) {
+                return Err(CodexErr::Sandbox(SandboxErr::Timeout));
             }
 
             let exit_code = raw_output.exit_status.code().unwrap_or(-1);
 
+            // Report a hit limit as regular output so the model can adjust the
+            // command rather than treat it as a sandbox failure.
+            if let Some((limits, memory_cap_exceeded)) = &enforced_limits
+                && let Some(message) = describe_exceeded_limit(
+                    limits,
+                    &LimitedExit {
+                        signal,
+                        exit_code,
+                        stderr: &stderr.text,
+                        memory_cap_exceeded: *memory_cap_exceeded,
+                    },
+                )
+            {
+                return Ok(ExecToolCallOutput {
+                    exit_code: signal.map_or(exit_code, |signal| 128 + signal),
+                    stdout,
+                    stderr,
+                    duration,
+                    resource_limit_exceeded: Some(message),
+                });
+            }
+
+            if let Some(signal) = signal {
+                return Err(CodexErr::Sandbox(SandboxErr::Signal(signal)));
+            }
+
             if exit_code != 0 && is_likely_sandbox_denied(sandbox_type, exit_code) {
                 return Err(CodexErr::Sandbox(SandboxErr::Denied(
                     exit_code,
@@ -154,6 +255,7 @@ pub async fn process_exec_tool_call(
                 stdout,
                 stderr,
                 duration,
+                resource_limit_exceeded: None,
             })
         }
         Err(err) => {
@@ -186,7 +288,45 @@ fn is_likely_sandbox_denied(sandbox_type: SandboxType, exit_code: i32) -> bool {
 #[derive(Debug)]
 pub struct StreamOutput<T> {
     pub text: T,
-    pub truncated_after_lines: Option<u32>,
+    /// Set when `text` only holds the head and tail of the output.
+    pub truncation: Option<OutputTruncation>,
+}
+
+/// Describes the part of a stream left out between its head and tail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTruncation {
+    pub omitted_bytes: usize,
+    pub omitted_lines: usize,
+    /// Temporary file holding the full output, unless it could not be written.
+    pub full_output_path: Option<PathBuf>,
+    /// Set when the output was longer than the file may hold: the file only
+    /// has its first this many bytes.
+    pub full_output_cut_at: Option<usize>,
+}
+
+impl OutputTruncation {
+    /// The note that replaces the omitted output.
+    pub(crate) fn marker(&self) -> String {
+        let Self {
+            omitted_bytes,
+            omitted_lines,
+            full_output_path,
+            full_output_cut_at,
+        } = self;
+        match (full_output_path, full_output_cut_at) {
+            (Some(path), None) => format!(
+                "\n[... {omitted_lines} lines ({omitted_bytes} bytes) omitted; the full output is in {} ...]\n\n",
+                path.display()
+            ),
+            (Some(path), Some(cut_at)) => format!(
+                "\n[... {omitted_lines} lines ({omitted_bytes} bytes) omitted; the first {cut_at} bytes of the output are in {} ...]\n\n",
+                path.display()
+            ),
+            (None, _) => {
+                format!("\n[... {omitted_lines} lines ({omitted_bytes} bytes) omitted ...]\n\n")
+            }
+        }
+    }
 }
 #[derive(Debug)]
 pub struct RawExecToolCallOutput {
@@ -199,7 +339,7 @@ impl StreamOutput<String> {
     pub fn new(text: String) -> Self {
         Self {
             text,
-            truncated_after_lines: None,
+            truncation: None,
         }
     }
 }
@@ -208,7 +348,7 @@ impl StreamOutput<Vec<u8>> {
     pub fn from_utf8_lossy(&self) -> StreamOutput<String> {
         StreamOutput {
             text: String::from_utf8_lossy(&self.text).to_string(),
-            truncated_after_lines: self.truncated_after_lines,
+            truncation: self.truncation.clone(),
         }
     }
 }
@@ -219,36 +359,74 @@ pub struct ExecToolCallOutput {
     pub stdout: StreamOutput<String>,
     pub stderr: StreamOutput<String>,
     pub duration: Duration,
+    /// Set when the command was stopped by one of its [`ResourceLimits`],
+    /// describing which one for the model.
+    pub resource_limit_exceeded: Option<String>,
 }
 
-async fn exec(
-    params: ExecParams,
+/// Spawns `command` under the sandbox for `sandbox_type`. `network_proxy` must
+/// be running if the policy limits network access to some domains.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn spawn_command_for_sandbox_type(
+    command: Vec<String>,
+    cwd: PathBuf,
+    env: HashMap<String, String>,
+    sandbox_type: SandboxType,
     sandbox_policy: &SandboxPolicy,
-    stdout_stream: Option<StdoutStream>,
-) -> Result<RawExecToolCallOutput> {
-    let timeout = params.timeout_duration();
-    let ExecParams {
-        command, cwd, env, ..
-    } = params;
-
-    let (program, args) = command.split_first().ok_or_else(|| {
-        CodexErr::Io(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "command args are empty",
-        ))
-    })?;
-    let arg0 = None;
-    let child = spawn_child_async(
-        PathBuf::from(program),
-        args.into(),
-        arg0,
-        cwd,
-        sandbox_policy,
-        StdioPolicy::RedirectForShellTool,
-        env,
-    )
-    .await?;
-    consume_truncated_output(child, timeout, stdout_stream).await
+    network_proxy: Option<&NetworkProxy>,
+    resource_limits: &ResourceLimits,
+    codex_linux_sandbox_exe: &Option<PathBuf>,
+    stdio_policy: StdioPolicy,
+) -> Result<Child> {
+    let child = match sandbox_type {
+        SandboxType::None => {
+            let (program, args) = command.split_first().ok_or_else(|| {
+                CodexErr::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "command args are empty",
+                ))
+            })?;
+            let arg0 = None;
+            spawn_child_async(
+                PathBuf::from(program),
+                args.into(),
+                arg0,
+                cwd,
+                sandbox_policy,
+                stdio_policy,
+                env,
+            )
+            .await?
+        }
+        SandboxType::MacosSeatbelt => {
+            spawn_command_under_seatbelt(
+                command,
+                sandbox_policy,
+                network_proxy,
+                cwd,
+                stdio_policy,
+                env,
+            )
+            .await?
+        }
+        SandboxType::LinuxSeccomp => {
+            let codex_linux_sandbox_exe = codex_linux_sandbox_exe
+                .as_ref()
+                .ok_or(CodexErr::LandlockSandboxExecutableNotProvided)?;
+            spawn_command_under_linux_sandbox(
+                codex_linux_sandbox_exe,
+                command,
+                sandbox_policy,
+                network_proxy,
+                resource_limits,
+                cwd,
+                stdio_policy,
+                env,
+            )
+            .await?
+        }
+    };
+    Ok(child)
 }
 
 /// Consumes the output of a child process, truncating it so it is suitable for
@@ -256,6 +434,7 @@ async fn exec(
 pub(crate) async fn consume_truncated_output(
     mut child: Child,
     timeout: Duration,
+    output_limits: ExecOutputLimits,
     stdout_stream: Option<StdoutStream>,
 ) -> Result<RawExecToolCallOutput> {
     // Both stdout and stderr were configured with `Stdio::piped()`
@@ -275,15 +454,13 @@ pub(crate) async fn consume_truncated_output(
 
     let stdout_handle = tokio::spawn(read_capped(
         BufReader::new(stdout_reader),
-        MAX_STREAM_OUTPUT,
-        MAX_STREAM_OUTPUT_LINES,
+        output_limits.clone(),
         stdout_stream.clone(),
         false,
     ));
     let stderr_handle = tokio::spawn(read_capped(
         BufReader::new(stderr_reader),
-        MAX_STREAM_OUTPUT,
-        MAX_STREAM_OUTPUT_LINES,
+        output_limits,
         stdout_stream.clone(),
         true,
     ));
@@ -319,16 +496,20 @@ pub(crate) async fn consume_truncated_output(
 
 async fn read_capped<R: AsyncRead + Unpin + Send + 'static>(
     mut reader: R,
-    max_output: usize,
-    max_lines: usize,
+    limits: ExecOutputLimits,
     stream: Option<StdoutStream>,
     is_stderr: bool,
 ) -> io::Result<StreamOutput<Vec<u8>>> {
-    let mut buf = Vec::with_capacity(max_output.min(8 * 1024));
+    // Cleared when saving the full output fails.
+    let mut spill_dir = limits.spill_dir.clone();
+    let max_spill_bytes = limits.max_spill_bytes;
+    let mut buffer = HeadTailBuffer::new(limits);
     let mut tmp = [0u8; 8192];
 
-    let mut remaining_bytes = max_output;
-    let mut remaining_lines = max_lines;
+    // Everything read so far, until the output outgrows the limits and is
+    // moved to the spill file.
+    let mut unspilled = Vec::new();
+    let mut spill: Option<Spill> = None;
 
     loop {
         let n = reader.read(&mut tmp).await?;
@@ -355,34 +536,127 @@ async fn read_capped<R: AsyncRead + Unpin + Send + 'static>(
             let _ = stream.tx_event.send(event).await;
         }
 
-        // Copy into the buffer only while we still have byte and line budget.
-        if remaining_bytes > 0 && remaining_lines > 0 {
-            let mut copy_len = 0;
-            for &b in &tmp[..n] {
-                if remaining_bytes == 0 || remaining_lines == 0 {
-                    break;
+        // Continue reading to EOF to avoid back-pressure, but only keep what
+        // fits in the buffer.
+        buffer.push(&tmp[..n]);
+        match &mut spill {
+            Some(spill_file) => {
+                if let Err(e) = spill_file.write(&tmp[..n], max_spill_bytes).await {
+                    tracing::warn!(
+                        "failed to write command output to {}: {e}",
+                        spill_file.path.display()
+                    );
+                    spill = None;
+                    spill_dir = None;
                 }
-                copy_len += 1;
-                remaining_bytes -= 1;
-                if b == b'\n' {
-                    remaining_lines -= 1;
+            }
+            None => {
+                if let Some(dir) = &spill_dir {
+                    unspilled.extend_from_slice(&tmp[..n]);
+                    if buffer.is_truncated() {
+                        match Spill::create(
+                            dir.clone(),
+                            std::mem::take(&mut unspilled),
+                            max_spill_bytes,
+                        )
+                        .await
+                        {
+                            Ok(file) => spill = Some(file),
+                            Err(e) => {
+                                tracing::warn!("failed to save the full command output: {e}");
+                                spill_dir = None;
+                            }
+                        }
+                    }
                 }
             }
-            buf.extend_from_slice(&tmp[..copy_len]);
         }
-        // Continue reading to EOF to avoid back-pressure, but discard once caps are hit.
     }
 
-    let truncated = remaining_lines == 0 || remaining_bytes == 0;
+    // `tokio::fs::File` may still be writing when `write_all` returns.
+    if let Some(spill_file) = &mut spill
+        && let Err(e) = spill_file.file.flush().await
+    {
+        tracing::warn!(
+            "failed to write command output to {}: {e}",
+            spill_file.path.display()
+        );
+        spill = None;
+    }
+    let (text, truncation) = match &spill {
+        Some(spill) => buffer.finish(Some(&spill.path), spill.cut_at()),
+        None => buffer.finish(None, None),
+    };
+    Ok(StreamOutput { text, truncation })
+}
 
-    Ok(StreamOutput {
-        text: buf,
-        truncated_after_lines: if truncated {
-            Some((max_lines - remaining_lines) as u32)
-        } else {
-            None
-        },
-    })
+/// A file in the session's output folder that receives the full output of a
+/// stream, so that the model can read the parts that were left out.
+struct Spill {
+    file: tokio::fs::File,
+    path: PathBuf,
+    written: usize,
+    /// Set once the output outgrew `max_spill_bytes`; nothing more is written.
+    full: bool,
+}
+
+impl Spill {
+    /// Writes `output` to a new file in `dir` that is kept until the session
+    /// ends.
+    async fn create(dir: PathBuf, output: Vec<u8>, max_spill_bytes: usize) -> io::Result<Self> {
+        let (file, path) = tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(&dir)?;
+            tempfile::Builder::new()
+                .prefix("codex-output-")
+                .suffix(".txt")
+                .tempfile_in(&dir)?
+                .keep()
+                .map_err(|e| e.error)
+        })
+        .await
+        .map_err(io::Error::other)??;
+        let mut spill = Self {
+            file: tokio::fs::File::from_std(file),
+            path,
+            written: 0,
+            full: false,
+        };
+        spill.write(&output, max_spill_bytes).await?;
+        Ok(spill)
+    }
+
+    async fn write(&mut self, chunk: &[u8], max_spill_bytes: usize) -> io::Result<()> {
+        if self.full {
+            return Ok(());
+        }
+        let len = chunk.len().min(max_spill_bytes - self.written);
+        self.file.write_all(&chunk[..len]).await?;
+        self.written += len;
+        self.full = len < chunk.len();
+        Ok(())
+    }
+
+    /// The size the file was cut at, if the output did not fit.
+    fn cut_at(&self) -> Option<usize> {
+        self.full.then_some(self.written)
+    }
+}
+
+/// The exit code of a finished process, or 128 plus the signal that killed
+/// it.
+#[cfg(unix)]
+pub(crate) fn exit_code_of(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(-1)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn exit_code_of(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(-1)
 }
 
 #[cfg(unix)]
@@ -0,0 +1,233 @@
+//! Keeps the beginning and the end of a command's output within the limits of
+//! what is sent to the model, eliding the middle. The end is where compilers
+//! and test runners print their errors and summaries, so it matters as much
+//! as the beginning.
+
+use std::path::Path;
+
+use crate::exec::ExecOutputLimits;
+use crate::exec::OutputTruncation;
+
+pub(crate) struct HeadTailBuffer {
+    head: Vec<u8>,
+    head_lines: usize,
+    head_max_bytes: usize,
+    head_max_lines: usize,
+    /// Set once the head budget is used up; later output goes to the tail.
+    head_full: bool,
+
+    tail: Vec<u8>,
+    tail_lines: usize,
+    tail_max_bytes: usize,
+    tail_max_lines: usize,
+    /// Whether the tail starts in the middle of a line.
+    tail_starts_mid_line: bool,
+
+    omitted_bytes: usize,
+    omitted_lines: usize,
+}
+
+impl HeadTailBuffer {
+    /// Splits `limits` evenly between the head and the tail.
+    pub(crate) fn new(limits: ExecOutputLimits) -> Self {
+        let head_max_bytes = limits.max_bytes / 2;
+        let head_max_lines = limits.max_lines / 2;
+        Self {
+            head: Vec::new(),
+            head_lines: 0,
+            head_max_bytes,
+            head_max_lines,
+            head_full: head_max_bytes == 0 || head_max_lines == 0,
+            tail: Vec::new(),
+            tail_lines: 0,
+            tail_max_bytes: limits.max_bytes - head_max_bytes,
+            tail_max_lines: limits.max_lines - head_max_lines,
+            tail_starts_mid_line: false,
+            omitted_bytes: 0,
+            omitted_lines: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, mut chunk: &[u8]) {
+        if !self.head_full {
+            let mut copy_len = 0;
+            for &b in chunk {
+                if self.head.len() + copy_len == self.head_max_bytes
+                    || self.head_lines == self.head_max_lines
+                {
+                    break;
+                }
+                copy_len += 1;
+                if b == b'\n' {
+                    self.head_lines += 1;
+                }
+            }
+            self.head.extend_from_slice(&chunk[..copy_len]);
+            chunk = &chunk[copy_len..];
+            self.head_full =
+                self.head.len() == self.head_max_bytes || self.head_lines == self.head_max_lines;
+        }
+
+        self.tail.extend_from_slice(chunk);
+        self.tail_lines += count_lines(chunk);
+        self.trim_tail();
+    }
+
+    /// Whether output has been dropped since the buffer was created.
+    pub(crate) fn is_truncated(&self) -> bool {
+        self.omitted_bytes > 0
+    }
+
+    /// Returns the kept output, with a marker where the middle was elided
+    /// that points to `full_output_path` when there is one, and says where
+    /// that file was cut off if it did not fit the whole output.
+    pub(crate) fn finish(
+        mut self,
+        full_output_path: Option<&Path>,
+        full_output_cut_at: Option<usize>,
+    ) -> (Vec<u8>, Option<OutputTruncation>) {
+        if !self.is_truncated() {
+            self.head.append(&mut self.tail);
+            return (self.head, None);
+        }
+
+        // Show whole lines on both sides of the marker where possible.
+        if let Some(end) = self.head.iter().rposition(|&b| b == b'\n') {
+            self.omitted_bytes += self.head.len() - (end + 1);
+            self.head.truncate(end + 1);
+        } else if !self.head.is_empty() {
+            self.head.push(b'\n');
+        }
+        if self.tail_starts_mid_line
+            && let Some(start) = self.tail.iter().position(|&b| b == b'\n')
+        {
+            self.omitted_bytes += start + 1;
+            self.omitted_lines += 1;
+            self.tail.drain(..=start);
+        }
+
+        let truncation = OutputTruncation {
+            omitted_bytes: self.omitted_bytes,
+            omitted_lines: self.omitted_lines,
+            full_output_path: full_output_path.map(Path::to_path_buf),
+            full_output_cut_at,
+        };
+        let mut text = self.head;
+        text.extend_from_slice(truncation.marker().as_bytes());
+        text.extend_from_slice(&self.tail);
+        (text, Some(truncation))
+    }
+
+    fn trim_tail(&mut self) {
+        let byte_cut = self.tail.len().saturating_sub(self.tail_max_bytes);
+        let line_cut = match self.tail_lines.checked_sub(self.tail_max_lines) {
+            Some(excess) if excess > 0 => self
+                .tail
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .nth(excess - 1)
+                .map_or(0, |(i, _)| i + 1),
+            _ => 0,
+        };
+        let cut = byte_cut.max(line_cut);
+        if cut == 0 {
+            return;
+        }
+
+        let dropped_lines = count_lines(&self.tail[..cut]);
+        self.tail_starts_mid_line = self.tail[cut - 1] != b'\n';
+        self.tail.drain(..cut);
+        self.tail_lines -= dropped_lines;
+        self.omitted_bytes += cut;
+        self.omitted_lines += dropped_lines;
+    }
+}
+
+fn count_lines(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| b == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn numbered_lines(count: usize) -> String {
+        (1..=count).map(|i| format!("{i}\n")).collect()
+    }
+
+    fn capture(
+        output: &str,
+        limits: ExecOutputLimits,
+        chunk_size: usize,
+        full_output_path: Option<&Path>,
+    ) -> (String, Option<OutputTruncation>) {
+        let mut buffer = HeadTailBuffer::new(limits);
+        for chunk in output.as_bytes().chunks(chunk_size) {
+            buffer.push(chunk);
+        }
+        let (text, truncation) = buffer.finish(full_output_path, None);
+        (String::from_utf8_lossy(&text).to_string(), truncation)
+    }
+
+    #[test]
+    fn output_within_limits_is_kept_whole() {
+        let output = numbered_lines(10);
+        let limits = ExecOutputLimits {
+            max_bytes: 1024,
+            max_lines: 10,
+            spill_dir: None,
+            max_spill_bytes: 0,
+        };
+        let (text, truncation) = capture(&output, limits, 3, None);
+        assert_eq!(text, output);
+        assert_eq!(truncation, None);
+    }
+
+    #[test]
+    fn too_many_lines_keeps_head_and_tail() {
+        let limits = ExecOutputLimits {
+            max_bytes: 1024,
+            max_lines: 4,
+            spill_dir: None,
+            max_spill_bytes: 0,
+        };
+        let path = PathBuf::from("/tmp/full.txt");
+        let (text, truncation) = capture(&numbered_lines(10), limits, 4, Some(&path));
+        assert_eq!(
+            text,
+            "1\n2\n\n[... 6 lines (12 bytes) omitted; the full output is in /tmp/full.txt ...]\n\n9\n10\n"
+        );
+        assert_eq!(
+            truncation,
+            Some(OutputTruncation {
+                omitted_bytes: 12,
+                omitted_lines: 6,
+                full_output_path: Some(path),
+                full_output_cut_at: None,
+            })
+        );
+    }
+
+    #[test]
+    fn too_many_bytes_keeps_whole_lines() {
+        let output: String = (1..=10).map(|i| format!("line {i:02}\n")).collect();
+        let limits = ExecOutputLimits {
+            max_bytes: 20,
+            max_lines: 100,
+            spill_dir: None,
+            max_spill_bytes: 0,
+        };
+        let (text, truncation) = capture(&output, limits, 7, None);
+        assert_eq!(
+            text,
+            "line 01\n\n[... 8 lines (64 bytes) omitted ...]\n\nline 10\n"
+        );
+        assert_eq!(
+            truncation.map(|t| (t.omitted_lines, t.omitted_bytes)),
+            Some((8, 64))
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Command policies written in the `codex-execpolicy` language.
+//!
+//! Two optional policy files are consulted before a shell command runs:
+//! `$CODEX_HOME/exec.policy` and `exec.policy` in the project `.codex`
+//! directory found from the session's working directory. Rules can
+//! auto-approve a command (inside or outside the sandbox), require approval,
+//! or forbid it; see [`crate::safety::assess_command_safety`] for how verdicts
+//! are applied. A project policy can only tighten verdicts unless the project
+//! is trusted.
+
+use std::path::Path;
+
+use codex_execpolicy::Decision;
+use codex_execpolicy::ExecCall;
+use codex_execpolicy::MatchedExec;
+use codex_execpolicy::Policy;
+use codex_execpolicy::PolicyParser;
+
+use crate::bash::try_parse_bash;
+use crate::bash::try_parse_word_only_commands_sequence;
+use crate::config::find_project_codex_dir;
+use crate::config::is_project_trusted;
+
+pub const EXEC_POLICY_FILENAME: &str = "exec.policy";
+
+/// What a policy wants done with a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyVerdict {
+    AutoApproveSandboxed,
+    AutoApproveUnsandboxed,
+    Ask,
+    Forbid { reason: String },
+}
+
+impl PolicyVerdict {
+    fn strictness(&self) -> u8 {
+        match self {
+            PolicyVerdict::AutoApproveUnsandboxed => 0,
+            PolicyVerdict::AutoApproveSandboxed => 1,
+            PolicyVerdict::Ask => 2,
+            PolicyVerdict::Forbid { .. } => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyMatch {
+    pub verdict: PolicyVerdict,
+    /// Name of the rule that produced the verdict.
+    pub rule: String,
+}
+
+/// The user and project policies in effect for a session.
+#[derive(Default)]
+pub struct ExecPolicy {
+    policies: Vec<LoadedPolicy>,
+}
+
+struct LoadedPolicy {
+    policy: Policy,
+    /// False for the policy of an untrusted project, whose rules may only
+    /// ask or forbid.
+    may_approve: bool,
+}
+
+impl ExecPolicy {
+    /// Loads `$CODEX_HOME/exec.policy` and the project `.codex/exec.policy`
+    /// found from `cwd`, skipping whichever does not exist.
+    pub fn load(codex_home: &Path, cwd: &Path) -> std::io::Result<Self> {
+        let mut paths = vec![(codex_home.join(EXEC_POLICY_FILENAME), true)];
+        // `$CODEX_HOME` is usually `~/.codex`, which must not double as the
+        // project directory of every folder under `~`.
+        if let Some(project_codex_dir) = find_project_codex_dir(cwd)
+            && codex_home.canonicalize().ok().as_ref() != Some(&project_codex_dir)
+        {
+            let trusted = is_project_trusted(codex_home, cwd);
+            paths.push((project_codex_dir.join(EXEC_POLICY_FILENAME), trusted));
+        }
+
+        let mut policies = Vec::new();
+        for (path, may_approve) in paths {
+            let source = match std::fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let policy = PolicyParser::new(&path.to_string_lossy(), &source)
+                .parse()
+                .map_err(|e| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("failed to parse {}: {e}", path.display()),
+                    )
+                })?;
+            policies.push(LoadedPolicy {
+                policy,
+                may_approve,
+            });
+        }
+        Ok(Self { policies })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Evaluates `command` against every policy. When several rules match,
+    /// the strictest verdict wins. A `bash -lc` script made of plain commands
+    /// is evaluated command by command and is only auto-approved if every
+    /// command is.
+    pub fn evaluate(&self, command: &[String]) -> Option<PolicyMatch> {
+        if self.policies.is_empty() {
+            return None;
+        }
+
+        let commands = match command {
+            [bash, flag, script] if bash == "bash" && flag == "-lc" => try_parse_bash(script)
+                .and_then(|tree| try_parse_word_only_commands_sequence(&tree, script))
+                .unwrap_or_else(|| vec![command.to_vec()]),
+            _ => vec![command.to_vec()],
+        };
+
+        let mut result: Option<PolicyMatch> = None;
+        let mut all_matched = true;
+        for command in &commands {
+            match self.evaluate_single(command) {
+                Some(m) => result = Some(stricter(result, m)),
+                None => all_matched = false,
+            }
+        }
+        match result {
+            Some(PolicyMatch {
+                verdict: PolicyVerdict::AutoApproveSandboxed | PolicyVerdict::AutoApproveUnsandboxed,
+                ..
+            }) if !all_matched => None,
+            result => result,
+        }
+    }
+
+    fn evaluate_single(&self, command: &[String]) -> Option<PolicyMatch> {
+        let (program, args) = command.split_first()?;
+        let exec_call = ExecCall {
+            program: program.clone(),
+            args: args.to_vec(),
+        };
+        let mut result: Option<PolicyMatch> = None;
+        for LoadedPolicy {
+            policy,
+            may_approve,
+        } in &self.policies
+        {
+            let Ok(rule_match) = policy.check_with_rule(&exec_call) else {
+                continue;
+            };
+            let verdict = match (rule_match.matched_exec, rule_match.decision) {
+                (MatchedExec::Forbidden { reason, .. }, _) => PolicyVerdict::Forbid { reason },
+                (MatchedExec::Match { .. }, Some(Decision::Allow)) => {
+                    PolicyVerdict::AutoApproveSandboxed
+                }
+                (MatchedExec::Match { .. }, Some(Decision::AllowUnsandboxed)) => {
+                    PolicyVerdict::AutoApproveUnsandboxed
+                }
+                (MatchedExec::Match { .. }, Some(Decision::Ask)) => PolicyVerdict::Ask,
+                // Without an explicit decision, only read-only matches are
+                // auto-approved; anything else follows the approval policy.
+                (MatchedExec::Match { exec }, None) if !exec.might_write_files() => {
+                    PolicyVerdict::AutoApproveSandboxed
+                }
+                (MatchedExec::Match { .. }, None) => continue,
+            };
+            if !may_approve
+                && matches!(
+                    verdict,
+                    PolicyVerdict::AutoApproveSandboxed | PolicyVerdict::AutoApproveUnsandboxed
+                )
+            {
+                continue;
+            }
+            result = Some(stricter(
+                result,
+                PolicyMatch {
+                    verdict,
+                    rule: rule_match.rule,
+                },
+            ));
+        }
+        result
+    }
+}
+
+fn stricter(current: Option<PolicyMatch>, candidate: PolicyMatch) -> PolicyMatch {
+    match current {
+        Some(current) if current.verdict.strictness() >= candidate.verdict.strictness() => current,
+        _ => candidate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    fn load(user_policy: &str, project_policy: &str, trusted: bool) -> ExecPolicy {
+        let codex_home = TempDir::new().unwrap();
+        std::fs::write(codex_home.path().join(EXEC_POLICY_FILENAME), user_policy).unwrap();
+        let project = TempDir::new().unwrap();
+        let project_codex_dir = project.path().join(".codex");
+        std::fs::create_dir(&project_codex_dir).unwrap();
+        std::fs::write(project_codex_dir.join("config.toml"), "").unwrap();
+        std::fs::write(project_codex_dir.join(EXEC_POLICY_FILENAME), project_policy).unwrap();
+        let nested = project.path().join("src");
+        std::fs::create_dir(&nested).unwrap();
+        if trusted {
+            crate::config::set_project_trusted(codex_home.path(), &nested).unwrap();
+        }
+        ExecPolicy::load(codex_home.path(), &nested).unwrap()
+    }
+
+    fn cmd(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn strictest_verdict_across_policies_wins() {
+        let policy = load(
+            r#"
+define_program(program="cargo", name="user-cargo", args=[ARG_UNVERIFIED_VARARGS], decision="ask")
+"#,
+            r#"
+define_program(program="cargo", name="project-cargo", args=["test"], decision="allow-unsandboxed")
+define_program(program="ls", args=[ARG_RFILES_OR_CWD])
+"#,
+            true,
+        );
+
+        assert_eq!(
+            Some(PolicyMatch {
+                verdict: PolicyVerdict::Ask,
+                rule: "user-cargo".to_string(),
+            }),
+            policy.evaluate(&cmd(&["cargo", "test"]))
+        );
+        assert_eq!(
+            Some(PolicyMatch {
+                verdict: PolicyVerdict::AutoApproveSandboxed,
+                rule: "ls".to_string(),
+            }),
+            policy.evaluate(&cmd(&["ls"]))
+        );
+        assert_eq!(None, policy.evaluate(&cmd(&["rm", "-rf", "/"])));
+    }
+
+    #[test]
+    fn bash_scripts_are_evaluated_per_command() {
+        let policy = load(
+            r#"
+define_program(program="ls", args=[ARG_RFILES_OR_CWD])
+define_program(program="curl", args=[ARG_UNVERIFIED_VARARGS], forbidden="no network access")
+"#,
+            "",
+            false,
+        );
+
+        assert_eq!(
+            Some(PolicyMatch {
+                verdict: PolicyVerdict::AutoApproveSandboxed,
+                rule: "ls".to_string(),
+            }),
+            policy.evaluate(&cmd(&["bash", "-lc", "ls && ls"]))
+        );
+        // Not every command matched, so no auto-approval.
+        assert_eq!(None, policy.evaluate(&cmd(&["bash", "-lc", "ls && make"])));
+        assert_eq!(
+            Some(PolicyMatch {
+                verdict: PolicyVerdict::Forbid {
+                    reason: "no network access".to_string(),
+                },
+                rule: "curl".to_string(),
+            }),
+            policy.evaluate(&cmd(&["bash", "-lc", "ls && curl example.com"]))
+        );
+    }
+
+    #[test]
+    fn untrusted_project_policy_can_only_tighten() {
+        let project_policy = r#"
+define_program(program="cargo", args=["test"], decision="allow-unsandboxed")
+define_program(program="ls", args=[ARG_RFILES_OR_CWD])
+define_program(program="git", args=["push"], forbidden="pushing is done by CI")
+"#;
+        let policy = load("", project_policy, false);
+
+        assert_eq!(None, policy.evaluate(&cmd(&["cargo", "test"])));
+        assert_eq!(None, policy.evaluate(&cmd(&["ls"])));
+        assert_eq!(
+            Some(PolicyMatch {
+                verdict: PolicyVerdict::Forbid {
+                    reason: "pushing is done by CI".to_string(),
+                },
+                rule: "git".to_string(),
+            }),
+            policy.evaluate(&cmd(&["git", "push"]))
+        );
+
+        let policy = load("", project_policy, true);
+        assert_eq!(
+            Some(PolicyMatch {
+                verdict: PolicyVerdict::AutoApproveUnsandboxed,
+                rule: "cargo".to_string(),
+            }),
+            policy.evaluate(&cmd(&["cargo", "test"]))
+        );
+    }
+
+    #[test]
+    fn codex_home_is_not_a_project_directory() {
+        let home = TempDir::new().unwrap();
+        let codex_home = home.path().join(".codex");
+        std::fs::create_dir(&codex_home).unwrap();
+        std::fs::write(codex_home.join("config.toml"), "").unwrap();
+        std::fs::write(
+            codex_home.join(EXEC_POLICY_FILENAME),
+            r#"define_program(program="ls", args=[ARG_RFILES_OR_CWD])"#,
+        )
+        .unwrap();
+        let repo = home.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+
+        let policy = ExecPolicy::load(&codex_home, &repo).unwrap();
+        assert_eq!(1, policy.policies.len());
+    }
+}
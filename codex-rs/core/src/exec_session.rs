@@ -0,0 +1,723 @@
+//! Interactive commands that outlive a single tool call ("exec sessions").
+//!
+//! `exec_session_start` spawns a command in the sandbox a `shell` call would
+//! use, with its stdio attached to a pseudo-terminal, so that REPLs, dev
+//! servers and interactive prompts behave as they would in a terminal. The
+//! model then addresses the session by id to write to the command's stdin,
+//! read what it printed since the previous call, or kill it. Output is also
+//! streamed to clients as `ExecCommandOutputDelta` events for the call that
+//! started the session.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_bytes::ByteBuf;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::error::CodexErr;
+use crate::exec::ExecParams;
+use crate::exec::SandboxType;
+use crate::exec::StdoutStream;
+use crate::exec::exit_code_of;
+use crate::exec::spawn_command_for_sandbox_type;
+use crate::file_tools::function_output;
+use crate::file_tools::parse_error;
+use crate::models::ResponseInputItem;
+use crate::network_proxy::NetworkProxy;
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::create_function_tool;
+use crate::protocol::Event;
+use crate::protocol::EventMsg;
+use crate::protocol::ExecCommandOutputDeltaEvent;
+use crate::protocol::ExecOutputStream;
+use crate::protocol::SandboxPolicy;
+use crate::resource_limits::exec_cgroup_path;
+use crate::resource_limits::remove_exec_cgroup;
+
+/// How long `exec_session_start` waits for output by default, so that quick
+/// commands finish within a single call.
+pub(crate) const DEFAULT_START_YIELD_TIME_MS: u64 = 10_000;
+
+/// How long the other session tools wait for output by default.
+const DEFAULT_YIELD_TIME_MS: u64 = 250;
+
+/// Most output returned by a single call; older unread output is elided.
+const MAX_OUTPUT_BYTES: usize = 10 * 1024;
+
+/// Most unread output kept per session.
+const MAX_BUFFERED_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Size of the pseudo-terminal. Wide, so that fewer lines get wrapped.
+const PTY_ROWS: u16 = 40;
+const PTY_COLS: u16 = 200;
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ExecSessionStartParams {
+    pub(crate) command: Vec<String>,
+    #[serde(default)]
+    pub(crate) workdir: Option<String>,
+    #[serde(default)]
+    pub(crate) yield_time_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ExecSessionWriteParams {
+    session_id: u32,
+    input: String,
+    #[serde(default)]
+    yield_time_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ExecSessionReadParams {
+    session_id: u32,
+    #[serde(default)]
+    yield_time_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ExecSessionKillParams {
+    session_id: u32,
+}
+
+fn yield_time_ms_schema(default_ms: u64) -> JsonSchema {
+    JsonSchema::Number {
+        description: Some(format!(
+            "How long to wait for output before returning, in milliseconds. Defaults to {default_ms}. Returns early if the command exits."
+        )),
+    }
+}
+
+fn session_id_schema() -> JsonSchema {
+    JsonSchema::Number {
+        description: Some("The id returned by exec_session_start.".to_string()),
+    }
+}
+
+pub(crate) static EXEC_SESSION_TOOLS: LazyLock<Vec<OpenAiTool>> = LazyLock::new(|| {
+    let mut start_properties = BTreeMap::new();
+    start_properties.insert(
+        "command".to_string(),
+        JsonSchema::Array {
+            items: Box::new(JsonSchema::String { description: None }),
+            description: Some("The command to execute".to_string()),
+        },
+    );
+    start_properties.insert(
+        "workdir".to_string(),
+        JsonSchema::String {
+            description: Some("The working directory to execute the command in".to_string()),
+        },
+    );
+    start_properties.insert(
+        "yield_time_ms".to_string(),
+        yield_time_ms_schema(DEFAULT_START_YIELD_TIME_MS),
+    );
+
+    let mut write_properties = BTreeMap::new();
+    write_properties.insert("session_id".to_string(), session_id_schema());
+    write_properties.insert(
+        "input".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Text to type into the terminal. Include \"\\n\" to submit a line; \"\\u0003\" sends Ctrl-C."
+                    .to_string(),
+            ),
+        },
+    );
+    write_properties.insert(
+        "yield_time_ms".to_string(),
+        yield_time_ms_schema(DEFAULT_YIELD_TIME_MS),
+    );
+
+    let mut read_properties = BTreeMap::new();
+    read_properties.insert("session_id".to_string(), session_id_schema());
+    read_properties.insert(
+        "yield_time_ms".to_string(),
+        yield_time_ms_schema(DEFAULT_YIELD_TIME_MS),
+    );
+
+    let mut kill_properties = BTreeMap::new();
+    kill_properties.insert("session_id".to_string(), session_id_schema());
+
+    vec![
+        create_function_tool(
+            "exec_session_start",
+            "Starts a command in a pseudo-terminal and keeps it running across tool calls. Use it for REPLs, dev servers, watchers and commands that prompt for input; use `shell` for everything else. Returns a session_id and the output printed so far.",
+            start_properties,
+            &["command"],
+        ),
+        create_function_tool(
+            "exec_session_write",
+            "Writes to the stdin of a running exec session, then returns the output printed since the previous call.",
+            write_properties,
+            &["session_id", "input"],
+        ),
+        create_function_tool(
+            "exec_session_read",
+            "Returns the output an exec session printed since the previous call, and whether its command is still running.",
+            read_properties,
+            &["session_id"],
+        ),
+        create_function_tool(
+            "exec_session_kill",
+            "Kills the command of an exec session along with everything it started, and returns its remaining output.",
+            kill_properties,
+            &["session_id"],
+        ),
+    ]
+});
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ExecSessionError {
+    #[error("no exec session with id {0}; it may have exited and been read to the end")]
+    UnknownSession(u32),
+
+    #[error("failed to start exec session: {0}")]
+    Start(#[from] CodexErr),
+
+    #[error("failed to write to exec session: {0}")]
+    Write(io::Error),
+}
+
+/// Output of a session since the previous call, as returned to the model.
+#[derive(Debug)]
+pub(crate) struct ExecSessionOutput {
+    pub(crate) session_id: u32,
+    pub(crate) output: String,
+    /// `None` while the command is still running.
+    pub(crate) exit_code: Option<i32>,
+}
+
+impl ExecSessionOutput {
+    pub(crate) fn format_for_model(&self) -> String {
+        #[derive(Serialize)]
+        struct Metadata {
+            session_id: u32,
+            running: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            exit_code: Option<i32>,
+        }
+
+        #[derive(Serialize)]
+        struct Payload<'a> {
+            output: &'a str,
+            metadata: Metadata,
+        }
+
+        let payload = Payload {
+            output: &self.output,
+            metadata: Metadata {
+                session_id: self.session_id,
+                running: self.exit_code.is_none(),
+                exit_code: self.exit_code,
+            },
+        };
+
+        #[expect(clippy::expect_used)]
+        serde_json::to_string(&payload).expect("serialize ExecSessionOutput")
+    }
+}
+
+#[derive(Default)]
+struct SessionState {
+    unread: Vec<u8>,
+    /// Unread bytes dropped because the model did not keep up.
+    elided_bytes: usize,
+    /// Set once nothing holds the terminal open anymore.
+    output_closed: bool,
+    exit_code: Option<i32>,
+}
+
+#[derive(Default)]
+struct SharedState {
+    state: Mutex<SessionState>,
+    changed: Notify,
+}
+
+impl SharedState {
+    fn update(&self, f: impl FnOnce(&mut SessionState)) {
+        if let Ok(mut state) = self.state.lock() {
+            f(&mut state);
+        }
+        self.changed.notify_one();
+    }
+}
+
+struct ExecSession {
+    /// Leader of the process group the command runs in.
+    pid: Option<u32>,
+    stdin: std::fs::File,
+    shared: Arc<SharedState>,
+    /// Kept alive for the commands's network access.
+    _network_proxy: Option<NetworkProxy>,
+}
+
+impl ExecSession {
+    fn kill(&self) {
+        if let Some(pid) = self.pid {
+            // The command is a session leader, so this also reaches whatever
+            // it started, including what outlived it: the group id is not
+            // handed out again while the group has members.
+            unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+        }
+    }
+
+    /// Waits up to `yield_time` for the command to exit, then returns the
+    /// output it printed since the previous call.
+    async fn collect_output(&self, session_id: u32, yield_time: Duration) -> ExecSessionOutput {
+        let deadline = Instant::now() + yield_time;
+        loop {
+            let changed = self.shared.changed.notified();
+            let finished = self
+                .shared
+                .state
+                .lock()
+                .is_ok_and(|state| state.exit_code.is_some() && state.output_closed);
+            if finished || tokio::time::timeout_at(deadline, changed).await.is_err() {
+                break;
+            }
+        }
+
+        let mut output = String::new();
+        let mut exit_code = None;
+        if let Ok(mut state) = self.shared.state.lock() {
+            let unread = std::mem::take(&mut state.unread);
+            let start = unread.len().saturating_sub(MAX_OUTPUT_BYTES);
+            let elided_bytes = std::mem::take(&mut state.elided_bytes) + start;
+            if elided_bytes > 0 {
+                output.push_str(&format!("[{elided_bytes} earlier bytes omitted]\n"));
+            }
+            output.push_str(&String::from_utf8_lossy(&unread[start..]));
+            exit_code = state.exit_code;
+        }
+        ExecSessionOutput {
+            session_id,
+            output,
+            exit_code,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.shared.state.lock().is_ok_and(|state| {
+            state.exit_code.is_some() && state.output_closed && state.unread.is_empty()
+        })
+    }
+}
+
+impl Drop for ExecSession {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// The exec sessions of a Codex session, addressed by id.
+#[derive(Default)]
+pub(crate) struct ExecSessionManager {
+    next_session_id: AtomicU32,
+    sessions: Mutex<HashMap<u32, Arc<ExecSession>>>,
+}
+
+impl ExecSessionManager {
+    /// Starts `params.command` in a pseudo-terminal and returns its output
+    /// after up to `yield_time`. `events` receives the output as it arrives.
+    pub(crate) async fn start(
+        &self,
+        mut params: ExecParams,
+        sandbox_type: SandboxType,
+        sandbox_policy: &SandboxPolicy,
+        codex_linux_sandbox_exe: &Option<PathBuf>,
+        events: StdoutStream,
+        yield_time: Duration,
+    ) -> Result<ExecSessionOutput, ExecSessionError> {
+        let network_proxy = match sandbox_policy.get_proxied_domains() {
+            Some(allowed_domains) if sandbox_type != SandboxType::None => Some(
+                NetworkProxy::start(allowed_domains.to_vec(), Some(events.clone()))
+                    .await
+                    .map_err(CodexErr::Io)?,
+            ),
+            _ => None,
+        };
+
+        let (master, slave) = open_pty().map_err(CodexErr::Io)?;
+        // The output is read by the model rather than rendered, so ask for
+        // plain text.
+        params.env.insert("TERM".to_string(), "dumb".to_string());
+        let child = spawn_command_for_sandbox_type(
+            params.command,
+            params.cwd,
+            params.env,
+            sandbox_type,
+            sandbox_policy,
+            network_proxy.as_ref(),
+            &params.resource_limits,
+            codex_linux_sandbox_exe,
+            spawn_stdio_policy(&slave),
+        )
+        .await?;
+        // Only the command may keep the terminal open, so that reads see EOF
+        // once it and everything it started are gone.
+        drop(slave);
+
+        let pid = child.id();
+        let exec_cgroup = match (sandbox_type, pid) {
+            (SandboxType::LinuxSeccomp, Some(pid)) if params.resource_limits.uses_cgroup() => {
+                exec_cgroup_path(pid)
+            }
+            _ => None,
+        };
+        let stdin = std::fs::File::from(master.try_clone().map_err(CodexErr::Io)?);
+        let shared = Arc::new(SharedState::default());
+        spawn_output_reader(std::fs::File::from(master), shared.clone(), events);
+        let wait_shared = shared.clone();
+        tokio::spawn(async move {
+            let mut child = child;
+            let exit_code = match child.wait().await {
+                Ok(status) => exit_code_of(status),
+                Err(_) => -1,
+            };
+            if let Some(exec_cgroup) = exec_cgroup {
+                remove_exec_cgroup(&exec_cgroup);
+            }
+            wait_shared.update(|state| state.exit_code = Some(exit_code));
+        });
+
+        let session_id = self.next_session_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let session = Arc::new(ExecSession {
+            pid,
+            stdin,
+            shared,
+            _network_proxy: network_proxy,
+        });
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.insert(session_id, session.clone());
+        }
+        let output = session.collect_output(session_id, yield_time).await;
+        self.forget_if_finished(session_id, &session);
+        Ok(output)
+    }
+
+    /// Writes `input` to the session's terminal, if any, and returns its output
+    /// after up to `yield_time`.
+    async fn write_and_read(
+        &self,
+        session_id: u32,
+        input: Option<String>,
+        yield_time: Duration,
+    ) -> Result<ExecSessionOutput, ExecSessionError> {
+        let session = self.get(session_id)?;
+        if let Some(input) = input {
+            let writer = session.clone();
+            tokio::task::spawn_blocking(move || {
+                use std::io::Write;
+                (&writer.stdin).write_all(input.as_bytes())
+            })
+            .await
+            .map_err(|e| ExecSessionError::Write(io::Error::other(e)))?
+            .map_err(ExecSessionError::Write)?;
+        }
+        let output = session.collect_output(session_id, yield_time).await;
+        self.forget_if_finished(session_id, &session);
+        Ok(output)
+    }
+
+    async fn kill(&self, session_id: u32) -> Result<ExecSessionOutput, ExecSessionError> {
+        let session = self.get(session_id)?;
+        session.kill();
+        let output = session
+            .collect_output(session_id, Duration::from_millis(DEFAULT_YIELD_TIME_MS))
+            .await;
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(&session_id);
+        }
+        Ok(output)
+    }
+
+    /// Kills every session, e.g. when Codex shuts down.
+    pub(crate) fn kill_all(&self) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            for session in sessions.values() {
+                session.kill();
+            }
+            sessions.clear();
+        }
+    }
+
+    fn get(&self, session_id: u32) -> Result<Arc<ExecSession>, ExecSessionError> {
+        self.sessions
+            .lock()
+            .ok()
+            .and_then(|sessions| sessions.get(&session_id).cloned())
+            .ok_or(ExecSessionError::UnknownSession(session_id))
+    }
+
+    fn forget_if_finished(&self, session_id: u32, session: &ExecSession) {
+        if session.is_finished()
+            && let Ok(mut sessions) = self.sessions.lock()
+        {
+            sessions.remove(&session_id);
+        }
+    }
+}
+
+/// Handles the session tools other than `exec_session_start`, which needs
+/// the approval flow in `codex.rs`.
+pub(crate) async fn handle_exec_session_call(
+    manager: &ExecSessionManager,
+    name: &str,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let result = match name {
+        "exec_session_write" => match serde_json::from_str::<ExecSessionWriteParams>(&arguments) {
+            Ok(params) => Ok(manager
+                .write_and_read(
+                    params.session_id,
+                    Some(params.input),
+                    yield_time(params.yield_time_ms, DEFAULT_YIELD_TIME_MS),
+                )
+                .await),
+            Err(e) => Err(e),
+        },
+        "exec_session_read" => match serde_json::from_str::<ExecSessionReadParams>(&arguments) {
+            Ok(params) => Ok(manager
+                .write_and_read(
+                    params.session_id,
+                    None,
+                    yield_time(params.yield_time_ms, DEFAULT_YIELD_TIME_MS),
+                )
+                .await),
+            Err(e) => Err(e),
+        },
+        _ => match serde_json::from_str::<ExecSessionKillParams>(&arguments) {
+            Ok(params) => Ok(manager.kill(params.session_id).await),
+            Err(e) => Err(e),
+        },
+    };
+
+    match result {
+        Ok(result) => session_call_output(call_id, result),
+        Err(e) => parse_error(call_id, e),
+    }
+}
+
+pub(crate) fn session_call_output(
+    call_id: String,
+    result: Result<ExecSessionOutput, ExecSessionError>,
+) -> ResponseInputItem {
+    function_output(
+        call_id,
+        result
+            .map(|output| output.format_for_model())
+            .map_err(|e| e.to_string()),
+    )
+}
+
+pub(crate) fn yield_time(yield_time_ms: Option<u64>, default_ms: u64) -> Duration {
+    Duration::from_millis(yield_time_ms.unwrap_or(default_ms))
+}
+
+/// Forwards the terminal's output to `shared` and to `events` until nothing
+/// holds the terminal open anymore.
+fn spawn_output_reader(mut master: std::fs::File, shared: Arc<SharedState>, events: StdoutStream) {
+    std::thread::spawn(move || {
+        use std::io::Read;
+
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = match master.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                // Linux reports EIO once the last process holding the
+                // terminal exits.
+                Err(_) => break,
+            };
+            let chunk = &buf[..n];
+            shared.update(|state| {
+                state.unread.extend_from_slice(chunk);
+                let excess = state.unread.len().saturating_sub(MAX_BUFFERED_OUTPUT_BYTES);
+                if excess > 0 {
+                    state.unread.drain(..excess);
+                    state.elided_bytes += excess;
+                }
+            });
+            let _ = events.tx_event.try_send(Event {
+                id: events.sub_id.clone(),
+                msg: EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent {
+                    call_id: events.call_id.clone(),
+                    stream: ExecOutputStream::Stdout,
+                    chunk: ByteBuf::from(chunk.to_vec()),
+                }),
+            });
+        }
+        shared.update(|state| state.output_closed = true);
+    });
+}
+
+#[cfg(unix)]
+fn open_pty() -> io::Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd)> {
+    use std::os::fd::AsRawFd;
+    use std::os::fd::FromRawFd;
+    use std::os::fd::OwnedFd;
+
+    let mut master = -1;
+    let mut slave = -1;
+    let mut size = libc::winsize {
+        ws_row: PTY_ROWS,
+        ws_col: PTY_COLS,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    if unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            // A const pointer on Linux but a mutable one on macOS.
+            &raw mut size,
+        )
+    } != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    // Keep other commands Codex spawns from inheriting the terminal.
+    for fd in [&master, &slave] {
+        if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok((master, slave))
+}
+
+#[cfg(not(unix))]
+fn open_pty() -> io::Result<(std::fs::File, std::fs::File)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "exec sessions require a Unix pseudo-terminal",
+    ))
+}
+
+#[cfg(unix)]
+fn spawn_stdio_policy(slave: &std::os::fd::OwnedFd) -> crate::spawn::StdioPolicy {
+    use std::os::fd::AsRawFd;
+
+    crate::spawn::StdioPolicy::Pty(slave.as_raw_fd())
+}
+
+#[cfg(not(unix))]
+fn spawn_stdio_policy(_slave: &std::fs::File) -> crate::spawn::StdioPolicy {
+    crate::spawn::StdioPolicy::RedirectForShellTool
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[expect(clippy::unwrap_used)]
+    async fn start(
+        manager: &ExecSessionManager,
+        command: &[&str],
+        yield_time_ms: u64,
+    ) -> (ExecSessionOutput, async_channel::Receiver<Event>) {
+        let (tx_event, rx_event) = async_channel::unbounded();
+        let params = ExecParams {
+            command: command.iter().map(|s| s.to_string()).collect(),
+            cwd: std::env::temp_dir(),
+            timeout_ms: None,
+            env: HashMap::from([("PATH".to_string(), "/usr/bin:/bin".to_string())]),
+            with_escalated_permissions: None,
+            justification: None,
+            resource_limits: Default::default(),
+            output_limits: Default::default(),
+        };
+        let output = manager
+            .start(
+                params,
+                SandboxType::None,
+                &SandboxPolicy::DangerFullAccess,
+                &None,
+                StdoutStream {
+                    sub_id: "sub".to_string(),
+                    call_id: "call".to_string(),
+                    tx_event,
+                },
+                Duration::from_millis(yield_time_ms),
+            )
+            .await
+            .unwrap();
+        (output, rx_event)
+    }
+
+    #[tokio::test]
+    async fn short_command_finishes_within_start_and_streams_output() {
+        let manager = ExecSessionManager::default();
+        let (output, rx_event) = start(
+            &manager,
+            &["sh", "-c", "test -t 0 && test -t 1 && echo on a tty"],
+            5_000,
+        )
+        .await;
+
+        assert_eq!(output.exit_code, Some(0));
+        assert_eq!(output.output.trim_end(), "on a tty");
+        // Fully read sessions are forgotten.
+        assert!(matches!(
+            manager
+                .write_and_read(output.session_id, None, Duration::ZERO)
+                .await,
+            Err(ExecSessionError::UnknownSession(_))
+        ));
+
+        let mut streamed = Vec::new();
+        while let Ok(event) = rx_event.try_recv() {
+            if let EventMsg::ExecCommandOutputDelta(delta) = event.msg {
+                assert_eq!(delta.call_id, "call");
+                streamed.extend_from_slice(&delta.chunk);
+            }
+        }
+        assert_eq!(String::from_utf8_lossy(&streamed).trim_end(), "on a tty");
+    }
+
+    #[tokio::test]
+    async fn interactive_command_reads_stdin_until_killed() {
+        let manager = ExecSessionManager::default();
+        let (output, _rx_event) = start(&manager, &["cat"], 100).await;
+        assert_eq!(output.exit_code, None);
+        let session_id = output.session_id;
+
+        let output = manager
+            .write_and_read(
+                session_id,
+                Some("hello\n".to_string()),
+                Duration::from_millis(500),
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.exit_code, None);
+        // The terminal echoes the input, then `cat` prints it again.
+        assert_eq!(output.output, "hello\r\nhello\r\n");
+
+        let output = manager.kill(session_id).await.unwrap();
+        assert_eq!(output.exit_code, Some(128 + libc::SIGKILL));
+        assert!(matches!(
+            manager.kill(session_id).await,
+            Err(ExecSessionError::UnknownSession(_))
+        ));
+    }
+}
@@ -0,0 +1,718 @@
+//! The `read_file`, `list_dir` and `edit_file` tools: structured alternatives
+//! to `cat`, `ls` and `apply_patch` for models that are unreliable with patch
+//! grammars or shell quoting.
+//!
+//! Reads and listings never ask for approval, so they are limited by a
+//! [`ReadScope`] to what a sandboxed command could read. Edits are made by Codex itself
+//! rather than in the sandbox, so `codex.rs` checks them against the writable
+//! roots with [`crate::safety::assess_direct_write_safety`] and reports them
+//! with the same `PatchApplyBegin`/`PatchApplyEnd` events as `apply_patch`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use codex_apply_patch::ApplyPatchAction;
+use ignore::WalkBuilder;
+use serde::Deserialize;
+
+use crate::landlock::default_read_denied_paths;
+use crate::models::FunctionCallOutputPayload;
+use crate::models::ResponseInputItem;
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+use crate::protocol::SandboxPolicy;
+
+/// Lines `read_file` returns when the model does not ask for a number.
+const DEFAULT_READ_LIMIT: usize = 2000;
+
+/// Longer lines are cut off so that minified files do not flood the context.
+const MAX_LINE_CHARS: usize = 2000;
+
+/// A NUL byte in this many leading bytes marks a file as binary, as in
+/// `codex_file_search`.
+const BINARY_DETECTION_BYTES: usize = 8 * 1024;
+
+const DEFAULT_LIST_DEPTH: usize = 1;
+
+const MAX_LIST_DEPTH: usize = 5;
+
+/// Most entries a single `list_dir` call prints.
+const MAX_LIST_ENTRIES: usize = 1000;
+
+#[derive(Deserialize, Debug, Clone)]
+struct ReadFileParams {
+    path: String,
+    #[serde(default)]
+    offset: Option<usize>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ListDirParams {
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    depth: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct EditFileParams {
+    path: String,
+    old_string: String,
+    new_string: String,
+    #[serde(default)]
+    replace_all: bool,
+}
+
+pub(crate) static FILE_TOOLS: LazyLock<Vec<OpenAiTool>> =
+    LazyLock::new(|| vec![read_file_tool(), list_dir_tool(), edit_file_tool()]);
+
+fn read_file_tool() -> OpenAiTool {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "path".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "File to read, absolute or relative to the working directory.".to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "offset".to_string(),
+        JsonSchema::Number {
+            description: Some("1-based line to start reading at. Defaults to 1.".to_string()),
+        },
+    );
+    properties.insert(
+        "limit".to_string(),
+        JsonSchema::Number {
+            description: Some(format!(
+                "Most lines to return. Defaults to {DEFAULT_READ_LIMIT}."
+            )),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "read_file".to_string(),
+        description: "Reads a text file. Each line is prefixed with its 1-based line number and a tab, which are not part of the file. Use offset and limit to read a range of lines from a large file. This tool only reads files and never needs approval; prefer it to running cat, head or sed through the shell.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(vec!["path".to_string()]),
+            additional_properties: Some(false),
+        },
+    })
+}
+
+fn list_dir_tool() -> OpenAiTool {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "path".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Directory to list, relative to the working directory. Defaults to the working directory."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "depth".to_string(),
+        JsonSchema::Number {
+            description: Some(format!(
+                "How many levels of subdirectories to descend into, at most {MAX_LIST_DEPTH}. Defaults to {DEFAULT_LIST_DEPTH}."
+            )),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "list_dir".to_string(),
+        description: "Lists the entries of a directory as an indented tree, with a trailing / on directories. Entries ignored by .gitignore are skipped. This tool only reads the file system and never needs approval.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: None,
+            additional_properties: Some(false),
+        },
+    })
+}
+
+fn edit_file_tool() -> OpenAiTool {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "path".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "File to edit, absolute or relative to the working directory.".to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "old_string".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Exact text to replace, including whitespace and indentation, without line number prefixes. Use an empty string to create a new file."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "new_string".to_string(),
+        JsonSchema::String {
+            description: Some("Text to replace old_string with.".to_string()),
+        },
+    );
+    properties.insert(
+        "replace_all".to_string(),
+        JsonSchema::Boolean {
+            description: Some(
+                "Replace every occurrence of old_string. By default old_string must occur exactly once."
+                    .to_string(),
+            ),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "edit_file".to_string(),
+        description: "Edits a file by replacing an exact string with another. Unless replace_all is set, old_string must occur exactly once in the file, so include enough surrounding lines to make it unique. To create a file, pass an empty old_string and the whole contents as new_string. Edits outside the writable roots need the user's approval.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(vec![
+                "path".to_string(),
+                "old_string".to_string(),
+                "new_string".to_string(),
+            ]),
+            additional_properties: Some(false),
+        },
+    })
+}
+
+/// The files that tools which read without asking for approval may read:
+/// whatever the sandbox lets commands read, plus the folder this session
+/// spills long command output to.
+#[derive(Debug, Clone)]
+pub(crate) struct ReadScope {
+    pub(crate) cwd: PathBuf,
+    pub(crate) sandbox_policy: SandboxPolicy,
+    pub(crate) output_dir: PathBuf,
+}
+
+impl ReadScope {
+    /// Resolves `path` against the working directory, following symlinks,
+    /// and checks that it may be read. Unless the sandbox is off, the
+    /// credential stores in [`default_read_denied_paths`] are refused and,
+    /// when the policy limits reads to its `readable_roots`, so is anything
+    /// outside them and the writable roots.
+    pub(crate) fn resolve(&self, path: &Path) -> Result<PathBuf, String> {
+        let path = resolve_symlinks(&self.cwd.join(path));
+        if matches!(self.sandbox_policy, SandboxPolicy::DangerFullAccess) {
+            return Ok(path);
+        }
+        if default_read_denied_paths()
+            .iter()
+            .any(|denied| path.starts_with(resolve_symlinks(denied)))
+        {
+            return Err(format!(
+                "{} holds credentials that the sandbox does not let Codex read",
+                path.display()
+            ));
+        }
+        if let Some(readable_roots) = self.sandbox_policy.get_readable_roots_with_cwd(&self.cwd) {
+            let readable = readable_roots
+                .into_iter()
+                .chain(
+                    self.sandbox_policy
+                        .get_writable_roots_with_cwd(&self.cwd)
+                        .into_iter()
+                        .map(|writable_root| writable_root.root),
+                )
+                .chain(std::iter::once(self.output_dir.clone()))
+                .any(|root| path.starts_with(resolve_symlinks(&root)));
+            if !readable {
+                return Err(format!(
+                    "{} is outside the folders the sandbox can read",
+                    path.display()
+                ));
+            }
+        }
+        Ok(path)
+    }
+}
+
+pub(crate) async fn handle_read_file(
+    scope: &ReadScope,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<ReadFileParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => return parse_error(call_id, e),
+    };
+    let path = match scope.resolve(Path::new(&params.path)) {
+        Ok(path) => path,
+        Err(message) => return function_output(call_id, Err(message)),
+    };
+    let result = match tokio::fs::read(&path).await {
+        Ok(bytes) => format_file_lines(
+            &path,
+            &bytes,
+            params.offset.unwrap_or(1),
+            params.limit.unwrap_or(DEFAULT_READ_LIMIT),
+        ),
+        Err(e) if path.is_dir() => Err(format!(
+            "{} is a directory ({e}); use list_dir instead",
+            path.display()
+        )),
+        Err(e) => Err(format!("failed to read {}: {e}", path.display())),
+    };
+    function_output(call_id, result)
+}
+
+/// Numbers the lines `offset..offset + limit` of a file the way `cat -n`
+/// does, with a note on how to read more when the range stops short of the
+/// end of the file.
+fn format_file_lines(
+    path: &Path,
+    bytes: &[u8],
+    offset: usize,
+    limit: usize,
+) -> Result<String, String> {
+    if bytes[..bytes.len().min(BINARY_DETECTION_BYTES)].contains(&0) {
+        return Err(format!("{} is a binary file", path.display()));
+    }
+    let text = String::from_utf8_lossy(bytes);
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return Ok("(empty file)".to_string());
+    }
+    let start = offset.max(1);
+    if start > lines.len() {
+        return Err(format!(
+            "offset {start} is past the end of {}, which has {} lines",
+            path.display(),
+            lines.len()
+        ));
+    }
+    let end = (start - 1 + limit.max(1)).min(lines.len());
+
+    let mut out = String::new();
+    for (index, line) in lines[start - 1..end].iter().enumerate() {
+        let line = match line.char_indices().nth(MAX_LINE_CHARS) {
+            Some((cut, _)) => format!("{}…", &line[..cut]),
+            None => line.to_string(),
+        };
+        out.push_str(&format!("{:>6}\t{line}\n", start + index));
+    }
+    if end < lines.len() {
+        out.push_str(&format!(
+            "\n(showing lines {start}-{end} of {}; use offset={} to read more)\n",
+            lines.len(),
+            end + 1
+        ));
+    }
+    Ok(out)
+}
+
+pub(crate) async fn handle_list_dir(
+    scope: &ReadScope,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<ListDirParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => return parse_error(call_id, e),
+    };
+    let dir = match scope.resolve(Path::new(params.path.as_deref().unwrap_or("."))) {
+        Ok(dir) => dir,
+        Err(message) => return function_output(call_id, Err(message)),
+    };
+    let depth = params
+        .depth
+        .unwrap_or(DEFAULT_LIST_DEPTH)
+        .clamp(1, MAX_LIST_DEPTH);
+
+    let result = if dir.is_dir() {
+        tokio::task::spawn_blocking(move || list_dir(&dir, depth))
+            .await
+            .map_err(|e| format!("failed to list directory: {e}"))
+    } else {
+        Err(format!("{} is not a directory", dir.display()))
+    };
+    function_output(call_id, result)
+}
+
+fn list_dir(dir: &Path, depth: usize) -> String {
+    let walker = WalkBuilder::new(dir)
+        .hidden(false)
+        .max_depth(Some(depth))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+
+    let mut out = String::new();
+    let mut count = 0;
+    for entry in walker.flatten() {
+        if entry.depth() == 0 {
+            continue;
+        }
+        count += 1;
+        if count > MAX_LIST_ENTRIES {
+            continue;
+        }
+        let suffix = if entry.file_type().is_some_and(|ft| ft.is_dir()) {
+            "/"
+        } else {
+            ""
+        };
+        out.push_str(&format!(
+            "{}{}{suffix}\n",
+            "  ".repeat(entry.depth() - 1),
+            entry.file_name().to_string_lossy()
+        ));
+    }
+
+    if count == 0 {
+        return "(empty directory)".to_string();
+    }
+    if count > MAX_LIST_ENTRIES {
+        out.push_str(&format!(
+            "\n(showing the first {MAX_LIST_ENTRIES} of {count} entries; list a subdirectory or lower the depth)\n"
+        ));
+    }
+    out
+}
+
+/// An edit that has been checked against the current contents of the file
+/// but not yet written.
+pub(crate) struct FileEdit {
+    pub(crate) path: PathBuf,
+    new_content: String,
+    created: bool,
+    /// What the edit does, for the tool output.
+    summary: String,
+    /// The edit as an `apply_patch` action, for approval and patch events.
+    pub(crate) action: ApplyPatchAction,
+}
+
+impl FileEdit {
+    /// An edit that replaces the contents of the existing file at `path`.
+    pub(crate) fn update(
+        cwd: &Path,
+        path: PathBuf,
+        old_content: &str,
+        new_content: String,
+        summary: String,
+    ) -> Self {
+        let action = ApplyPatchAction::new_write(
+            path.clone(),
+            Some(old_content),
+            new_content.clone(),
+            cwd.into(),
+        );
+        Self {
+            path,
+            new_content,
+            created: false,
+            summary,
+            action,
+        }
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        self.summary.clone()
+    }
+
+    pub(crate) fn write(&self) -> std::io::Result<()> {
+        if self.created
+            && let Some(parent) = self.path.parent()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, &self.new_content)
+    }
+}
+
+/// Works out the new contents of the file an `edit_file` call targets. The
+/// returned path has its symlinks resolved, so that checking it against the
+/// writable roots reflects where the write actually lands.
+pub(crate) fn prepare_edit(cwd: &Path, params: EditFileParams) -> Result<FileEdit, String> {
+    let EditFileParams {
+        path,
+        old_string,
+        new_string,
+        replace_all,
+    } = params;
+    let path = resolve_symlinks(&cwd.join(path));
+
+    if path.is_dir() {
+        return Err(format!("{} is a directory", path.display()));
+    }
+    if !path.exists() {
+        if !old_string.is_empty() {
+            return Err(format!(
+                "{} does not exist; pass an empty old_string to create it",
+                path.display()
+            ));
+        }
+        let action =
+            ApplyPatchAction::new_write(path.clone(), None, new_string.clone(), cwd.into());
+        return Ok(FileEdit {
+            summary: format!("Created {}.", path.display()),
+            path,
+            new_content: new_string,
+            created: true,
+            action,
+        });
+    }
+
+    if old_string.is_empty() {
+        return Err(format!(
+            "{} already exists; old_string must not be empty when editing a file",
+            path.display()
+        ));
+    }
+    if old_string == new_string {
+        return Err("old_string and new_string are the same".to_string());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let replacements = match content.matches(&old_string).count() {
+        0 => {
+            return Err(format!(
+                "old_string was not found in {}; read the file again and copy the text exactly",
+                path.display()
+            ));
+        }
+        n if n > 1 && !replace_all => {
+            return Err(format!(
+                "old_string occurs {n} times in {}; include more surrounding lines to make it unique, or set replace_all",
+                path.display()
+            ));
+        }
+        n => n,
+    };
+    let new_content = if replace_all {
+        content.replace(&old_string, &new_string)
+    } else {
+        content.replacen(&old_string, &new_string, 1)
+    };
+    let summary = if replacements == 1 {
+        format!("Edited {}.", path.display())
+    } else {
+        format!(
+            "Edited {}: replaced {replacements} occurrences.",
+            path.display()
+        )
+    };
+    Ok(FileEdit::update(cwd, path, &content, new_content, summary))
+}
+
+/// Canonicalizes the longest prefix of `path` that exists and appends the
+/// rest, so files that do not exist yet are resolved too.
+pub(crate) fn resolve_symlinks(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(resolved) = ancestor.canonicalize() {
+            return match path.strip_prefix(ancestor) {
+                Ok(rest) if !rest.as_os_str().is_empty() => resolved.join(rest),
+                _ => resolved,
+            };
+        }
+    }
+    path.to_path_buf()
+}
+
+pub(crate) fn parse_error(call_id: String, e: serde_json::Error) -> ResponseInputItem {
+    ResponseInputItem::FunctionCallOutput {
+        call_id,
+        output: FunctionCallOutputPayload {
+            content: format!("failed to parse function arguments: {e}"),
+            success: None,
+        },
+    }
+}
+
+pub(crate) fn function_output(
+    call_id: String,
+    result: Result<String, String>,
+) -> ResponseInputItem {
+    let output = match result {
+        Ok(content) => FunctionCallOutputPayload {
+            content,
+            success: Some(true),
+        },
+        Err(content) => FunctionCallOutputPayload {
+            content,
+            success: Some(false),
+        },
+    };
+    ResponseInputItem::FunctionCallOutput { call_id, output }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_apply_patch::ApplyPatchFileChange;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    fn edit(old_string: &str, new_string: &str, replace_all: bool) -> EditFileParams {
+        EditFileParams {
+            path: "a.txt".to_string(),
+            old_string: old_string.to_string(),
+            new_string: new_string.to_string(),
+            replace_all,
+        }
+    }
+
+    #[test]
+    fn numbers_lines_and_points_at_the_next_offset() {
+        let path = Path::new("a.txt");
+        let bytes = b"one\ntwo\nthree\nfour\n";
+
+        assert_eq!(
+            format_file_lines(path, bytes, 2, 2).unwrap(),
+            "     2\ttwo\n     3\tthree\n\n(showing lines 2-3 of 4; use offset=4 to read more)\n"
+        );
+        assert_eq!(
+            format_file_lines(path, bytes, 4, 10).unwrap(),
+            "     4\tfour\n"
+        );
+        assert!(format_file_lines(path, bytes, 5, 10).is_err());
+        assert!(format_file_lines(path, b"a\0b", 1, 10).is_err());
+    }
+
+    #[test]
+    fn lists_nested_entries_and_skips_ignored_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        fs::create_dir(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("src/bin/main.rs"), "").unwrap();
+
+        assert_eq!(list_dir(dir.path(), 1), ".gitignore\nsrc/\n");
+        assert_eq!(
+            list_dir(dir.path(), 2),
+            ".gitignore\nsrc/\n  bin/\n  lib.rs\n"
+        );
+    }
+
+    #[test]
+    fn read_scope_follows_symlinks_and_keeps_to_the_sandbox() {
+        let readable = tempfile::tempdir().unwrap();
+        let cwd = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let output_dir = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.txt"),
+            readable.path().join("link.txt"),
+        )
+        .unwrap();
+        let scope = ReadScope {
+            cwd: cwd.path().to_path_buf(),
+            sandbox_policy: SandboxPolicy::ReadOnly {
+                readable_roots: vec![readable.path().to_path_buf()],
+            },
+            output_dir: output_dir.path().to_path_buf(),
+        };
+
+        assert!(scope.resolve(Path::new("notes.txt")).is_ok());
+        assert!(scope.resolve(&output_dir.path().join("out.txt")).is_ok());
+        let err = scope
+            .resolve(&outside.path().join("secret.txt"))
+            .unwrap_err();
+        assert!(
+            err.contains("outside the folders the sandbox can read"),
+            "{err}"
+        );
+        #[cfg(unix)]
+        assert!(scope.resolve(&readable.path().join("link.txt")).is_err());
+
+        let unlimited = ReadScope {
+            sandbox_policy: SandboxPolicy::new_read_only_policy(),
+            ..scope
+        };
+        assert!(
+            unlimited
+                .resolve(&outside.path().join("secret.txt"))
+                .is_ok()
+        );
+        if let Some(home) = dirs::home_dir() {
+            let err = unlimited
+                .resolve(&home.join(".ssh/id_ed25519"))
+                .unwrap_err();
+            assert!(err.contains("holds credentials"), "{err}");
+        }
+    }
+
+    #[test]
+    fn replaces_a_unique_string() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "fn a() {}\nfn b() {}\n").unwrap();
+
+        let file_edit = prepare_edit(dir.path(), edit("fn b", "fn c", false)).unwrap();
+        file_edit.write().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "fn a() {}\nfn c() {}\n"
+        );
+        let path = dir.path().canonicalize().unwrap().join("a.txt");
+        assert_eq!(file_edit.path, path);
+        let Some(ApplyPatchFileChange::Update { unified_diff, .. }) =
+            file_edit.action.changes().get(&path)
+        else {
+            panic!("expected an update");
+        };
+        assert_eq!(
+            unified_diff,
+            "@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}\n"
+        );
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_missing_strings() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "x = 1\nx = 1\n").unwrap();
+
+        let err = prepare_edit(dir.path(), edit("x = 1", "x = 2", false))
+            .err()
+            .unwrap();
+        assert!(err.contains("occurs 2 times"), "{err}");
+        assert!(prepare_edit(dir.path(), edit("y", "z", false)).is_err());
+        assert!(prepare_edit(dir.path(), edit("", "z", false)).is_err());
+
+        let file_edit = prepare_edit(dir.path(), edit("x = 1", "x = 2", true)).unwrap();
+        assert!(file_edit.summary().ends_with("replaced 2 occurrences."));
+    }
+
+    #[test]
+    fn creates_a_file_and_its_parents() {
+        let dir = tempfile::tempdir().unwrap();
+        let params = EditFileParams {
+            path: "new/dir/a.txt".to_string(),
+            old_string: String::new(),
+            new_string: "hello\n".to_string(),
+            replace_all: false,
+        };
+
+        let file_edit = prepare_edit(dir.path(), params).unwrap();
+        assert!(matches!(
+            file_edit.action.changes().values().next(),
+            Some(ApplyPatchFileChange::Add { .. })
+        ));
+        file_edit.write().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("new/dir/a.txt")).unwrap(),
+            "hello\n"
+        );
+    }
+}
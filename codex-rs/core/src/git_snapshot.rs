@@ -0,0 +1,492 @@
+//! Snapshots of the working tree taken before and after each turn, so that a
+//! turn can be undone without touching the user's index, branches or stash.
+//!
+//! A snapshot is a commit of every tracked and untracked, non-ignored file,
+//! built with a temporary copy of the index and kept alive by a ref under
+//! `refs/codex/snapshots/`. Undoing a turn puts back only the files that
+//! differ between the two snapshots, which covers edits made by `apply_patch`
+//! and by shell commands alike, and refuses if any of them was changed again
+//! after the turn.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tempfile::TempDir;
+use tokio::process::Command;
+use tokio::time::timeout;
+use uuid::Uuid;
+
+/// Snapshots hash every changed file, so allow for large working trees.
+const SNAPSHOT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SNAPSHOT_REF_PREFIX: &str = "refs/codex/snapshots";
+
+/// Snapshots left behind by sessions that did not shut down cleanly are
+/// deleted once they are this old.
+const STALE_SNAPSHOT_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The identity snapshots are committed with. Snapshots must not depend on
+/// the user having configured one.
+const SNAPSHOT_IDENTITY: [(&str, &str); 4] = [
+    ("GIT_AUTHOR_NAME", "Codex"),
+    ("GIT_AUTHOR_EMAIL", "codex@localhost"),
+    ("GIT_COMMITTER_NAME", "Codex"),
+    ("GIT_COMMITTER_EMAIL", "codex@localhost"),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum GitSnapshotError {
+    #[error("failed to run git: {0}")]
+    Spawn(#[from] std::io::Error),
+
+    #[error("git {0} timed out")]
+    Timeout(String),
+
+    #[error("git {command} failed: {stderr}")]
+    Git { command: String, stderr: String },
+
+    #[error("the turn did not finish, so its changes cannot be told apart from later edits")]
+    Unfinished,
+
+    #[error("changed since the turn ended, undo would discard those edits: {}", display_paths(.0))]
+    ChangedSinceTurn(Vec<PathBuf>),
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The state of a repository's working tree before a turn and, once the turn
+/// finished, after it.
+#[derive(Debug, Clone)]
+pub struct GitSnapshot {
+    /// Top level of the repository the snapshot was taken in.
+    repo_root: PathBuf,
+    /// The ref that keeps the snapshot commit from being garbage collected.
+    ref_name: String,
+    /// The ref of the snapshot taken when the turn ended.
+    end_ref_name: Option<String>,
+}
+
+/// Files that undoing a turn put back.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RestoredFiles {
+    /// Files changed or deleted during the turn, restored from the snapshot.
+    pub restored: Vec<PathBuf>,
+    /// Files created during the turn, removed again.
+    pub removed: Vec<PathBuf>,
+}
+
+impl RestoredFiles {
+    pub fn is_empty(&self) -> bool {
+        self.restored.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl GitSnapshot {
+    /// Snapshots the working tree of the repository that contains `cwd`.
+    /// Returns `Ok(None)` when `cwd` is not inside a git repository.
+    pub async fn take(
+        cwd: &Path,
+        session_id: Uuid,
+        turn: u64,
+    ) -> Result<Option<Self>, GitSnapshotError> {
+        let Ok(top_level) = git(cwd, &["rev-parse", "--show-toplevel"], &[]).await else {
+            return Ok(None);
+        };
+        let repo_root = PathBuf::from(top_level.trim_end());
+
+        let head = git(
+            &repo_root,
+            &["rev-parse", "--verify", "--quiet", "HEAD"],
+            &[],
+        )
+        .await
+        .ok()
+        .map(|head| head.trim_end().to_string());
+        let ref_name = format!("{SNAPSHOT_REF_PREFIX}/{session_id}/{turn}");
+        commit_worktree(&repo_root, head.as_deref(), &ref_name).await?;
+        Ok(Some(Self {
+            repo_root,
+            ref_name,
+            end_ref_name: None,
+        }))
+    }
+
+    pub fn ref_name(&self) -> &str {
+        &self.ref_name
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.end_ref_name.is_some()
+    }
+
+    /// Snapshots the working tree again now that the turn has ended, so that
+    /// undoing the turn can tell its changes from later edits.
+    pub async fn finish(&mut self) -> Result<(), GitSnapshotError> {
+        let end_ref_name = format!("{}-end", self.ref_name);
+        commit_worktree(&self.repo_root, Some(&self.ref_name), &end_ref_name).await?;
+        self.end_ref_name = Some(end_ref_name);
+        Ok(())
+    }
+
+    /// Puts every file the turn changed back the way it was before the turn,
+    /// then deletes the snapshot's refs. Fails without changing anything if
+    /// one of those files was changed again after the turn. Ignored files are
+    /// not part of the snapshot and are left alone.
+    pub async fn restore(&self) -> Result<RestoredFiles, GitSnapshotError> {
+        let Some(end_ref_name) = &self.end_ref_name else {
+            return Err(GitSnapshotError::Unfinished);
+        };
+        let start_tree = format!("{}^{{tree}}", self.ref_name);
+        let end_tree = format!("{end_ref_name}^{{tree}}");
+        let turn_changes = diff_trees(&self.repo_root, &start_tree, &end_tree).await?;
+
+        if !turn_changes.is_empty() {
+            let current = write_worktree_tree(&self.repo_root).await?;
+            let later_changes = diff_trees(&self.repo_root, &end_tree, &current).await?;
+            let changed_since: Vec<PathBuf> = turn_changes
+                .iter()
+                .filter(|(_, path)| later_changes.iter().any(|(_, later)| later == path))
+                .map(|(_, path)| path.clone())
+                .collect();
+            if !changed_since.is_empty() {
+                return Err(GitSnapshotError::ChangedSinceTurn(changed_since));
+            }
+        }
+
+        let mut files = RestoredFiles::default();
+        for (status, path) in turn_changes {
+            if status == "A" {
+                files.removed.push(path);
+            } else {
+                files.restored.push(path);
+            }
+        }
+
+        for path in &files.removed {
+            let path = self.repo_root.join(path);
+            std::fs::remove_file(&path)?;
+            remove_empty_parents(&self.repo_root, &path);
+        }
+        if !files.restored.is_empty() {
+            // `restore --worktree` leaves the user's index as it is.
+            let mut args = vec![
+                "restore",
+                "--worktree",
+                "--source",
+                self.ref_name.as_str(),
+                "--",
+            ];
+            let paths = files
+                .restored
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+            args.extend(paths.iter().map(String::as_str));
+            git(&self.repo_root, &args, &[("GIT_LITERAL_PATHSPECS", "1")]).await?;
+        }
+
+        self.delete().await?;
+        Ok(files)
+    }
+
+    /// Deletes the snapshot's refs, letting git collect the snapshots.
+    pub async fn delete(&self) -> Result<(), GitSnapshotError> {
+        git(&self.repo_root, &["update-ref", "-d", &self.ref_name], &[]).await?;
+        if let Some(end_ref_name) = &self.end_ref_name {
+            git(&self.repo_root, &["update-ref", "-d", end_ref_name], &[]).await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the snapshots in the repository that contains `cwd` that are
+    /// old enough that no running session can still undo them.
+    pub async fn prune_stale(cwd: &Path) -> Result<(), GitSnapshotError> {
+        let Ok(top_level) = git(cwd, &["rev-parse", "--show-toplevel"], &[]).await else {
+            return Ok(());
+        };
+        let repo_root = PathBuf::from(top_level.trim_end());
+        let refs = git(
+            &repo_root,
+            &[
+                "for-each-ref",
+                "--format=%(refname) %(committerdate:unix)",
+                SNAPSHOT_REF_PREFIX,
+            ],
+            &[],
+        )
+        .await?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        for line in refs.lines() {
+            let Some((ref_name, committed)) = line.rsplit_once(' ') else {
+                continue;
+            };
+            let age = now.saturating_sub(committed.parse().unwrap_or(now));
+            if age > STALE_SNAPSHOT_AGE.as_secs() {
+                git(&repo_root, &["update-ref", "-d", ref_name], &[]).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Commits the working tree on top of `parent` and points `ref_name` at the
+/// commit.
+async fn commit_worktree(
+    repo_root: &Path,
+    parent: Option<&str>,
+    ref_name: &str,
+) -> Result<(), GitSnapshotError> {
+    let tree = write_worktree_tree(repo_root).await?;
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", "codex snapshot"];
+    if let Some(parent) = parent {
+        args.extend(["-p", parent]);
+    }
+    let commit = git(repo_root, &args, &SNAPSHOT_IDENTITY).await?;
+    git(repo_root, &["update-ref", ref_name, commit.trim_end()], &[]).await?;
+    Ok(())
+}
+
+/// The files that differ between two trees, with their `--name-status`
+/// letter.
+async fn diff_trees(
+    repo_root: &Path,
+    from: &str,
+    to: &str,
+) -> Result<Vec<(String, PathBuf)>, GitSnapshotError> {
+    let diff = git(
+        repo_root,
+        &[
+            "diff-tree",
+            "-r",
+            "-z",
+            "--no-renames",
+            "--name-status",
+            from,
+            to,
+        ],
+        &[],
+    )
+    .await?;
+    let mut changes = Vec::new();
+    let mut fields = diff.split('\0').filter(|field| !field.is_empty());
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        changes.push((status.to_string(), PathBuf::from(path)));
+    }
+    Ok(changes)
+}
+
+/// Writes a tree of the working tree, tracked and untracked files alike,
+/// without touching the repository's index.
+async fn write_worktree_tree(repo_root: &Path) -> Result<String, GitSnapshotError> {
+    // Start from a copy of the real index so that `git add` only has to hash
+    // files whose stat information changed.
+    let index_dir = TempDir::new()?;
+    let index = index_dir.path().join("index");
+    let real_index = git(repo_root, &["rev-parse", "--git-path", "index"], &[]).await?;
+    let real_index = repo_root.join(real_index.trim_end());
+    if real_index.exists() {
+        std::fs::copy(&real_index, &index)?;
+    }
+    let index = index.to_string_lossy().into_owned();
+    let env = [("GIT_INDEX_FILE", index.as_str())];
+
+    git(repo_root, &["add", "--all", "--", "."], &env).await?;
+    let tree = git(repo_root, &["write-tree"], &env).await?;
+    Ok(tree.trim_end().to_string())
+}
+
+fn remove_empty_parents(repo_root: &Path, path: &Path) {
+    let mut dir = path.parent();
+    while let Some(parent) = dir {
+        if parent == repo_root || std::fs::remove_dir(parent).is_err() {
+            break;
+        }
+        dir = parent.parent();
+    }
+}
+
+async fn git(cwd: &Path, args: &[&str], env: &[(&str, &str)]) -> Result<String, GitSnapshotError> {
+    let command = args.first().copied().unwrap_or_default().to_string();
+    let output = timeout(
+        SNAPSHOT_COMMAND_TIMEOUT,
+        Command::new("git")
+            .args(args)
+            .envs(env.iter().copied())
+            .current_dir(cwd)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| GitSnapshotError::Timeout(command.clone()))??;
+    if !output.status.success() {
+        return Err(GitSnapshotError::Git {
+            command,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    async fn git_in(repo: &Path, args: &[&str]) -> String {
+        git(repo, args, &[("GIT_CONFIG_GLOBAL", "/dev/null")])
+            .await
+            .unwrap()
+    }
+
+    async fn create_repo(temp_dir: &TempDir) -> PathBuf {
+        let repo = temp_dir.path().join("repo");
+        fs::create_dir(&repo).unwrap();
+        git_in(&repo, &["init"]).await;
+        git_in(&repo, &["config", "user.name", "Test User"]).await;
+        git_in(&repo, &["config", "user.email", "test@example.com"]).await;
+        fs::write(repo.join("tracked.txt"), "committed\n").unwrap();
+        fs::write(repo.join("deleted.txt"), "keep me\n").unwrap();
+        fs::write(repo.join(".gitignore"), "ignored.txt\n").unwrap();
+        git_in(&repo, &["add", "."]).await;
+        git_in(&repo, &["commit", "-m", "initial"]).await;
+        repo
+    }
+
+    #[tokio::test]
+    async fn restore_undoes_changes_and_keeps_earlier_edits() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = create_repo(&temp_dir).await;
+
+        // Uncommitted edits the user made before the turn.
+        fs::write(repo.join("tracked.txt"), "user edit\n").unwrap();
+        fs::write(repo.join("untracked.txt"), "user file\n").unwrap();
+        git_in(&repo, &["add", "untracked.txt"]).await;
+        let status_before = git_in(&repo, &["status", "--porcelain"]).await;
+
+        let mut snapshot = GitSnapshot::take(&repo, Uuid::new_v4(), 1)
+            .await
+            .unwrap()
+            .expect("repo should be snapshotted");
+
+        // What the turn did.
+        fs::write(repo.join("tracked.txt"), "agent edit\n").unwrap();
+        fs::remove_file(repo.join("deleted.txt")).unwrap();
+        fs::create_dir_all(repo.join("new/dir")).unwrap();
+        fs::write(repo.join("new/dir/created.txt"), "agent file\n").unwrap();
+        fs::write(repo.join("ignored.txt"), "build output\n").unwrap();
+        snapshot.finish().await.unwrap();
+
+        // The user keeps working on a file the turn did not touch.
+        fs::write(repo.join("later.txt"), "written after the turn\n").unwrap();
+
+        let files = snapshot.restore().await.unwrap();
+        assert_eq!(
+            files,
+            RestoredFiles {
+                restored: vec![PathBuf::from("deleted.txt"), PathBuf::from("tracked.txt")],
+                removed: vec![PathBuf::from("new/dir/created.txt")],
+            }
+        );
+        assert_eq!(
+            fs::read_to_string(repo.join("tracked.txt")).unwrap(),
+            "user edit\n"
+        );
+        assert_eq!(
+            fs::read_to_string(repo.join("deleted.txt")).unwrap(),
+            "keep me\n"
+        );
+        assert!(!repo.join("new").exists());
+        assert!(repo.join("ignored.txt").exists());
+        fs::remove_file(repo.join("later.txt")).unwrap();
+        assert_eq!(
+            git_in(&repo, &["status", "--porcelain"]).await,
+            status_before
+        );
+        assert_eq!(git_in(&repo, &["for-each-ref", "refs/codex/"]).await, "");
+    }
+
+    #[tokio::test]
+    async fn restore_refuses_when_the_turn_changes_were_edited_again() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = create_repo(&temp_dir).await;
+
+        let mut snapshot = GitSnapshot::take(&repo, Uuid::new_v4(), 1)
+            .await
+            .unwrap()
+            .expect("repo should be snapshotted");
+        assert!(matches!(
+            snapshot.restore().await,
+            Err(GitSnapshotError::Unfinished)
+        ));
+
+        fs::write(repo.join("tracked.txt"), "agent edit\n").unwrap();
+        snapshot.finish().await.unwrap();
+        fs::write(repo.join("tracked.txt"), "user edit after the turn\n").unwrap();
+
+        match snapshot.restore().await {
+            Err(GitSnapshotError::ChangedSinceTurn(paths)) => {
+                assert_eq!(paths, vec![PathBuf::from("tracked.txt")]);
+            }
+            other => panic!("expected ChangedSinceTurn, got {other:?}"),
+        }
+        assert_eq!(
+            fs::read_to_string(repo.join("tracked.txt")).unwrap(),
+            "user edit after the turn\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn prune_stale_deletes_only_old_snapshots() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = create_repo(&temp_dir).await;
+
+        let snapshot = GitSnapshot::take(&repo, Uuid::new_v4(), 1)
+            .await
+            .unwrap()
+            .expect("repo should be snapshotted");
+        let tree = git_in(&repo, &["write-tree"]).await;
+        let old_commit = git(
+            &repo,
+            &["commit-tree", tree.trim_end(), "-m", "old snapshot"],
+            &[
+                ("GIT_CONFIG_GLOBAL", "/dev/null"),
+                ("GIT_COMMITTER_DATE", "2000-01-01T00:00:00Z"),
+            ],
+        )
+        .await
+        .unwrap();
+        let old_ref = format!("{SNAPSHOT_REF_PREFIX}/{}/1", Uuid::new_v4());
+        git_in(&repo, &["update-ref", &old_ref, old_commit.trim_end()]).await;
+
+        GitSnapshot::prune_stale(&repo).await.unwrap();
+
+        assert_eq!(
+            git_in(
+                &repo,
+                &["for-each-ref", "--format=%(refname)", "refs/codex/"]
+            )
+            .await,
+            format!("{}\n", snapshot.ref_name())
+        );
+    }
+
+    #[tokio::test]
+    async fn take_outside_a_repository_returns_none() {
+        let temp_dir = TempDir::new().unwrap();
+        let snapshot = GitSnapshot::take(temp_dir.path(), Uuid::new_v4(), 1)
+            .await
+            .unwrap();
+        assert!(snapshot.is_none());
+    }
+}
@@ -0,0 +1,600 @@
+//! A small HTML to markdown converter for pages fetched with `web_fetch`.
+//!
+//! It is not a full HTML parser: it keeps headings, paragraphs, lists, links,
+//! code and tables, drops scripts, styles and navigation, and is forgiving of
+//! malformed markup, which is all the model needs to read documentation.
+
+use reqwest::Url;
+
+/// Elements whose contents are not shown.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "head", "nav", "noscript", "template", "svg", "iframe", "select", "button", "form",
+];
+
+/// Elements whose contents are raw text rather than markup, and so are
+/// scanned for their closing tag instead of being tokenized.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "title", "textarea"];
+
+/// Elements that never have a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements that start on a new paragraph.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "header",
+    "main",
+    "p",
+    "section",
+    "summary",
+    "table",
+];
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Markdown {
+    /// Contents of the `<title>` element.
+    pub(crate) title: Option<String>,
+    pub(crate) text: String,
+}
+
+/// Converts `html` to markdown. Relative links are resolved against
+/// `base_url` when one is given.
+pub(crate) fn html_to_markdown(html: &str, base_url: Option<&Url>) -> Markdown {
+    let mut writer = Writer::default();
+    let mut title = None;
+    let mut rest = html;
+    let mut skip_depth = 0usize;
+
+    while let Some(lt) = rest.find('<') {
+        if skip_depth == 0 {
+            writer.text(&decode_entities(&rest[..lt]));
+        }
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(tag) = Tag::parse(rest) else {
+            // A `<` that does not start a tag is text.
+            if skip_depth == 0 {
+                writer.text("<");
+            }
+            rest = &rest[1..];
+            continue;
+        };
+        rest = &rest[tag.len..];
+
+        if !tag.closing && RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) {
+            let end = find_closing_tag(rest, &tag.name);
+            let contents = &rest[..end.unwrap_or(rest.len())];
+            if tag.name == "title" {
+                let text = collapse_whitespace(&decode_entities(contents));
+                if title.is_none() && !text.is_empty() {
+                    title = Some(text);
+                }
+            } else if tag.name == "textarea" && skip_depth == 0 {
+                writer.text(&decode_entities(contents));
+            }
+            rest = match end {
+                Some(end) => {
+                    let after = &rest[end..];
+                    after.find('>').map_or("", |gt| &after[gt + 1..])
+                }
+                None => "",
+            };
+            continue;
+        }
+
+        if SKIPPED_ELEMENTS.contains(&tag.name.as_str()) {
+            if tag.closing {
+                skip_depth = skip_depth.saturating_sub(1);
+            } else if !tag.self_closing {
+                skip_depth += 1;
+            }
+            continue;
+        }
+        if skip_depth > 0 {
+            continue;
+        }
+
+        if tag.closing {
+            writer.close(&tag.name);
+        } else {
+            writer.open(&tag, base_url);
+        }
+    }
+    if skip_depth == 0 {
+        writer.text(&decode_entities(rest));
+    }
+
+    Markdown {
+        title,
+        text: writer.finish(),
+    }
+}
+
+struct Tag {
+    name: String,
+    /// The text between the tag name and the closing `>`.
+    attributes: String,
+    closing: bool,
+    self_closing: bool,
+    /// Length of the tag in bytes, including `<` and `>`.
+    len: usize,
+}
+
+impl Tag {
+    /// Parses the tag at the start of `s`, which begins with `<`. Returns
+    /// `None` when `s` does not start with something that looks like a tag.
+    fn parse(s: &str) -> Option<Self> {
+        let body = &s[1..];
+        let (closing, body) = match body.strip_prefix('/') {
+            Some(body) => (true, body),
+            None => (false, body),
+        };
+        // `<!DOCTYPE>` and `<?xml ?>` are parsed as tags with empty names,
+        // which are then ignored.
+        let first = body.chars().next()?;
+        if !(first.is_ascii_alphabetic() || first == '!' || first == '?') {
+            return None;
+        }
+
+        // Find the closing `>`, skipping over quoted attribute values.
+        let mut quote = None;
+        let mut end = None;
+        for (i, c) in body.char_indices() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None if c == '>' => {
+                    end = Some(i);
+                    break;
+                }
+                None => {}
+            }
+        }
+        let end = end?;
+        let inner = &body[..end];
+        let name_end = inner
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(inner.len());
+        let name = if first.is_ascii_alphabetic() {
+            inner[..name_end].to_ascii_lowercase()
+        } else {
+            String::new()
+        };
+        Some(Self {
+            self_closing: inner.ends_with('/') || VOID_ELEMENTS.contains(&name.as_str()),
+            name,
+            attributes: inner[name_end..].to_string(),
+            closing,
+            len: 1 + usize::from(closing) + end + 1,
+        })
+    }
+
+    fn attribute(&self, name: &str) -> Option<String> {
+        let mut rest = self.attributes.as_str();
+        loop {
+            rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+            if rest.is_empty() {
+                return None;
+            }
+            let name_end = rest
+                .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+                .unwrap_or(rest.len());
+            let attr_name = &rest[..name_end];
+            rest = rest[name_end..].trim_start();
+            let value = match rest.strip_prefix('=') {
+                Some(after) => {
+                    let after = after.trim_start();
+                    let (value, remaining) = match after.chars().next() {
+                        Some(q @ ('"' | '\'')) => {
+                            let inner = &after[1..];
+                            let end = inner.find(q).unwrap_or(inner.len());
+                            (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                        }
+                        _ => {
+                            let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                            (&after[..end], &after[end..])
+                        }
+                    };
+                    rest = remaining;
+                    value
+                }
+                None => "",
+            };
+            if attr_name.eq_ignore_ascii_case(name) {
+                return Some(decode_entities(value));
+            }
+        }
+    }
+}
+
+/// Returns the byte offset of `</name` in `s`, ignoring case.
+fn find_closing_tag(s: &str, name: &str) -> Option<usize> {
+    let needle = format!("</{name}");
+    s.as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+#[derive(Default)]
+struct Writer {
+    out: String,
+    /// Whitespace was seen since the last text was written.
+    pending_space: bool,
+    /// For each open list, `None` for `<ul>` or the next item number for
+    /// `<ol>`.
+    lists: Vec<Option<usize>>,
+    pre_depth: usize,
+    /// For each open link, the markdown target, or `None` when the link is
+    /// written as plain text.
+    links: Vec<Option<String>>,
+    /// The number of cells written so far in the current table row.
+    row_cells: usize,
+}
+
+impl Writer {
+    fn text(&mut self, text: &str) {
+        if self.pre_depth > 0 {
+            self.out.push_str(text);
+            return;
+        }
+        for word in text.split_inclusive(char::is_whitespace) {
+            let trimmed = word.trim_end_matches(char::is_whitespace);
+            if !trimmed.is_empty() {
+                if self.pending_space && !self.at_line_start() && !self.out.ends_with(' ') {
+                    self.out.push(' ');
+                }
+                self.out.push_str(trimmed);
+                self.pending_space = false;
+            }
+            if trimmed.len() < word.len() {
+                self.pending_space = true;
+            }
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n') || self.out.ends_with('[')
+    }
+
+    /// Ends the current paragraph.
+    fn paragraph(&mut self) {
+        self.trim_trailing_spaces();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push_str(if self.out.ends_with('\n') {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        self.pending_space = false;
+    }
+
+    fn line_break(&mut self) {
+        self.trim_trailing_spaces();
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.pending_space = false;
+    }
+
+    fn trim_trailing_spaces(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed);
+    }
+
+    /// Writes an inline marker such as `**`, with a space before it if the
+    /// text before it ended in whitespace.
+    fn inline(&mut self, marker: &str) {
+        if self.pending_space && !self.at_line_start() {
+            self.out.push(' ');
+            self.pending_space = false;
+        }
+        self.out.push_str(marker);
+    }
+
+    fn open(&mut self, tag: &Tag, base_url: Option<&Url>) {
+        let name = tag.name.as_str();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.paragraph();
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                self.out.push_str(&"#".repeat(level));
+                self.out.push(' ');
+            }
+            "br" => self.line_break(),
+            "hr" => {
+                self.paragraph();
+                self.out.push_str("---");
+                self.paragraph();
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.paragraph();
+                }
+                self.lists.push((name == "ol").then_some(1));
+            }
+            "li" => {
+                self.line_break();
+                let depth = self.lists.len().max(1);
+                self.out.push_str(&"  ".repeat(depth - 1));
+                match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        self.out.push_str(&format!("{number}. "));
+                        *number += 1;
+                    }
+                    _ => self.out.push_str("- "),
+                }
+            }
+            "pre" => {
+                self.paragraph();
+                if self.pre_depth == 0 {
+                    self.out.push_str("```\n");
+                }
+                self.pre_depth += 1;
+            }
+            "code" | "kbd" | "samp" if self.pre_depth == 0 => self.inline("`"),
+            "strong" | "b" => self.inline("**"),
+            "em" | "i" => self.inline("_"),
+            "a" => {
+                let target = tag
+                    .attribute("href")
+                    .filter(|href| !href.starts_with('#') && !href.starts_with("javascript:"))
+                    .map(|href| resolve_url(&href, base_url));
+                if target.is_some() {
+                    self.inline("[");
+                }
+                self.links.push(target);
+            }
+            "img" => {
+                if let Some(alt) = tag.attribute("alt").filter(|alt| !alt.trim().is_empty()) {
+                    self.inline(&format!("[image: {}]", collapse_whitespace(&alt)));
+                }
+            }
+            "tr" => {
+                self.line_break();
+                self.row_cells = 0;
+            }
+            "td" | "th" => {
+                self.out
+                    .push_str(if self.row_cells == 0 { "| " } else { " | " });
+                self.row_cells += 1;
+                self.pending_space = false;
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) => self.paragraph(),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.paragraph(),
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.paragraph();
+                }
+            }
+            "pre" => {
+                self.pre_depth = self.pre_depth.saturating_sub(1);
+                if self.pre_depth == 0 {
+                    if !self.out.ends_with('\n') {
+                        self.out.push('\n');
+                    }
+                    self.out.push_str("```");
+                    self.paragraph();
+                }
+            }
+            "code" | "kbd" | "samp" if self.pre_depth == 0 => self.out.push('`'),
+            "strong" | "b" => self.out.push_str("**"),
+            "em" | "i" => self.out.push('_'),
+            "a" => {
+                if let Some(Some(target)) = self.links.pop() {
+                    if self.out.ends_with('[') {
+                        // Drop links without text.
+                        self.out.pop();
+                    } else {
+                        self.out.push_str(&format!("]({target})"));
+                    }
+                }
+            }
+            "tr" => {
+                if self.row_cells > 0 {
+                    self.out.push_str(" |");
+                }
+                self.line_break();
+            }
+            "table" => self.paragraph(),
+            _ if BLOCK_ELEMENTS.contains(&name) => self.paragraph(),
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> String {
+        while !self.links.is_empty() {
+            self.close("a");
+        }
+        // Collapse the blank lines left by empty blocks.
+        self.paragraph();
+        let mut out = String::with_capacity(self.out.len());
+        let mut blank_lines = 0;
+        for line in self.out.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                blank_lines += 1;
+                if blank_lines > 1 {
+                    continue;
+                }
+            } else {
+                blank_lines = 0;
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        out.trim().to_string()
+    }
+}
+
+fn resolve_url(href: &str, base_url: Option<&Url>) -> String {
+    match base_url.and_then(|base| base.join(href).ok()) {
+        Some(url) => url.to_string(),
+        None => href.to_string(),
+    }
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decodes character references and the named entities common in prose.
+/// Unknown entities are left as they are.
+pub(crate) fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&semi| semi <= 10)
+            .and_then(|semi| Some((decode_entity(&rest[1..=semi])?, semi + 2)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "middot" => '·',
+        "bull" => '•',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "times" => '×',
+        "rarr" => '→',
+        "larr" => '←',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn converts_documentation_markup() {
+        let html = r##"<!DOCTYPE html>
+<html>
+<head><title>Vec &mdash; docs</title><style>body { color: red; }</style></head>
+<body>
+  <nav><a href="/">Home</a></nav>
+  <h1>Struct <code>Vec</code></h1>
+  <p>A contiguous   growable array type, written as
+     <code>Vec&lt;T&gt;</code>. See <a href="../slice/index.html">slices</a>
+     and <a href="#examples">below</a>.</p>
+  <!-- a comment with <p>markup</p> -->
+  <script>if (a < b) { document.write("<p>no</p>"); }</script>
+  <h2 id="examples">Examples</h2>
+  <pre><code>let mut v = Vec::new();
+v.push(1);</code></pre>
+  <ul>
+    <li>First <strong>item</strong></li>
+    <li>Second<ol><li>nested</li></ol></li>
+  </ul>
+  <table><tr><th>Method</th><th>Cost</th></tr><tr><td>push</td><td>O(1)</td></tr></table>
+</body>
+</html>"##;
+        let base = Url::parse("https://doc.rust-lang.org/std/vec/struct.Vec.html").unwrap();
+
+        let markdown = html_to_markdown(html, Some(&base));
+
+        assert_eq!(markdown.title.as_deref(), Some("Vec — docs"));
+        assert_eq!(
+            markdown.text,
+            "# Struct `Vec`
+
+A contiguous growable array type, written as `Vec<T>`. See [slices](https://doc.rust-lang.org/std/slice/index.html) and below.
+
+## Examples
+
+```
+let mut v = Vec::new();
+v.push(1);
+```
+
+- First **item**
+- Second
+  1. nested
+
+| Method | Cost |
+| push | O(1) |"
+        );
+    }
+
+    #[test]
+    fn tolerates_malformed_markup() {
+        let markdown = html_to_markdown(
+            "<p>1 < 2 && 3 > 2 &bogus; &#x41;<br/>next line<p>unclosed <a href='x'>link",
+            None,
+        );
+
+        assert_eq!(markdown.title, None);
+        assert_eq!(
+            markdown.text,
+            "1 < 2 && 3 > 2 &bogus; A\nnext line\n\nunclosed [link](x)"
+        );
+    }
+}
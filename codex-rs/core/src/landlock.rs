@@ -1,4 +1,7 @@
+use crate::config::find_codex_home;
+use crate::network_proxy::NetworkProxy;
 use crate::protocol::SandboxPolicy;
+use crate::resource_limits::ResourceLimits;
 use crate::spawn::StdioPolicy;
 use crate::spawn::spawn_child_async;
 use std::collections::HashMap;
@@ -6,6 +9,36 @@ use std::path::Path;
 use std::path::PathBuf;
 use tokio::process::Child;
 
+/// Credential files and folders, relative to the home directory, that stay
+/// unreadable inside the sandbox whatever the sandbox policy.
+const HOME_READ_DENIED_PATHS: &[&str] = &[
+    ".ssh",
+    ".aws",
+    ".azure",
+    ".gnupg",
+    ".kube",
+    ".config/gcloud",
+    ".docker/config.json",
+    ".netrc",
+    ".git-credentials",
+];
+
+/// Returns the paths the Linux sandbox denies reads to: well-known credential
+/// stores under the home directory and `$CODEX_HOME/auth.json`.
+pub fn default_read_denied_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match dirs::home_dir() {
+        Some(home) => HOME_READ_DENIED_PATHS
+            .iter()
+            .map(|path| home.join(path))
+            .collect(),
+        None => Vec::new(),
+    };
+    if let Ok(codex_home) = find_codex_home() {
+        paths.push(codex_home.join("auth.json"));
+    }
+    paths
+}
+
 /// Spawn a shell tool command under the Linux Landlock+seccomp sandbox helper
 /// (codex-linux-sandbox).
 ///
@@ -13,10 +46,13 @@ use tokio::process::Child;
 /// helper accepts a list of `--sandbox-permission`/`-s` flags mirroring the
 /// public CLI. We convert the internal [`SandboxPolicy`] representation into
 /// the equivalent CLI options.
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_command_under_linux_sandbox<P>(
     codex_linux_sandbox_exe: P,
     command: Vec<String>,
     sandbox_policy: &SandboxPolicy,
+    network_proxy: Option<&NetworkProxy>,
+    resource_limits: &ResourceLimits,
     cwd: PathBuf,
     stdio_policy: StdioPolicy,
     env: HashMap<String, String>,
@@ -24,7 +60,14 @@ pub async fn spawn_command_under_linux_sandbox<P>(
 where
     P: AsRef<Path>,
 {
-    let args = create_linux_sandbox_command_args(command, sandbox_policy, &cwd);
+    let proxy_socket = network_proxy.and_then(NetworkProxy::unix_socket);
+    let args = create_linux_sandbox_command_args(
+        command,
+        sandbox_policy,
+        proxy_socket,
+        resource_limits,
+        &cwd,
+    );
     let arg0 = Some("codex-linux-sandbox");
     spawn_child_async(
         codex_linux_sandbox_exe.as_ref().to_path_buf(),
@@ -42,6 +85,8 @@ where
 fn create_linux_sandbox_command_args(
     command: Vec<String>,
     sandbox_policy: &SandboxPolicy,
+    proxy_socket: Option<&Path>,
+    resource_limits: &ResourceLimits,
     cwd: &Path,
 ) -> Vec<String> {
     #[expect(clippy::expect_used)]
@@ -51,13 +96,31 @@ fn create_linux_sandbox_command_args(
     let sandbox_policy_json =
         serde_json::to_string(sandbox_policy).expect("Failed to serialize SandboxPolicy to JSON");
 
-    let mut linux_cmd: Vec<String> = vec![
+    // The deny list is resolved here rather than in the helper because the
+    // child's environment may not carry `HOME` or `CODEX_HOME`.
+    let mut linux_cmd: Vec<String> = Vec::new();
+    for path in default_read_denied_paths() {
+        linux_cmd.push("--deny-read".to_string());
+        linux_cmd.push(path.to_string_lossy().to_string());
+    }
+    if let Some(proxy_socket) = proxy_socket {
+        linux_cmd.push("--proxy-socket".to_string());
+        linux_cmd.push(proxy_socket.to_string_lossy().to_string());
+    }
+    if !resource_limits.is_empty() {
+        #[expect(clippy::expect_used)]
+        let resource_limits_json = serde_json::to_string(resource_limits)
+            .expect("Failed to serialize ResourceLimits to JSON");
+        linux_cmd.push("--resource-limits".to_string());
+        linux_cmd.push(resource_limits_json);
+    }
+    linux_cmd.extend([
         sandbox_policy_cwd,
         sandbox_policy_json,
         // Separator so that command arguments starting with `-` are not parsed as
         // options of the helper itself.
         "--".to_string(),
-    ];
+    ]);
 
     // Append the original tool command.
     linux_cmd.extend(command);
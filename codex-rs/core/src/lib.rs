@@ -6,24 +6,35 @@
 #![deny(clippy::print_stdout, clippy::print_stderr)]
 
 pub mod agents;
+mod anthropic_messages;
 mod apply_patch;
+mod background_jobs;
 mod bash;
+pub mod budget;
 mod chat_completions;
 mod client;
 mod client_common;
 pub mod codex;
 mod codex_conversation;
 pub use codex_conversation::CodexConversation;
+mod command_approvals;
 pub mod config;
 pub mod config_profile;
 pub mod config_types;
+mod content_search;
 mod conversation_history;
 mod environment_context;
 pub mod error;
 pub mod exec;
 pub mod exec_env;
+mod exec_output;
+pub mod exec_policy;
+mod exec_session;
+mod file_tools;
 mod flags;
 pub mod git_info;
+mod git_snapshot;
+mod html_to_markdown;
 mod is_safe_command;
 pub mod landlock;
 mod mcp_connection_manager;
@@ -32,6 +43,7 @@ mod message_history;
 mod model_provider_info;
 pub mod parse_command;
 pub use model_provider_info::BUILT_IN_OSS_MODEL_PROVIDER_ID;
+pub use model_provider_info::ModelFallback;
 pub use model_provider_info::ModelProviderInfo;
 pub use model_provider_info::WireApi;
 pub use model_provider_info::built_in_model_providers;
@@ -41,10 +53,16 @@ pub use conversation_manager::ConversationManager;
 pub use conversation_manager::NewConversation;
 pub mod model_family;
 mod models;
+pub mod network_proxy;
+mod notebook;
 mod openai_model_info;
 mod openai_tools;
+pub mod output_schema;
 pub mod plan_tool;
 mod project_doc;
+mod redact;
+mod repo_map;
+pub mod resource_limits;
 mod rollout;
 pub(crate) mod safety;
 pub mod seatbelt;
@@ -54,8 +72,12 @@ pub mod turn_diff_tracker;
 pub mod user_agent;
 mod user_notification;
 pub mod util;
+mod view_image;
+mod web_tools;
 pub mod workflows;
 pub use apply_patch::CODEX_APPLY_PATCH_ARG1;
+pub use apply_patch::CODEX_APPLY_PATCH_FUZZY_THRESHOLD_ENV_VAR;
+pub use apply_patch::CODEX_APPLY_PATCH_REPORT_RESULTS_ENV_VAR;
 pub use safety::get_platform_sandbox;
 // Re-export the protocol types from the standalone `codex-protocol` crate so existing
 // `codex_core::protocol::...` references continue to work across the workspace.
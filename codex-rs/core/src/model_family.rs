@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use crate::config_types::ModelMetadata;
+
 /// A model family is a group of models that share certain characteristics.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ModelFamily {
@@ -65,6 +69,43 @@ macro_rules! simple_model_family {
     }};
 }
 
+/// Like [`find_family_for_model`], but applies the user's `[models.<slug>]`
+/// entry, if any. The entry's `family` selects the built-in traits to start
+/// from, which lets unknown slugs behave like a known family; its flags then
+/// override those traits. Returns `None` only when the slug is neither built
+/// in nor declared by the user.
+pub fn find_family_for_model_with_metadata(
+    slug: &str,
+    models: &HashMap<String, ModelMetadata>,
+) -> Option<ModelFamily> {
+    let Some(metadata) = models.get(slug) else {
+        return find_family_for_model(slug);
+    };
+
+    let family_name = metadata.family.as_deref().unwrap_or(slug);
+    let mut mf = find_family_for_model(family_name)
+        .or_else(|| find_family_for_model(slug))
+        .unwrap_or_else(|| ModelFamily {
+            slug: slug.to_string(),
+            family: family_name.to_string(),
+            needs_special_apply_patch_instructions: false,
+            supports_reasoning_summaries: false,
+            uses_local_shell_tool: false,
+            uses_apply_patch_tool: false,
+        });
+    mf.slug = slug.to_string();
+    if let Some(family) = metadata.family.as_ref() {
+        mf.family = family.clone();
+    }
+    if let Some(v) = metadata.uses_apply_patch_tool {
+        mf.uses_apply_patch_tool = v;
+    }
+    if let Some(v) = metadata.supports_reasoning_summaries {
+        mf.supports_reasoning_summaries = v;
+    }
+    Some(mf)
+}
+
 /// Returns a `ModelFamily` for the given model slug, or `None` if the slug
 /// does not match any known model family.
 pub fn find_family_for_model(slug: &str) -> Option<ModelFamily> {
@@ -13,6 +13,7 @@ use std::collections::HashMap;
 use std::env::VarError;
 use std::time::Duration;
 
+use crate::budget::ModelPricing;
 use crate::error::EnvVarError;
 const DEFAULT_STREAM_IDLE_TIMEOUT_MS: u64 = 300_000;
 const DEFAULT_STREAM_MAX_RETRIES: u64 = 5;
@@ -21,9 +22,10 @@ const DEFAULT_REQUEST_MAX_RETRIES: u64 = 4;
 /// Wire protocol that the provider speaks. Most third-party services only
 /// implement the classic OpenAI Chat Completions JSON schema, whereas OpenAI
 /// itself (and a handful of others) additionally expose the more modern
-/// *Responses* API. The two protocols use different request/response shapes
-/// and *cannot* be auto-detected at runtime, therefore each provider entry
-/// must declare which one it expects.
+/// *Responses* API, and Anthropic speaks its own *Messages* API. The
+/// protocols use different request/response shapes and *cannot* be
+/// auto-detected at runtime, therefore each provider entry must declare which
+/// one it expects.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum WireApi {
@@ -33,6 +35,9 @@ pub enum WireApi {
     /// Regular Chat Completions compatible with `/v1/chat/completions`.
     #[default]
     Chat,
+
+    /// The Anthropic Messages API exposed at `/v1/messages`.
+    Messages,
 }
 
 /// Serializable representation of a provider definition.
@@ -79,6 +84,63 @@ pub struct ModelProviderInfo {
     /// Whether this provider requires some form of standard authentication (API key, ChatGPT token).
     #[serde(default)]
     pub requires_openai_auth: bool,
+
+    /// Ordered list of `"provider_id:model"` entries (or just `"provider_id"`
+    /// to keep the current model) to switch to when requests to this provider
+    /// keep failing with 5xx or rate-limit errors after all retries.
+    pub fallback: Option<Vec<String>>,
+
+    /// Per-model prices keyed by model slug, used to report cost and enforce
+    /// `max_cost_usd`.
+    pub pricing: Option<HashMap<String, ModelPricing>>,
+
+    /// Whether the provider accepts an output schema as a JSON Schema
+    /// response format. Defaults to `true` for the Responses API and `false`
+    /// otherwise, in which case the schema is given in the instructions.
+    pub supports_output_schema: Option<bool>,
+}
+
+/// A provider/model pair that [`crate::client::ModelClient`] switches to when
+/// the active provider is unavailable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelFallback {
+    /// Key into the `model_providers` map.
+    pub provider_id: String,
+    pub provider: ModelProviderInfo,
+    pub model: String,
+}
+
+impl ModelFallback {
+    /// Resolves `"provider_id:model"` entries against `model_providers`. An
+    /// entry without a `:model` suffix reuses `default_model`.
+    pub fn resolve_chain(
+        entries: &[String],
+        model_providers: &HashMap<String, ModelProviderInfo>,
+        default_model: &str,
+    ) -> std::io::Result<Vec<ModelFallback>> {
+        entries
+            .iter()
+            .map(|entry| {
+                // Split on the first `:` only: model slugs such as
+                // `llama3:8b` may contain colons themselves.
+                let (provider_id, model) = match entry.split_once(':') {
+                    Some((provider_id, model)) => (provider_id.trim(), model.trim()),
+                    None => (entry.trim(), default_model),
+                };
+                let provider = model_providers.get(provider_id).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("Fallback model provider `{provider_id}` not found"),
+                    )
+                })?;
+                Ok(ModelFallback {
+                    provider_id: provider_id.to_string(),
+                    provider: provider.clone(),
+                    model: model.to_string(),
+                })
+            })
+            .collect()
+    }
 }
 
 impl ModelProviderInfo {
@@ -112,7 +174,13 @@ impl ModelProviderInfo {
         let mut builder = client.post(url);
 
         if let Some(auth) = effective_auth.as_ref() {
-            builder = builder.bearer_auth(auth.get_token().await?);
+            let token = auth.get_token().await?;
+            builder = match self.wire_api {
+                // The Messages API authenticates with `x-api-key` rather than
+                // a bearer token.
+                WireApi::Messages => builder.header("x-api-key", token),
+                WireApi::Responses | WireApi::Chat => builder.bearer_auth(token),
+            };
         }
 
         Ok(self.apply_http_headers(builder))
@@ -152,6 +220,7 @@ impl ModelProviderInfo {
         match self.wire_api {
             WireApi::Responses => format!("{base_url}/responses{query_string}"),
             WireApi::Chat => format!("{base_url}/chat/completions{query_string}"),
+            WireApi::Messages => format!("{base_url}/messages{query_string}"),
         }
     }
 
@@ -203,6 +272,11 @@ impl ModelProviderInfo {
         }
     }
 
+    /// Returns the configured price of `model` on this provider, if any.
+    pub fn pricing_for(&self, model: &str) -> Option<ModelPricing> {
+        self.pricing.as_ref()?.get(model).copied()
+    }
+
     /// Effective maximum number of request retries for this provider.
     pub fn request_max_retries(&self) -> u64 {
         self.request_max_retries
@@ -273,6 +347,9 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 stream_max_retries: None,
                 stream_idle_timeout_ms: None,
                 requires_openai_auth: true,
+                fallback: None,
+                pricing: None,
+                supports_output_schema: None,
             },
         ),
         (BUILT_IN_OSS_MODEL_PROVIDER_ID, create_oss_provider()),
@@ -317,6 +394,9 @@ pub fn create_oss_provider_with_base_url(base_url: &str) -> ModelProviderInfo {
         stream_max_retries: None,
         stream_idle_timeout_ms: None,
         requires_openai_auth: false,
+        fallback: None,
+        pricing: None,
+        supports_output_schema: None,
     }
 }
 
@@ -344,6 +424,9 @@ base_url = "http://localhost:11434/v1"
             stream_max_retries: None,
             stream_idle_timeout_ms: None,
             requires_openai_auth: false,
+            fallback: None,
+            pricing: None,
+            supports_output_schema: None,
         };
 
         let provider: ModelProviderInfo = toml::from_str(azure_provider_toml).unwrap();
@@ -373,6 +456,9 @@ query_params = { api-version = "2025-04-01-preview" }
             stream_max_retries: None,
             stream_idle_timeout_ms: None,
             requires_openai_auth: false,
+            fallback: None,
+            pricing: None,
+            supports_output_schema: None,
         };
 
         let provider: ModelProviderInfo = toml::from_str(azure_provider_toml).unwrap();
@@ -405,9 +491,58 @@ env_http_headers = { "X-Example-Env-Header" = "EXAMPLE_ENV_VAR" }
             stream_max_retries: None,
             stream_idle_timeout_ms: None,
             requires_openai_auth: false,
+            fallback: None,
+            pricing: None,
+            supports_output_schema: None,
         };
 
         let provider: ModelProviderInfo = toml::from_str(azure_provider_toml).unwrap();
         assert_eq!(expected_provider, provider);
     }
+
+    #[test]
+    fn test_resolve_fallback_chain() {
+        let provider_toml = r#"
+name = "Ollama"
+base_url = "http://localhost:11434/v1"
+fallback = ["openai:gpt-4.1", "ollama:llama3:8b"]
+        "#;
+        let ollama: ModelProviderInfo = toml::from_str(provider_toml).unwrap();
+        let mut providers = built_in_model_providers();
+        providers.insert("ollama".to_string(), ollama.clone());
+
+        let chain = ModelFallback::resolve_chain(
+            &[
+                "openai:gpt-4.1".to_string(),
+                "ollama:llama3:8b".to_string(),
+                "ollama".to_string(),
+            ],
+            &providers,
+            "default-model",
+        )
+        .unwrap();
+        let resolved: Vec<(&str, &str)> = chain
+            .iter()
+            .map(|f| (f.provider_id.as_str(), f.model.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("openai", "gpt-4.1"),
+                ("ollama", "llama3:8b"),
+                ("ollama", "default-model"),
+            ],
+            resolved
+        );
+        assert_eq!(
+            Some(vec![
+                "openai:gpt-4.1".to_string(),
+                "ollama:llama3:8b".to_string()
+            ]),
+            ollama.fallback
+        );
+
+        let err =
+            ModelFallback::resolve_chain(&["missing".to_string()], &providers, "m").unwrap_err();
+        assert_eq!(std::io::ErrorKind::NotFound, err.kind());
+    }
 }
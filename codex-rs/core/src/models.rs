@@ -169,9 +169,8 @@ impl From<Vec<InputItem>> for ResponseInputItem {
                                 .first()
                                 .map(|m| m.essence_str().to_owned())
                                 .unwrap_or_else(|| "application/octet-stream".to_string());
-                            let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
                             Some(ContentItem::InputImage {
-                                image_url: format!("data:{mime};base64,{encoded}"),
+                                image_url: image_data_url(&mime, &bytes),
                             })
                         }
                         Err(err) => {
@@ -190,6 +189,12 @@ impl From<Vec<InputItem>> for ResponseInputItem {
     }
 }
 
+/// Encodes an image as the `data:` URL sent in [`ContentItem::InputImage`].
+pub(crate) fn image_data_url(mime: &str, bytes: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    format!("data:{mime};base64,{encoded}")
+}
+
 /// If the `name` of a `ResponseItem::FunctionCall` is either `container.exec`
 /// or shell`, the `arguments` field should deserialize to this struct.
 #[derive(Deserialize, Debug, Clone, PartialEq)]
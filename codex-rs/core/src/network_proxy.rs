@@ -0,0 +1,493 @@
+//! HTTP(S) proxy that lets sandboxed commands reach an allowlist of domains.
+//!
+//! When a [`SandboxPolicy`](crate::protocol::SandboxPolicy) blocks network
+//! access but lists `allowed_domains`, Codex starts a [`NetworkProxy`] for the
+//! duration of the command and points the child at it through `HTTP_PROXY` and
+//! friends. The sandbox only lets the child reach the proxy (over loopback on
+//! macOS, over a Unix socket bridged into a private network namespace on
+//! Linux), so every outbound connection goes through [`domain_is_allowed`].
+//!
+//! Both `CONNECT host:port` tunnels (used for HTTPS) and plain HTTP requests in
+//! absolute form (`GET http://host/path`) are supported.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tempfile::TempDir;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::exec::StdoutStream;
+use crate::protocol::BackgroundEventEvent;
+use crate::protocol::Event;
+use crate::protocol::EventMsg;
+
+/// Environment variables pointed at the proxy in the sandboxed child.
+pub const PROXY_ENV_VARS: &[&str] = &[
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "ALL_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "all_proxy",
+];
+
+/// Environment variables removed from the sandboxed child so that no host
+/// bypasses the proxy (a direct connection would fail anyway).
+pub const NO_PROXY_ENV_VARS: &[&str] = &["NO_PROXY", "no_proxy"];
+
+/// Upper bound on the size of a request line plus headers.
+const MAX_REQUEST_HEAD_BYTES: usize = 16 * 1024;
+
+const CONNECTION_ESTABLISHED_RESPONSE: &[u8] = b"HTTP/1.1 200 Connection Established\r\n\r\n";
+const BAD_REQUEST_RESPONSE: &[u8] =
+    b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+const FORBIDDEN_RESPONSE: &[u8] = b"HTTP/1.1 403 Forbidden\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\nBlocked by the Codex sandbox: domain not in allowed_domains\n";
+const BAD_GATEWAY_RESPONSE: &[u8] =
+    b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// A running proxy. Stops accepting connections when dropped.
+pub struct NetworkProxy {
+    tcp_addr: SocketAddr,
+    unix_socket: Option<PathBuf>,
+    /// Holds the Unix socket until the proxy is dropped.
+    _socket_dir: TempDir,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+struct ProxyState {
+    allowed_domains: Vec<String>,
+    events: Option<StdoutStream>,
+}
+
+impl NetworkProxy {
+    /// Starts a proxy that forwards connections to `allowed_domains` and
+    /// refuses everything else. Refused connections are reported as
+    /// `BackgroundEvent`s on `events` when provided.
+    pub async fn start(
+        allowed_domains: Vec<String>,
+        events: Option<StdoutStream>,
+    ) -> io::Result<Self> {
+        let state = Arc::new(ProxyState {
+            allowed_domains,
+            events,
+        });
+        let socket_dir = tempfile::Builder::new().prefix("codex-proxy-").tempdir()?;
+
+        let tcp_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let tcp_addr = tcp_listener.local_addr()?;
+        let mut tasks = vec![tokio::spawn(serve_tcp(tcp_listener, state.clone()))];
+
+        #[cfg(unix)]
+        let unix_socket = {
+            let unix_socket = socket_dir.path().join("proxy.sock");
+            let unix_listener = tokio::net::UnixListener::bind(&unix_socket)?;
+            tasks.push(tokio::spawn(serve_unix(unix_listener, state)));
+            Some(unix_socket)
+        };
+        #[cfg(not(unix))]
+        let unix_socket = None;
+
+        Ok(Self {
+            tcp_addr,
+            unix_socket,
+            _socket_dir: socket_dir,
+            tasks,
+        })
+    }
+
+    /// Loopback address the proxy listens on.
+    pub fn tcp_addr(&self) -> SocketAddr {
+        self.tcp_addr
+    }
+
+    /// Unix socket the proxy listens on (Unix platforms only).
+    pub fn unix_socket(&self) -> Option<&Path> {
+        self.unix_socket.as_deref()
+    }
+
+    /// Points the proxy environment variables in `env` at this proxy's
+    /// loopback address.
+    pub fn apply_to_env(&self, env: &mut HashMap<String, String>) {
+        let url = format!("http://{}", self.tcp_addr);
+        for name in PROXY_ENV_VARS {
+            env.insert(name.to_string(), url.clone());
+        }
+        for name in NO_PROXY_ENV_VARS {
+            env.remove(*name);
+        }
+    }
+}
+
+impl Drop for NetworkProxy {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn serve_tcp(listener: TcpListener, state: Arc<ProxyState>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(stream, state.clone()));
+            }
+            Err(e) => warn!("network proxy failed to accept a connection: {e}"),
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn serve_unix(listener: tokio::net::UnixListener, state: Arc<ProxyState>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(stream, state.clone()));
+            }
+            Err(e) => warn!("network proxy failed to accept a connection: {e}"),
+        }
+    }
+}
+
+/// What the client asked the proxy to do.
+#[derive(Debug, PartialEq)]
+enum ProxyRequest {
+    /// `CONNECT host:port`: open a raw tunnel.
+    Connect { host: String, port: u16 },
+    /// A plain HTTP request in absolute form, to be forwarded with its
+    /// request line rewritten to origin form.
+    Forward {
+        host: String,
+        port: u16,
+        request_head: Vec<u8>,
+    },
+}
+
+async fn handle_connection<S>(mut client: S, state: Arc<ProxyState>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Err(e) = proxy_connection(&mut client, &state).await {
+        tracing::debug!("network proxy connection ended with an error: {e}");
+    }
+}
+
+async fn proxy_connection<S>(client: &mut S, state: &ProxyState) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (head, body_prefix) = read_request_head(client).await?;
+    let Some(request) = parse_request_head(&head) else {
+        return client.write_all(BAD_REQUEST_RESPONSE).await;
+    };
+    let (host, port) = match &request {
+        ProxyRequest::Connect { host, port } | ProxyRequest::Forward { host, port, .. } => {
+            (host.as_str(), *port)
+        }
+    };
+
+    if !domain_is_allowed(host, &state.allowed_domains) {
+        state.report_denied(host, port).await;
+        return client.write_all(FORBIDDEN_RESPONSE).await;
+    }
+
+    let mut upstream = match TcpStream::connect((host, port)).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            warn!("network proxy failed to connect to {host}:{port}: {e}");
+            return client.write_all(BAD_GATEWAY_RESPONSE).await;
+        }
+    };
+    match &request {
+        ProxyRequest::Connect { .. } => client.write_all(CONNECTION_ESTABLISHED_RESPONSE).await?,
+        ProxyRequest::Forward { request_head, .. } => upstream.write_all(request_head).await?,
+    }
+    upstream.write_all(&body_prefix).await?;
+    tokio::io::copy_bidirectional(client, &mut upstream).await?;
+    Ok(())
+}
+
+impl ProxyState {
+    async fn report_denied(&self, host: &str, port: u16) {
+        let message =
+            format!("Sandbox blocked network access to {host}:{port} (not in allowed_domains)");
+        match &self.events {
+            Some(stream) => {
+                let event = Event {
+                    id: stream.sub_id.clone(),
+                    msg: EventMsg::BackgroundEvent(BackgroundEventEvent { message }),
+                };
+                let _ = stream.tx_event.send(event).await;
+            }
+            None => warn!("{message}"),
+        }
+    }
+}
+
+/// Reads up to and including the blank line that ends the request headers.
+/// Returns the head and whatever bytes of the body were read past it.
+async fn read_request_head<S>(client: &mut S) -> io::Result<(Vec<u8>, Vec<u8>)>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let body_prefix = buf.split_off(end + 4);
+            return Ok((buf, body_prefix));
+        }
+        if buf.len() > MAX_REQUEST_HEAD_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request head too large",
+            ));
+        }
+        let n = client.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+fn parse_request_head(head: &[u8]) -> Option<ProxyRequest> {
+    let head = std::str::from_utf8(head).ok()?;
+    let (request_line, headers) = head.split_once("\r\n")?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?;
+    let target = parts.next()?;
+    let version = parts.next()?;
+
+    if method.eq_ignore_ascii_case("CONNECT") {
+        let (host, port) = split_host_port(target, None)?;
+        return Some(ProxyRequest::Connect { host, port });
+    }
+
+    let scheme_len = "http://".len();
+    if !target.get(..scheme_len)?.eq_ignore_ascii_case("http://") {
+        return None;
+    }
+    let rest = &target[scheme_len..];
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let (host, port) = split_host_port(authority, Some(80))?;
+
+    let mut request_head = format!("{method} {path} {version}\r\n");
+    for line in headers.split("\r\n") {
+        // Proxy-* headers are meant for us, not the origin server. The final
+        // empty line is re-added below.
+        if line.is_empty() || line.to_ascii_lowercase().starts_with("proxy-") {
+            continue;
+        }
+        request_head.push_str(line);
+        request_head.push_str("\r\n");
+    }
+    request_head.push_str("\r\n");
+    Some(ProxyRequest::Forward {
+        host,
+        port,
+        request_head: request_head.into_bytes(),
+    })
+}
+
+/// Splits `host:port` (with `[...]` around IPv6 literals), falling back to
+/// `default_port` when no port is given.
+fn split_host_port(authority: &str, default_port: Option<u16>) -> Option<(String, u16)> {
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        (host, rest.strip_prefix(':'))
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None => default_port?,
+    };
+    Some((host.to_string(), port))
+}
+
+/// Returns `true` if `host` is one of `allowed_domains` or a subdomain of one.
+/// Entries may be written as `example.com`, `.example.com` or
+/// `*.example.com`; all three allow `example.com` and its subdomains.
+/// Matching is case-insensitive.
+pub fn domain_is_allowed(host: &str, allowed_domains: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    allowed_domains.iter().any(|allowed| {
+        let allowed = allowed
+            .trim_start_matches("*.")
+            .trim_start_matches('.')
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        !allowed.is_empty()
+            && (host == allowed
+                || host
+                    .strip_suffix(allowed.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.')))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn domains(domains: &[&str]) -> Vec<String> {
+        domains.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn matches_domains_and_subdomains() {
+        let allowed = domains(&["crates.io", "*.internal.example", ".Registry.Example"]);
+        assert!(domain_is_allowed("crates.io", &allowed));
+        assert!(domain_is_allowed("static.crates.io", &allowed));
+        assert!(domain_is_allowed("STATIC.CRATES.IO.", &allowed));
+        assert!(domain_is_allowed("internal.example", &allowed));
+        assert!(domain_is_allowed("a.b.internal.example", &allowed));
+        assert!(domain_is_allowed("registry.example", &allowed));
+
+        assert!(!domain_is_allowed("evilcrates.io", &allowed));
+        assert!(!domain_is_allowed("crates.io.evil.com", &allowed));
+        assert!(!domain_is_allowed("example", &allowed));
+        assert!(!domain_is_allowed("127.0.0.1", &allowed));
+    }
+
+    #[test]
+    fn parses_connect_request() {
+        assert_eq!(
+            Some(ProxyRequest::Connect {
+                host: "crates.io".to_string(),
+                port: 443,
+            }),
+            parse_request_head(b"CONNECT crates.io:443 HTTP/1.1\r\nHost: crates.io:443\r\n\r\n")
+        );
+        assert_eq!(
+            Some(ProxyRequest::Connect {
+                host: "::1".to_string(),
+                port: 8443,
+            }),
+            parse_request_head(b"CONNECT [::1]:8443 HTTP/1.1\r\n\r\n")
+        );
+        // CONNECT requires an explicit port.
+        assert_eq!(
+            None,
+            parse_request_head(b"CONNECT crates.io HTTP/1.1\r\n\r\n")
+        );
+    }
+
+    #[test]
+    fn rewrites_absolute_form_request_to_origin_form() {
+        let head = b"GET http://crates.io/api/v1/crates?q=serde HTTP/1.1\r\nHost: crates.io\r\nProxy-Connection: keep-alive\r\nAccept: */*\r\n\r\n";
+        assert_eq!(
+            Some(ProxyRequest::Forward {
+                host: "crates.io".to_string(),
+                port: 80,
+                request_head:
+                    b"GET /api/v1/crates?q=serde HTTP/1.1\r\nHost: crates.io\r\nAccept: */*\r\n\r\n"
+                        .to_vec(),
+            }),
+            parse_request_head(head)
+        );
+        assert_eq!(
+            None,
+            parse_request_head(b"GET /relative HTTP/1.1\r\nHost: crates.io\r\n\r\n")
+        );
+    }
+
+    /// Starts a server on loopback that answers every connection with
+    /// `response` and returns its port.
+    async fn start_origin_server(response: &'static [u8]) -> u16 {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = read_request_head(&mut stream).await;
+                let _ = stream.write_all(response).await;
+            }
+        });
+        port
+    }
+
+    async fn send_through_proxy(proxy: &NetworkProxy, request: String) -> String {
+        let mut stream = TcpStream::connect(proxy.tcp_addr()).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn forwards_allowed_and_reports_denied_connections() {
+        let port = start_origin_server(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+        )
+        .await;
+        let (tx_event, rx_event) = async_channel::unbounded();
+        let proxy = NetworkProxy::start(
+            domains(&["localhost"]),
+            Some(StdoutStream {
+                sub_id: "sub".to_string(),
+                call_id: "call".to_string(),
+                tx_event,
+            }),
+        )
+        .await
+        .unwrap();
+
+        let response = send_through_proxy(
+            &proxy,
+            format!("GET http://localhost:{port}/ HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("hello"), "{response}");
+
+        let response = send_through_proxy(
+            &proxy,
+            format!("CONNECT localhost:{port} HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n"),
+        )
+        .await;
+        assert!(
+            response.starts_with("HTTP/1.1 200 Connection Established\r\n\r\nHTTP/1.1 200 OK"),
+            "{response}"
+        );
+        assert!(rx_event.is_empty());
+
+        let response =
+            send_through_proxy(&proxy, format!("CONNECT 127.0.0.1:{port} HTTP/1.1\r\n\r\n")).await;
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden"), "{response}");
+        let event = rx_event.recv().await.unwrap();
+        assert_eq!("sub", event.id);
+        match event.msg {
+            EventMsg::BackgroundEvent(BackgroundEventEvent { message }) => assert_eq!(
+                format!(
+                    "Sandbox blocked network access to 127.0.0.1:{port} (not in allowed_domains)"
+                ),
+                message
+            ),
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+}
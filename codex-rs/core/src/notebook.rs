@@ -0,0 +1,711 @@
+//! The `read_notebook` and `edit_notebook` tools: cell-addressed access to
+//! Jupyter notebooks, so the model never has to patch their JSON by hand.
+//!
+//! Notebooks are handled as [`serde_json::Value`]s so that metadata and
+//! fields this module does not know about are written back unchanged. Edits
+//! become [`FileEdit`]s and go through the same approval and patch events as
+//! `edit_file`. [`render_for_diff`] is used by the turn diff to show notebook
+//! changes cell by cell rather than as JSON.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::file_tools::FileEdit;
+use crate::file_tools::ReadScope;
+use crate::file_tools::function_output;
+use crate::file_tools::parse_error;
+use crate::file_tools::resolve_symlinks;
+use crate::models::ResponseInputItem;
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+
+/// Longer outputs are cut off in `read_notebook`, since dataframes and logs
+/// can be very large.
+const MAX_OUTPUT_CHARS: usize = 2000;
+
+#[derive(Deserialize, Debug, Clone)]
+struct ReadNotebookParams {
+    path: String,
+    #[serde(default = "default_include_outputs")]
+    include_outputs: bool,
+}
+
+fn default_include_outputs() -> bool {
+    true
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum NotebookOperation {
+    Insert,
+    Replace,
+    Delete,
+    Edit,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum CellType {
+    Code,
+    Markdown,
+    Raw,
+}
+
+impl CellType {
+    fn as_str(self) -> &'static str {
+        match self {
+            CellType::Code => "code",
+            CellType::Markdown => "markdown",
+            CellType::Raw => "raw",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct EditNotebookParams {
+    path: String,
+    operation: NotebookOperation,
+    cell: usize,
+    #[serde(default)]
+    cell_type: Option<CellType>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    old_string: Option<String>,
+    #[serde(default)]
+    new_string: Option<String>,
+}
+
+pub(crate) static NOTEBOOK_TOOLS: LazyLock<Vec<OpenAiTool>> =
+    LazyLock::new(|| vec![read_notebook_tool(), edit_notebook_tool()]);
+
+fn read_notebook_tool() -> OpenAiTool {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "path".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Notebook to read, absolute or relative to the working directory.".to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "include_outputs".to_string(),
+        JsonSchema::Boolean {
+            description: Some("Show the text outputs of code cells. Defaults to true.".to_string()),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "read_notebook".to_string(),
+        description: "Reads a Jupyter notebook (.ipynb) and shows each cell with its 0-based index, type and source, followed by its outputs. Use the indices with edit_notebook. Never edit a notebook's JSON with apply_patch or the shell.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(vec!["path".to_string()]),
+            additional_properties: Some(false),
+        },
+    })
+}
+
+fn edit_notebook_tool() -> OpenAiTool {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "path".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Notebook to edit, absolute or relative to the working directory.".to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "operation".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "One of \"insert\" (add a new cell before index cell, or at the end when cell is the number of cells), \"replace\" (replace the source and optionally the type of a cell), \"delete\" (remove a cell) or \"edit\" (replace old_string with new_string in the source of a cell)."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "cell".to_string(),
+        JsonSchema::Number {
+            description: Some("0-based index of the cell, as shown by read_notebook.".to_string()),
+        },
+    );
+    properties.insert(
+        "cell_type".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "\"code\", \"markdown\" or \"raw\". Used by insert, where it defaults to \"code\", and by replace, where it defaults to the current type."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "source".to_string(),
+        JsonSchema::String {
+            description: Some("The whole source of the cell, for insert and replace.".to_string()),
+        },
+    );
+    properties.insert(
+        "old_string".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "For edit: exact text to replace, which must occur exactly once in the cell."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "new_string".to_string(),
+        JsonSchema::String {
+            description: Some("For edit: text to replace old_string with.".to_string()),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "edit_notebook".to_string(),
+        description: "Changes one cell of a Jupyter notebook (.ipynb) and writes the notebook back as valid JSON, keeping its metadata. Inserting or deleting a cell shifts the indices of the cells after it, so read the notebook again before further edits. The outputs of a code cell are cleared when its source changes. Edits outside the writable roots need the user's approval.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(vec![
+                "path".to_string(),
+                "operation".to_string(),
+                "cell".to_string(),
+            ]),
+            additional_properties: Some(false),
+        },
+    })
+}
+
+pub(crate) async fn handle_read_notebook(
+    scope: &ReadScope,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<ReadNotebookParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => return parse_error(call_id, e),
+    };
+    let path = match scope.resolve(Path::new(&params.path)) {
+        Ok(path) => path,
+        Err(message) => return function_output(call_id, Err(message)),
+    };
+    let result = match tokio::fs::read_to_string(&path).await {
+        Ok(text) => parse_notebook(&path, &text)
+            .map(|notebook| render_notebook(&notebook, params.include_outputs)),
+        Err(e) => Err(format!("failed to read {}: {e}", path.display())),
+    };
+    function_output(call_id, result)
+}
+
+/// Works out the new contents of the notebook an `edit_notebook` call
+/// targets. As with `edit_file`, the path has its symlinks resolved.
+pub(crate) fn prepare_notebook_edit(
+    cwd: &Path,
+    params: EditNotebookParams,
+) -> Result<FileEdit, String> {
+    let path = resolve_symlinks(&cwd.join(&params.path));
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let mut notebook = parse_notebook(&path, &text)?;
+    let with_ids = notebook_has_cell_ids(&notebook);
+    let Some(cells) = notebook.get_mut("cells").and_then(Value::as_array_mut) else {
+        return Err(format!("{} has no cells array", path.display()));
+    };
+
+    let index = params.cell;
+    let count = cells.len();
+    let out_of_range = || {
+        format!(
+            "cell {index} does not exist; {} has {count} cells, numbered from 0",
+            path.display()
+        )
+    };
+    let summary = match params.operation {
+        NotebookOperation::Insert => {
+            if index > count {
+                return Err(out_of_range());
+            }
+            let source = required(params.source, "source", "insert")?;
+            let cell_type = params.cell_type.unwrap_or(CellType::Code);
+            cells.insert(index, new_cell(cell_type, &source, with_ids));
+            format!(
+                "Inserted {} cell {index} into {}.",
+                cell_type.as_str(),
+                path.display()
+            )
+        }
+        NotebookOperation::Replace => {
+            let cell = cells.get_mut(index).ok_or_else(out_of_range)?;
+            let source = required(params.source, "source", "replace")?;
+            if let Some(cell_type) = params.cell_type
+                && cell.get("cell_type").and_then(Value::as_str) != Some(cell_type.as_str())
+            {
+                let mut replacement = new_cell(cell_type, &source, false);
+                for key in ["id", "metadata"] {
+                    if let (Some(value), Some(fields)) =
+                        (cell.get(key), replacement.as_object_mut())
+                    {
+                        fields.insert(key.to_string(), value.clone());
+                    }
+                }
+                *cell = replacement;
+            } else {
+                set_cell_source(cell, &source);
+            }
+            format!("Replaced cell {index} of {}.", path.display())
+        }
+        NotebookOperation::Delete => {
+            if index >= count {
+                return Err(out_of_range());
+            }
+            cells.remove(index);
+            format!(
+                "Deleted cell {index} of {}; the cells after it moved up one index.",
+                path.display()
+            )
+        }
+        NotebookOperation::Edit => {
+            let cell = cells.get_mut(index).ok_or_else(out_of_range)?;
+            let old_string = required(params.old_string, "old_string", "edit")?;
+            let new_string = required(params.new_string, "new_string", "edit")?;
+            if old_string.is_empty() {
+                return Err(
+                    "old_string must not be empty; use replace to set the whole source".to_string(),
+                );
+            }
+            let source = cell_source(cell);
+            match source.matches(&old_string).count() {
+                0 => {
+                    return Err(format!(
+                        "old_string was not found in cell {index}; read the notebook again and copy the text exactly"
+                    ));
+                }
+                1 => {}
+                n => {
+                    return Err(format!(
+                        "old_string occurs {n} times in cell {index}; include more of the cell to make it unique"
+                    ));
+                }
+            }
+            set_cell_source(cell, &source.replacen(&old_string, &new_string, 1));
+            format!("Edited cell {index} of {}.", path.display())
+        }
+    };
+
+    let new_content = serialize_notebook(&notebook, &text)?;
+    if new_content == text {
+        return Err("the edit does not change the notebook".to_string());
+    }
+    Ok(FileEdit::update(cwd, path, &text, new_content, summary))
+}
+
+fn required(value: Option<String>, name: &str, operation: &str) -> Result<String, String> {
+    value.ok_or_else(|| format!("{operation} needs {name}"))
+}
+
+fn parse_notebook(path: &Path, text: &str) -> Result<Value, String> {
+    let notebook: Value = serde_json::from_str(text)
+        .map_err(|e| format!("{} is not valid notebook JSON: {e}", path.display()))?;
+    if !notebook.get("cells").is_some_and(Value::is_array) {
+        return Err(format!(
+            "{} is not a Jupyter notebook: it has no cells array",
+            path.display()
+        ));
+    }
+    Ok(notebook)
+}
+
+/// Cells carry an `id` from nbformat 4.5 on.
+fn notebook_has_cell_ids(notebook: &Value) -> bool {
+    let major = notebook
+        .get("nbformat")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    let minor = notebook
+        .get("nbformat_minor")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    major > 4 || (major == 4 && minor >= 5)
+}
+
+fn new_cell(cell_type: CellType, source: &str, with_id: bool) -> Value {
+    let mut cell = match cell_type {
+        CellType::Code => json!({
+            "cell_type": "code",
+            "execution_count": null,
+            "metadata": {},
+            "outputs": [],
+            "source": source_lines(source),
+        }),
+        CellType::Markdown | CellType::Raw => json!({
+            "cell_type": cell_type.as_str(),
+            "metadata": {},
+            "source": source_lines(source),
+        }),
+    };
+    if with_id && let Some(fields) = cell.as_object_mut() {
+        let id = Uuid::new_v4().simple().to_string();
+        fields.insert("id".to_string(), Value::String(id[..8].to_string()));
+    }
+    cell
+}
+
+/// Sets the source of `cell`, clearing the outputs of a code cell since they
+/// no longer match it.
+fn set_cell_source(cell: &mut Value, source: &str) {
+    let Some(fields) = cell.as_object_mut() else {
+        return;
+    };
+    fields.insert("source".to_string(), source_lines(source));
+    if fields.get("cell_type").and_then(Value::as_str) == Some("code") {
+        fields.insert("outputs".to_string(), json!([]));
+        fields.insert("execution_count".to_string(), Value::Null);
+    }
+}
+
+/// nbformat stores multiline strings either as one string or as a list of
+/// lines that keep their newlines.
+fn multiline_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn cell_source(cell: &Value) -> String {
+    multiline_text(cell.get("source"))
+}
+
+/// The list-of-lines form Jupyter writes sources in.
+fn source_lines(source: &str) -> Value {
+    Value::Array(
+        source
+            .split_inclusive('\n')
+            .map(|line| Value::String(line.to_string()))
+            .collect(),
+    )
+}
+
+/// Serializes `notebook` the way Jupyter does, with the indentation and
+/// trailing newline of the `original` file, so that unchanged parts of the
+/// notebook stay byte for byte the same.
+fn serialize_notebook(notebook: &Value, original: &str) -> Result<String, String> {
+    let indent = original
+        .lines()
+        .nth(1)
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .filter(|indent| *indent > 0)
+        .unwrap_or(1);
+    let indent = " ".repeat(indent);
+    let mut out = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    notebook
+        .serialize(&mut serializer)
+        .map_err(|e| format!("failed to serialize the notebook: {e}"))?;
+    let mut text =
+        String::from_utf8(out).map_err(|e| format!("failed to serialize the notebook: {e}"))?;
+    if original.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+fn render_notebook(notebook: &Value, include_outputs: bool) -> String {
+    let cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    if cells.is_empty() {
+        return "(notebook has no cells)".to_string();
+    }
+
+    let mut out = String::new();
+    for (index, cell) in cells.iter().enumerate() {
+        let cell_type = cell
+            .get("cell_type")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown");
+        out.push_str(&format!("<cell index={index} type={cell_type}"));
+        if let Some(count) = cell.get("execution_count").and_then(Value::as_u64) {
+            out.push_str(&format!(" execution_count={count}"));
+        }
+        out.push_str(">\n");
+        push_line(&mut out, &cell_source(cell));
+
+        let outputs = cell
+            .get("outputs")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if include_outputs {
+            for output in outputs {
+                out.push_str("<output>\n");
+                push_line(&mut out, &render_output(output));
+                out.push_str("</output>\n");
+            }
+        } else if !outputs.is_empty() {
+            out.push_str(&format!("({} outputs hidden)\n", outputs.len()));
+        }
+        out.push_str("</cell>\n");
+    }
+    out
+}
+
+fn push_line(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// The text of a cell output, or a note on its type when it has none.
+fn render_output(output: &Value) -> String {
+    let text = match output.get("output_type").and_then(Value::as_str) {
+        Some("stream") => multiline_text(output.get("text")),
+        Some("error") => format!(
+            "{}: {}",
+            output
+                .get("ename")
+                .and_then(Value::as_str)
+                .unwrap_or("Error"),
+            output.get("evalue").and_then(Value::as_str).unwrap_or("")
+        ),
+        _ => {
+            let data = output.get("data");
+            match data.and_then(|data| data.get("text/plain")) {
+                Some(text) => multiline_text(Some(text)),
+                None => {
+                    let mime_types: Vec<&str> = data
+                        .and_then(Value::as_object)
+                        .map(|data| data.keys().map(String::as_str).collect())
+                        .unwrap_or_default();
+                    format!("[{} output]", mime_types.join(", "))
+                }
+            }
+        }
+    };
+    match text.char_indices().nth(MAX_OUTPUT_CHARS) {
+        Some((cut, _)) => format!("{}… (output truncated)", &text[..cut]),
+        None => text,
+    }
+}
+
+/// Renders the cells of a notebook as text for the turn diff, in the
+/// "percent" format of jupytext, so a change to a cell shows up as a change
+/// to its lines. Outputs are left out. Returns `None` when `text` is not a
+/// notebook.
+pub(crate) fn render_for_diff(text: &str) -> Option<String> {
+    let notebook: Value = serde_json::from_str(text).ok()?;
+    let cells = notebook.get("cells")?.as_array()?;
+    let mut out = String::new();
+    for cell in cells {
+        let cell_type = cell
+            .get("cell_type")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown");
+        out.push_str(&format!("# %% [{cell_type}]\n"));
+        push_line(&mut out, &cell_source(cell));
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SandboxPolicy;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    const NOTEBOOK: &str = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "id": "a1",
+   "metadata": {},
+   "source": [
+    "# Sales"
+   ]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 3,
+   "id": "b2",
+   "metadata": {
+    "tags": []
+   },
+   "outputs": [
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "42\n"
+     ]
+    }
+   ],
+   "source": [
+    "x = 40 + 2\n",
+    "print(x)"
+   ]
+  }
+ ],
+ "metadata": {
+  "kernelspec": {
+   "display_name": "Python 3",
+   "language": "python",
+   "name": "python3"
+  }
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"##;
+
+    fn params(operation: NotebookOperation, cell: usize) -> EditNotebookParams {
+        EditNotebookParams {
+            path: "sales.ipynb".to_string(),
+            operation,
+            cell,
+            cell_type: None,
+            source: None,
+            old_string: None,
+            new_string: None,
+        }
+    }
+
+    fn edit(dir: &Path, params: EditNotebookParams) -> Result<Value, String> {
+        let file_edit = prepare_notebook_edit(dir, params)?;
+        file_edit.write().unwrap();
+        let text = fs::read_to_string(dir.join("sales.ipynb")).unwrap();
+        Ok(serde_json::from_str(&text).unwrap())
+    }
+
+    #[test]
+    fn renders_cells_with_indices_and_outputs() {
+        let notebook = parse_notebook(Path::new("sales.ipynb"), NOTEBOOK).unwrap();
+
+        assert_eq!(
+            render_notebook(&notebook, true),
+            "<cell index=0 type=markdown>\n# Sales\n</cell>\n<cell index=1 type=code execution_count=3>\nx = 40 + 2\nprint(x)\n<output>\n42\n</output>\n</cell>\n"
+        );
+        assert!(render_notebook(&notebook, false).contains("(1 outputs hidden)\n"));
+        assert!(parse_notebook(Path::new("a.ipynb"), "{\"cells\": 1}").is_err());
+    }
+
+    #[tokio::test]
+    async fn reads_only_notebooks_the_sandbox_can_read() {
+        let readable = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(readable.path().join("sales.ipynb"), NOTEBOOK).unwrap();
+        fs::write(outside.path().join("sales.ipynb"), NOTEBOOK).unwrap();
+        let scope = ReadScope {
+            cwd: readable.path().to_path_buf(),
+            sandbox_policy: SandboxPolicy::ReadOnly {
+                readable_roots: vec![readable.path().to_path_buf()],
+            },
+            output_dir: readable.path().join("output"),
+        };
+        let read = async |path: &Path| {
+            let arguments = serde_json::json!({ "path": path }).to_string();
+            match handle_read_notebook(&scope, arguments, "call".to_string()).await {
+                ResponseInputItem::FunctionCallOutput { output, .. } => output.content,
+                other => panic!("expected a function call output, got {other:?}"),
+            }
+        };
+
+        let content = read(Path::new("sales.ipynb")).await;
+        assert!(content.contains("# Sales"), "{content}");
+        let content = read(&outside.path().join("sales.ipynb")).await;
+        assert!(
+            content.contains("outside the folders the sandbox can read"),
+            "{content}"
+        );
+    }
+
+    #[test]
+    fn inserts_replaces_and_deletes_cells() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("sales.ipynb"), NOTEBOOK).unwrap();
+
+        let mut insert = params(NotebookOperation::Insert, 2);
+        insert.source = Some("x * 2\n".to_string());
+        let notebook = edit(dir.path(), insert).unwrap();
+        let cell = &notebook["cells"][2];
+        assert_eq!(cell["cell_type"], "code");
+        assert_eq!(cell["source"], json!(["x * 2\n"]));
+        assert_eq!(cell["outputs"], json!([]));
+        assert_eq!(cell["id"].as_str().map(str::len), Some(8));
+
+        let mut replace = params(NotebookOperation::Replace, 1);
+        replace.cell_type = Some(CellType::Markdown);
+        replace.source = Some("Note".to_string());
+        let notebook = edit(dir.path(), replace).unwrap();
+        assert_eq!(
+            notebook["cells"][1],
+            json!({
+                "cell_type": "markdown",
+                "id": "b2",
+                "metadata": {"tags": []},
+                "source": ["Note"],
+            })
+        );
+
+        let notebook = edit(dir.path(), params(NotebookOperation::Delete, 0)).unwrap();
+        assert_eq!(notebook["cells"].as_array().map(Vec::len), Some(2));
+        assert_eq!(notebook["cells"][0]["id"], "b2");
+        assert_eq!(notebook["metadata"]["kernelspec"]["name"], "python3");
+
+        let err = edit(dir.path(), params(NotebookOperation::Delete, 2)).unwrap_err();
+        assert!(err.contains("cell 2 does not exist"), "{err}");
+    }
+
+    #[test]
+    fn edits_a_cell_source_and_keeps_the_rest_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("sales.ipynb"), NOTEBOOK).unwrap();
+
+        let mut params = params(NotebookOperation::Edit, 1);
+        params.old_string = Some("40 + 2".to_string());
+        params.new_string = Some("6 * 7".to_string());
+        let file_edit = prepare_notebook_edit(dir.path(), params.clone()).unwrap();
+        file_edit.write().unwrap();
+
+        let expected = NOTEBOOK
+            .replace("40 + 2", "6 * 7")
+            .replace("\"execution_count\": 3", "\"execution_count\": null")
+            .replace(
+                "   \"outputs\": [\n    {\n     \"name\": \"stdout\",\n     \"output_type\": \"stream\",\n     \"text\": [\n      \"42\\n\"\n     ]\n    }\n   ],",
+                "   \"outputs\": [],",
+            );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("sales.ipynb")).unwrap(),
+            expected
+        );
+
+        let err = prepare_notebook_edit(dir.path(), params).err().unwrap();
+        assert!(err.contains("old_string was not found in cell 1"), "{err}");
+    }
+
+    #[test]
+    fn renders_cells_for_diff() {
+        assert_eq!(
+            render_for_diff(NOTEBOOK).unwrap(),
+            "# %% [markdown]\n# Sales\n# %% [code]\nx = 40 + 2\nprint(x)\n"
+        );
+        assert_eq!(render_for_diff("not json"), None);
+    }
+}
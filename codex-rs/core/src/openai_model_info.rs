@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use crate::config_types::ModelMetadata;
 use crate::model_family::ModelFamily;
 
 /// Metadata about a model, particularly OpenAI models.
@@ -14,17 +17,42 @@ pub(crate) struct ModelInfo {
     pub(crate) max_output_tokens: u64,
 }
 
+/// Returns `(context_window, max_output_tokens)` for `model_family`. Each
+/// value comes from the user's `[models.<slug>]` entry when set there, and
+/// otherwise from the built-in table. A declared model that is unknown to the
+/// table inherits the limits of its `family`, if that one is known.
+pub(crate) fn get_model_limits(
+    model_family: &ModelFamily,
+    models: &HashMap<String, ModelMetadata>,
+) -> (Option<u64>, Option<u64>) {
+    let metadata = models.get(&model_family.slug);
+    let info = get_model_info(model_family).or_else(|| {
+        metadata
+            .and_then(|m| m.family.as_deref())
+            .and_then(get_model_info_for_slug)
+    });
+    (
+        metadata
+            .and_then(|m| m.context_window)
+            .or(info.as_ref().map(|i| i.context_window)),
+        metadata
+            .and_then(|m| m.max_output_tokens)
+            .or(info.as_ref().map(|i| i.max_output_tokens)),
+    )
+}
+
 pub(crate) fn get_model_info(model_family: &ModelFamily) -> Option<ModelInfo> {
-    let slug = model_family.slug.as_str();
+    get_model_info_for_slug(&model_family.slug)
+}
+
+fn get_model_info_for_slug(slug: &str) -> Option<ModelInfo> {
     match slug {
         // OSS models have a 128k shared token pool.
         // Arbitrarily splitting it: 3/4 input context, 1/4 output.
         // https://openai.com/index/gpt-oss-model-card/
-        "gpt-oss-20b" => Some(ModelInfo {
-            context_window: 96_000,
-            max_output_tokens: 32_000,
-        }),
-        "gpt-oss-120b" => Some(ModelInfo {
+        // Matching on the prefix also covers the family name itself, which
+        // models declared with `family = "gpt-oss"` are looked up by.
+        _ if slug.starts_with("gpt-oss") => Some(ModelInfo {
             context_window: 96_000,
             max_output_tokens: 32_000,
         }),
@@ -73,7 +101,7 @@ pub(crate) fn get_model_info(model_family: &ModelFamily) -> Option<ModelInfo> {
         }),
 
         // https://platform.openai.com/docs/models/gpt-3.5-turbo
-        "gpt-3.5-turbo" => Some(ModelInfo {
+        "gpt-3.5-turbo" | "gpt-3.5" => Some(ModelInfo {
             context_window: 16_385,
             max_output_tokens: 4_096,
         }),
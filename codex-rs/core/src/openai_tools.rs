@@ -5,10 +5,19 @@ use serde_json::json;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 
+use crate::background_jobs::BACKGROUND_JOB_TOOLS;
+use crate::content_search::SEARCH_FILE_CONTENTS_TOOL;
+use crate::exec_session::EXEC_SESSION_TOOLS;
+use crate::file_tools::FILE_TOOLS;
 use crate::model_family::ModelFamily;
+use crate::notebook::NOTEBOOK_TOOLS;
 use crate::plan_tool::PLAN_TOOL;
 use crate::protocol::AskForApproval;
 use crate::protocol::SandboxPolicy;
+use crate::repo_map::REPO_MAP_TOOL;
+use crate::view_image::VIEW_IMAGE_TOOL;
+use crate::web_tools::WEB_FETCH_TOOL;
+use crate::web_tools::WEB_SEARCH_TOOL;
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub struct ResponsesApiTool {
@@ -44,15 +53,34 @@ pub struct ToolsConfig {
     pub shell_type: ConfigShellToolType,
     pub plan_tool: bool,
     pub apply_patch_tool: bool,
+    pub exec_session_tools: bool,
+    pub background_job_tools: bool,
+    pub content_search_tool: bool,
+    pub repo_map_tool: bool,
+    pub file_tools: bool,
+    pub web_fetch_tool: bool,
+    pub web_search_tool: bool,
+    pub view_image_tool: bool,
+    pub notebook_tools: bool,
 }
 
 impl ToolsConfig {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         model_family: &ModelFamily,
         approval_policy: AskForApproval,
         sandbox_policy: SandboxPolicy,
         include_plan_tool: bool,
         include_apply_patch_tool: bool,
+        include_exec_session_tools: bool,
+        include_background_job_tools: bool,
+        include_content_search_tool: bool,
+        include_repo_map_tool: bool,
+        include_file_tools: bool,
+        include_web_fetch_tool: bool,
+        include_web_search_tool: bool,
+        include_view_image_tool: bool,
+        include_notebook_tools: bool,
     ) -> Self {
         let mut shell_type = if model_family.uses_local_shell_tool {
             ConfigShellToolType::LocalShell
@@ -69,6 +97,15 @@ impl ToolsConfig {
             shell_type,
             plan_tool: include_plan_tool,
             apply_patch_tool: include_apply_patch_tool || model_family.uses_apply_patch_tool,
+            exec_session_tools: include_exec_session_tools,
+            background_job_tools: include_background_job_tools,
+            content_search_tool: include_content_search_tool,
+            repo_map_tool: include_repo_map_tool,
+            file_tools: include_file_tools,
+            web_fetch_tool: include_web_fetch_tool,
+            web_search_tool: include_web_search_tool,
+            view_image_tool: include_view_image_tool,
+            notebook_tools: include_notebook_tools,
         }
     }
 }
@@ -139,6 +176,26 @@ fn create_shell_tool() -> OpenAiTool {
     })
 }
 
+/// A function tool taking an object with `properties`, of which `required`
+/// must be given.
+pub(crate) fn create_function_tool(
+    name: &str,
+    description: &str,
+    properties: BTreeMap<String, JsonSchema>,
+    required: &[&str],
+) -> OpenAiTool {
+    OpenAiTool::Function(ResponsesApiTool {
+        name: name.to_string(),
+        description: description.to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(required.iter().map(|s| s.to_string()).collect()),
+            additional_properties: Some(false),
+        },
+    })
+}
+
 fn create_shell_tool_for_sandbox(sandbox_policy: &SandboxPolicy) -> OpenAiTool {
     let mut properties = BTreeMap::new();
     properties.insert(
@@ -179,6 +236,7 @@ fn create_shell_tool_for_sandbox(sandbox_policy: &SandboxPolicy) -> OpenAiTool {
     let description = match sandbox_policy {
         SandboxPolicy::WorkspaceWrite {
             network_access,
+            allowed_domains,
             ..
         } => {
             format!(
@@ -196,17 +254,22 @@ The shell tool is used to execute shell commands.
 - When invoking a command that will require escalated privileges:
   - Provide the with_escalated_permissions parameter with the boolean value true
   - Include a short, 1 sentence explanation for why we need to run with_escalated_permissions in the justification parameter."#,
-                if !network_access {
-                    "\n  - Commands that require network access\n"
+                if *network_access {
+                    "".to_string()
+                } else if allowed_domains.is_empty() {
+                    "\n  - Commands that require network access\n".to_string()
                 } else {
-                    ""
+                    format!(
+                        "\n  - Commands that require network access to hosts other than {} (and their subdomains), which are reachable through HTTP_PROXY\n",
+                        allowed_domains.join(", ")
+                    )
                 }
             )
         }
         SandboxPolicy::DangerFullAccess => {
             "Runs a shell command and returns its output.".to_string()
         }
-        SandboxPolicy::ReadOnly => {
+        SandboxPolicy::ReadOnly { .. } => {
             r#"
 The shell tool is used to execute shell commands.
 - When invoking the shell tool, your call will be running in a landlock sandbox, and some shell commands (including apply_patch) will require escalated permissions:
@@ -280,14 +343,21 @@ Within a hunk each line starts with:
   space ( ) for context.
   At the end of a truncated hunk you can emit \*\*\* End of File.
 
+Add File and Update File may be followed by \*\*\* File Mode: 100755 to make the file executable, or \*\*\* File Mode: 100644 to make it non-executable. An Update File can change only the mode.
+To add or replace a binary file, follow the Add File or Update File header with \*\*\* Binary Contents and then the contents in base64, on one or more lines.
+To add or replace a symlink, follow the header with \*\*\* Symlink To: <target>.
+
 Patch := Begin { FileOp } End
 Begin := "**_ Begin Patch" NEWLINE
 End := "_** End Patch" NEWLINE
 FileOp := AddFile | DeleteFile | UpdateFile
-AddFile := "**_ Add File: " path NEWLINE { "+" line NEWLINE }
+AddFile := "**_ Add File: " path NEWLINE ( [ FileMode ] ( { "+" line NEWLINE } | Binary ) | SymlinkTo )
 DeleteFile := "_** Delete File: " path NEWLINE
-UpdateFile := "**_ Update File: " path NEWLINE [ MoveTo ] { Hunk }
+UpdateFile := "**_ Update File: " path NEWLINE ( [ MoveTo ] [ FileMode ] { Hunk } | [ FileMode ] Binary | SymlinkTo )
 MoveTo := "_** Move to: " newPath NEWLINE
+FileMode := "*** File Mode: " ( "100644" | "100755" ) NEWLINE
+Binary := "*** Binary Contents" NEWLINE { base64 NEWLINE }
+SymlinkTo := "*** Symlink To: " target NEWLINE
 Hunk := "@@" [ header ] NEWLINE { HunkLine } [ "*** End of File" NEWLINE ]
 HunkLine := (" " | "-" | "+") text NEWLINE
 
@@ -365,6 +435,34 @@ pub(crate) fn create_tools_json_for_chat_completions_api(
     Ok(tools_json)
 }
 
+/// Returns JSON values that are compatible with tool use in the Anthropic
+/// Messages API:
+/// https://docs.anthropic.com/en/docs/agents-and-tools/tool-use/overview
+pub(crate) fn create_tools_json_for_messages_api(
+    tools: &Vec<OpenAiTool>,
+) -> crate::error::Result<Vec<serde_json::Value>> {
+    let mut tools_json = Vec::new();
+
+    for tool in tools {
+        // `local_shell` is an OpenAI built-in and has no Messages equivalent.
+        if let OpenAiTool::Function(ResponsesApiTool {
+            name,
+            description,
+            parameters,
+            ..
+        }) = tool
+        {
+            tools_json.push(json!({
+                "name": name,
+                "description": description,
+                "input_schema": serde_json::to_value(parameters)?,
+            }));
+        }
+    }
+
+    Ok(tools_json)
+}
+
 pub(crate) fn mcp_tool_to_openai_tool(
     fully_qualified_name: String,
     tool: mcp_types::Tool,
@@ -543,6 +641,42 @@ pub(crate) fn get_openai_tools(
         tools.push(create_apply_patch_tool());
     }
 
+    if config.exec_session_tools {
+        tools.extend(EXEC_SESSION_TOOLS.iter().cloned());
+    }
+
+    if config.background_job_tools {
+        tools.extend(BACKGROUND_JOB_TOOLS.iter().cloned());
+    }
+
+    if config.content_search_tool {
+        tools.push(SEARCH_FILE_CONTENTS_TOOL.clone());
+    }
+
+    if config.repo_map_tool {
+        tools.push(REPO_MAP_TOOL.clone());
+    }
+
+    if config.file_tools {
+        tools.extend(FILE_TOOLS.iter().cloned());
+    }
+
+    if config.notebook_tools {
+        tools.extend(NOTEBOOK_TOOLS.iter().cloned());
+    }
+
+    if config.web_fetch_tool {
+        tools.push(WEB_FETCH_TOOL.clone());
+    }
+
+    if config.web_search_tool {
+        tools.push(WEB_SEARCH_TOOL.clone());
+    }
+
+    if config.view_image_tool {
+        tools.push(VIEW_IMAGE_TOOL.clone());
+    }
+
     if let Some(mcp_tools) = mcp_tools {
         for (name, tool) in mcp_tools {
             match mcp_tool_to_openai_tool(name.clone(), tool.clone()) {
@@ -594,9 +728,18 @@ mod tests {
         let config = ToolsConfig::new(
             &model_family,
             AskForApproval::Never,
-            SandboxPolicy::ReadOnly,
+            SandboxPolicy::new_read_only_policy(),
             true,
             model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
         );
         let tools = get_openai_tools(&config, Some(HashMap::new()));
 
@@ -609,24 +752,190 @@ mod tests {
         let config = ToolsConfig::new(
             &model_family,
             AskForApproval::Never,
-            SandboxPolicy::ReadOnly,
+            SandboxPolicy::new_read_only_policy(),
             true,
             model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
         );
         let tools = get_openai_tools(&config, Some(HashMap::new()));
 
         assert_eq_tool_names(&tools, &["shell", "update_plan"]);
     }
 
+    #[test]
+    fn test_get_openai_tools_exec_session_tools() {
+        let model_family = find_family_for_model("o3").expect("o3 should be a valid model family");
+        let config = ToolsConfig::new(
+            &model_family,
+            AskForApproval::Never,
+            SandboxPolicy::new_read_only_policy(),
+            false,
+            model_family.uses_apply_patch_tool,
+            true,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+        );
+        let tools = get_openai_tools(&config, Some(HashMap::new()));
+
+        assert_eq_tool_names(
+            &tools,
+            &[
+                "shell",
+                "exec_session_start",
+                "exec_session_write",
+                "exec_session_read",
+                "exec_session_kill",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_get_openai_tools_background_job_tools() {
+        let model_family = find_family_for_model("o3").expect("o3 should be a valid model family");
+        let config = ToolsConfig::new(
+            &model_family,
+            AskForApproval::Never,
+            SandboxPolicy::new_read_only_policy(),
+            false,
+            model_family.uses_apply_patch_tool,
+            false,
+            true,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+        );
+        let tools = get_openai_tools(&config, Some(HashMap::new()));
+
+        assert_eq_tool_names(
+            &tools,
+            &[
+                "shell",
+                "background_job_start",
+                "background_job_status",
+                "background_job_tail",
+                "background_job_kill",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_get_openai_tools_read_only_search_tools() {
+        let model_family = find_family_for_model("o3").expect("o3 should be a valid model family");
+        let config = ToolsConfig::new(
+            &model_family,
+            AskForApproval::Never,
+            SandboxPolicy::new_read_only_policy(),
+            false,
+            model_family.uses_apply_patch_tool,
+            false,
+            false,
+            true,
+            true,
+            false,
+            false,
+            false,
+            false,
+            false,
+        );
+        let tools = get_openai_tools(&config, Some(HashMap::new()));
+
+        assert_eq_tool_names(&tools, &["shell", "search_file_contents", "repo_map"]);
+    }
+
+    #[test]
+    fn test_get_openai_tools_file_tools() {
+        let model_family = find_family_for_model("o3").expect("o3 should be a valid model family");
+        let config = ToolsConfig::new(
+            &model_family,
+            AskForApproval::Never,
+            SandboxPolicy::new_read_only_policy(),
+            false,
+            model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            true,
+            false,
+            false,
+            false,
+            true,
+        );
+        let tools = get_openai_tools(&config, Some(HashMap::new()));
+
+        assert_eq_tool_names(
+            &tools,
+            &[
+                "shell",
+                "read_file",
+                "list_dir",
+                "edit_file",
+                "read_notebook",
+                "edit_notebook",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_get_openai_tools_web_tools() {
+        let model_family = find_family_for_model("o3").expect("o3 should be a valid model family");
+        let config = ToolsConfig::new(
+            &model_family,
+            AskForApproval::Never,
+            SandboxPolicy::new_read_only_policy(),
+            false,
+            model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            false,
+            true,
+            true,
+            true,
+            false,
+        );
+        let tools = get_openai_tools(&config, Some(HashMap::new()));
+
+        assert_eq_tool_names(&tools, &["shell", "web_fetch", "web_search", "view_image"]);
+    }
+
     #[test]
     fn test_get_openai_tools_mcp_tools() {
         let model_family = find_family_for_model("o3").expect("o3 should be a valid model family");
         let config = ToolsConfig::new(
             &model_family,
             AskForApproval::Never,
-            SandboxPolicy::ReadOnly,
+            SandboxPolicy::new_read_only_policy(),
             false,
             model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
         );
         let tools = get_openai_tools(
             &config,
@@ -718,9 +1027,18 @@ mod tests {
         let config = ToolsConfig::new(
             &model_family,
             AskForApproval::Never,
-            SandboxPolicy::ReadOnly,
+            SandboxPolicy::new_read_only_policy(),
             false,
             model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
         );
 
         let tools = get_openai_tools(
@@ -774,9 +1092,18 @@ mod tests {
         let config = ToolsConfig::new(
             &model_family,
             AskForApproval::Never,
-            SandboxPolicy::ReadOnly,
+            SandboxPolicy::new_read_only_policy(),
             false,
             model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
         );
 
         let tools = get_openai_tools(
@@ -825,9 +1152,18 @@ mod tests {
         let config = ToolsConfig::new(
             &model_family,
             AskForApproval::Never,
-            SandboxPolicy::ReadOnly,
+            SandboxPolicy::new_read_only_policy(),
             false,
             model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
         );
 
         let tools = get_openai_tools(
@@ -879,9 +1215,18 @@ mod tests {
         let config = ToolsConfig::new(
             &model_family,
             AskForApproval::Never,
-            SandboxPolicy::ReadOnly,
+            SandboxPolicy::new_read_only_policy(),
             false,
             model_family.uses_apply_patch_tool,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
         );
 
         let tools = get_openai_tools(
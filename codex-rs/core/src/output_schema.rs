@@ -0,0 +1,150 @@
+//! JSON Schema constraints on the agent's final message.
+//!
+//! When a schema is configured, it is sent as the response format to
+//! providers that accept one. Since not every provider enforces it, callers
+//! also check the final message with [`OutputSchema::validate`] and, if it
+//! does not conform, ask the model to try again with
+//! [`OutputSchema::correction_prompt`].
+
+use std::path::Path;
+
+use jsonschema::Validator;
+use serde_json::Value;
+
+/// How many times a non-conforming final message is sent back to the model
+/// before giving up.
+pub const DEFAULT_OUTPUT_SCHEMA_RETRIES: usize = 2;
+
+/// Name under which the schema is sent to the model.
+pub(crate) const OUTPUT_SCHEMA_NAME: &str = "codex_output_schema";
+
+/// A compiled JSON Schema for the agent's final message.
+pub struct OutputSchema {
+    schema: Value,
+    validator: Validator,
+}
+
+impl OutputSchema {
+    pub fn new(schema: Value) -> std::io::Result<Self> {
+        let validator = jsonschema::validator_for(&schema).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid output schema: {e}"),
+            )
+        })?;
+        Ok(Self { schema, validator })
+    }
+
+    /// Reads and compiles the schema stored in the JSON file at `path`.
+    pub fn from_path(path: &Path) -> std::io::Result<Self> {
+        let raw = std::fs::read_to_string(path).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("failed to read output schema {}: {e}", path.display()),
+            )
+        })?;
+        let schema = serde_json::from_str(&raw).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("failed to parse output schema {}: {e}", path.display()),
+            )
+        })?;
+        Self::new(schema)
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// Parses `message` as JSON and checks it against the schema, returning
+    /// the parsed value or a list of human-readable problems. A message
+    /// wrapped in a Markdown code fence is accepted.
+    pub fn validate(&self, message: Option<&str>) -> Result<Value, Vec<String>> {
+        let Some(message) = message else {
+            return Err(vec!["no final message was produced".to_string()]);
+        };
+        let value: Value = serde_json::from_str(strip_code_fence(message))
+            .map_err(|e| vec![format!("the final message is not valid JSON: {e}")])?;
+        let errors: Vec<String> = self
+            .validator
+            .iter_errors(&value)
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                if path.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{path}: {e}")
+                }
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Follow-up message asking the model to fix a final message that failed
+    /// [`OutputSchema::validate`].
+    pub fn correction_prompt(&self, errors: &[String]) -> String {
+        let schema = serde_json::to_string_pretty(&self.schema).unwrap_or_default();
+        format!(
+            "Your final message does not match the required JSON Schema:\n- {}\n\nReply again with only a JSON document that conforms to this schema, without any other text:\n{schema}",
+            errors.join("\n- ")
+        )
+    }
+}
+
+fn strip_code_fence(message: &str) -> &str {
+    let trimmed = message.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let Some(body) = rest.strip_suffix("```") else {
+        return trimmed;
+    };
+    // Drop the info string (e.g. `json`) on the opening fence line.
+    match body.split_once('\n') {
+        Some((_, body)) => body.trim(),
+        None => body.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn schema() -> OutputSchema {
+        OutputSchema::new(json!({
+            "type": "object",
+            "properties": {
+                "summary": { "type": "string" },
+                "files": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["summary", "files"]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_conforming_message_in_code_fence() {
+        let message = "```json\n{\"summary\": \"ok\", \"files\": [\"a.rs\"]}\n```";
+        assert_eq!(
+            Ok(json!({"summary": "ok", "files": ["a.rs"]})),
+            schema().validate(Some(message))
+        );
+    }
+
+    #[test]
+    fn reports_schema_violations() {
+        let errors = schema()
+            .validate(Some(r#"{"summary": 3, "files": []}"#))
+            .unwrap_err();
+        assert_eq!(vec![r#"/summary: 3 is not of type "string""#], errors);
+
+        let errors = schema().validate(Some("All done!")).unwrap_err();
+        assert!(errors[0].starts_with("the final message is not valid JSON"));
+    }
+}
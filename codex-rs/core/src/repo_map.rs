@@ -0,0 +1,828 @@
+//! The `repo_map` tool: an outline of the symbols defined in a repository,
+//! ranked by how often the rest of the code refers to them, so the model can
+//! find its way around a large codebase without a string of `ls` and `grep`
+//! calls.
+//!
+//! Files are parsed with tree-sitter. Each file's symbols, imports and the
+//! identifiers it uses are cached in `$CODEX_HOME/index/<hash of root>.json`
+//! along with the file's size and modification time, so later calls only
+//! reparse the files that changed.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+use ignore::WalkBuilder;
+use serde::Deserialize;
+use serde::Serialize;
+use sha1::Digest;
+use sha1::Sha1;
+use tree_sitter::Language;
+use tree_sitter::Node;
+use tree_sitter::Parser;
+use tree_sitter::Query;
+use tree_sitter::QueryCursor;
+use tree_sitter::StreamingIterator;
+
+use crate::models::FunctionCallOutputPayload;
+use crate::models::ResponseInputItem;
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+
+/// Bumped whenever the layout of the index or the extracted symbols change,
+/// so stale indexes are rebuilt rather than misread.
+const INDEX_VERSION: u32 = 1;
+
+/// Larger files are usually generated or vendored and are not indexed.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Budget for the outline when the model does not ask for one.
+const DEFAULT_MAX_TOKENS: usize = 2048;
+
+/// Largest outline a single call can ask for.
+const MAX_TOKENS_LIMIT: usize = 16 * 1024;
+
+/// Rough number of characters per token, used to fit the outline in its
+/// budget without running a tokenizer.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Most definitions returned when searching for a symbol.
+const MAX_SYMBOL_RESULTS: usize = 100;
+
+/// Longer signatures and imports are cut off.
+const MAX_SIGNATURE_CHARS: usize = 160;
+
+/// Signatures spanning more lines than this are cut off.
+const MAX_SIGNATURE_LINES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Lang {
+    Rust,
+    Python,
+    Go,
+    JavaScript,
+    TypeScript,
+    Tsx,
+}
+
+impl Lang {
+    fn for_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "go" => Some(Self::Go),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            _ => None,
+        }
+    }
+
+    fn language(self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+        }
+    }
+
+    /// Captures `@definition.<kind>` with its `@name`, and `@import`.
+    fn query_source(self) -> &'static str {
+        match self {
+            Self::Rust => RUST_QUERY,
+            Self::Python => PYTHON_QUERY,
+            Self::Go => GO_QUERY,
+            Self::JavaScript => JAVASCRIPT_QUERY,
+            Self::TypeScript | Self::Tsx => TYPESCRIPT_QUERY,
+        }
+    }
+
+    fn query(self) -> &'static Query {
+        static QUERIES: LazyLock<HashMap<Lang, Query>> = LazyLock::new(|| {
+            [
+                Lang::Rust,
+                Lang::Python,
+                Lang::Go,
+                Lang::JavaScript,
+                Lang::TypeScript,
+                Lang::Tsx,
+            ]
+            .into_iter()
+            .map(|lang| {
+                #[expect(clippy::expect_used)]
+                let query = Query::new(&lang.language(), lang.query_source())
+                    .expect("repo map queries are valid");
+                (lang, query)
+            })
+            .collect()
+        });
+        &QUERIES[&self]
+    }
+}
+
+const RUST_QUERY: &str = r#"
+(function_item name: (identifier) @name) @definition.function
+(function_signature_item name: (identifier) @name) @definition.function
+(struct_item name: (type_identifier) @name) @definition.type
+(enum_item name: (type_identifier) @name) @definition.type
+(union_item name: (type_identifier) @name) @definition.type
+(type_item name: (type_identifier) @name) @definition.type
+(trait_item name: (type_identifier) @name) @definition.type
+(impl_item type: (_) @name) @definition.impl
+(mod_item name: (identifier) @name) @definition.module
+(macro_definition name: (identifier) @name) @definition.macro
+(use_declaration) @import
+"#;
+
+const PYTHON_QUERY: &str = r#"
+(function_definition name: (identifier) @name) @definition.function
+(class_definition name: (identifier) @name) @definition.type
+(import_statement) @import
+(import_from_statement) @import
+"#;
+
+const GO_QUERY: &str = r#"
+(function_declaration name: (identifier) @name) @definition.function
+(method_declaration name: (field_identifier) @name) @definition.function
+(type_spec name: (type_identifier) @name) @definition.type
+(import_spec) @import
+"#;
+
+const JAVASCRIPT_QUERY: &str = r#"
+(function_declaration name: (identifier) @name) @definition.function
+(generator_function_declaration name: (identifier) @name) @definition.function
+(method_definition name: (_) @name) @definition.function
+(variable_declarator
+  name: (identifier) @name
+  value: [(arrow_function) (function_expression)]) @definition.function
+(class_declaration name: (identifier) @name) @definition.type
+(import_statement) @import
+"#;
+
+const TYPESCRIPT_QUERY: &str = r#"
+(function_declaration name: (identifier) @name) @definition.function
+(generator_function_declaration name: (identifier) @name) @definition.function
+(function_signature name: (identifier) @name) @definition.function
+(method_definition name: (_) @name) @definition.function
+(method_signature name: (_) @name) @definition.function
+(variable_declarator
+  name: (identifier) @name
+  value: [(arrow_function) (function_expression)]) @definition.function
+(class_declaration name: (type_identifier) @name) @definition.type
+(abstract_class_declaration name: (type_identifier) @name) @definition.type
+(interface_declaration name: (type_identifier) @name) @definition.type
+(type_alias_declaration name: (type_identifier) @name) @definition.type
+(enum_declaration name: (identifier) @name) @definition.type
+(internal_module name: (_) @name) @definition.module
+(import_statement) @import
+"#;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SymbolKind {
+    Function,
+    Type,
+    Impl,
+    Module,
+    Macro,
+}
+
+impl SymbolKind {
+    fn from_capture(name: &str) -> Option<Self> {
+        match name {
+            "definition.function" => Some(Self::Function),
+            "definition.type" => Some(Self::Type),
+            "definition.impl" => Some(Self::Impl),
+            "definition.module" => Some(Self::Module),
+            "definition.macro" => Some(Self::Macro),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Symbol {
+    pub(crate) name: String,
+    pub(crate) kind: SymbolKind,
+    /// 1-based line the definition starts on.
+    pub(crate) line: usize,
+    /// How many other definitions this one is nested in, e.g. 1 for a
+    /// method in an `impl` block or a class.
+    pub(crate) depth: usize,
+    /// The definition up to its body, e.g. `pub fn new(name: &str) -> Self`.
+    pub(crate) signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+struct FileIndex {
+    size: u64,
+    modified: Option<SystemTime>,
+    symbols: Vec<Symbol>,
+    imports: Vec<String>,
+    /// Sorted identifiers the file uses, which rank the files defining them.
+    references: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct RepoIndex {
+    version: u32,
+    /// Keyed by path relative to the indexed directory.
+    files: BTreeMap<String, FileIndex>,
+}
+
+/// Where the index of `root` is cached.
+fn index_path(codex_home: &Path, root: &Path) -> PathBuf {
+    let mut hasher = Sha1::new();
+    hasher.update(root.to_string_lossy().as_bytes());
+    codex_home
+        .join("index")
+        .join(format!("{:x}.json", hasher.finalize()))
+}
+
+impl RepoIndex {
+    /// Loads the cached index of `root` and brings it up to date with the
+    /// files on disk, saving it again when anything changed.
+    pub(crate) fn load_and_update(codex_home: &Path, root: &Path) -> io::Result<Self> {
+        let path = index_path(codex_home, root);
+        let mut index = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<RepoIndex>(&bytes).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_else(|| RepoIndex {
+                version: INDEX_VERSION,
+                files: BTreeMap::new(),
+            });
+        if index.update(root) {
+            index.save(&path)?;
+        }
+        Ok(index)
+    }
+
+    /// Reparses the files whose size or modification time changed and drops
+    /// the ones that no longer exist. Returns whether anything changed.
+    fn update(&mut self, root: &Path) -> bool {
+        let mut parser = Parser::new();
+        let mut seen = HashSet::new();
+        let mut changed = false;
+        for entry in WalkBuilder::new(root).build().flatten() {
+            if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+                continue;
+            }
+            let path = entry.path();
+            let Some(lang) = Lang::for_path(path) else {
+                continue;
+            };
+            let Some(rel_path) = path.strip_prefix(root).ok().and_then(|p| p.to_str()) else {
+                continue;
+            };
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.len() > MAX_FILE_SIZE {
+                continue;
+            }
+
+            let modified = metadata.modified().ok();
+            if let Some(existing) = self.files.get(rel_path)
+                && modified.is_some()
+                && existing.modified == modified
+                && existing.size == metadata.len()
+            {
+                seen.insert(rel_path.to_string());
+                continue;
+            }
+            let Ok(source) = std::fs::read_to_string(path) else {
+                continue;
+            };
+            let Some(mut file) = index_file(lang, &mut parser, &source) else {
+                continue;
+            };
+            file.size = metadata.len();
+            file.modified = modified;
+            seen.insert(rel_path.to_string());
+            self.files.insert(rel_path.to_string(), file);
+            changed = true;
+        }
+
+        let indexed = self.files.len();
+        self.files.retain(|path, _| seen.contains(path));
+        changed || self.files.len() != indexed
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let dir = path
+            .parent()
+            .ok_or_else(|| io::Error::other("index path has no parent"))?;
+        std::fs::create_dir_all(dir)?;
+        // Write to a temporary file first so that a concurrent reader never
+        // sees a partially written index.
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        serde_json::to_writer(&mut file, self)?;
+        file.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// Files under `prefix` that define symbols, most referenced first. A
+    /// file's score adds up, for each name it defines, the square root of the
+    /// number of other files using that name, so that a few very common names
+    /// like `new` do not dominate.
+    fn ranked_files(&self, prefix: &Path) -> Vec<(&str, &FileIndex)> {
+        let mut referencing_files: HashMap<&str, usize> = HashMap::new();
+        for file in self.files.values() {
+            for name in &file.references {
+                *referencing_files.entry(name.as_str()).or_default() += 1;
+            }
+        }
+
+        let mut ranked: Vec<(f64, &str, &FileIndex)> = self
+            .files
+            .iter()
+            .filter(|(path, file)| !file.symbols.is_empty() && Path::new(path).starts_with(prefix))
+            .map(|(path, file)| {
+                let names: BTreeSet<&str> = file.symbols.iter().map(|s| s.name.as_str()).collect();
+                let score = names
+                    .into_iter()
+                    .map(|name| {
+                        let count = referencing_files.get(name).copied().unwrap_or(0);
+                        let own = file
+                            .references
+                            .binary_search_by(|r| r.as_str().cmp(name))
+                            .is_ok();
+                        (count.saturating_sub(usize::from(own)) as f64).sqrt()
+                    })
+                    .sum();
+                (score, path.as_str(), file)
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        ranked
+            .into_iter()
+            .map(|(_, path, file)| (path, file))
+            .collect()
+    }
+
+    /// Renders the outline of the files under `prefix`, most referenced
+    /// first, in about `max_tokens` tokens.
+    pub(crate) fn render_map(&self, prefix: &Path, max_tokens: usize) -> String {
+        let ranked = self.ranked_files(prefix);
+        if ranked.is_empty() {
+            return "No symbols found.".to_string();
+        }
+
+        let budget = max_tokens * CHARS_PER_TOKEN;
+        let mut out = String::new();
+        let mut shown = 0;
+        for (path, file) in &ranked {
+            let block = render_file(path, file, false);
+            if out.len() + block.len() > budget {
+                if shown == 0 {
+                    // Show as much of the top file as fits rather than nothing.
+                    for line in block.lines() {
+                        if out.len() + line.len() + 1 > budget {
+                            break;
+                        }
+                        out.push_str(line);
+                        out.push('\n');
+                    }
+                    shown = 1;
+                }
+                break;
+            }
+            out.push_str(&block);
+            shown += 1;
+        }
+        if shown < ranked.len() {
+            out.push_str(&format!(
+                "... {} more files with symbols not shown; pass `path` to narrow the map or raise `max_tokens`.\n",
+                ranked.len() - shown
+            ));
+        }
+        out
+    }
+
+    /// Renders the imports and every symbol of one file.
+    fn render_file_details(&self, rel_path: &str) -> Option<String> {
+        self.files
+            .get(rel_path)
+            .map(|file| render_file(rel_path, file, true))
+    }
+
+    /// Lists the definitions under `prefix` whose name contains `query`,
+    /// ignoring case, with exact matches first.
+    fn find_symbols(&self, query: &str, prefix: &Path) -> String {
+        let query = query.to_lowercase();
+        let mut found: Vec<(bool, &str, &Symbol)> = self
+            .files
+            .iter()
+            .filter(|(path, _)| Path::new(path).starts_with(prefix))
+            .flat_map(|(path, file)| {
+                file.symbols
+                    .iter()
+                    .map(move |symbol| (path.as_str(), symbol))
+            })
+            .filter_map(|(path, symbol)| {
+                let name = symbol.name.to_lowercase();
+                name.contains(&query)
+                    .then_some((name != query, path, symbol))
+            })
+            .collect();
+        if found.is_empty() {
+            return format!("No symbols matching `{query}` found.");
+        }
+        found.sort_by(|a, b| (a.0, a.1, a.2.line).cmp(&(b.0, b.1, b.2.line)));
+
+        let mut out = String::new();
+        for (_, path, symbol) in found.iter().take(MAX_SYMBOL_RESULTS) {
+            out.push_str(&format!("{path}:{}: {}\n", symbol.line, symbol.signature));
+        }
+        if found.len() > MAX_SYMBOL_RESULTS {
+            out.push_str(&format!(
+                "... {} more matching symbols not shown.\n",
+                found.len() - MAX_SYMBOL_RESULTS
+            ));
+        }
+        out
+    }
+}
+
+fn render_file(path: &str, file: &FileIndex, with_imports: bool) -> String {
+    let mut out = format!("{path}\n");
+    if with_imports && !file.imports.is_empty() {
+        out.push_str("  imports:\n");
+        for import in &file.imports {
+            out.push_str(&format!("    {import}\n"));
+        }
+    }
+    for symbol in &file.symbols {
+        let indent = "  ".repeat(symbol.depth + 1);
+        out.push_str(&format!("{indent}{}: {}\n", symbol.line, symbol.signature));
+    }
+    out
+}
+
+/// Extracts the symbols, imports and referenced identifiers of one file.
+fn index_file(lang: Lang, parser: &mut Parser, source: &str) -> Option<FileIndex> {
+    parser.set_language(&lang.language()).ok()?;
+    let tree = parser.parse(source, None)?;
+    let query = lang.query();
+    let capture_names = query.capture_names();
+    let bytes = source.as_bytes();
+
+    let mut definitions: Vec<(Node, SymbolKind, &str)> = Vec::new();
+    let mut imports = Vec::new();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(query, tree.root_node(), bytes);
+    while let Some(m) = matches.next() {
+        let mut name = None;
+        let mut definition = None;
+        for capture in m.captures {
+            let text = capture.node.utf8_text(bytes).unwrap_or_default();
+            match capture_names[capture.index as usize] {
+                "name" => name = Some(text),
+                "import" => imports.push(signature(text)),
+                other => {
+                    if let Some(kind) = SymbolKind::from_capture(other) {
+                        definition = Some((capture.node, kind));
+                    }
+                }
+            }
+        }
+        if let (Some(name), Some((node, kind))) = (name, definition)
+            && !definitions
+                .iter()
+                .any(|(other, _, _)| other.id() == node.id())
+        {
+            definitions.push((node, kind, name));
+        }
+    }
+
+    definitions.sort_by_key(|(node, _, _)| (node.start_byte(), std::cmp::Reverse(node.end_byte())));
+    let symbols = definitions
+        .iter()
+        .map(|(node, kind, name)| {
+            let depth = definitions
+                .iter()
+                .filter(|(other, _, _)| {
+                    other.id() != node.id()
+                        && other.start_byte() <= node.start_byte()
+                        && node.end_byte() <= other.end_byte()
+                })
+                .count();
+            Symbol {
+                name: name.to_string(),
+                kind: *kind,
+                line: node.start_position().row + 1,
+                depth,
+                signature: signature(node.utf8_text(bytes).unwrap_or_default()),
+            }
+        })
+        .collect();
+
+    Some(FileIndex {
+        size: 0,
+        modified: None,
+        symbols,
+        imports,
+        references: referenced_identifiers(tree.root_node(), bytes),
+    })
+}
+
+/// The text of a definition up to where its body starts, on one line.
+fn signature(text: &str) -> String {
+    let mut signature = String::new();
+    for line in text.lines().take(MAX_SIGNATURE_LINES) {
+        let (line, done) = match line.find('{') {
+            Some(brace) => (&line[..brace], true),
+            None => {
+                let trimmed = line.trim_end();
+                (line, trimmed.ends_with(':') || trimmed.ends_with(';'))
+            }
+        };
+        if !signature.is_empty() && !line.trim().is_empty() {
+            signature.push(' ');
+        }
+        signature.push_str(line.trim());
+        if done {
+            break;
+        }
+    }
+    let signature = signature.trim_end();
+    match signature.char_indices().nth(MAX_SIGNATURE_CHARS) {
+        Some((end, _)) => format!("{}…", &signature[..end]),
+        None => signature.to_string(),
+    }
+}
+
+/// Every distinct identifier in the tree, sorted.
+fn referenced_identifiers(root: Node, source: &[u8]) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    let mut cursor = root.walk();
+    loop {
+        let node = cursor.node();
+        if node.child_count() == 0
+            && node.kind().ends_with("identifier")
+            && let Ok(text) = node.utf8_text(source)
+        {
+            names.insert(text.to_string());
+        }
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return names.into_iter().collect();
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct RepoMapParams {
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    max_tokens: Option<usize>,
+}
+
+pub(crate) static REPO_MAP_TOOL: LazyLock<OpenAiTool> = LazyLock::new(|| {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "path".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "File or directory to map, relative to the working directory. For a file, its imports and every symbol are listed. Defaults to the working directory."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "symbol".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Instead of the outline, list where symbols whose name contains this text are defined."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "max_tokens".to_string(),
+        JsonSchema::Number {
+            description: Some(format!(
+                "Approximate size of the outline in tokens, at most {MAX_TOKENS_LIMIT}. Defaults to {DEFAULT_MAX_TOKENS}."
+            )),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "repo_map".to_string(),
+        description: "Returns an outline of the functions, types and impls defined in the repository as `line: signature` under each file, with the files whose symbols are used most elsewhere first. Use it to get oriented in a large codebase or to find where a symbol is defined, instead of running ls and grep. Supports Rust, Python, Go, JavaScript and TypeScript. This tool only reads files and never needs approval.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(Vec::new()),
+            additional_properties: Some(false),
+        },
+    })
+});
+
+pub(crate) async fn handle_repo_map(
+    codex_home: &Path,
+    cwd: &Path,
+    arguments: String,
+    call_id: String,
+) -> ResponseInputItem {
+    let params = match serde_json::from_str::<RepoMapParams>(&arguments) {
+        Ok(params) => params,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: None,
+                },
+            };
+        }
+    };
+
+    let codex_home = codex_home.to_path_buf();
+    let root = cwd.to_path_buf();
+    let result = tokio::task::spawn_blocking(move || {
+        let index = RepoIndex::load_and_update(&codex_home, &root)?;
+        let prefix = PathBuf::from(params.path.as_deref().unwrap_or(""));
+        let prefix = prefix.strip_prefix(&root).unwrap_or(&prefix);
+        let prefix = prefix.strip_prefix(".").unwrap_or(prefix);
+        if let Some(symbol) = params.symbol {
+            return Ok(index.find_symbols(&symbol, prefix));
+        }
+        if let Some(details) = prefix.to_str().and_then(|p| index.render_file_details(p)) {
+            return Ok(details);
+        }
+        let max_tokens = params
+            .max_tokens
+            .unwrap_or(DEFAULT_MAX_TOKENS)
+            .clamp(1, MAX_TOKENS_LIMIT);
+        Ok::<_, io::Error>(index.render_map(prefix, max_tokens))
+    })
+    .await
+    .map_err(io::Error::other)
+    .and_then(|result| result);
+
+    let output = match result {
+        Ok(content) => FunctionCallOutputPayload {
+            content,
+            success: Some(true),
+        },
+        Err(e) => FunctionCallOutputPayload {
+            content: format!("failed to build the repository map: {e}"),
+            success: Some(false),
+        },
+    };
+    ResponseInputItem::FunctionCallOutput { call_id, output }
+}
+
+/// The outline of `cwd` included in the environment context when
+/// `repo_map_context_tokens` is set, or `None` if it cannot be built.
+pub(crate) async fn repo_map_summary(
+    codex_home: &Path,
+    cwd: &Path,
+    max_tokens: usize,
+) -> Option<String> {
+    let codex_home = codex_home.to_path_buf();
+    let root = cwd.to_path_buf();
+    let result = tokio::task::spawn_blocking(move || {
+        RepoIndex::load_and_update(&codex_home, &root)
+            .map(|index| index.render_map(Path::new(""), max_tokens))
+    })
+    .await;
+    match result {
+        Ok(Ok(map)) => Some(map),
+        Ok(Err(e)) => {
+            tracing::warn!("failed to build the repository map: {e}");
+            None
+        }
+        Err(e) => {
+            tracing::warn!("failed to build the repository map: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    fn symbols(lang: Lang, source: &str) -> Vec<(usize, usize, String)> {
+        index_file(lang, &mut Parser::new(), source)
+            .unwrap()
+            .symbols
+            .into_iter()
+            .map(|s| (s.line, s.depth, s.signature))
+            .collect()
+    }
+
+    #[test]
+    fn every_query_compiles() {
+        for lang in [
+            Lang::Rust,
+            Lang::Python,
+            Lang::Go,
+            Lang::JavaScript,
+            Lang::TypeScript,
+            Lang::Tsx,
+        ] {
+            Query::new(&lang.language(), lang.query_source()).unwrap();
+        }
+    }
+
+    #[test]
+    fn extracts_rust_symbols_and_imports() {
+        let source = "use std::fmt;\n\
+                      pub struct Point {\n    x: i32,\n}\n\
+                      impl Point {\n    pub fn new(\n        x: i32,\n    ) -> Self {\n        Self { x }\n    }\n}\n\
+                      trait Shape {\n    fn area(&self) -> f64;\n}\n";
+        let file = index_file(Lang::Rust, &mut Parser::new(), source).unwrap();
+        assert_eq!(file.imports, vec!["use std::fmt;".to_string()]);
+        assert_eq!(
+            symbols(Lang::Rust, source),
+            vec![
+                (2, 0, "pub struct Point".to_string()),
+                (5, 0, "impl Point".to_string()),
+                (6, 1, "pub fn new( x: i32, ) -> Self".to_string()),
+                (12, 0, "trait Shape".to_string()),
+                (13, 1, "fn area(&self) -> f64;".to_string()),
+            ]
+        );
+        assert!(file.references.contains(&"Point".to_string()));
+    }
+
+    #[test]
+    fn extracts_python_and_typescript_symbols() {
+        assert_eq!(
+            symbols(
+                Lang::Python,
+                "import os\n\nclass Greeter:\n    def greet(self, name):\n        return name\n"
+            ),
+            vec![
+                (3, 0, "class Greeter:".to_string()),
+                (4, 1, "def greet(self, name):".to_string()),
+            ]
+        );
+        assert_eq!(
+            symbols(
+                Lang::TypeScript,
+                "export interface Shape {\n  area(): number;\n}\nexport const double = (x: number) => x * 2;\n"
+            ),
+            vec![
+                (1, 0, "interface Shape".to_string()),
+                (2, 1, "area(): number".to_string()),
+                (4, 0, "double = (x: number) => x * 2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ranks_referenced_files_first_and_updates_incrementally() {
+        let codex_home = tempfile::tempdir().unwrap();
+        let repo = tempfile::tempdir().unwrap();
+        fs::write(repo.path().join("util.rs"), "pub fn helper() {}\n").unwrap();
+        fs::write(repo.path().join("a.rs"), "fn a() { helper(); }\n").unwrap();
+        fs::write(repo.path().join("b.rs"), "fn b() { helper(); }\n").unwrap();
+
+        let index = RepoIndex::load_and_update(codex_home.path(), repo.path()).unwrap();
+        assert_eq!(
+            index.render_map(Path::new(""), 1000),
+            "util.rs\n  1: pub fn helper()\na.rs\n  1: fn a()\nb.rs\n  1: fn b()\n"
+        );
+        assert_eq!(
+            index.render_map(Path::new(""), 8),
+            "util.rs\n  1: pub fn helper()\n... 2 more files with symbols not shown; pass `path` to narrow the map or raise `max_tokens`.\n"
+        );
+        assert_eq!(
+            index.find_symbols("HELP", Path::new("")),
+            "util.rs:1: pub fn helper()\n"
+        );
+        assert!(index_path(codex_home.path(), repo.path()).exists());
+
+        fs::remove_file(repo.path().join("b.rs")).unwrap();
+        fs::write(repo.path().join("c.rs"), "fn c() {}\n").unwrap();
+        let index = RepoIndex::load_and_update(codex_home.path(), repo.path()).unwrap();
+        assert_eq!(
+            index.files.keys().collect::<Vec<_>>(),
+            vec!["a.rs", "c.rs", "util.rs"]
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Resource limits for shell commands run under the Linux sandbox.
+//!
+//! The `codex-linux-sandbox` helper applies [`ResourceLimits`] right before it
+//! execs the command: rlimits via `setrlimit(2)`, and the memory and process
+//! caps by moving itself into a dedicated cgroup v2 (see [`exec_cgroup_path`]). Once the
+//! command has finished, Codex removes that cgroup and uses
+//! [`describe_exceeded_limit`] to tell the model which limit, if any, stopped
+//! the command.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub use crate::config_types::ResourceLimits;
+
+/// Mount point of the unified (v2) cgroup hierarchy.
+const CGROUP_V2_ROOT: &str = "/sys/fs/cgroup";
+
+/// Signal sent when `RLIMIT_CPU` is exceeded. Hardcoded like the signals in
+/// `exec.rs` since it is the same on every platform the sandbox supports.
+const SIGXCPU_CODE: i32 = 24;
+
+/// `rmdir(2)` error for a cgroup that still has processes.
+const EBUSY: i32 = 16;
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self == &ResourceLimits::default()
+    }
+
+    /// Whether the sandbox helper needs a cgroup of its own to apply these
+    /// limits.
+    pub fn uses_cgroup(&self) -> bool {
+        self.memory_max_mb.is_some() || self.max_processes.is_some()
+    }
+}
+
+impl FromStr for ResourceLimits {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+/// Returns the cgroup the sandbox helper running as `pid` creates for a memory
+/// or process cap. It is a sibling of the calling process's own cgroup v2: the helper
+/// inherits that cgroup from Codex, so both sides agree on the path, and cgroup
+/// v2 only lets a cgroup without processes of its own hand memory control down
+/// to children.
+pub fn exec_cgroup_path(pid: u32) -> Option<PathBuf> {
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    // The cgroup v2 entry is the one with hierarchy ID 0 and no controllers.
+    let own_cgroup = Path::new(cgroups.lines().find_map(|line| line.strip_prefix("0::"))?);
+    let parent = own_cgroup.parent().unwrap_or(own_cgroup);
+    let mut path = PathBuf::from(CGROUP_V2_ROOT);
+    path.push(parent.strip_prefix("/").unwrap_or(parent));
+    path.push(format!("codex-exec-{pid}"));
+    Some(path)
+}
+
+/// Removes a cgroup created for a memory or process cap, killing whatever the command left
+/// running in it. Returns `true` if the kernel OOM-killed a process in it.
+pub fn remove_exec_cgroup(path: &Path) -> bool {
+    let oom_killed = std::fs::read_to_string(path.join("memory.events"))
+        .ok()
+        .and_then(|events| {
+            events.lines().find_map(|line| {
+                line.strip_prefix("oom_kill ")
+                    .and_then(|count| count.trim().parse::<u64>().ok())
+            })
+        })
+        .is_some_and(|count| count > 0);
+
+    // `cgroup.kill` needs Linux 5.14; without it leftover processes keep the
+    // cgroup busy and it is merely leaked.
+    let _ = std::fs::write(path.join("cgroup.kill"), "1");
+    let mut result = std::fs::remove_dir(path);
+    for _ in 0..10 {
+        match &result {
+            Err(e) if e.raw_os_error() == Some(EBUSY) => {
+                std::thread::sleep(Duration::from_millis(10));
+                result = std::fs::remove_dir(path);
+            }
+            _ => break,
+        }
+    }
+    if let Err(e) = result {
+        tracing::warn!("failed to remove cgroup {}: {e}", path.display());
+    }
+    oom_killed
+}
+
+/// How a command run under [`ResourceLimits`] ended.
+pub struct LimitedExit<'a> {
+    /// Signal that terminated the command, if any.
+    pub signal: Option<i32>,
+    pub exit_code: i32,
+    pub stderr: &'a str,
+    /// Whether the memory cap's cgroup recorded an OOM kill.
+    pub memory_cap_exceeded: bool,
+}
+
+/// Returns a message for the model describing the limit that most likely
+/// stopped the command, or `None` if no configured limit seems to be involved.
+///
+/// CPU time and the memory cap are detected exactly. The other limits make
+/// system calls fail rather than kill the command, so they are inferred from
+/// the usual error messages on stderr.
+pub fn describe_exceeded_limit(limits: &ResourceLimits, exit: &LimitedExit) -> Option<String> {
+    if let Some(mb) = limits.memory_max_mb
+        && exit.memory_cap_exceeded
+    {
+        return Some(format!(
+            "The command was killed for exceeding its memory cap of {mb} MiB (resource_limits.memory_max_mb). Reduce its memory use, e.g. by processing less data at once or lowering build parallelism."
+        ));
+    }
+
+    if let Some(seconds) = limits.cpu_time_seconds {
+        // A shell reports a child killed by a signal as 128 + the signal.
+        if exit.signal == Some(SIGXCPU_CODE) || exit.exit_code == 128 + SIGXCPU_CODE {
+            return Some(format!(
+                "The command was killed for exceeding its CPU time limit of {seconds} seconds (resource_limits.cpu_time_seconds). Split the work into smaller commands or reduce its scope."
+            ));
+        }
+    }
+
+    if exit.signal.is_none() && exit.exit_code == 0 {
+        return None;
+    }
+    let stderr = exit.stderr.to_ascii_lowercase();
+
+    if let Some(count) = limits.max_open_files
+        && stderr.contains("too many open files")
+    {
+        return Some(format!(
+            "The command likely ran out of file descriptors: it is limited to {count} open files (resource_limits.max_open_files). Open fewer files at once."
+        ));
+    }
+
+    if let Some(count) = limits.max_processes
+        && (stderr.contains("resource temporarily unavailable") || stderr.contains("cannot fork"))
+    {
+        return Some(format!(
+            "The command likely could not start a process or thread: it is limited to {count} processes and threads (resource_limits.max_processes). Reduce parallelism, e.g. with `-j1` or fewer worker threads."
+        ));
+    }
+
+    if let Some(mb) = limits.address_space_mb
+        && [
+            "cannot allocate memory",
+            "memory allocation of",
+            "out of memory",
+            "memoryerror",
+            "bad_alloc",
+        ]
+        .iter()
+        .any(|needle| stderr.contains(needle))
+    {
+        return Some(format!(
+            "The command likely failed to allocate memory: its address space is limited to {mb} MiB (resource_limits.address_space_mb). Reduce its memory use, e.g. by processing less data at once or lowering build parallelism."
+        ));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn exit(signal: Option<i32>, exit_code: i32, stderr: &str) -> LimitedExit<'_> {
+        LimitedExit {
+            signal,
+            exit_code,
+            stderr,
+            memory_cap_exceeded: false,
+        }
+    }
+
+    #[test]
+    fn parses_limits_from_json() {
+        assert_eq!(
+            Ok(ResourceLimits {
+                cpu_time_seconds: Some(60),
+                max_open_files: Some(256),
+                ..Default::default()
+            }),
+            r#"{"cpu_time_seconds":60,"max_open_files":256}"#
+                .parse::<ResourceLimits>()
+                .map_err(|e| e.to_string())
+        );
+        assert!(ResourceLimits::default().is_empty());
+    }
+
+    #[test]
+    fn detects_cpu_time_limit_from_signal_or_shell_exit_code() {
+        let limits = ResourceLimits {
+            cpu_time_seconds: Some(5),
+            ..Default::default()
+        };
+        for exit in [
+            exit(Some(SIGXCPU_CODE), -1, ""),
+            exit(None, 128 + SIGXCPU_CODE, ""),
+        ] {
+            let message = describe_exceeded_limit(&limits, &exit);
+            assert!(
+                message
+                    .as_deref()
+                    .is_some_and(|m| m.contains("CPU time limit of 5 seconds")),
+                "{message:?}"
+            );
+        }
+        assert_eq!(
+            None,
+            describe_exceeded_limit(
+                &ResourceLimits::default(),
+                &exit(Some(SIGXCPU_CODE), -1, "")
+            )
+        );
+    }
+
+    #[test]
+    fn detects_memory_cap_from_cgroup_oom_kill() {
+        let limits = ResourceLimits {
+            memory_max_mb: Some(512),
+            ..Default::default()
+        };
+        let exit = LimitedExit {
+            memory_cap_exceeded: true,
+            ..exit(Some(9), -1, "")
+        };
+        let message = describe_exceeded_limit(&limits, &exit);
+        assert!(
+            message
+                .as_deref()
+                .is_some_and(|m| m.contains("memory cap of 512 MiB")),
+            "{message:?}"
+        );
+    }
+
+    #[test]
+    fn infers_other_limits_from_stderr() {
+        let limits = ResourceLimits {
+            address_space_mb: Some(256),
+            max_processes: Some(32),
+            max_open_files: Some(64),
+            ..Default::default()
+        };
+        let describe = |stderr| describe_exceeded_limit(&limits, &exit(None, 1, stderr));
+
+        assert!(
+            describe("cat: foo: Too many open files")
+                .is_some_and(|m| m.contains("limited to 64 open files"))
+        );
+        assert!(
+            describe("bash: fork: retry: Resource temporarily unavailable")
+                .is_some_and(|m| m.contains("limited to 32 processes"))
+        );
+        assert!(
+            describe("memory allocation of 1073741824 bytes failed")
+                .is_some_and(|m| m.contains("limited to 256 MiB"))
+        );
+        assert_eq!(None, describe("error: could not compile `foo`"));
+        // A successful command did not hit anything, whatever it printed.
+        assert_eq!(
+            None,
+            describe_exceeded_limit(&limits, &exit(None, 0, "Too many open files"))
+        );
+    }
+}
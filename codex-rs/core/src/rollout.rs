@@ -4,6 +4,7 @@ use std::fs::File;
 use std::fs::{self};
 use std::io::Error as IoError;
 use std::path::Path;
+use std::sync::Arc;
 
 use serde::Deserialize;
 use serde::Serialize;
@@ -23,6 +24,8 @@ use crate::config::Config;
 use crate::git_info::GitInfo;
 use crate::git_info::collect_git_info;
 use crate::models::ResponseItem;
+use crate::redact::RedactionCounts;
+use crate::redact::Redactor;
 
 const SESSIONS_SUBDIR: &str = "sessions";
 
@@ -66,12 +69,16 @@ pub struct SavedSession {
 #[derive(Clone)]
 pub(crate) struct RolloutRecorder {
     tx: Sender<RolloutCmd>,
+    redactor: Arc<Redactor>,
 }
 
 enum RolloutCmd {
-    AddItems(Vec<ResponseItem>),
+    /// Items already serialized and redacted.
+    AddItems(Vec<Value>),
     UpdateState(SessionStateSnapshot),
-    Shutdown { ack: oneshot::Sender<()> },
+    Shutdown {
+        ack: oneshot::Sender<()>,
+    },
 }
 
 impl RolloutRecorder {
@@ -82,6 +89,7 @@ impl RolloutRecorder {
         config: &Config,
         uuid: Uuid,
         instructions: Option<String>,
+        redactor: Arc<Redactor>,
     ) -> std::io::Result<Self> {
         let LogFileInfo {
             file,
@@ -118,10 +126,17 @@ impl RolloutRecorder {
             cwd,
         ));
 
-        Ok(Self { tx })
+        Ok(Self { tx, redactor })
     }
 
-    pub(crate) async fn record_items(&self, items: &[ResponseItem]) -> std::io::Result<()> {
+    /// Queues `items` for writing and returns the secrets that were redacted
+    /// from them. Tool output was redacted before it reached the model, but
+    /// user messages may still carry secrets.
+    pub(crate) async fn record_items(
+        &self,
+        items: &[ResponseItem],
+    ) -> std::io::Result<RedactionCounts> {
+        let mut counts = RedactionCounts::default();
         let mut filtered = Vec::new();
         for item in items {
             match item {
@@ -132,7 +147,11 @@ impl RolloutRecorder {
                 | ResponseItem::LocalShellCall { .. }
                 | ResponseItem::FunctionCall { .. }
                 | ResponseItem::FunctionCallOutput { .. }
-                | ResponseItem::Reasoning { .. } => filtered.push(item.clone()),
+                | ResponseItem::Reasoning { .. } => {
+                    let mut item = serde_json::to_value(item)?;
+                    self.redactor.redact_json(&mut item, &mut counts);
+                    filtered.push(item);
+                }
                 ResponseItem::Other => {
                     // These should never be serialized.
                     continue;
@@ -140,12 +159,13 @@ impl RolloutRecorder {
             }
         }
         if filtered.is_empty() {
-            return Ok(());
+            return Ok(counts);
         }
         self.tx
             .send(RolloutCmd::AddItems(filtered))
             .await
-            .map_err(|e| IoError::other(format!("failed to queue rollout items: {e}")))
+            .map_err(|e| IoError::other(format!("failed to queue rollout items: {e}")))?;
+        Ok(counts)
     }
 
     pub(crate) async fn record_state(&self, state: SessionStateSnapshot) -> std::io::Result<()> {
@@ -158,6 +178,7 @@ impl RolloutRecorder {
     pub async fn resume(
         path: &Path,
         cwd: std::path::PathBuf,
+        redactor: Arc<Redactor>,
     ) -> std::io::Result<(Self, SavedSession)> {
         info!("Resuming rollout from {path:?}");
         let text = tokio::fs::read_to_string(path).await?;
@@ -223,7 +244,7 @@ impl RolloutRecorder {
             cwd,
         ));
         info!("Resumed rollout successfully from {path:?}");
-        Ok((Self { tx }, saved))
+        Ok((Self { tx, redactor }, saved))
     }
 
     pub async fn shutdown(&self) -> std::io::Result<()> {
@@ -312,16 +333,7 @@ async fn rollout_writer(
         match cmd {
             RolloutCmd::AddItems(items) => {
                 for item in items {
-                    match item {
-                        ResponseItem::Message { .. }
-                        | ResponseItem::LocalShellCall { .. }
-                        | ResponseItem::FunctionCall { .. }
-                        | ResponseItem::FunctionCallOutput { .. }
-                        | ResponseItem::Reasoning { .. } => {
-                            writer.write_line(&item).await?;
-                        }
-                        ResponseItem::Other => {}
-                    }
+                    writer.write_line(&item).await?;
                 }
             }
             RolloutCmd::UpdateState(state) => {
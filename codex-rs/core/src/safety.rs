@@ -1,4 +1,3 @@
-use std::collections::HashSet;
 use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
@@ -6,7 +5,10 @@ use std::path::PathBuf;
 use codex_apply_patch::ApplyPatchAction;
 use codex_apply_patch::ApplyPatchFileChange;
 
+use crate::command_approvals::ApprovedCommands;
 use crate::exec::SandboxType;
+use crate::exec_policy::PolicyMatch;
+use crate::exec_policy::PolicyVerdict;
 use crate::is_safe_command::is_known_safe_command;
 use crate::protocol::AskForApproval;
 use crate::protocol::SandboxPolicy;
@@ -65,33 +67,82 @@ pub fn assess_patch_safety(
     }
 }
 
+/// Like [`assess_patch_safety`], but for edits that Codex writes itself rather
+/// than by running `apply_patch`. Such a write cannot be sandboxed, so it is
+/// only auto-approved when every path is inside the writable roots; the caller
+/// is expected to have resolved symlinks in `action` already.
+pub fn assess_direct_write_safety(
+    action: &ApplyPatchAction,
+    policy: AskForApproval,
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+) -> SafetyCheck {
+    if action.is_empty() {
+        return SafetyCheck::Reject {
+            reason: "empty edit".to_string(),
+        };
+    }
+
+    if policy == AskForApproval::UnlessTrusted {
+        return SafetyCheck::AskUser;
+    }
+
+    if is_write_patch_constrained_to_writable_paths(action, sandbox_policy, cwd) {
+        SafetyCheck::AutoApprove {
+            sandbox_type: SandboxType::None,
+        }
+    } else if policy == AskForApproval::Never {
+        SafetyCheck::Reject {
+            reason: "writing outside of the project; rejected by user approval settings"
+                .to_string(),
+        }
+    } else {
+        SafetyCheck::AskUser
+    }
+}
+
 /// For a command to be run _without_ a sandbox, one of the following must be
 /// true:
 ///
-/// - the user has explicitly approved the command
+/// - the user has explicitly approved the command, for the session or for
+///   the project
+/// - an exec policy rule allows it with `decision="allow-unsandboxed"`
 /// - the command is on the "known safe" list
 /// - `DangerFullAccess` was specified and `UnlessTrusted` was not
+///
+/// `policy_match` is the verdict of the user and project exec policies, if
+/// any rule matched. A forbidding rule always wins; otherwise a command the
+/// user approved for the session is trusted before the policy is consulted.
 pub fn assess_command_safety(
     command: &[String],
     approval_policy: AskForApproval,
     sandbox_policy: &SandboxPolicy,
-    approved: &HashSet<Vec<String>>,
+    approved: &ApprovedCommands,
+    policy_match: Option<&PolicyMatch>,
     with_escalated_permissions: bool,
 ) -> SafetyCheck {
-    // A command is "trusted" because either:
-    // - it belongs to a set of commands we consider "safe" by default, or
-    // - the user has explicitly approved the command for this session
-    //
-    // Currently, whether a command is "trusted" is a simple boolean, but we
-    // should include more metadata on this command test to indicate whether it
-    // should be run inside a sandbox or not. (This could be something the user
-    // defines as part of `execpolicy`.)
-    //
-    // For example, when `is_known_safe_command(command)` returns `true`, it
-    // would probably be fine to run the command in a sandbox, but when
-    // `approved.contains(command)` is `true`, the user may have approved it for
-    // the session _because_ they know it needs to run outside a sandbox.
-    if is_known_safe_command(command) || approved.contains(command) {
+    if let Some(PolicyMatch {
+        verdict: PolicyVerdict::Forbid { reason },
+        rule,
+    }) = policy_match
+    {
+        return SafetyCheck::Reject {
+            reason: format!("forbidden by exec policy rule `{rule}`: {reason}"),
+        };
+    }
+    if approved.contains(command) {
+        return SafetyCheck::AutoApprove {
+            sandbox_type: SandboxType::None,
+        };
+    }
+    if let Some(policy_match) = policy_match {
+        return assess_policy_verdict(policy_match, approval_policy, with_escalated_permissions);
+    }
+
+    // Commands on the built-in "known safe" list are trusted outright. Users
+    // who want finer control (e.g. run a command, but only in the sandbox)
+    // can express it with an exec policy rule, which is consulted above.
+    if is_known_safe_command(command) {
         return SafetyCheck::AutoApprove {
             sandbox_type: SandboxType::None,
         };
@@ -100,6 +151,37 @@ pub fn assess_command_safety(
     assess_safety_for_untrusted_command(approval_policy, sandbox_policy, with_escalated_permissions)
 }
 
+fn assess_policy_verdict(
+    PolicyMatch { verdict, rule }: &PolicyMatch,
+    approval_policy: AskForApproval,
+    with_escalated_permissions: bool,
+) -> SafetyCheck {
+    // With `Never` there is nobody to ask.
+    let ask = || match approval_policy {
+        AskForApproval::Never => SafetyCheck::Reject {
+            reason: format!(
+                "exec policy rule `{rule}` requires approval; rejected by user approval settings"
+            ),
+        },
+        _ => SafetyCheck::AskUser,
+    };
+    match verdict {
+        PolicyVerdict::AutoApproveUnsandboxed => SafetyCheck::AutoApprove {
+            sandbox_type: SandboxType::None,
+        },
+        PolicyVerdict::AutoApproveSandboxed if !with_escalated_permissions => {
+            match get_platform_sandbox() {
+                Some(sandbox_type) => SafetyCheck::AutoApprove { sandbox_type },
+                None => ask(),
+            }
+        }
+        PolicyVerdict::AutoApproveSandboxed | PolicyVerdict::Ask => ask(),
+        PolicyVerdict::Forbid { reason } => SafetyCheck::Reject {
+            reason: format!("forbidden by exec policy rule `{rule}`: {reason}"),
+        },
+    }
+}
+
 pub(crate) fn assess_safety_for_untrusted_command(
     approval_policy: AskForApproval,
     sandbox_policy: &SandboxPolicy,
@@ -120,7 +202,7 @@ pub(crate) fn assess_safety_for_untrusted_command(
         | (OnRequest, DangerFullAccess) => SafetyCheck::AutoApprove {
             sandbox_type: SandboxType::None,
         },
-        (OnRequest, ReadOnly) | (OnRequest, WorkspaceWrite { .. }) => {
+        (OnRequest, ReadOnly { .. }) | (OnRequest, WorkspaceWrite { .. }) => {
             if with_escalated_permissions {
                 SafetyCheck::AskUser
             } else {
@@ -132,9 +214,9 @@ pub(crate) fn assess_safety_for_untrusted_command(
                 }
             }
         }
-        (Never, ReadOnly)
+        (Never, ReadOnly { .. })
         | (Never, WorkspaceWrite { .. })
-        | (OnFailure, ReadOnly)
+        | (OnFailure, ReadOnly { .. })
         | (OnFailure, WorkspaceWrite { .. }) => {
             match get_platform_sandbox() {
                 Some(sandbox_type) => SafetyCheck::AutoApprove { sandbox_type },
@@ -176,7 +258,7 @@ fn is_write_patch_constrained_to_writable_paths(
 ) -> bool {
     // Early‑exit if there are no declared writable roots.
     let writable_roots = match sandbox_policy {
-        SandboxPolicy::ReadOnly => {
+        SandboxPolicy::ReadOnly { .. } => {
             return false;
         }
         SandboxPolicy::DangerFullAccess => {
@@ -222,7 +304,10 @@ fn is_write_patch_constrained_to_writable_paths(
 
     for (path, change) in action.changes() {
         match change {
-            ApplyPatchFileChange::Add { .. } | ApplyPatchFileChange::Delete => {
+            ApplyPatchFileChange::Add { .. }
+            | ApplyPatchFileChange::Delete
+            | ApplyPatchFileChange::Binary { .. }
+            | ApplyPatchFileChange::Symlink { .. } => {
                 if !is_path_writable(path) {
                     return false;
                 }
@@ -269,6 +354,8 @@ mod tests {
             network_access: false,
             exclude_tmpdir_env_var: true,
             exclude_slash_tmp: true,
+            readable_roots: vec![],
+            allowed_domains: vec![],
         };
 
         assert!(is_write_patch_constrained_to_writable_paths(
@@ -290,6 +377,8 @@ mod tests {
             network_access: false,
             exclude_tmpdir_env_var: true,
             exclude_slash_tmp: true,
+            readable_roots: vec![],
+            allowed_domains: vec![],
         };
         assert!(is_write_patch_constrained_to_writable_paths(
             &add_outside,
@@ -298,13 +387,140 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_direct_write_safety() {
+        let tmp = TempDir::new().unwrap();
+        let cwd = tmp.path().to_path_buf();
+        let parent = cwd.parent().unwrap().to_path_buf();
+        let inside = ApplyPatchAction::new_add_for_test(&cwd.join("a.txt"), "".to_string());
+        let outside = ApplyPatchAction::new_add_for_test(&parent.join("a.txt"), "".to_string());
+        let workspace = SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec![],
+            network_access: false,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+            readable_roots: vec![],
+            allowed_domains: vec![],
+        };
+
+        assert_eq!(
+            assess_direct_write_safety(&inside, AskForApproval::OnRequest, &workspace, &cwd),
+            SafetyCheck::AutoApprove {
+                sandbox_type: SandboxType::None
+            }
+        );
+        // Unlike `apply_patch`, an edit outside the writable roots is never
+        // auto-approved under `OnFailure`, since there is no sandbox to fail.
+        assert_eq!(
+            assess_direct_write_safety(&outside, AskForApproval::OnFailure, &workspace, &cwd),
+            SafetyCheck::AskUser
+        );
+        assert!(matches!(
+            assess_direct_write_safety(&outside, AskForApproval::Never, &workspace, &cwd),
+            SafetyCheck::Reject { .. }
+        ));
+        assert_eq!(
+            assess_direct_write_safety(
+                &inside,
+                AskForApproval::OnRequest,
+                &SandboxPolicy::new_read_only_policy(),
+                &cwd
+            ),
+            SafetyCheck::AskUser
+        );
+    }
+
+    #[test]
+    fn test_exec_policy_verdicts() {
+        let command = vec!["cargo".to_string(), "test".to_string()];
+        let approved = ApprovedCommands::default();
+        let policy_match = |verdict| PolicyMatch {
+            verdict,
+            rule: "cargo-test".to_string(),
+        };
+        let assess = |m: &PolicyMatch, approval_policy| {
+            assess_command_safety(
+                &command,
+                approval_policy,
+                &SandboxPolicy::new_read_only_policy(),
+                &approved,
+                Some(m),
+                false,
+            )
+        };
+
+        assert_eq!(
+            SafetyCheck::AutoApprove {
+                sandbox_type: SandboxType::None
+            },
+            assess(
+                &policy_match(PolicyVerdict::AutoApproveUnsandboxed),
+                AskForApproval::UnlessTrusted
+            )
+        );
+        assert_eq!(
+            SafetyCheck::AskUser,
+            assess(&policy_match(PolicyVerdict::Ask), AskForApproval::OnFailure)
+        );
+        assert_eq!(
+            SafetyCheck::Reject {
+                reason: "exec policy rule `cargo-test` requires approval; rejected by user approval settings".to_string()
+            },
+            assess(&policy_match(PolicyVerdict::Ask), AskForApproval::Never)
+        );
+
+        // A forbidding rule wins even over a command approved for the session.
+        let mut approved = ApprovedCommands::default();
+        approved.add_for_session(command.clone());
+        let forbid = policy_match(PolicyVerdict::Forbid {
+            reason: "no tests today".to_string(),
+        });
+        assert_eq!(
+            SafetyCheck::Reject {
+                reason: "forbidden by exec policy rule `cargo-test`: no tests today".to_string()
+            },
+            assess_command_safety(
+                &command,
+                AskForApproval::OnRequest,
+                &SandboxPolicy::DangerFullAccess,
+                &approved,
+                Some(&forbid),
+                false,
+            )
+        );
+    }
+
+    #[test]
+    fn test_project_approved_commands_run_unsandboxed() {
+        let approved = ApprovedCommands::new(&["cargo test *".to_string()]);
+        let assess = |command: &[&str]| {
+            let command: Vec<String> = command.iter().map(|s| s.to_string()).collect();
+            assess_command_safety(
+                &command,
+                AskForApproval::UnlessTrusted,
+                &SandboxPolicy::new_read_only_policy(),
+                &approved,
+                None,
+                false,
+            )
+        };
+
+        assert_eq!(
+            SafetyCheck::AutoApprove {
+                sandbox_type: SandboxType::None
+            },
+            assess(&["cargo", "test", "-p", "codex-core"])
+        );
+        assert_eq!(SafetyCheck::AskUser, assess(&["cargo", "publish"]));
+    }
+
     #[test]
     fn test_request_escalated_privileges() {
         // Should not be a trusted command
         let command = vec!["git commit".to_string()];
         let approval_policy = AskForApproval::OnRequest;
-        let sandbox_policy = SandboxPolicy::ReadOnly;
-        let approved: HashSet<Vec<String>> = HashSet::new();
+        let sandbox_policy = SandboxPolicy::new_read_only_policy();
+        let approved = ApprovedCommands::default();
         let request_escalated_privileges = true;
 
         let safety_check = assess_command_safety(
@@ -312,6 +528,7 @@ mod tests {
             approval_policy,
             &sandbox_policy,
             &approved,
+            None,
             request_escalated_privileges,
         );
 
@@ -322,8 +539,8 @@ mod tests {
     fn test_request_escalated_privileges_no_sandbox_fallback() {
         let command = vec!["git".to_string(), "commit".to_string()];
         let approval_policy = AskForApproval::OnRequest;
-        let sandbox_policy = SandboxPolicy::ReadOnly;
-        let approved: HashSet<Vec<String>> = HashSet::new();
+        let sandbox_policy = SandboxPolicy::new_read_only_policy();
+        let approved = ApprovedCommands::default();
         let request_escalated_privileges = false;
 
         let safety_check = assess_command_safety(
@@ -331,6 +548,7 @@ mod tests {
             approval_policy,
             &sandbox_policy,
             &approved,
+            None,
             request_escalated_privileges,
         );
 
@@ -3,6 +3,7 @@ use std::path::Path;
 use std::path::PathBuf;
 use tokio::process::Child;
 
+use crate::network_proxy::NetworkProxy;
 use crate::protocol::SandboxPolicy;
 use crate::spawn::CODEX_SANDBOX_ENV_VAR;
 use crate::spawn::StdioPolicy;
@@ -16,16 +17,34 @@ const MACOS_SEATBELT_BASE_POLICY: &str = include_str!("seatbelt_base_policy.sbpl
 /// already has root access.
 const MACOS_PATH_TO_SEATBELT_EXECUTABLE: &str = "/usr/bin/sandbox-exec";
 
+/// Folders that stay readable when a policy limits reads to its
+/// `readable_roots`, so that programs and their shared libraries still load.
+const MACOS_SYSTEM_READABLE_ROOTS: &[&str] = &[
+    "/bin",
+    "/sbin",
+    "/usr",
+    "/System",
+    "/Library",
+    "/private/etc",
+    "/dev",
+    "/opt",
+];
+
 pub async fn spawn_command_under_seatbelt(
     command: Vec<String>,
     sandbox_policy: &SandboxPolicy,
+    network_proxy: Option<&NetworkProxy>,
     cwd: PathBuf,
     stdio_policy: StdioPolicy,
     mut env: HashMap<String, String>,
 ) -> std::io::Result<Child> {
-    let args = create_seatbelt_command_args(command, sandbox_policy, &cwd);
+    let proxy_port = network_proxy.map(|proxy| proxy.tcp_addr().port());
+    let args = create_seatbelt_command_args(command, sandbox_policy, proxy_port, &cwd);
     let arg0 = None;
     env.insert(CODEX_SANDBOX_ENV_VAR.to_string(), "seatbelt".to_string());
+    if let Some(network_proxy) = network_proxy {
+        network_proxy.apply_to_env(&mut env);
+    }
     spawn_child_async(
         PathBuf::from(MACOS_PATH_TO_SEATBELT_EXECUTABLE),
         args,
@@ -41,6 +60,7 @@ pub async fn spawn_command_under_seatbelt(
 fn create_seatbelt_command_args(
     command: Vec<String>,
     sandbox_policy: &SandboxPolicy,
+    proxy_port: Option<u16>,
     cwd: &Path,
 ) -> Vec<String> {
     let (file_write_policy, extra_cli_args) = {
@@ -96,17 +116,52 @@ fn create_seatbelt_command_args(
         }
     };
 
-    let file_read_policy = if sandbox_policy.has_full_disk_read_access() {
-        "; allow read-only file operations\n(allow file-read*)"
-    } else {
-        ""
+    let (file_read_policy, read_cli_args) = match sandbox_policy.get_readable_roots_with_cwd(cwd) {
+        None => (
+            "; allow read-only file operations\n(allow file-read*)".to_string(),
+            Vec::<String>::new(),
+        ),
+        Some(readable_roots) => {
+            // Writable roots are readable too.
+            let roots = readable_roots
+                .into_iter()
+                .chain(
+                    sandbox_policy
+                        .get_writable_roots_with_cwd(cwd)
+                        .into_iter()
+                        .map(|wr| wr.root),
+                )
+                .chain(MACOS_SYSTEM_READABLE_ROOTS.iter().map(PathBuf::from));
+
+            let mut readable_folder_policies: Vec<String> = Vec::new();
+            let mut cli_args: Vec<String> = Vec::new();
+            for (index, root) in roots.enumerate() {
+                let canonical_root = root.canonicalize().unwrap_or(root);
+                let root_param = format!("READABLE_ROOT_{index}");
+                cli_args.push(format!(
+                    "-D{root_param}={}",
+                    canonical_root.to_string_lossy()
+                ));
+                readable_folder_policies.push(format!("(subpath (param \"{root_param}\"))"));
+            }
+            (
+                format!(
+                    "; allow reads beneath the readable roots\n(allow file-read-metadata)\n(allow file-read*\n{}\n)",
+                    readable_folder_policies.join(" ")
+                ),
+                cli_args,
+            )
+        }
     };
 
     // TODO(mbolin): apply_patch calls must also honor the SandboxPolicy.
     let network_policy = if sandbox_policy.has_full_network_access() {
-        "(allow network-outbound)\n(allow network-inbound)\n(allow system-socket)"
+        "(allow network-outbound)\n(allow network-inbound)\n(allow system-socket)".to_string()
+    } else if let Some(proxy_port) = proxy_port {
+        // Only the network proxy, which enforces `allowed_domains`, is reachable.
+        format!("(allow network-outbound (remote ip \"localhost:{proxy_port}\"))")
     } else {
-        ""
+        "".to_string()
     };
 
     let full_policy = format!(
@@ -115,6 +170,7 @@ fn create_seatbelt_command_args(
 
     let mut seatbelt_args: Vec<String> = vec!["-p".to_string(), full_policy];
     seatbelt_args.extend(extra_cli_args);
+    seatbelt_args.extend(read_cli_args);
     seatbelt_args.push("--".to_string());
     seatbelt_args.extend(command);
     seatbelt_args
@@ -157,11 +213,14 @@ mod tests {
             network_access: false,
             exclude_tmpdir_env_var: true,
             exclude_slash_tmp: true,
+            readable_roots: vec![],
+            allowed_domains: vec![],
         };
 
         let args = create_seatbelt_command_args(
             vec!["/bin/echo".to_string(), "hello".to_string()],
             &policy,
+            None,
             &cwd,
         );
 
@@ -232,11 +291,14 @@ mod tests {
             network_access: false,
             exclude_tmpdir_env_var: false,
             exclude_slash_tmp: false,
+            readable_roots: vec![],
+            allowed_domains: vec![],
         };
 
         let args = create_seatbelt_command_args(
             vec!["/bin/echo".to_string(), "hello".to_string()],
             &policy,
+            None,
             root_with_git.as_path(),
         );
 
@@ -300,6 +362,62 @@ mod tests {
         assert_eq!(expected_args, args);
     }
 
+    #[test]
+    fn create_seatbelt_args_with_readable_roots() {
+        let tmp = TempDir::new().expect("tempdir");
+        let readable = tmp.path().join("readable");
+        fs::create_dir_all(&readable).expect("create readable");
+        let cwd = tmp.path().join("cwd");
+
+        let policy = SandboxPolicy::ReadOnly {
+            readable_roots: vec![readable.clone()],
+        };
+        let args = create_seatbelt_command_args(vec!["/bin/ls".to_string()], &policy, None, &cwd);
+
+        let policy_text = &args[1];
+        assert!(!policy_text.contains("(allow file-read*)"));
+        assert!(policy_text.contains(
+            r#"(subpath (param "READABLE_ROOT_0")) (subpath (param "READABLE_ROOT_1"))"#
+        ));
+        assert_eq!(
+            format!(
+                "-DREADABLE_ROOT_0={}",
+                readable
+                    .canonicalize()
+                    .expect("canonicalize readable")
+                    .to_string_lossy()
+            ),
+            args[2]
+        );
+        assert_eq!(
+            format!("-DREADABLE_ROOT_1={}", cwd.to_string_lossy()),
+            args[3]
+        );
+    }
+
+    #[test]
+    fn create_seatbelt_args_with_network_proxy() {
+        let tmp = TempDir::new().expect("tempdir");
+        let mut policy = SandboxPolicy::new_workspace_write_policy();
+        if let SandboxPolicy::WorkspaceWrite {
+            allowed_domains, ..
+        } = &mut policy
+        {
+            allowed_domains.push("crates.io".to_string());
+        }
+
+        let args = create_seatbelt_command_args(
+            vec!["/bin/ls".to_string()],
+            &policy,
+            Some(8080),
+            tmp.path(),
+        );
+
+        let policy_text = &args[1];
+        assert!(policy_text.ends_with(r#"(allow network-outbound (remote ip "localhost:8080"))"#));
+        assert!(!policy_text.contains("(allow network-outbound)"));
+    }
+
     struct PopulatedTmp {
         root_with_git: PathBuf,
         root_without_git: PathBuf,
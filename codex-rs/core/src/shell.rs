@@ -212,6 +212,8 @@ mod tests {
                     )]),
                     with_escalated_permissions: None,
                     justification: None,
+                    resource_limits: Default::default(),
+                    output_limits: Default::default(),
                 },
                 SandboxType::None,
                 &SandboxPolicy::DangerFullAccess,
@@ -26,6 +26,14 @@ pub const CODEX_SANDBOX_ENV_VAR: &str = "CODEX_SANDBOX";
 pub enum StdioPolicy {
     RedirectForShellTool,
     Inherit,
+    /// Attach stdin, stdout and stderr to the given pseudo-terminal slave and
+    /// make it the controlling terminal of a new session.
+    #[cfg(unix)]
+    Pty(std::os::fd::RawFd),
+    /// Send stdout and stderr to the given file and start a new session, so
+    /// that the command and everything it starts can be killed as a group.
+    #[cfg(unix)]
+    Background(std::os::fd::RawFd),
 }
 
 /// Spawns the appropriate child process for the ExecParams and SandboxPolicy,
@@ -101,6 +109,45 @@ pub(crate) async fn spawn_child_async(
                 .stdout(Stdio::inherit())
                 .stderr(Stdio::inherit());
         }
+        #[cfg(unix)]
+        StdioPolicy::Pty(slave) => {
+            use std::os::fd::BorrowedFd;
+
+            // SAFETY: the caller keeps the slave open until the child is spawned.
+            let slave = unsafe { BorrowedFd::borrow_raw(slave) };
+            cmd.stdin(Stdio::from(slave.try_clone_to_owned()?))
+                .stdout(Stdio::from(slave.try_clone_to_owned()?))
+                .stderr(Stdio::from(slave.try_clone_to_owned()?));
+            unsafe {
+                cmd.pre_exec(|| {
+                    // Become a session leader so the terminal (now stdin) can
+                    // become the controlling one, which job control and
+                    // Ctrl-C handling in the command rely on.
+                    if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+        #[cfg(unix)]
+        StdioPolicy::Background(log) => {
+            use std::os::fd::BorrowedFd;
+
+            // SAFETY: the caller keeps the file open until the child is spawned.
+            let log = unsafe { BorrowedFd::borrow_raw(log) };
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::from(log.try_clone_to_owned()?))
+                .stderr(Stdio::from(log.try_clone_to_owned()?));
+            unsafe {
+                cmd.pre_exec(|| {
+                    if libc::setsid() == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
     }
 
     cmd.kill_on_drop(true).spawn()
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
@@ -10,6 +11,7 @@ use anyhow::anyhow;
 use sha1::digest::Output;
 use uuid::Uuid;
 
+use crate::notebook;
 use crate::protocol::FileChange;
 
 const ZERO_OID: &str = "0000000000000000000000000000000000000000";
@@ -62,7 +64,7 @@ impl TurnDiffTracker {
                     .insert(internal.clone(), path.clone());
 
                 // If the file exists on disk now, snapshot as baseline; else leave missing to represent /dev/null.
-                let baseline_file_info = if path.exists() {
+                let baseline_file_info = if path_exists(path) {
                     let mode = file_mode_for_path(path);
                     let mode_val = mode.unwrap_or(FileMode::Regular);
                     let content = blob_bytes(path, &mode_val).unwrap_or_default();
@@ -294,8 +296,9 @@ impl TurnDiffTracker {
             None
         };
 
-        // Fast path: identical bytes or both missing.
-        if left_bytes == right_bytes.as_deref() {
+        // Fast path: identical bytes and mode, or both missing.
+        let same_bytes = left_bytes == right_bytes.as_deref();
+        if same_bytes && (baseline_mode == current_mode || right_bytes.is_none()) {
             return aggregated;
         }
 
@@ -311,6 +314,10 @@ impl TurnDiffTracker {
         } else if baseline_mode != current_mode {
             aggregated.push_str(&format!("old mode {baseline_mode}\n"));
             aggregated.push_str(&format!("new mode {current_mode}\n"));
+            // Like git, a change to only the mode has no contents to show.
+            if same_bytes {
+                return aggregated;
+            }
         }
 
         let left_text = left_bytes.and_then(|b| std::str::from_utf8(b).ok());
@@ -324,10 +331,30 @@ impl TurnDiffTracker {
         );
 
         if can_text_diff {
-            let l = left_text.unwrap_or("");
-            let r = right_text.unwrap_or("");
+            let mut l = Cow::Borrowed(left_text.unwrap_or(""));
+            let mut r = Cow::Borrowed(right_text.unwrap_or(""));
+            // Show notebooks cell by cell instead of as JSON, unless only
+            // something the cells do not show, such as metadata, changed.
+            // The cells are not the blob's contents, so such an entry has no
+            // `index` line and cannot be applied with `git apply`.
+            let mut cell_view = false;
+            if current_external_path
+                .extension()
+                .is_some_and(|ext| ext == "ipynb")
+                && let (Some(left_cells), Some(right_cells)) = (
+                    notebook_cells_for_diff(left_text, left_present),
+                    notebook_cells_for_diff(right_text, right_bytes.is_some()),
+                )
+                && left_cells != right_cells
+            {
+                l = Cow::Owned(left_cells);
+                r = Cow::Owned(right_cells);
+                cell_view = true;
+            }
 
-            aggregated.push_str(&format!("index {left_oid}..{right_oid}\n"));
+            if !cell_view {
+                aggregated.push_str(&format!("index {left_oid}..{right_oid}\n"));
+            }
 
             let old_header = if left_present {
                 format!("a/{left_display}")
@@ -340,7 +367,7 @@ impl TurnDiffTracker {
                 DEV_NULL.to_string()
             };
 
-            let diff = similar::TextDiff::from_lines(l, r);
+            let diff = similar::TextDiff::from_lines(l.as_ref(), r.as_ref());
             let unified = diff
                 .unified_diff()
                 .context_radius(3)
@@ -368,6 +395,16 @@ impl TurnDiffTracker {
     }
 }
 
+/// The cells of one side of a notebook diff, rendered as text. A side that
+/// is missing has no cells.
+fn notebook_cells_for_diff(text: Option<&str>, present: bool) -> Option<String> {
+    if present {
+        notebook::render_for_diff(text?)
+    } else {
+        Some(String::new())
+    }
+}
+
 /// Compute the Git SHA-1 blob object ID for the given content (bytes).
 fn git_blob_sha1_hex_bytes(data: &[u8]) -> Output<sha1::Sha1> {
     // Git blob hash is sha1 of: "blob <len>\0<data>"
@@ -427,8 +464,13 @@ fn file_mode_for_path(_path: &Path) -> Option<FileMode> {
     Some(FileMode::Regular)
 }
 
+/// Whether `path` exists, counting symlinks whose target does not.
+fn path_exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
 fn blob_bytes(path: &Path, mode: &FileMode) -> Option<Vec<u8>> {
-    if path.exists() {
+    if path_exists(path) {
         let contents = if *mode == FileMode::Symlink {
             symlink_blob_bytes(path)
                 .ok_or_else(|| anyhow!("failed to read symlink target for {}", path.display()))
@@ -515,6 +557,7 @@ mod tests {
             file.clone(),
             FileChange::Add {
                 content: "foo\n".to_string(),
+                executable: false,
             },
         )]);
         acc.on_patch_begin(&add_changes);
@@ -545,6 +588,7 @@ index {ZERO_OID}..{right_oid}
             FileChange::Update {
                 unified_diff: "".to_owned(),
                 move_path: None,
+                executable: None,
             },
         )]);
         acc.on_patch_begin(&update_changes);
@@ -615,6 +659,7 @@ index {left_oid}..{ZERO_OID}
             FileChange::Update {
                 unified_diff: "".to_owned(),
                 move_path: Some(dest.clone()),
+                executable: None,
             },
         )]);
         acc.on_patch_begin(&mv_changes);
@@ -655,6 +700,7 @@ index {left_oid}..{right_oid}
             FileChange::Update {
                 unified_diff: "".to_owned(),
                 move_path: Some(dest.clone()),
+                executable: None,
             },
         )]);
         acc.on_patch_begin(&mv_changes);
@@ -677,6 +723,7 @@ index {left_oid}..{right_oid}
             FileChange::Update {
                 unified_diff: "".into(),
                 move_path: Some(dest.clone()),
+                executable: None,
             },
         )]);
         acc.on_patch_begin(&mv);
@@ -717,6 +764,7 @@ index {ZERO_OID}..{right_oid}
             FileChange::Update {
                 unified_diff: "".to_owned(),
                 move_path: None,
+                executable: None,
             },
         )]);
         acc.on_patch_begin(&update_a);
@@ -792,6 +840,7 @@ index {left_oid_b}..{ZERO_OID}
             FileChange::Update {
                 unified_diff: "".to_owned(),
                 move_path: None,
+                executable: None,
             },
         )]);
         acc.on_patch_begin(&update_changes);
@@ -816,6 +865,59 @@ Binary files differ
         assert_eq!(diff, expected);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn mode_change_and_symlink_add() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("run.sh");
+        let link = dir.path().join("link");
+        fs::write(&script, "echo hi\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut acc = TurnDiffTracker::new();
+        acc.on_patch_begin(&HashMap::from([
+            (
+                script.clone(),
+                FileChange::Update {
+                    unified_diff: String::new(),
+                    move_path: None,
+                    executable: Some(true),
+                },
+            ),
+            (
+                link.clone(),
+                FileChange::Symlink {
+                    target: PathBuf::from("run.sh"),
+                },
+            ),
+        ]));
+
+        // Apply the changes on disk.
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("run.sh", &link).unwrap();
+
+        let diff = acc.get_unified_diff().unwrap().unwrap();
+        let diff = normalize_diff_for_test(&diff, dir.path());
+        let link_oid = git_blob_sha1_hex("run.sh");
+        let expected = format!(
+            r#"diff --git a/<TMP>/link b/<TMP>/link
+new file mode 120000
+index {ZERO_OID}..{link_oid}
+--- {DEV_NULL}
++++ b/<TMP>/link
+@@ -0,0 +1 @@
++run.sh
+\ No newline at end of file
+diff --git a/<TMP>/run.sh b/<TMP>/run.sh
+old mode 100644
+new mode 100755
+"#
+        );
+        assert_eq!(diff, expected);
+    }
+
     #[test]
     fn filenames_with_spaces_add_and_update() {
         let mut acc = TurnDiffTracker::new();
@@ -828,6 +930,7 @@ Binary files differ
             file.clone(),
             FileChange::Add {
                 content: "foo\n".to_string(),
+                executable: false,
             },
         )]);
         acc.on_patch_begin(&add_changes);
@@ -858,6 +961,7 @@ index {ZERO_OID}..{right_oid}
             FileChange::Update {
                 unified_diff: "".to_owned(),
                 move_path: None,
+                executable: None,
             },
         )]);
         acc.on_patch_begin(&update_changes);
@@ -883,4 +987,60 @@ index {ZERO_OID}..{right_oid}
         };
         assert_eq!(combined, expected_combined);
     }
+
+    #[test]
+    fn notebooks_diff_by_cell() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("nb.ipynb");
+        let notebook = |sources: &[&str]| {
+            let cells: Vec<String> = sources
+                .iter()
+                .map(|source| {
+                    format!(r#"{{"cell_type": "code", "metadata": {{}}, "outputs": [], "source": ["{source}"]}}"#)
+                })
+                .collect();
+            format!(
+                "{{\"cells\": [{}], \"metadata\": {{}}, \"nbformat\": 4, \"nbformat_minor\": 4}}\n",
+                cells.join(", ")
+            )
+        };
+        let before = notebook(&["a = 1", "b = 2"]);
+        let after = notebook(&["a = 1", "b = 3", "c = 4"]);
+        fs::write(&file, &before).unwrap();
+
+        let mut acc = TurnDiffTracker::new();
+        let update_changes = HashMap::from([(
+            file.clone(),
+            FileChange::Update {
+                unified_diff: "".to_owned(),
+                move_path: None,
+                executable: None,
+            },
+        )]);
+        acc.on_patch_begin(&update_changes);
+        fs::write(&file, &after).unwrap();
+
+        let diff = acc.get_unified_diff().unwrap().unwrap();
+        let diff = normalize_diff_for_test(&diff, dir.path());
+        let expected = r#"diff --git a/<TMP>/nb.ipynb b/<TMP>/nb.ipynb
+--- a/<TMP>/nb.ipynb
++++ b/<TMP>/nb.ipynb
+@@ -1,4 +1,6 @@
+ # %% [code]
+ a = 1
+ # %% [code]
+-b = 2
++b = 3
++# %% [code]
++c = 4
+"#;
+        assert_eq!(diff, expected);
+
+        // A change the cells do not show falls back to a diff of the JSON.
+        let metadata_only = before.replace("\"nbformat_minor\": 4", "\"nbformat_minor\": 5");
+        fs::write(&file, &metadata_only).unwrap();
+        let diff = acc.get_unified_diff().unwrap().unwrap();
+        assert!(diff.contains("+{\"cells\""), "{diff}");
+        assert!(diff.contains("\nindex "), "{diff}");
+    }
 }
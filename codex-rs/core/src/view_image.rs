@@ -0,0 +1,269 @@
+//! The `view_image` tool: lets the model look at an image on disk, such as a
+//! screenshot taken by a test or a headless browser, so it can check UI work.
+//!
+//! Function call outputs can only hold text, so the image is attached to a
+//! user message that is sent at the start of the next turn, encoded the same
+//! way as an `InputItem::LocalImage`.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use image::DynamicImage;
+use image::GenericImageView;
+use image::ImageFormat;
+use image::imageops::FilterType;
+use serde::Deserialize;
+
+use crate::file_tools::ReadScope;
+use crate::models::ContentItem;
+use crate::models::ResponseInputItem;
+use crate::models::image_data_url;
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+
+/// Larger files are refused without being decoded.
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Images are downscaled to fit in this many pixels on each side, which is
+/// as much detail as the models look at.
+const MAX_DIMENSION: u32 = 2048;
+
+/// Images are downscaled further until they encode to at most this many
+/// bytes, to keep requests and rollouts small.
+const MAX_ENCODED_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ViewImageParams {
+    path: String,
+}
+
+pub(crate) static VIEW_IMAGE_TOOL: LazyLock<OpenAiTool> = LazyLock::new(|| {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "path".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "PNG or JPEG file to look at, absolute or relative to the working directory."
+                    .to_string(),
+            ),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "view_image".to_string(),
+        description: "Shows you a PNG or JPEG image from disk, such as a screenshot taken by a test or a headless browser, so you can check what you built. The image is attached to the next message you receive. Large images are downscaled.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(vec!["path".to_string()]),
+            additional_properties: Some(false),
+        },
+    })
+});
+
+/// An image ready to be sent to the model.
+#[derive(Debug)]
+pub(crate) struct LoadedImage {
+    path: PathBuf,
+    data_url: String,
+    width: u32,
+    height: u32,
+    /// The size of the image on disk, when it was downscaled.
+    original_size: Option<(u32, u32)>,
+}
+
+impl LoadedImage {
+    /// What the tool did, for the function call output.
+    pub(crate) fn summary(&self) -> String {
+        let mut summary = format!(
+            "Attached {} ({}x{}) to the next message.",
+            self.path.display(),
+            self.width,
+            self.height
+        );
+        if let Some((width, height)) = self.original_size {
+            summary.push_str(&format!(" It was downscaled from {width}x{height}."));
+        }
+        summary
+    }
+
+    /// The user message that carries the image.
+    pub(crate) fn into_input_item(self) -> ResponseInputItem {
+        ResponseInputItem::Message {
+            role: "user".to_string(),
+            content: vec![
+                ContentItem::InputText {
+                    text: format!("Image {} requested with view_image:", self.path.display()),
+                },
+                ContentItem::InputImage {
+                    image_url: self.data_url,
+                },
+            ],
+        }
+    }
+}
+
+/// Reads, checks and if necessary downscales the image `params` names.
+pub(crate) fn load_image(
+    scope: &ReadScope,
+    params: &ViewImageParams,
+) -> Result<LoadedImage, String> {
+    let path = scope.resolve(Path::new(&params.path))?;
+    let size = std::fs::metadata(&path)
+        .map_err(|e| format!("failed to open {}: {e}", path.display()))?
+        .len();
+    if size > MAX_FILE_BYTES {
+        return Err(format!(
+            "{} is {size} bytes, more than the {MAX_FILE_BYTES} bytes view_image accepts",
+            path.display()
+        ));
+    }
+    let bytes =
+        std::fs::read(&path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+
+    let format = match image::guess_format(&bytes) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg)) => format,
+        _ => {
+            return Err(format!("{} is not a PNG or JPEG image", path.display()));
+        }
+    };
+    let decoded = image::load_from_memory_with_format(&bytes, format)
+        .map_err(|e| format!("failed to decode {}: {e}", path.display()))?;
+    let (width, height) = decoded.dimensions();
+
+    if width <= MAX_DIMENSION && height <= MAX_DIMENSION && bytes.len() <= MAX_ENCODED_BYTES {
+        return Ok(LoadedImage {
+            data_url: image_data_url(format.to_mime_type(), &bytes),
+            path,
+            width,
+            height,
+            original_size: None,
+        });
+    }
+
+    let (resized, encoded) = downscale(decoded, format)
+        .map_err(|e| format!("failed to downscale {}: {e}", path.display()))?;
+    Ok(LoadedImage {
+        data_url: image_data_url(format.to_mime_type(), &encoded),
+        path,
+        width: resized.width(),
+        height: resized.height(),
+        original_size: Some((width, height)),
+    })
+}
+
+/// Shrinks `image` to fit in [`MAX_DIMENSION`] and then halves it until it
+/// encodes to at most [`MAX_ENCODED_BYTES`].
+fn downscale(
+    image: DynamicImage,
+    format: ImageFormat,
+) -> image::ImageResult<(DynamicImage, Vec<u8>)> {
+    let mut image = if image.width() > MAX_DIMENSION || image.height() > MAX_DIMENSION {
+        image.resize(MAX_DIMENSION, MAX_DIMENSION, FilterType::Triangle)
+    } else {
+        image
+    };
+    loop {
+        let mut encoded = Vec::new();
+        match format {
+            // JPEG has no alpha channel.
+            ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_to(&mut Cursor::new(&mut encoded), format)?,
+            _ => image.write_to(&mut Cursor::new(&mut encoded), format)?,
+        }
+        if encoded.len() <= MAX_ENCODED_BYTES || image.width().max(image.height()) <= 64 {
+            return Ok((image, encoded));
+        }
+        image = image.resize(
+            (image.width() / 2).max(1),
+            (image.height() / 2).max(1),
+            FilterType::Triangle,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SandboxPolicy;
+    use image::Rgb;
+    use image::RgbImage;
+    use pretty_assertions::assert_eq;
+
+    fn params(path: &str) -> ViewImageParams {
+        ViewImageParams {
+            path: path.to_string(),
+        }
+    }
+
+    fn scope(cwd: &Path, sandbox_policy: SandboxPolicy) -> ReadScope {
+        ReadScope {
+            cwd: cwd.to_path_buf(),
+            sandbox_policy,
+            output_dir: cwd.join("output"),
+        }
+    }
+
+    #[test]
+    fn downscales_large_images_and_keeps_small_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        RgbImage::from_pixel(3000, 1000, Rgb([200, 30, 30]))
+            .save(dir.path().join("wide.png"))
+            .unwrap();
+        RgbImage::from_pixel(40, 20, Rgb([0, 0, 0]))
+            .save(dir.path().join("small.png"))
+            .unwrap();
+        let scope = scope(dir.path(), SandboxPolicy::new_read_only_policy());
+
+        let wide = load_image(&scope, &params("wide.png")).unwrap();
+        assert_eq!((wide.width, wide.height), (2048, 683));
+        assert_eq!(wide.original_size, Some((3000, 1000)));
+        assert!(wide.data_url.starts_with("data:image/png;base64,"));
+
+        let small = load_image(&scope, &params("small.png")).unwrap();
+        assert_eq!(
+            (small.width, small.height, small.original_size),
+            (40, 20, None)
+        );
+        let bytes = std::fs::read(dir.path().join("small.png")).unwrap();
+        assert_eq!(small.data_url, image_data_url("image/png", &bytes));
+    }
+
+    #[test]
+    fn refuses_files_outside_readable_roots_and_non_images() {
+        let readable = tempfile::tempdir().unwrap();
+        let cwd = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        RgbImage::from_pixel(4, 4, Rgb([0, 0, 0]))
+            .save(outside.path().join("secret.png"))
+            .unwrap();
+        std::fs::write(cwd.path().join("notes.txt"), "not an image").unwrap();
+        let limited = scope(
+            cwd.path(),
+            SandboxPolicy::ReadOnly {
+                readable_roots: vec![readable.path().to_path_buf()],
+            },
+        );
+
+        let outside_path = outside.path().join("secret.png");
+        let err = load_image(&limited, &params(outside_path.to_str().unwrap())).unwrap_err();
+        assert!(
+            err.contains("outside the folders the sandbox can read"),
+            "{err}"
+        );
+        assert!(
+            load_image(
+                &scope(cwd.path(), SandboxPolicy::DangerFullAccess),
+                &params(outside_path.to_str().unwrap())
+            )
+            .is_ok()
+        );
+
+        let err = load_image(&limited, &params("notes.txt")).unwrap_err();
+        assert!(err.contains("is not a PNG or JPEG image"), "{err}");
+    }
+}
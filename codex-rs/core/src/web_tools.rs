@@ -0,0 +1,585 @@
+//! The `web_fetch` and `web_search` tools, which let the model read
+//! documentation on the web.
+//!
+//! Codex makes these requests itself rather than from the sandbox, so
+//! `codex.rs` first checks each host against the sandbox's network policy
+//! with [`sandbox_allows_host`] and asks the user about any other host. The
+//! tool calls and their output are recorded in the rollout like any other.
+
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use reqwest::Url;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::header::LOCATION;
+use reqwest::redirect;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config_types::WebSearchConfig;
+use crate::html_to_markdown::html_to_markdown;
+use crate::network_proxy::domain_is_allowed;
+use crate::openai_tools::JsonSchema;
+use crate::openai_tools::OpenAiTool;
+use crate::openai_tools::ResponsesApiTool;
+use crate::protocol::SandboxPolicy;
+use crate::user_agent::get_codex_user_agent;
+
+/// Characters of a page `web_fetch` returns when the model does not ask for
+/// a number.
+const DEFAULT_MAX_CHARS: usize = 20_000;
+
+/// Most characters of a page a single `web_fetch` call can return.
+const MAX_CHARS_LIMIT: usize = 100_000;
+
+/// Larger response bodies are cut off at this size.
+const MAX_BODY_BYTES: usize = 5 * 1024 * 1024;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+const MAX_REDIRECTS: usize = 10;
+
+/// A NUL byte in this many leading bytes marks a response as binary.
+const BINARY_DETECTION_BYTES: usize = 8 * 1024;
+
+const DEFAULT_MAX_SEARCH_RESULTS: usize = 8;
+
+const MAX_SEARCH_RESULTS_LIMIT: usize = 20;
+
+const BRAVE_SEARCH_URL: &str = "https://api.search.brave.com/res/v1/web/search";
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct WebFetchParams {
+    pub(crate) url: String,
+    #[serde(default)]
+    offset: Option<usize>,
+    #[serde(default)]
+    max_chars: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct WebSearchParams {
+    query: String,
+    #[serde(default)]
+    max_results: Option<usize>,
+}
+
+pub(crate) static WEB_FETCH_TOOL: LazyLock<OpenAiTool> = LazyLock::new(|| {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "url".to_string(),
+        JsonSchema::String {
+            description: Some("The http or https URL to fetch.".to_string()),
+        },
+    );
+    properties.insert(
+        "offset".to_string(),
+        JsonSchema::Number {
+            description: Some(
+                "Character of the converted page to start at, for reading past a truncated result. Defaults to 0."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "max_chars".to_string(),
+        JsonSchema::Number {
+            description: Some(format!(
+                "Most characters to return, at most {MAX_CHARS_LIMIT}. Defaults to {DEFAULT_MAX_CHARS}."
+            )),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "web_fetch".to_string(),
+        description: "Fetches a web page and returns it as markdown, or as plain text for text and JSON responses. Use it to read documentation. Hosts the sandbox does not allow network access to need the user's approval.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(vec!["url".to_string()]),
+            additional_properties: Some(false),
+        },
+    })
+});
+
+pub(crate) static WEB_SEARCH_TOOL: LazyLock<OpenAiTool> = LazyLock::new(|| {
+    let mut properties = BTreeMap::new();
+    properties.insert(
+        "query".to_string(),
+        JsonSchema::String {
+            description: Some("What to search the web for.".to_string()),
+        },
+    );
+    properties.insert(
+        "max_results".to_string(),
+        JsonSchema::Number {
+            description: Some(format!(
+                "Most results to return, at most {MAX_SEARCH_RESULTS_LIMIT}. Defaults to {DEFAULT_MAX_SEARCH_RESULTS}."
+            )),
+        },
+    );
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "web_search".to_string(),
+        description: "Searches the web and returns the title, URL and a snippet of each result. Use web_fetch to read a result.".to_string(),
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: Some(vec!["query".to_string()]),
+            additional_properties: Some(false),
+        },
+    })
+});
+
+/// Whether the sandbox policy lets commands reach `host`, in which case Codex
+/// may reach it on the model's behalf without asking.
+pub(crate) fn sandbox_allows_host(sandbox_policy: &SandboxPolicy, host: &str) -> bool {
+    sandbox_policy.has_full_network_access()
+        || sandbox_policy
+            .get_proxied_domains()
+            .is_some_and(|allowed_domains| domain_is_allowed(host, allowed_domains))
+}
+
+/// Parses a URL the model asked for, which must be http or https.
+pub(crate) fn parse_web_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("invalid URL {url}: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!("{url} is not an http or https URL"));
+    }
+    Ok(parsed)
+}
+
+/// Fetches `url` and formats it for the model. Redirects are followed to the
+/// same host and to hosts the sandbox allows; any other redirect is reported
+/// so that the model can fetch the new URL, which asks the user again.
+pub(crate) async fn web_fetch(
+    url: Url,
+    params: &WebFetchParams,
+    sandbox_policy: SandboxPolicy,
+) -> Result<String, String> {
+    let approved_host = url.host_str().unwrap_or_default().to_string();
+    let redirect_policy = redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+        let next = attempt.url();
+        let allowed = matches!(next.scheme(), "http" | "https")
+            && next.host_str().is_some_and(|host| {
+                host == approved_host || sandbox_allows_host(&sandbox_policy, host)
+            });
+        if allowed {
+            attempt.follow()
+        } else {
+            attempt.stop()
+        }
+    });
+    let client = build_client(redirect_policy)?;
+
+    let mut response = client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| format!("failed to fetch {url}: {e}"))?;
+    let status = response.status();
+    if status.is_redirection()
+        && let Some(location) = response.headers().get(LOCATION)
+    {
+        let location = location.to_str().unwrap_or_default();
+        let location = response
+            .url()
+            .join(location)
+            .map_or_else(|_| location.to_string(), |u| u.to_string());
+        return Err(format!(
+            "{} redirects to {location}, which needs approval; call web_fetch with that URL to follow it",
+            response.url()
+        ));
+    }
+    let final_url = response.url().clone();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("failed to read {final_url}: {e}"))?
+    {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_BODY_BYTES {
+            body.truncate(MAX_BODY_BYTES);
+            break;
+        }
+    }
+
+    let (title, text) = page_text(&final_url, &content_type, &body)?;
+    let content = format_page(
+        &final_url,
+        (!status.is_success()).then_some(status.as_u16()),
+        title.as_deref(),
+        &text,
+        params.offset.unwrap_or(0),
+        params
+            .max_chars
+            .unwrap_or(DEFAULT_MAX_CHARS)
+            .clamp(1, MAX_CHARS_LIMIT),
+    );
+    if status.is_success() {
+        Ok(content)
+    } else {
+        Err(content)
+    }
+}
+
+fn build_client(redirect_policy: redirect::Policy) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .redirect(redirect_policy)
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(get_codex_user_agent(None))
+        .build()
+        .map_err(|e| format!("failed to create HTTP client: {e}"))
+}
+
+/// Returns the title and text of a response body, converting HTML to
+/// markdown.
+fn page_text(
+    url: &Url,
+    content_type: &str,
+    body: &[u8],
+) -> Result<(Option<String>, String), String> {
+    let binary = body[..body.len().min(BINARY_DETECTION_BYTES)].contains(&0);
+    let text = String::from_utf8_lossy(body);
+    let sniffed: String = text.trim_start().chars().take(15).collect();
+    let sniffed = sniffed.to_ascii_lowercase();
+    let is_html = content_type.contains("html")
+        || (content_type.is_empty()
+            && (sniffed.starts_with("<!doctype html") || sniffed.starts_with("<html")));
+    if is_html {
+        let markdown = html_to_markdown(&text, Some(url));
+        return Ok((markdown.title, markdown.text));
+    }
+    let is_text = content_type.starts_with("text/")
+        || ["json", "xml", "javascript", "yaml", "toml"]
+            .iter()
+            .any(|kind| content_type.contains(kind))
+        || content_type.is_empty();
+    if is_text && !binary {
+        Ok((None, text.into_owned()))
+    } else {
+        Err(format!(
+            "{url} returned {}, which web_fetch cannot show as text",
+            if content_type.is_empty() {
+                "binary data"
+            } else {
+                content_type
+            }
+        ))
+    }
+}
+
+/// Formats characters `offset..offset + max_chars` of a page, with a note on
+/// how to read more when the page is longer.
+fn format_page(
+    url: &Url,
+    error_status: Option<u16>,
+    title: Option<&str>,
+    text: &str,
+    offset: usize,
+    max_chars: usize,
+) -> String {
+    let total = text.chars().count();
+    let start = offset.min(total);
+    let page: String = text.chars().skip(start).take(max_chars).collect();
+    let end = start + page.chars().count();
+
+    let mut out = format!("URL: {url}\n");
+    if let Some(status) = error_status {
+        out.push_str(&format!("Status: {status}\n"));
+    }
+    if let Some(title) = title {
+        out.push_str(&format!("Title: {title}\n"));
+    }
+    out.push('\n');
+    out.push_str(&page);
+    if end < total {
+        out.push_str(&format!(
+            "\n\n(showing characters {start}-{end} of {total}; use offset={end} to read more)"
+        ));
+    }
+    out
+}
+
+/// The URL `web_search` sends its request to, which is checked against the
+/// sandbox policy before searching.
+pub(crate) fn search_endpoint(config: &WebSearchConfig) -> Result<Url, String> {
+    match config {
+        WebSearchConfig::Searxng { url } => {
+            let mut endpoint = parse_web_url(url)?;
+            let path = format!("{}/search", endpoint.path().trim_end_matches('/'));
+            endpoint.set_path(&path);
+            Ok(endpoint)
+        }
+        WebSearchConfig::Brave { .. } => parse_web_url(BRAVE_SEARCH_URL),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct SearchResult {
+    title: String,
+    url: String,
+    snippet: String,
+}
+
+/// Sends `params.query` to the configured search engine at `endpoint`.
+pub(crate) async fn web_search(
+    config: &WebSearchConfig,
+    endpoint: Url,
+    params: &WebSearchParams,
+) -> Result<String, String> {
+    let max_results = params
+        .max_results
+        .unwrap_or(DEFAULT_MAX_SEARCH_RESULTS)
+        .clamp(1, MAX_SEARCH_RESULTS_LIMIT);
+    let client = build_client(redirect::Policy::none())?;
+
+    let request = match config {
+        WebSearchConfig::Searxng { .. } => client
+            .get(endpoint)
+            .query(&[("q", params.query.as_str()), ("format", "json")]),
+        WebSearchConfig::Brave { api_key_env } => {
+            let api_key = std::env::var(api_key_env)
+                .map_err(|_| format!("the {api_key_env} environment variable is not set"))?;
+            client
+                .get(endpoint)
+                .query(&[
+                    ("q", params.query.as_str()),
+                    ("count", &max_results.to_string()),
+                ])
+                .header("Accept", "application/json")
+                .header("X-Subscription-Token", api_key)
+        }
+    };
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("search request failed: {e}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "search request failed with status {status}: {}",
+            body.chars().take(500).collect::<String>()
+        ));
+    }
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("search engine returned invalid JSON: {e}"))?;
+
+    let mut results = parse_search_results(config, &body);
+    results.truncate(max_results);
+    Ok(format_search_results(&params.query, &results))
+}
+
+fn parse_search_results(config: &WebSearchConfig, body: &Value) -> Vec<SearchResult> {
+    let (results, snippet_key) = match config {
+        WebSearchConfig::Searxng { .. } => (&body["results"], "content"),
+        WebSearchConfig::Brave { .. } => (&body["web"]["results"], "description"),
+    };
+    let field = |result: &Value, key: &str| {
+        // Snippets can contain markup such as `<strong>` around the terms
+        // that matched.
+        let raw = result[key].as_str().unwrap_or_default();
+        html_to_markdown(raw, None)
+            .text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    results
+        .as_array()
+        .map(|results| {
+            results
+                .iter()
+                .filter_map(|result| {
+                    Some(SearchResult {
+                        url: result["url"].as_str()?.to_string(),
+                        title: field(result, "title"),
+                        snippet: field(result, snippet_key),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn format_search_results(query: &str, results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return format!("No results found for {query:?}.");
+    }
+    let mut out = String::new();
+    for (index, result) in results.iter().enumerate() {
+        out.push_str(&format!(
+            "{}. {}\n   {}\n",
+            index + 1,
+            result.title,
+            result.url
+        ));
+        if !result.snippet.is_empty() {
+            out.push_str(&format!("   {}\n", result.snippet));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+
+    fn fetch_params(url: &str, offset: Option<usize>, max_chars: Option<usize>) -> WebFetchParams {
+        WebFetchParams {
+            url: url.to_string(),
+            offset,
+            max_chars,
+        }
+    }
+
+    fn proxied_policy(allowed_domains: &[&str]) -> SandboxPolicy {
+        SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec![],
+            network_access: false,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+            readable_roots: vec![],
+            allowed_domains: allowed_domains.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn sandbox_policy_decides_which_hosts_are_allowed() {
+        assert!(sandbox_allows_host(
+            &SandboxPolicy::DangerFullAccess,
+            "example.com"
+        ));
+        assert!(!sandbox_allows_host(
+            &SandboxPolicy::new_read_only_policy(),
+            "example.com"
+        ));
+        let policy = proxied_policy(&["docs.rs"]);
+        assert!(sandbox_allows_host(&policy, "docs.rs"));
+        assert!(sandbox_allows_host(&policy, "www.docs.rs"));
+        assert!(!sandbox_allows_host(&policy, "example.com"));
+    }
+
+    #[tokio::test]
+    async fn fetches_html_as_markdown_and_pages_through_it() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/old"))
+            .respond_with(ResponseTemplate::new(301).insert_header("Location", "/guide"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/guide"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "<html><head><title>Guide</title></head><body><h1>Install</h1><p>Run <code>make</code>.</p></body></html>",
+                "text/html; charset=utf-8",
+            ))
+            .mount(&server)
+            .await;
+
+        let url = parse_web_url(&format!("{}/old", server.uri())).unwrap();
+        let content = web_fetch(url, &fetch_params("", None, Some(13)), proxied_policy(&[]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            content,
+            format!(
+                "URL: {}/guide\nTitle: Guide\n\n# Install\n\nRu\n\n(showing characters 0-13 of 22; use offset=13 to read more)",
+                server.uri()
+            )
+        );
+
+        let url = parse_web_url(&format!("{}/guide", server.uri())).unwrap();
+        let content = web_fetch(url, &fetch_params("", Some(13), None), proxied_policy(&[]))
+            .await
+            .unwrap();
+        assert!(content.ends_with("\n\nn `make`."), "{content}");
+    }
+
+    #[tokio::test]
+    async fn reports_redirects_to_hosts_that_need_approval() {
+        let server = MockServer::start().await;
+        let port = server.address().port();
+        Mock::given(method("GET"))
+            .and(path("/moved"))
+            .respond_with(
+                ResponseTemplate::new(302)
+                    .insert_header("Location", format!("http://localhost:{port}/new").as_str()),
+            )
+            .mount(&server)
+            .await;
+
+        let url = parse_web_url(&format!("http://127.0.0.1:{port}/moved")).unwrap();
+        let err = web_fetch(url, &fetch_params("", None, None), proxied_policy(&[]))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            format!(
+                "http://127.0.0.1:{port}/moved redirects to http://localhost:{port}/new, which needs approval; call web_fetch with that URL to follow it"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn searches_searxng() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/searx/search"))
+            .and(query_param("q", "tokio select"))
+            .and(query_param("format", "json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "results": [
+                    {
+                        "url": "https://docs.rs/tokio/latest/tokio/macro.select.html",
+                        "title": "select in tokio",
+                        "content": "Waits on multiple <strong>concurrent</strong> branches",
+                    },
+                    { "url": "https://example.com/", "title": "Example" },
+                    { "title": "no url" },
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let config = WebSearchConfig::Searxng {
+            url: format!("{}/searx", server.uri()),
+        };
+        let endpoint = search_endpoint(&config).unwrap();
+        assert_eq!(endpoint.path(), "/searx/search");
+        let params = WebSearchParams {
+            query: "tokio select".to_string(),
+            max_results: None,
+        };
+
+        assert_eq!(
+            web_search(&config, endpoint, &params).await.unwrap(),
+            "1. select in tokio\n   https://docs.rs/tokio/latest/tokio/macro.select.html\n   Waits on multiple **concurrent** branches\n\
+             2. Example\n   https://example.com/\n"
+        );
+    }
+}
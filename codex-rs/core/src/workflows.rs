@@ -1,4 +1,7 @@
+use crate::budget::Budget;
+use crate::output_schema::OutputSchema;
 use serde::Deserialize;
+use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
@@ -11,6 +14,10 @@ pub struct WorkflowToml {
     pub steps: Vec<String>,
     #[serde(default)]
     pub step: HashMap<String, WorkflowStepToml>,
+    /// Spend cap for the whole workflow, in US dollars.
+    pub max_cost_usd: Option<f64>,
+    /// Token cap for the whole workflow.
+    pub max_total_tokens: Option<u64>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -19,6 +26,9 @@ pub struct WorkflowStepToml {
     pub id: String,     // agent name or team name
     pub prompt: Option<String>,
     pub max_turns: Option<usize>,
+    /// JSON Schema file, relative to the project `.codex/` directory, that
+    /// the step's final message must conform to.
+    pub output_schema: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
@@ -27,6 +37,8 @@ pub struct WorkflowDefinition {
     pub name: String,
     pub description: Option<String>,
     pub steps: Vec<WorkflowStep>,
+    /// Limits shared by all steps; each step runs with whatever is left.
+    pub budget: Budget,
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +47,9 @@ pub struct WorkflowStep {
     pub id: String,
     pub prompt: Option<String>,
     pub max_turns: Option<usize>,
+    /// Schema for the step's final message. The validated JSON is handed to
+    /// later steps via [`prompt_with_step_outputs`].
+    pub output_schema: Option<Value>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -98,11 +113,20 @@ pub fn load_workflow(project_codex_dir: &Path, name: &str) -> std::io::Result<Wo
                 ));
             }
         };
+        let output_schema = match &st.output_schema {
+            Some(path) => Some(
+                OutputSchema::from_path(&project_codex_dir.join(path))?
+                    .schema()
+                    .clone(),
+            ),
+            None => None,
+        };
         steps.push(WorkflowStep {
             kind,
             id: st.id.clone(),
             prompt: st.prompt.clone(),
             max_turns: st.max_turns,
+            output_schema,
         });
     }
 
@@ -111,5 +135,23 @@ pub fn load_workflow(project_codex_dir: &Path, name: &str) -> std::io::Result<Wo
         name: wf.name.unwrap_or_else(|| name.to_string()),
         description: wf.description,
         steps,
+        budget: Budget {
+            max_cost_usd: wf.max_cost_usd,
+            max_total_tokens: wf.max_total_tokens,
+        },
     })
 }
+
+/// Appends the structured output of earlier steps, keyed by step id, to a
+/// step's prompt so it can build on their results.
+pub fn prompt_with_step_outputs(prompt: &str, outputs: &[(String, Value)]) -> String {
+    if outputs.is_empty() {
+        return prompt.to_string();
+    }
+    let mut out = format!("{prompt}\n\nStructured output from earlier workflow steps:");
+    for (step_id, value) in outputs {
+        let json = serde_json::to_string_pretty(value).unwrap_or_default();
+        out.push_str(&format!("\n\n{step_id}:\n```json\n{json}\n```"));
+    }
+    out
+}
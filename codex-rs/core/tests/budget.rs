@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use codex_core::ConversationManager;
+use codex_core::ModelProviderInfo;
+use codex_core::WireApi;
+use codex_core::budget::Budget;
+use codex_core::budget::ModelPricing;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+use codex_core::protocol::TurnAbortReason;
+use codex_core::protocol::TurnAbortedEvent;
+use codex_core::spawn::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use codex_login::CodexAuth;
+use core_test_support::load_default_config_for_test;
+use core_test_support::load_sse_fixture_with_id_from_str;
+use core_test_support::wait_for_event_with_timeout;
+use tempfile::TempDir;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+const SSE_COMPLETED_WITH_USAGE: &str = r#"[
+  {
+    "type": "response.completed",
+    "response": {
+      "id": "__ID__",
+      "usage": {
+        "input_tokens": 1000,
+        "input_tokens_details": null,
+        "output_tokens": 500,
+        "output_tokens_details": null,
+        "total_tokens": 1500
+      },
+      "output": []
+    }
+  }
+]"#;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn turn_is_aborted_once_cost_budget_is_spent() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let server = MockServer::start().await;
+
+    // Only the first turn may reach the model; the second must be stopped by
+    // the budget before any request is made.
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(
+                    load_sse_fixture_with_id_from_str(SSE_COMPLETED_WITH_USAGE, "resp1"),
+                    "text/event-stream",
+                ),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&home);
+    // $1 + $5 per million tokens: the first turn costs 0.001 + 0.0025 USD.
+    config.model_provider = ModelProviderInfo {
+        name: "mock-openai".into(),
+        base_url: Some(format!("{}/v1", server.uri())),
+        env_key: Some("PATH".into()),
+        env_key_instructions: None,
+        wire_api: WireApi::Responses,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(2_000),
+        requires_openai_auth: false,
+        fallback: None,
+        pricing: Some(HashMap::from([(
+            config.model.clone(),
+            ModelPricing {
+                input: 1.0,
+                cached_input: None,
+                output: 5.0,
+            },
+        )])),
+        supports_output_schema: None,
+    };
+    config.budget = Budget {
+        max_cost_usd: Some(0.003),
+        max_total_tokens: None,
+    };
+
+    let conversation_manager = ConversationManager::default();
+    let codex = conversation_manager
+        .new_conversation_with_auth(config, Some(CodexAuth::from_api_key("Test API Key")))
+        .await
+        .unwrap()
+        .conversation;
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "first message".into(),
+            }],
+        })
+        .await
+        .unwrap();
+    wait_for_event_with_timeout(
+        &codex,
+        |ev| matches!(ev, EventMsg::TaskComplete(_)),
+        Duration::from_secs(5),
+    )
+    .await;
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "second message".into(),
+            }],
+        })
+        .await
+        .unwrap();
+    let EventMsg::BackgroundEvent(event) = wait_for_event_with_timeout(
+        &codex,
+        |ev| matches!(ev, EventMsg::BackgroundEvent(_)),
+        Duration::from_secs(5),
+    )
+    .await
+    else {
+        unreachable!()
+    };
+    assert!(
+        event.message.contains("cost budget exhausted"),
+        "unexpected message: {}",
+        event.message
+    );
+    wait_for_event_with_timeout(
+        &codex,
+        |ev| {
+            matches!(
+                ev,
+                EventMsg::TurnAborted(TurnAbortedEvent {
+                    reason: TurnAbortReason::BudgetExceeded
+                })
+            )
+        },
+        Duration::from_secs(5),
+    )
+    .await;
+}
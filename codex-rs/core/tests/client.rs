@@ -625,6 +625,9 @@ async fn azure_overrides_assign_properties_used_for_responses_url() {
         stream_max_retries: None,
         stream_idle_timeout_ms: None,
         requires_openai_auth: false,
+        fallback: None,
+        pricing: None,
+        supports_output_schema: None,
     };
 
     // Init session
@@ -701,6 +704,9 @@ async fn env_var_overrides_loaded_auth() {
         stream_max_retries: None,
         stream_idle_timeout_ms: None,
         requires_openai_auth: false,
+        fallback: None,
+        pricing: None,
+        supports_output_schema: None,
     };
 
     // Init session
@@ -727,6 +733,110 @@ async fn env_var_overrides_loaded_auth() {
     wait_for_event(&codex, |ev| matches!(ev, EventMsg::TaskComplete(_))).await;
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn messages_wire_api_sends_anthropic_headers_and_streams_reply() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let server = MockServer::start().await;
+
+    let sse = [
+        serde_json::json!({"type": "message_start", "message": {"id": "msg_1", "usage": {"input_tokens": 3, "output_tokens": 1}}}),
+        serde_json::json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        serde_json::json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi there"}}),
+        serde_json::json!({"type": "content_block_stop", "index": 0}),
+        serde_json::json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 2}}),
+        serde_json::json!({"type": "message_stop"}),
+    ]
+    .iter()
+    .map(|e| format!("event: {}\ndata: {e}\n\n", e["type"].as_str().unwrap()))
+    .collect::<String>();
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(sse, "text/event-stream"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model_provider = ModelProviderInfo {
+        name: "anthropic".into(),
+        base_url: Some(format!("{}/v1", server.uri())),
+        // Reuse an existing environment variable to avoid using unsafe code
+        env_key: Some("PATH".to_string()),
+        env_key_instructions: None,
+        wire_api: WireApi::Messages,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: None,
+        requires_openai_auth: false,
+        fallback: None,
+        pricing: None,
+        supports_output_schema: None,
+    };
+
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.model_provider = model_provider;
+
+    let conversation_manager = ConversationManager::default();
+    let codex = conversation_manager
+        .new_conversation_with_auth(config, None)
+        .await
+        .expect("create new conversation")
+        .conversation;
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "hello".into(),
+            }],
+        })
+        .await
+        .unwrap();
+
+    let EventMsg::AgentMessage(message) =
+        wait_for_event(&codex, |ev| matches!(ev, EventMsg::AgentMessage(_))).await
+    else {
+        unreachable!()
+    };
+    assert_eq!(message.message, "Hi there");
+    wait_for_event(&codex, |ev| matches!(ev, EventMsg::TaskComplete(_))).await;
+
+    let request = &server.received_requests().await.unwrap()[0];
+    assert_eq!(
+        request.headers.get("x-api-key").unwrap().to_str().unwrap(),
+        std::env::var("PATH").unwrap()
+    );
+    assert_eq!(
+        request
+            .headers
+            .get("anthropic-version")
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        "2023-06-01"
+    );
+    assert!(request.headers.get("authorization").is_none());
+
+    let request_body = request.body_json::<serde_json::Value>().unwrap();
+    assert!(request_body["system"][0]["text"].is_string());
+    let messages = request_body["messages"].as_array().unwrap();
+    assert_eq!(messages.last().unwrap()["role"], "user");
+    assert!(request_body["max_tokens"].as_u64().unwrap() > 0);
+}
+
 fn create_dummy_codex_auth() -> CodexAuth {
     CodexAuth::create_dummy_chatgpt_auth_for_testing()
 }
@@ -35,6 +35,8 @@ async fn run_test_cmd(tmp: TempDir, cmd: Vec<&str>) -> Result<ExecToolCallOutput
         env: HashMap::new(),
         with_escalated_permissions: None,
         justification: None,
+        resource_limits: Default::default(),
+        output_limits: Default::default(),
     };
 
     let policy = SandboxPolicy::new_read_only_policy();
@@ -55,7 +57,7 @@ async fn exit_code_0_succeeds() {
     let output = run_test_cmd(tmp, cmd).await.unwrap();
     assert_eq!(output.stdout.text, "hello\n");
     assert_eq!(output.stderr.text, "");
-    assert_eq!(output.stdout.truncated_after_lines, None);
+    assert_eq!(output.stdout.truncation, None);
 }
 
 /// Command succeeds with exit code 0 normally
@@ -70,12 +72,23 @@ async fn truncates_output_lines() {
 
     let output = run_test_cmd(tmp, cmd).await.unwrap();
 
-    let expected_output = (1..=256)
-        .map(|i| format!("{i}\n"))
-        .collect::<Vec<_>>()
-        .join("");
-    assert_eq!(output.stdout.text, expected_output);
-    assert_eq!(output.stdout.truncated_after_lines, Some(256));
+    let head = (1..=128).map(|i| format!("{i}\n")).collect::<String>();
+    let tail = (173..=300).map(|i| format!("{i}\n")).collect::<String>();
+    let truncation = output
+        .stdout
+        .truncation
+        .expect("output should be truncated");
+    assert_eq!(truncation.omitted_lines, 44);
+    assert!(output.stdout.text.starts_with(&head));
+    assert!(output.stdout.text.ends_with(&tail));
+    let full_output_path = truncation
+        .full_output_path
+        .expect("full output should be saved");
+    let full_output = (1..=300).map(|i| format!("{i}\n")).collect::<String>();
+    assert_eq!(
+        std::fs::read_to_string(full_output_path).unwrap(),
+        full_output
+    );
 }
 
 /// Command succeeds with exit code 0 normally
@@ -91,8 +104,15 @@ async fn truncates_output_bytes() {
 
     let output = run_test_cmd(tmp, cmd).await.unwrap();
 
-    assert_eq!(output.stdout.text.len(), 10240);
-    assert_eq!(output.stdout.truncated_after_lines, Some(10));
+    // Whole lines only: 5 from the head and 5 from the tail.
+    let truncation = output
+        .stdout
+        .truncation
+        .expect("output should be truncated");
+    assert_eq!(truncation.omitted_lines, 5);
+    assert_eq!(truncation.omitted_bytes, 5 * 1001);
+    assert!(output.stdout.text.starts_with("1 "));
+    assert!(output.stdout.text.ends_with(&format!("{:<1000}\n", 15)));
 }
 
 /// Command not found returns exit code 127, this is not considered a sandbox error
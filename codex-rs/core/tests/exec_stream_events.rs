@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 
 use async_channel::Receiver;
+use codex_core::exec::ExecOutputLimits;
 use codex_core::exec::ExecParams;
 use codex_core::exec::SandboxType;
 use codex_core::exec::StdoutStream;
@@ -53,6 +54,8 @@ async fn test_exec_stdout_stream_events_echo() {
         env: HashMap::new(),
         with_escalated_permissions: None,
         justification: None,
+        resource_limits: Default::default(),
+        output_limits: Default::default(),
     };
 
     let policy = SandboxPolicy::new_read_only_policy();
@@ -103,6 +106,8 @@ async fn test_exec_stderr_stream_events_echo() {
         env: HashMap::new(),
         with_escalated_permissions: None,
         justification: None,
+        resource_limits: Default::default(),
+        output_limits: Default::default(),
     };
 
     let policy = SandboxPolicy::new_read_only_policy();
@@ -139,3 +144,123 @@ async fn test_exec_stderr_stream_events_echo() {
     }
     assert_eq!(String::from_utf8_lossy(&err), "oops\n");
 }
+
+#[tokio::test]
+async fn test_exec_long_output_keeps_head_and_tail() {
+    let spill_dir = tempfile::TempDir::new().unwrap();
+    let (tx, rx) = async_channel::unbounded::<Event>();
+
+    let stdout_stream = StdoutStream {
+        sub_id: "test-sub".to_string(),
+        call_id: "call-3".to_string(),
+        tx_event: tx,
+    };
+
+    let params = ExecParams {
+        command: vec!["seq".to_string(), "100".to_string()],
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout_ms: Some(5_000),
+        env: HashMap::from([("PATH".to_string(), "/usr/bin:/bin".to_string())]),
+        with_escalated_permissions: None,
+        justification: None,
+        resource_limits: Default::default(),
+        output_limits: ExecOutputLimits {
+            max_bytes: 1024,
+            max_lines: 10,
+            spill_dir: Some(spill_dir.path().to_path_buf()),
+            ..Default::default()
+        },
+    };
+
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result = process_exec_tool_call(
+        params,
+        SandboxType::None,
+        &policy,
+        &None,
+        Some(stdout_stream),
+    )
+    .await;
+
+    let result = match result {
+        Ok(r) => r,
+        Err(e) => panic!("process_exec_tool_call failed: {e}"),
+    };
+
+    let full_output: String = (1..=100).map(|i| format!("{i}\n")).collect();
+    let truncation = result
+        .stdout
+        .truncation
+        .expect("output should be truncated");
+    assert_eq!(truncation.omitted_lines, 90);
+    let full_output_path = truncation
+        .full_output_path
+        .expect("full output should be saved");
+    assert!(full_output_path.starts_with(spill_dir.path()));
+    assert_eq!(
+        result.stdout.text,
+        format!(
+            "1\n2\n3\n4\n5\n\n[... 90 lines ({} bytes) omitted; the full output is in {} ...]\n\n96\n97\n98\n99\n100\n",
+            truncation.omitted_bytes,
+            full_output_path.display()
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(&full_output_path).unwrap(),
+        full_output
+    );
+
+    // Clients still see every line.
+    assert_eq!(
+        String::from_utf8_lossy(&collect_stdout_events(rx)),
+        full_output
+    );
+}
+
+#[tokio::test]
+async fn test_exec_spill_file_is_capped() {
+    let spill_dir = tempfile::TempDir::new().unwrap();
+
+    let params = ExecParams {
+        command: vec!["seq".to_string(), "10000".to_string()],
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout_ms: Some(5_000),
+        env: HashMap::from([("PATH".to_string(), "/usr/bin:/bin".to_string())]),
+        with_escalated_permissions: None,
+        justification: None,
+        resource_limits: Default::default(),
+        output_limits: ExecOutputLimits {
+            max_bytes: 1024,
+            max_lines: 10,
+            spill_dir: Some(spill_dir.path().to_path_buf()),
+            max_spill_bytes: 1000,
+        },
+    };
+
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result = process_exec_tool_call(params, SandboxType::None, &policy, &None, None).await;
+    let result = match result {
+        Ok(r) => r,
+        Err(e) => panic!("process_exec_tool_call failed: {e}"),
+    };
+
+    let truncation = result
+        .stdout
+        .truncation
+        .expect("output should be truncated");
+    assert_eq!(truncation.full_output_cut_at, Some(1000));
+    let full_output_path = truncation
+        .full_output_path
+        .expect("the start of the output should be saved");
+    assert!(result.stdout.text.contains(&format!(
+        "the first 1000 bytes of the output are in {}",
+        full_output_path.display()
+    )));
+    let full_output: String = (1..=10000).map(|i| format!("{i}\n")).collect();
+    assert_eq!(
+        std::fs::read_to_string(&full_output_path).unwrap(),
+        full_output[..1000]
+    );
+}
@@ -195,6 +195,8 @@ async fn overrides_turn_context_but_keeps_cached_prefix_and_key_constant() {
                 network_access: true,
                 exclude_tmpdir_env_var: true,
                 exclude_slash_tmp: true,
+                readable_roots: vec![],
+                allowed_domains: vec![],
             }),
             model: Some("o3".to_string()),
             effort: Some(ReasoningEffort::High),
@@ -321,6 +323,8 @@ async fn per_turn_overrides_keep_cached_prefix_and_key_constant() {
                 network_access: true,
                 exclude_tmpdir_env_var: true,
                 exclude_slash_tmp: true,
+                readable_roots: vec![],
+                allowed_domains: vec![],
             },
             model: "o3".to_string(),
             effort: ReasoningEffort::High,
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use codex_core::ConversationManager;
+use codex_core::ModelFallback;
+use codex_core::ModelProviderInfo;
+use codex_core::WireApi;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+use codex_core::protocol_config_types::ReasoningEffort;
+use codex_core::spawn::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use codex_login::CodexAuth;
+use core_test_support::load_default_config_for_test;
+use core_test_support::load_sse_fixture_with_id;
+use core_test_support::wait_for_event_with_timeout;
+use tempfile::TempDir;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::body_string_contains;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+fn mock_provider(name: &str, server: &MockServer) -> ModelProviderInfo {
+    ModelProviderInfo {
+        name: name.into(),
+        base_url: Some(format!("{}/v1", server.uri())),
+        env_key: Some("PATH".into()),
+        env_key_instructions: None,
+        wire_api: WireApi::Responses,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(2_000),
+        requires_openai_auth: false,
+        fallback: None,
+        pricing: None,
+        supports_output_schema: None,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn falls_back_to_next_provider_after_server_errors() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let primary = MockServer::start().await;
+    let secondary = MockServer::start().await;
+
+    // The primary fails once and, since the switch is sticky, must not be
+    // contacted again for the second turn.
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&primary)
+        .await;
+
+    let sse = |id: &str| {
+        ResponseTemplate::new(200)
+            .insert_header("content-type", "text/event-stream")
+            .set_body_raw(
+                load_sse_fixture_with_id("tests/fixtures/completed_template.json", id),
+                "text/event-stream",
+            )
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(body_string_contains("first message"))
+        .and(body_string_contains("\"model\":\"backup-model\""))
+        .respond_with(sse("resp1"))
+        .expect(2)
+        .mount(&secondary)
+        .await;
+
+    let home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&home);
+    config.model_provider = mock_provider("primary", &primary);
+    config.model_fallbacks = vec![ModelFallback {
+        provider_id: "secondary".into(),
+        provider: mock_provider("secondary", &secondary),
+        model: "backup-model".into(),
+    }];
+
+    let conversation_manager = ConversationManager::default();
+    let codex = conversation_manager
+        .new_conversation_with_auth(config, Some(CodexAuth::from_api_key("Test API Key")))
+        .await
+        .unwrap()
+        .conversation;
+
+    for text in ["first message", "follow up"] {
+        codex
+            .submit(Op::UserInput {
+                items: vec![InputItem::Text { text: text.into() }],
+            })
+            .await
+            .unwrap();
+        if text == "first message" {
+            let EventMsg::BackgroundEvent(event) = wait_for_event_with_timeout(
+                &codex,
+                |ev| matches!(ev, EventMsg::BackgroundEvent(_)),
+                Duration::from_secs(5),
+            )
+            .await
+            else {
+                unreachable!()
+            };
+            assert!(
+                event
+                    .message
+                    .contains("falling back to secondary (backup-model)"),
+                "unexpected message: {}",
+                event.message
+            );
+        }
+        wait_for_event_with_timeout(
+            &codex,
+            |ev| matches!(ev, EventMsg::TaskComplete(_)),
+            Duration::from_secs(5),
+        )
+        .await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn keeps_fallback_after_turn_context_override() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let primary = MockServer::start().await;
+    let secondary = MockServer::start().await;
+
+    // Rebuilding the client for the override must not send the next turn
+    // back to the primary.
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&primary)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(body_string_contains("\"model\":\"backup-model\""))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(
+                    load_sse_fixture_with_id("tests/fixtures/completed_template.json", "resp1"),
+                    "text/event-stream",
+                ),
+        )
+        .expect(2)
+        .mount(&secondary)
+        .await;
+
+    let home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&home);
+    config.model_provider = mock_provider("primary", &primary);
+    config.model_fallbacks = vec![ModelFallback {
+        provider_id: "secondary".into(),
+        provider: mock_provider("secondary", &secondary),
+        model: "backup-model".into(),
+    }];
+
+    let conversation_manager = ConversationManager::default();
+    let codex = conversation_manager
+        .new_conversation_with_auth(config, Some(CodexAuth::from_api_key("Test API Key")))
+        .await
+        .unwrap()
+        .conversation;
+
+    for text in ["first message", "follow up"] {
+        if text == "follow up" {
+            codex
+                .submit(Op::OverrideTurnContext {
+                    cwd: None,
+                    approval_policy: None,
+                    sandbox_policy: None,
+                    model: None,
+                    effort: Some(ReasoningEffort::High),
+                    summary: None,
+                })
+                .await
+                .unwrap();
+        }
+        codex
+            .submit(Op::UserInput {
+                items: vec![InputItem::Text { text: text.into() }],
+            })
+            .await
+            .unwrap();
+        wait_for_event_with_timeout(
+            &codex,
+            |ev| matches!(ev, EventMsg::TaskComplete(_)),
+            Duration::from_secs(5),
+        )
+        .await;
+    }
+}
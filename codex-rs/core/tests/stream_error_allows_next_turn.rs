@@ -81,6 +81,9 @@ async fn continue_after_stream_error() {
         stream_max_retries: Some(1),
         stream_idle_timeout_ms: Some(2_000),
         requires_openai_auth: false,
+        fallback: None,
+        pricing: None,
+        supports_output_schema: None,
     };
 
     let home = TempDir::new().unwrap();
@@ -63,6 +63,21 @@ pub struct Cli {
     #[arg(long = "output-last-message")]
     pub last_message_file: Option<PathBuf>,
 
+    /// JSON Schema file the agent's final message must conform to. The
+    /// schema is sent to the model as the response format, and a message
+    /// that still does not conform is sent back for correction.
+    #[arg(long = "output-schema", value_name = "FILE")]
+    pub output_schema: Option<PathBuf>,
+
+    /// How many times to ask the model to fix a final message that does not
+    /// match `--output-schema` before exiting with an error.
+    #[arg(
+        long = "output-schema-retries",
+        value_name = "N",
+        default_value_t = codex_core::output_schema::DEFAULT_OUTPUT_SCHEMA_RETRIES
+    )]
+    pub output_schema_retries: usize,
+
     /// Initial instructions for the agent. If not provided as an argument (or
     /// if `-` is used), instructions are read from stdin.
     #[arg(value_name = "PROMPT")]
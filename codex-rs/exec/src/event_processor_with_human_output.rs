@@ -1,5 +1,6 @@
 use codex_common::elapsed::format_duration;
 use codex_common::elapsed::format_elapsed;
+use codex_core::budget::Spend;
 use codex_core::config::Config;
 use codex_core::plan_tool::UpdatePlanArgs;
 use codex_core::protocol::AgentMessageDeltaEvent;
@@ -21,6 +22,7 @@ use codex_core::protocol::PatchApplyBeginEvent;
 use codex_core::protocol::PatchApplyEndEvent;
 use codex_core::protocol::SessionConfiguredEvent;
 use codex_core::protocol::TaskCompleteEvent;
+use codex_core::protocol::TokenCountEvent;
 use codex_core::protocol::TurnAbortReason;
 use codex_core::protocol::TurnDiffEvent;
 use owo_colors::OwoColorize;
@@ -62,6 +64,8 @@ pub(crate) struct EventProcessorWithHumanOutput {
     reasoning_started: bool,
     raw_reasoning_started: bool,
     last_message_path: Option<PathBuf>,
+    /// What the session has used so far, reported alongside tokens.
+    spend: Spend,
 }
 
 impl EventProcessorWithHumanOutput {
@@ -90,6 +94,7 @@ impl EventProcessorWithHumanOutput {
                 reasoning_started: false,
                 raw_reasoning_started: false,
                 last_message_path,
+                spend: Spend::default(),
             }
         } else {
             Self {
@@ -108,6 +113,7 @@ impl EventProcessorWithHumanOutput {
                 reasoning_started: false,
                 raw_reasoning_started: false,
                 last_message_path,
+                spend: Spend::default(),
             }
         }
     }
@@ -183,8 +189,19 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                 }
                 return CodexStatus::InitiateShutdown;
             }
-            EventMsg::TokenCount(token_usage) => {
-                ts_println!(self, "tokens used: {}", token_usage.blended_total());
+            EventMsg::TokenCount(TokenCountEvent {
+                usage: token_usage,
+                cost_usd,
+            }) => {
+                self.spend.add(&token_usage, cost_usd);
+                match self.spend.cost_usd {
+                    Some(cost_usd) => ts_println!(
+                        self,
+                        "tokens used: {} (session cost: ${cost_usd:.4})",
+                        token_usage.blended_total()
+                    ),
+                    None => ts_println!(self, "tokens used: {}", token_usage.blended_total()),
+                }
             }
             EventMsg::AgentMessageDelta(AgentMessageDeltaEvent { delta }) => {
                 if !self.answer_started {
@@ -384,7 +401,7 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                 // it's easy to scan in the terminal output.
                 for (path, change) in changes.iter() {
                     match change {
-                        FileChange::Add { content } => {
+                        FileChange::Add { content, .. } => {
                             let header = format!(
                                 "{} {}",
                                 format_file_change(change),
@@ -406,6 +423,7 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                         FileChange::Update {
                             unified_diff,
                             move_path,
+                            ..
                         } => {
                             let header = if let Some(dest) = move_path {
                                 format!(
@@ -434,6 +452,24 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                                 }
                             }
                         }
+                        FileChange::Binary { content_base64, .. } => {
+                            let header = format!(
+                                "{} {} (binary, {} bytes base64)",
+                                format_file_change(change),
+                                path.to_string_lossy(),
+                                content_base64.len()
+                            );
+                            println!("{}", header.style(self.magenta));
+                        }
+                        FileChange::Symlink { target } => {
+                            let header = format!(
+                                "{} {} -> {}",
+                                format_file_change(change),
+                                path.to_string_lossy(),
+                                target.to_string_lossy()
+                            );
+                            println!("{}", header.style(self.magenta));
+                        }
                     }
                 }
             }
@@ -526,6 +562,9 @@ impl EventProcessor for EventProcessorWithHumanOutput {
             EventMsg::McpListToolsResponse(_) => {
                 // Currently ignored in exec output.
             }
+            EventMsg::BackgroundJobsUpdate(_) => {
+                // Job exits are reported as background events.
+            }
             EventMsg::TurnAborted(abort_reason) => match abort_reason.reason {
                 TurnAbortReason::Interrupted => {
                     ts_println!(self, "task interrupted");
@@ -533,6 +572,11 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                 TurnAbortReason::Replaced => {
                     ts_println!(self, "task aborted: replaced by a new task");
                 }
+                TurnAbortReason::BudgetExceeded => {
+                    let prefix = "ERROR:".style(self.red);
+                    ts_println!(self, "{prefix} task aborted: budget exceeded");
+                    return CodexStatus::InitiateShutdown;
+                }
             },
             EventMsg::ShutdownComplete => return CodexStatus::Shutdown,
         }
@@ -546,7 +590,7 @@ fn escape_command(command: &[String]) -> String {
 
 fn format_file_change(change: &FileChange) -> &'static str {
     match change {
-        FileChange::Add { .. } => "A",
+        FileChange::Add { .. } | FileChange::Binary { .. } | FileChange::Symlink { .. } => "A",
         FileChange::Delete => "D",
         FileChange::Update {
             move_path: Some(_), ..
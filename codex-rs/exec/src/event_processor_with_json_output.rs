@@ -5,6 +5,8 @@ use codex_core::config::Config;
 use codex_core::protocol::Event;
 use codex_core::protocol::EventMsg;
 use codex_core::protocol::TaskCompleteEvent;
+use codex_core::protocol::TurnAbortReason;
+use codex_core::protocol::TurnAbortedEvent;
 use serde_json::json;
 
 use crate::event_processor::CodexStatus;
@@ -52,6 +54,14 @@ impl EventProcessor for EventProcessorWithJsonOutput {
                 CodexStatus::InitiateShutdown
             }
             EventMsg::ShutdownComplete => CodexStatus::Shutdown,
+            EventMsg::TurnAborted(TurnAbortedEvent {
+                reason: TurnAbortReason::BudgetExceeded,
+            }) => {
+                if let Ok(line) = serde_json::to_string(&event) {
+                    println!("{line}");
+                }
+                CodexStatus::InitiateShutdown
+            }
             _ => {
                 if let Ok(line) = serde_json::to_string(&event) {
                     println!("{line}");
@@ -13,6 +13,7 @@ use codex_core::ConversationManager;
 use codex_core::NewConversation;
 use codex_core::config::Config;
 use codex_core::config::ConfigOverrides;
+use codex_core::output_schema::OutputSchema;
 use codex_core::protocol::AskForApproval;
 use codex_core::protocol::Event;
 use codex_core::protocol::EventMsg;
@@ -44,6 +45,8 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         skip_git_repo_check,
         color,
         last_message_file,
+        output_schema: output_schema_path,
+        output_schema_retries,
         json: json_mode,
         sandbox_mode: sandbox_mode_cli_arg,
         prompt,
@@ -159,7 +162,15 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         }
     };
 
-    let config = Config::load_with_cli_overrides(cli_kv_overrides, overrides)?;
+    let mut config = Config::load_with_cli_overrides(cli_kv_overrides, overrides)?;
+    let output_schema = match output_schema_path {
+        Some(path) => {
+            let schema = OutputSchema::from_path(&path)?;
+            config.output_schema = Some(schema.schema().clone());
+            Some(schema)
+        }
+        None => None,
+    };
     let mut event_processor: Box<dyn EventProcessor> = if json_mode {
         Box::new(EventProcessorWithJsonOutput::new(last_message_file.clone()))
     } else {
@@ -260,7 +271,31 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
     info!("Sent prompt with event ID: {initial_prompt_task_id}");
 
     // Run the loop until the task is complete.
-    while let Some(event) = rx.recv().await {
+    let mut corrections_sent = 0;
+    let mut schema_errors: Option<Vec<String>> = None;
+    while let Some(mut event) = rx.recv().await {
+        if let Some(schema) = &output_schema
+            && let EventMsg::TaskComplete(TaskCompleteEvent { last_agent_message }) = &mut event.msg
+        {
+            match schema.validate(last_agent_message.as_deref()) {
+                Ok(value) => {
+                    // Strip any code fence so the message is plain JSON.
+                    *last_agent_message = Some(serde_json::to_string_pretty(&value)?);
+                }
+                Err(errors) if corrections_sent < output_schema_retries => {
+                    corrections_sent += 1;
+                    eprintln!(
+                        "Final message does not match the output schema; asking for a correction ({corrections_sent}/{output_schema_retries})."
+                    );
+                    let items = vec![InputItem::Text {
+                        text: schema.correction_prompt(&errors),
+                    }];
+                    conversation.submit(Op::UserInput { items }).await?;
+                    continue;
+                }
+                Err(errors) => schema_errors = Some(errors),
+            }
+        }
         let shutdown: CodexStatus = event_processor.process_event(event);
         match shutdown {
             CodexStatus::Running => continue,
@@ -273,5 +308,12 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         }
     }
 
+    if let Some(errors) = schema_errors {
+        anyhow::bail!(
+            "final message does not match the output schema:\n- {}",
+            errors.join("\n- ")
+        );
+    }
+
     Ok(())
 }
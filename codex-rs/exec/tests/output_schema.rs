@@ -0,0 +1,120 @@
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use anyhow::Context;
+use assert_cmd::prelude::*;
+use core_test_support::load_sse_fixture_with_id_from_str;
+use std::process::Command;
+use tempfile::TempDir;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+const SCHEMA: &str = r#"{
+  "type": "object",
+  "properties": { "answer": { "type": "integer" } },
+  "required": ["answer"]
+}"#;
+
+/// SSE body for a turn whose final assistant message is `text`.
+fn message_sse(text: &str, id: &str) -> String {
+    let events = serde_json::json!([
+        {
+            "type": "response.output_item.done",
+            "item": {
+                "type": "message",
+                "role": "assistant",
+                "content": [{ "type": "output_text", "text": text }]
+            }
+        },
+        {
+            "type": "response.completed",
+            "response": { "id": "__ID__", "output": [] }
+        }
+    ]);
+    load_sse_fixture_with_id_from_str(&events.to_string(), id)
+}
+
+async fn mount_message(server: &MockServer, text: &str, id: &str) {
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(message_sse(text, id), "text/event-stream"),
+        )
+        .up_to_n_times(1)
+        .mount(server)
+        .await;
+}
+
+fn exec_cmd(server: &MockServer, home: &TempDir) -> Command {
+    std::fs::write(home.path().join("schema.json"), SCHEMA).unwrap();
+    let mut cmd = Command::cargo_bin("codex-exec").unwrap();
+    cmd.current_dir(home.path())
+        .env("CODEX_HOME", home.path())
+        .env("OPENAI_API_KEY", "dummy")
+        .env("OPENAI_BASE_URL", format!("{}/v1", server.uri()))
+        .arg("--skip-git-repo-check")
+        .arg("--output-schema")
+        .arg(home.path().join("schema.json"))
+        .arg("--output-last-message")
+        .arg(home.path().join("last.json"));
+    cmd
+}
+
+#[cfg(not(target_os = "windows"))]
+#[tokio::test]
+async fn invalid_final_message_is_sent_back_for_correction() -> anyhow::Result<()> {
+    let server = MockServer::start().await;
+    mount_message(&server, "The answer is 42.", "resp1").await;
+    mount_message(&server, "```json\n{\"answer\": 42}\n```", "resp2").await;
+
+    let home = TempDir::new()?;
+    exec_cmd(&server, &home)
+        .arg("what is the answer?")
+        .assert()
+        .success();
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let first: serde_json::Value = requests[0].body_json()?;
+    assert_eq!(first["text"]["format"]["type"], "json_schema");
+    assert_eq!(first["text"]["format"]["schema"]["required"][0], "answer");
+    let second = String::from_utf8(requests[1].body.clone())?;
+    assert!(
+        second.contains("does not match the required JSON Schema"),
+        "correction prompt missing from {second}"
+    );
+
+    let last = std::fs::read_to_string(home.path().join("last.json"))
+        .context("last message file should be written")?;
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&last)?,
+        serde_json::json!({ "answer": 42 })
+    );
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+#[tokio::test]
+async fn exits_non_zero_when_retries_are_exhausted() -> anyhow::Result<()> {
+    let server = MockServer::start().await;
+    mount_message(&server, "The answer is 42.", "resp1").await;
+    mount_message(&server, "{\"answer\": \"forty-two\"}", "resp2").await;
+
+    let home = TempDir::new()?;
+    exec_cmd(&server, &home)
+        .arg("--output-schema-retries")
+        .arg("1")
+        .arg("what is the answer?")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "final message does not match the output schema",
+        ));
+
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    Ok(())
+}
@@ -17,7 +17,7 @@ async fn spawn_command_under_sandbox(
     env: HashMap<String, String>,
 ) -> std::io::Result<Child> {
     use codex_core::seatbelt::spawn_command_under_seatbelt;
-    spawn_command_under_seatbelt(command, sandbox_policy, cwd, stdio_policy, env).await
+    spawn_command_under_seatbelt(command, sandbox_policy, None, cwd, stdio_policy, env).await
 }
 
 #[cfg(target_os = "linux")]
@@ -34,6 +34,8 @@ async fn spawn_command_under_sandbox(
         codex_linux_sandbox_exe,
         command,
         sandbox_policy,
+        None,
+        &Default::default(),
         cwd,
         stdio_policy,
         env,
@@ -58,6 +60,8 @@ async fn python_multiprocessing_lock_works_under_sandbox() {
         network_access: false,
         exclude_tmpdir_env_var: false,
         exclude_slash_tmp: false,
+        readable_roots: vec![],
+        allowed_domains: vec![],
     };
 
     let python_code = r#"import multiprocessing
@@ -169,7 +173,7 @@ fn unix_sock_body() {
 async fn allow_unix_socketpair_recvfrom() {
     run_code_under_sandbox(
         "allow_unix_socketpair_recvfrom",
-        &SandboxPolicy::ReadOnly,
+        &SandboxPolicy::new_read_only_policy(),
         || async { unix_sock_body() },
     )
     .await
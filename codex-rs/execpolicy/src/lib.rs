@@ -25,7 +25,9 @@ pub use exec_call::ExecCall;
 pub use execv_checker::ExecvChecker;
 pub use opt::Opt;
 pub use policy::Policy;
+pub use policy::RuleMatch;
 pub use policy_parser::PolicyParser;
+pub use program::Decision;
 pub use program::Forbidden;
 pub use program::MatchedExec;
 pub use program::NegativeExamplePassedCheck;
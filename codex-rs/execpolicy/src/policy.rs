@@ -2,6 +2,7 @@ use multimap::MultiMap;
 use regex_lite::Error as RegexError;
 use regex_lite::Regex;
 
+use crate::Decision;
 use crate::ExecCall;
 use crate::Forbidden;
 use crate::MatchedExec;
@@ -12,6 +13,17 @@ use crate::error::Result;
 use crate::policy_parser::ForbiddenProgramRegex;
 use crate::program::PositiveExampleFailedCheck;
 
+/// Result of [`Policy::check_with_rule`]: the match plus the rule that
+/// produced it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleMatch {
+    pub matched_exec: MatchedExec,
+    /// `name=` of the matching `define_program()`, or a description of the
+    /// `forbid_*` rule that matched.
+    pub rule: String,
+    pub decision: Option<Decision>,
+}
+
 pub struct Policy {
     programs: MultiMap<String, ProgramSpec>,
     forbidden_program_regexes: Vec<ForbiddenProgramRegex>,
@@ -42,15 +54,26 @@ impl Policy {
     }
 
     pub fn check(&self, exec_call: &ExecCall) -> Result<MatchedExec> {
+        self.check_with_rule(exec_call)
+            .map(|rule_match| rule_match.matched_exec)
+    }
+
+    /// Like [`Policy::check`], but also reports which rule matched and the
+    /// decision it carries.
+    pub fn check_with_rule(&self, exec_call: &ExecCall) -> Result<RuleMatch> {
         let ExecCall { program, args } = &exec_call;
         for ForbiddenProgramRegex { regex, reason } in &self.forbidden_program_regexes {
             if regex.is_match(program) {
-                return Ok(MatchedExec::Forbidden {
-                    cause: Forbidden::Program {
-                        program: program.clone(),
-                        exec_call: exec_call.clone(),
+                return Ok(RuleMatch {
+                    matched_exec: MatchedExec::Forbidden {
+                        cause: Forbidden::Program {
+                            program: program.clone(),
+                            exec_call: exec_call.clone(),
+                        },
+                        reason: reason.clone(),
                     },
-                    reason: reason.clone(),
+                    rule: format!("forbid_program_regex({})", regex.as_str()),
+                    decision: None,
                 });
             }
         }
@@ -59,12 +82,16 @@ impl Policy {
             if let Some(regex) = &self.forbidden_substrings_pattern
                 && regex.is_match(arg)
             {
-                return Ok(MatchedExec::Forbidden {
-                    cause: Forbidden::Arg {
-                        arg: arg.clone(),
-                        exec_call: exec_call.clone(),
+                return Ok(RuleMatch {
+                    matched_exec: MatchedExec::Forbidden {
+                        cause: Forbidden::Arg {
+                            arg: arg.clone(),
+                            exec_call: exec_call.clone(),
+                        },
+                        reason: format!("arg `{arg}` contains forbidden substring"),
                     },
-                    reason: format!("arg `{arg}` contains forbidden substring"),
+                    rule: "forbid_substrings".to_string(),
+                    decision: None,
                 });
             }
         }
@@ -75,7 +102,13 @@ impl Policy {
         if let Some(spec_list) = self.programs.get_vec(program) {
             for spec in spec_list {
                 match spec.check(exec_call) {
-                    Ok(matched_exec) => return Ok(matched_exec),
+                    Ok(matched_exec) => {
+                        return Ok(RuleMatch {
+                            matched_exec,
+                            rule: spec.rule_name().to_string(),
+                            decision: spec.decision,
+                        });
+                    }
                     Err(err) => {
                         last_err = Err(err);
                     }
@@ -1,5 +1,6 @@
 #![allow(clippy::needless_lifetimes)]
 
+use crate::Decision;
 use crate::Opt;
 use crate::Policy;
 use crate::ProgramSpec;
@@ -130,8 +131,14 @@ fn policy_builtins(builder: &mut GlobalsBuilder) {
         forbidden: Option<String>,
         should_match: Option<UnpackList<UnpackList<String>>>,
         should_not_match: Option<UnpackList<UnpackList<String>>>,
+        name: Option<String>,
+        decision: Option<String>,
         eval: &mut Evaluator,
     ) -> anyhow::Result<NoneType> {
+        let decision = decision
+            .map(|d| d.parse::<Decision>())
+            .transpose()
+            .map_err(|e| anyhow::format_err!(e))?;
         let option_bundling = option_bundling.unwrap_or(false);
         let system_path = system_path.map_or_else(Vec::new, |v| v.items.to_vec());
         let combined_format = combined_format.unwrap_or(false);
@@ -151,6 +158,8 @@ fn policy_builtins(builder: &mut GlobalsBuilder) {
 
         let program_spec = ProgramSpec::new(
             program,
+            name,
+            decision,
             system_path,
             option_bundling,
             combined_format,
@@ -1,6 +1,7 @@
 use serde::Serialize;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::str::FromStr;
 
 use crate::ArgType;
 use crate::ExecCall;
@@ -18,6 +19,10 @@ use crate::valid_exec::ValidExec;
 #[derive(Debug)]
 pub struct ProgramSpec {
     pub program: String,
+    /// Name reported when this rule matches; defaults to `program`.
+    pub name: Option<String>,
+    /// What the caller should do with a command that matches this rule.
+    pub decision: Option<Decision>,
     pub system_path: Vec<String>,
     pub option_bundling: bool,
     pub combined_format: bool,
@@ -32,6 +37,8 @@ pub struct ProgramSpec {
 impl ProgramSpec {
     pub fn new(
         program: String,
+        name: Option<String>,
+        decision: Option<Decision>,
         system_path: Vec<String>,
         option_bundling: bool,
         combined_format: bool,
@@ -53,6 +60,8 @@ impl ProgramSpec {
             .collect();
         Self {
             program,
+            name,
+            decision,
             system_path,
             option_bundling,
             combined_format,
@@ -66,6 +75,34 @@ impl ProgramSpec {
     }
 }
 
+/// What a policy asks the caller to do with a command that matched a rule,
+/// as set by `decision=` in `define_program()`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Decision {
+    /// Run the command without asking, inside the sandbox.
+    Allow,
+    /// Run the command without asking and without a sandbox.
+    AllowUnsandboxed,
+    /// Ask the user before running the command.
+    Ask,
+}
+
+impl FromStr for Decision {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Decision::Allow),
+            "allow-unsandboxed" => Ok(Decision::AllowUnsandboxed),
+            "ask" => Ok(Decision::Ask),
+            other => Err(format!(
+                "unknown decision `{other}`: expected `allow`, `allow-unsandboxed` or `ask`"
+            )),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub enum MatchedExec {
     Match { exec: ValidExec },
@@ -88,6 +125,10 @@ pub enum Forbidden {
 }
 
 impl ProgramSpec {
+    pub fn rule_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.program)
+    }
+
     // TODO(mbolin): The idea is that there should be a set of rules defined for
     // a program and the args should be checked against the rules to determine
     // if the program should be allowed to run.
@@ -0,0 +1,50 @@
+use codex_execpolicy::Decision;
+use codex_execpolicy::ExecCall;
+use codex_execpolicy::PolicyParser;
+
+extern crate codex_execpolicy;
+
+#[test]
+fn test_rule_name_and_decision_are_reported() {
+    let unparsed_policy = r#"
+define_program(
+    program="cargo",
+    name="cargo-test",
+    args=["test"],
+    decision="allow-unsandboxed",
+)
+
+define_program(
+    program="rm",
+    args=[ARG_WFILE],
+    forbidden="deleting files is not allowed",
+)
+"#;
+    let parser = PolicyParser::new("test_rule_name_and_decision_are_reported", unparsed_policy);
+    let policy = parser.parse().expect("failed to parse policy");
+
+    let cargo_test = policy
+        .check_with_rule(&ExecCall::new("cargo", &["test"]))
+        .expect("cargo test should match");
+    assert_eq!("cargo-test", cargo_test.rule);
+    assert_eq!(Some(Decision::AllowUnsandboxed), cargo_test.decision);
+
+    let rm = policy
+        .check_with_rule(&ExecCall::new("rm", &["foo"]))
+        .expect("rm should match");
+    assert_eq!("rm", rm.rule);
+    assert_eq!(None, rm.decision);
+}
+
+#[test]
+fn test_unknown_decision_is_rejected() {
+    let unparsed_policy = r#"
+define_program(
+    program="ls",
+    decision="sometimes",
+)
+"#;
+    let parser = PolicyParser::new("test_unknown_decision_is_rejected", unparsed_policy);
+    let err = parser.parse().err().expect("policy should not parse");
+    assert!(err.to_string().contains("unknown decision `sometimes`"));
+}
@@ -0,0 +1,293 @@
+//! Regex search over the contents of the files the walker visits, for callers
+//! that cannot rely on `rg` being installed.
+
+use regex_lite::Regex;
+use regex_lite::RegexBuilder;
+use serde::Serialize;
+use std::cell::UnsafeCell;
+use std::num::NonZero;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use crate::WorkerCount;
+use crate::create_walk_builder;
+use crate::create_worker_count;
+
+/// Files larger than this are skipped.
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// A NUL byte in this many leading bytes marks a file as binary, which is the
+/// same heuristic git uses.
+const BINARY_DETECTION_BYTES: usize = 8 * 1024;
+
+/// Longer lines are cut off so that a match in minified code does not flood
+/// the results.
+const MAX_LINE_CHARS: usize = 500;
+
+pub struct ContentSearchOptions {
+    /// Regular expression matched against every line.
+    pub pattern: String,
+    pub case_insensitive: bool,
+    /// Only files matching one of these globs are searched. All files are
+    /// searched when this is empty.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Number of lines returned before and after each matching line.
+    pub context_lines: usize,
+    /// Maximum number of matching lines to return.
+    pub limit: NonZero<usize>,
+    pub threads: NonZero<usize>,
+}
+
+/// A line that matched the pattern.
+///
+/// * `path` – Path to the file (relative to the search directory).
+/// * `line_number` – 1-based number of the matching line.
+/// * `before` / `after` – Up to `context_lines` lines around the match.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContentMatch {
+    pub path: String,
+    pub line_number: usize,
+    pub line: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+}
+
+pub struct ContentSearchResults {
+    /// The first `limit` matches, sorted by path and then line number.
+    pub matches: Vec<ContentMatch>,
+    pub total_match_count: usize,
+}
+
+/// Searches the text files under `search_directory` for lines matching
+/// `options.pattern`, honoring `.gitignore` the same way [`crate::run`] does.
+/// Binary files and files larger than 4 MiB are skipped.
+///
+/// The worker threads check `cancel_flag` before each file.
+pub fn run_content_search(
+    options: &ContentSearchOptions,
+    search_directory: &Path,
+    cancel_flag: Arc<AtomicBool>,
+) -> anyhow::Result<ContentSearchResults> {
+    let regex = RegexBuilder::new(&options.pattern)
+        .case_insensitive(options.case_insensitive)
+        .build()?;
+    let limit = options.limit.get();
+    let WorkerCount {
+        num_walk_builder_threads,
+        num_best_matches_lists,
+    } = create_worker_count(options.threads);
+    let matches_per_worker: Vec<UnsafeCell<ContentMatchesList>> = (0..num_best_matches_lists)
+        .map(|_| UnsafeCell::new(ContentMatchesList::new(limit, options.context_lines)))
+        .collect();
+
+    let mut walk_builder = create_walk_builder(
+        search_directory,
+        &options.include,
+        &options.exclude,
+        num_walk_builder_threads,
+    )?;
+    walk_builder.max_filesize(Some(MAX_FILE_SIZE));
+    let walker = walk_builder.build_parallel();
+
+    // As in `run`, each worker gets its own list so no locking is needed.
+    let index_counter = AtomicUsize::new(0);
+    walker.run(|| {
+        let index = index_counter.fetch_add(1, Ordering::Relaxed);
+        let list_ptr = matches_per_worker[index].get();
+        let list = unsafe { &mut *list_ptr };
+        let regex = &regex;
+        let cancel = cancel_flag.clone();
+
+        Box::new(move |entry| {
+            if cancel.load(Ordering::Relaxed) {
+                return ignore::WalkState::Quit;
+            }
+            if let Ok(entry) = entry
+                && entry.file_type().is_some_and(|ft| ft.is_file())
+                && let Ok(rel_path) = entry.path().strip_prefix(search_directory)
+                && let Some(rel_path) = rel_path.to_str()
+            {
+                list.search_file(entry.path(), rel_path, regex);
+            }
+            ignore::WalkState::Continue
+        })
+    });
+
+    if cancel_flag.load(Ordering::Relaxed) {
+        return Ok(ContentSearchResults {
+            matches: Vec::new(),
+            total_match_count: 0,
+        });
+    }
+
+    let mut total_match_count = 0;
+    let mut matches = Vec::new();
+    for list_cell in matches_per_worker {
+        let list = list_cell.into_inner();
+        total_match_count += list.num_matches;
+        matches.extend(list.matches);
+    }
+    sort_content_matches(&mut matches);
+    matches.truncate(limit);
+
+    Ok(ContentSearchResults {
+        matches,
+        total_match_count,
+    })
+}
+
+fn sort_content_matches(matches: &mut [ContentMatch]) {
+    matches.sort_by(|a, b| {
+        a.path
+            .cmp(&b.path)
+            .then_with(|| a.line_number.cmp(&b.line_number))
+    });
+}
+
+/// Keeps the first `max_count` matches (by path and line number) one worker
+/// has seen.
+struct ContentMatchesList {
+    max_count: usize,
+    context_lines: usize,
+    num_matches: usize,
+    matches: Vec<ContentMatch>,
+}
+
+impl ContentMatchesList {
+    fn new(max_count: usize, context_lines: usize) -> Self {
+        Self {
+            max_count,
+            context_lines,
+            num_matches: 0,
+            matches: Vec::new(),
+        }
+    }
+
+    fn search_file(&mut self, path: &Path, rel_path: &str, regex: &Regex) {
+        let Ok(bytes) = std::fs::read(path) else {
+            return;
+        };
+        if bytes[..bytes.len().min(BINARY_DETECTION_BYTES)].contains(&0) {
+            return;
+        }
+        let text = String::from_utf8_lossy(&bytes);
+        let lines: Vec<&str> = text.lines().collect();
+        for (index, line) in lines.iter().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+            self.num_matches += 1;
+            let start = index.saturating_sub(self.context_lines);
+            let end = (index + 1 + self.context_lines).min(lines.len());
+            self.matches.push(ContentMatch {
+                path: rel_path.to_string(),
+                line_number: index + 1,
+                line: truncate_line(line),
+                before: lines[start..index]
+                    .iter()
+                    .map(|l| truncate_line(l))
+                    .collect(),
+                after: lines[index + 1..end]
+                    .iter()
+                    .map(|l| truncate_line(l))
+                    .collect(),
+            });
+            // Sorting on every insert would be wasteful, so let the list grow
+            // to twice its size before dropping the matches that sort last.
+            if self.matches.len() >= 2 * self.max_count {
+                sort_content_matches(&mut self.matches);
+                self.matches.truncate(self.max_count);
+            }
+        }
+    }
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    fn search(dir: &Path, pattern: &str, include: &[&str], context_lines: usize) -> Vec<String> {
+        let options = ContentSearchOptions {
+            pattern: pattern.to_string(),
+            case_insensitive: false,
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: Vec::new(),
+            context_lines,
+            limit: NonZero::new(10).unwrap(),
+            threads: NonZero::new(2).unwrap(),
+        };
+        let results = run_content_search(&options, dir, Arc::new(AtomicBool::new(false))).unwrap();
+        results
+            .matches
+            .into_iter()
+            .map(|m| {
+                format!(
+                    "{}:{}:{}|{}|{}",
+                    m.path,
+                    m.line_number,
+                    m.line,
+                    m.before.join(","),
+                    m.after.join(",")
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_matching_lines_and_skips_ignored_and_binary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        fs::write(
+            dir.path().join("src/lib.rs"),
+            "use std::fs;\nfn needle() {}\nfn other() {}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.md"), "a needle in the notes\n").unwrap();
+        fs::create_dir(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("target/out.rs"), "fn needle() {}\n").unwrap();
+        fs::write(dir.path().join("blob.bin"), b"needle\0\x01").unwrap();
+
+        assert_eq!(
+            search(dir.path(), r"needle\b", &[], 1),
+            vec![
+                "notes.md:1:a needle in the notes||".to_string(),
+                "src/lib.rs:2:fn needle() {}|use std::fs;|fn other() {}".to_string(),
+            ]
+        );
+        assert_eq!(
+            search(dir.path(), "needle", &["*.rs"], 0),
+            vec!["src/lib.rs:2:fn needle() {}||".to_string()]
+        );
+    }
+
+    #[test]
+    fn keeps_the_first_matches_when_over_the_limit() {
+        let mut list = ContentMatchesList::new(2, 0);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "x1\nx2\nx3\nx4\nx5\n").unwrap();
+        list.search_file(&path, "a.txt", &Regex::new("x").unwrap());
+
+        assert_eq!(list.num_matches, 5);
+        sort_content_matches(&mut list.matches);
+        let lines: Vec<usize> = list.matches.iter().map(|m| m.line_number).collect();
+        assert_eq!(lines[..2], [1, 2]);
+    }
+}
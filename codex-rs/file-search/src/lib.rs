@@ -19,8 +19,13 @@ use std::sync::atomic::Ordering;
 use tokio::process::Command;
 
 mod cli;
+mod content;
 
 pub use cli::Cli;
+pub use content::ContentMatch;
+pub use content::ContentSearchOptions;
+pub use content::ContentSearchResults;
+pub use content::run_content_search;
 
 /// A single match result returned from the search.
 ///
@@ -148,21 +153,8 @@ pub fn run(
         })
         .collect();
 
-    // Use the same tree-walker library that ripgrep uses. We use it directly so
-    // that we can leverage the parallelism it provides.
-    let mut walk_builder = WalkBuilder::new(search_directory);
-    walk_builder.threads(num_walk_builder_threads);
-    if !exclude.is_empty() {
-        let mut override_builder = OverrideBuilder::new(search_directory);
-        for exclude in exclude {
-            // The `!` prefix is used to indicate an exclude pattern.
-            let exclude_pattern = format!("!{exclude}");
-            override_builder.add(&exclude_pattern)?;
-        }
-        let override_matcher = override_builder.build()?;
-        walk_builder.overrides(override_matcher);
-    }
-    let walker = walk_builder.build_parallel();
+    let walker = create_walk_builder(search_directory, &[], &exclude, num_walk_builder_threads)?
+        .build_parallel();
 
     // Each worker created by `WalkParallel::run()` will have its own
     // `BestMatchesList` to update.
@@ -354,6 +346,34 @@ fn create_worker_count(num_workers: NonZero<usize>) -> WorkerCount {
     }
 }
 
+/// Creates the walker shared by the file name and content searches. When
+/// `include` is not empty, only files matching one of its globs are visited.
+fn create_walk_builder(
+    search_directory: &Path,
+    include: &[String],
+    exclude: &[String],
+    num_walk_builder_threads: usize,
+) -> anyhow::Result<WalkBuilder> {
+    // Use the same tree-walker library that ripgrep uses. We use it directly so
+    // that we can leverage the parallelism it provides.
+    let mut walk_builder = WalkBuilder::new(search_directory);
+    walk_builder.threads(num_walk_builder_threads);
+    if !include.is_empty() || !exclude.is_empty() {
+        let mut override_builder = OverrideBuilder::new(search_directory);
+        for include in include {
+            override_builder.add(include)?;
+        }
+        for exclude in exclude {
+            // The `!` prefix is used to indicate an exclude pattern.
+            let exclude_pattern = format!("!{exclude}");
+            override_builder.add(&exclude_pattern)?;
+        }
+        let override_matcher = override_builder.build()?;
+        walk_builder.overrides(override_matcher);
+    }
+    Ok(walk_builder)
+}
+
 fn create_pattern(pattern: &str) -> Pattern {
     Pattern::new(
         pattern,
@@ -10,10 +10,12 @@ use codex_core::protocol::SandboxPolicy;
 use landlock::ABI;
 use landlock::Access;
 use landlock::AccessFs;
+use landlock::BitFlags;
 use landlock::CompatLevel;
 use landlock::Compatible;
 use landlock::Ruleset;
 use landlock::RulesetAttr;
+use landlock::RulesetCreated;
 use landlock::RulesetCreatedAttr;
 use seccompiler::BpfProgram;
 use seccompiler::SeccompAction;
@@ -25,14 +27,28 @@ use seccompiler::SeccompRule;
 use seccompiler::TargetArch;
 use seccompiler::apply_filter;
 
+/// Folders that stay readable when a policy limits reads to its
+/// `readable_roots`, so that programs and their shared libraries still load.
+const SYSTEM_READABLE_ROOTS: &[&str] = &[
+    "/bin", "/sbin", "/usr", "/lib", "/lib32", "/lib64", "/etc", "/opt", "/dev", "/proc", "/sys",
+    "/run", "/nix",
+];
+
 /// Apply sandbox policies inside this thread so only the child inherits
 /// them, not the entire CLI process.
+///
+/// `network_isolated` is set when the thread already runs in a network
+/// namespace whose only way out is the network proxy, in which case socket
+/// syscalls are left alone so the command can reach that proxy over loopback.
+/// The rest of the seccomp filter still applies.
 pub(crate) fn apply_sandbox_policy_to_current_thread(
     sandbox_policy: &SandboxPolicy,
     cwd: &Path,
+    deny_read: &[PathBuf],
+    network_isolated: bool,
 ) -> Result<()> {
     if !sandbox_policy.has_full_network_access() {
-        install_network_seccomp_filter_on_current_thread()?;
+        install_network_seccomp_filter_on_current_thread(network_isolated)?;
     }
 
     if !sandbox_policy.has_full_disk_write_access() {
@@ -41,36 +57,59 @@ pub(crate) fn apply_sandbox_policy_to_current_thread(
             .into_iter()
             .map(|writable_root| writable_root.root)
             .collect();
-        install_filesystem_landlock_rules_on_current_thread(writable_roots)?;
+        let readable_roots = match sandbox_policy.get_readable_roots_with_cwd(cwd) {
+            Some(mut roots) => {
+                roots.extend(SYSTEM_READABLE_ROOTS.iter().map(PathBuf::from));
+                roots
+            }
+            None => vec![PathBuf::from("/")],
+        };
+        install_filesystem_landlock_rules_on_current_thread(
+            readable_roots,
+            writable_roots,
+            deny_read,
+        )?;
     }
 
-    // TODO(ragona): Add appropriate restrictions if
-    // `sandbox_policy.has_full_disk_read_access()` is `false`.
-
     Ok(())
 }
 
 /// Installs Landlock file-system rules on the current thread allowing read
-/// access to the entire file-system while restricting write access to
-/// `/dev/null` and the provided list of `writable_roots`.
+/// access to `readable_roots` while restricting write access to `/dev/null`
+/// and the provided list of `writable_roots`. Nothing beneath `deny_read` is
+/// readable, even inside those roots; inside a writable root it can still be
+/// written to or removed.
 ///
 /// # Errors
 /// Returns [`CodexErr::Sandbox`] variants when the ruleset fails to apply.
-fn install_filesystem_landlock_rules_on_current_thread(writable_roots: Vec<PathBuf>) -> Result<()> {
+fn install_filesystem_landlock_rules_on_current_thread(
+    readable_roots: Vec<PathBuf>,
+    writable_roots: Vec<PathBuf>,
+    deny_read: &[PathBuf],
+) -> Result<()> {
     let abi = ABI::V5;
     let access_rw = AccessFs::from_all(abi);
     let access_ro = AccessFs::from_read(abi);
 
+    // Symlinks are resolved so that paths compare equal to the `read_dir`
+    // entries walked below. Paths that do not exist cannot be read anyway.
+    let deny_read: Vec<PathBuf> = deny_read
+        .iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect();
+
     let mut ruleset = Ruleset::default()
         .set_compatibility(CompatLevel::BestEffort)
         .handle_access(access_rw)?
         .create()?
-        .add_rules(landlock::path_beneath_rules(&["/"], access_ro))?
         .add_rules(landlock::path_beneath_rules(&["/dev/null"], access_rw))?
         .set_no_new_privs(true);
 
-    if !writable_roots.is_empty() {
-        ruleset = ruleset.add_rules(landlock::path_beneath_rules(&writable_roots, access_rw))?;
+    for root in &readable_roots {
+        ruleset = add_rules_excluding_denied(ruleset, root, access_ro, &deny_read)?;
+    }
+    for root in &writable_roots {
+        ruleset = add_rules_excluding_denied(ruleset, root, access_rw, &deny_read)?;
     }
 
     let status = ruleset.restrict_self()?;
@@ -82,12 +121,94 @@ fn install_filesystem_landlock_rules_on_current_thread(writable_roots: Vec<PathB
     Ok(())
 }
 
-/// Installs a seccomp filter that blocks outbound network access except for
-/// AF_UNIX domain sockets.
-fn install_network_seccomp_filter_on_current_thread() -> std::result::Result<(), SandboxErr> {
+/// Grants `access` beneath `root` except beneath any of the (canonical)
+/// `denied` paths.
+///
+/// Landlock rules can only grant access, so a denied path is carved out by
+/// granting each sibling along the way from `root` down to it instead. The
+/// folders along that way keep `access` except reading and executing files,
+/// which a rule on a folder would grant for the denied path too. Siblings are
+/// taken from a listing made when the sandbox starts: files and folders
+/// created later in one of those folders can be written to but not read.
+/// Symlinked entries are skipped: Landlock checks the resolved target, which
+/// is granted (or not) wherever it lives.
+fn add_rules_excluding_denied(
+    mut ruleset: RulesetCreated,
+    root: &Path,
+    access: BitFlags<AccessFs>,
+    denied: &[PathBuf],
+) -> Result<RulesetCreated> {
+    let Ok(root) = root.canonicalize() else {
+        return Ok(ruleset);
+    };
+    if denied.iter().any(|d| root.starts_with(d)) {
+        return Ok(ruleset);
+    }
+    if !denied.iter().any(|d| d.starts_with(&root)) {
+        return Ok(ruleset.add_rules(landlock::path_beneath_rules(&[root], access))?);
+    }
+
+    ruleset = ruleset.add_rules(landlock::path_beneath_rules(
+        &[&root],
+        access & !(AccessFs::ReadFile | AccessFs::Execute),
+    ))?;
+    let Ok(entries) = std::fs::read_dir(&root) else {
+        return Ok(ruleset);
+    };
+    let mut granted = Vec::new();
+    for entry in entries.flatten() {
+        if entry.file_type().map_or(true, |t| t.is_symlink()) {
+            continue;
+        }
+        let path = entry.path();
+        if denied.iter().any(|d| d.starts_with(&path)) {
+            ruleset = add_rules_excluding_denied(ruleset, &path, access, denied)?;
+        } else {
+            granted.push(path);
+        }
+    }
+    Ok(ruleset.add_rules(landlock::path_beneath_rules(&granted, access))?)
+}
+
+/// Installs a seccomp filter that blocks `ptrace` and outbound network access
+/// except for AF_UNIX domain sockets. With `network_isolated`, only `ptrace`
+/// is blocked: the network namespace already confines the sockets.
+fn install_network_seccomp_filter_on_current_thread(
+    network_isolated: bool,
+) -> std::result::Result<(), SandboxErr> {
     // Build rule map.
     let mut rules: BTreeMap<i64, Vec<SeccompRule>> = BTreeMap::new();
 
+    // Unconditional deny rule (empty rule vec = unconditional match).
+    rules.insert(libc::SYS_ptrace, vec![]);
+    if !network_isolated {
+        add_socket_rules(&mut rules)?;
+    }
+
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,                     // default – allow
+        SeccompAction::Errno(libc::EPERM as u32), // when rule matches – return EPERM
+        if cfg!(target_arch = "x86_64") {
+            TargetArch::x86_64
+        } else if cfg!(target_arch = "aarch64") {
+            TargetArch::aarch64
+        } else {
+            unimplemented!("unsupported architecture for seccomp filter");
+        },
+    )?;
+
+    let prog: BpfProgram = filter.try_into()?;
+
+    apply_filter(&prog)?;
+
+    Ok(())
+}
+
+/// Adds the rules that deny socket syscalls except for AF_UNIX sockets.
+fn add_socket_rules(
+    rules: &mut BTreeMap<i64, Vec<SeccompRule>>,
+) -> std::result::Result<(), SandboxErr> {
     // Helper – insert unconditional deny rule for syscall number.
     let mut deny_syscall = |nr: i64| {
         rules.insert(nr, vec![]); // empty rule vec = unconditional match
@@ -111,7 +232,6 @@ fn install_network_seccomp_filter_on_current_thread() -> std::result::Result<(),
     deny_syscall(libc::SYS_recvmmsg);
     deny_syscall(libc::SYS_getsockopt);
     deny_syscall(libc::SYS_setsockopt);
-    deny_syscall(libc::SYS_ptrace);
 
     // For `socket` we allow AF_UNIX (arg0 == AF_UNIX) and deny everything else.
     let unix_only_rule = SeccompRule::new(vec![SeccompCondition::new(
@@ -124,22 +244,5 @@ fn install_network_seccomp_filter_on_current_thread() -> std::result::Result<(),
     rules.insert(libc::SYS_socket, vec![unix_only_rule.clone()]);
     rules.insert(libc::SYS_socketpair, vec![unix_only_rule]); // always deny (Unix can use socketpair but fine, keep open?)
 
-    let filter = SeccompFilter::new(
-        rules,
-        SeccompAction::Allow,                     // default – allow
-        SeccompAction::Errno(libc::EPERM as u32), // when rule matches – return EPERM
-        if cfg!(target_arch = "x86_64") {
-            TargetArch::x86_64
-        } else if cfg!(target_arch = "aarch64") {
-            TargetArch::aarch64
-        } else {
-            unimplemented!("unsupported architecture for seccomp filter");
-        },
-    )?;
-
-    let prog: BpfProgram = filter.try_into()?;
-
-    apply_filter(&prog)?;
-
     Ok(())
 }
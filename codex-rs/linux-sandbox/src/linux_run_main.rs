@@ -1,11 +1,32 @@
 use clap::Parser;
+use codex_core::network_proxy::NO_PROXY_ENV_VARS;
+use codex_core::network_proxy::PROXY_ENV_VARS;
+use codex_core::resource_limits::ResourceLimits;
 use std::ffi::CString;
 use std::path::PathBuf;
 
 use crate::landlock::apply_sandbox_policy_to_current_thread;
+use crate::proxy_bridge::enter_isolated_network_namespace;
+use crate::proxy_bridge::fork_proxy_bridge;
+use crate::resource_limits::apply_rlimits;
+use crate::resource_limits::join_exec_cgroup;
 
 #[derive(Debug, Parser)]
 pub struct LandlockCommand {
+    /// Paths that must stay unreadable even beneath a readable root.
+    #[arg(long = "deny-read")]
+    pub deny_read: Vec<PathBuf>,
+
+    /// Unix socket of the network proxy enforcing the policy's
+    /// `allowed_domains`. When set, the command runs in a network namespace
+    /// where the proxy is the only reachable endpoint.
+    #[arg(long = "proxy-socket")]
+    pub proxy_socket: Option<PathBuf>,
+
+    /// Limits to apply to the command, as JSON.
+    #[arg(long = "resource-limits")]
+    pub resource_limits: Option<ResourceLimits>,
+
     /// It is possible that the cwd used in the context of the sandbox policy
     /// is different from the cwd of the process to spawn.
     pub sandbox_policy_cwd: PathBuf,
@@ -19,17 +40,74 @@ pub struct LandlockCommand {
 
 pub fn run_main() -> ! {
     let LandlockCommand {
+        deny_read,
+        proxy_socket,
+        resource_limits,
         sandbox_policy_cwd,
         sandbox_policy,
         command,
     } = LandlockCommand::parse();
 
-    if let Err(e) = apply_sandbox_policy_to_current_thread(&sandbox_policy, &sandbox_policy_cwd) {
+    if command.is_empty() {
+        panic!("No command specified to execute.");
+    }
+
+    let resource_limits = resource_limits.unwrap_or_default();
+    // Refuse to run rather than run without the caps.
+    if resource_limits.uses_cgroup()
+        && let Err(e) = join_exec_cgroup(&resource_limits)
+    {
+        panic!(
+            "error applying resource_limits.memory_max_mb or max_processes (requires cgroup v2): {e}"
+        );
+    }
+
+    let Some(proxy_socket) = proxy_socket else {
+        if let Err(e) = apply_sandbox_policy_to_current_thread(
+            &sandbox_policy,
+            &sandbox_policy_cwd,
+            &deny_read,
+            false,
+        ) {
+            panic!("error running landlock: {e:?}");
+        }
+        exec_command(&command, &resource_limits);
+    };
+
+    if let Err(e) = enter_isolated_network_namespace() {
+        panic!("error isolating the network for the proxy: {e:?}");
+    }
+    let proxy_port = fork_proxy_bridge(proxy_socket);
+    set_proxy_env_vars(proxy_port);
+    if let Err(e) = apply_sandbox_policy_to_current_thread(
+        &sandbox_policy,
+        &sandbox_policy_cwd,
+        &deny_read,
+        true,
+    ) {
         panic!("error running landlock: {e:?}");
     }
+    exec_command(&command, &resource_limits)
+}
 
-    if command.is_empty() {
-        panic!("No command specified to execute.");
+fn set_proxy_env_vars(port: u16) {
+    let url = format!("http://127.0.0.1:{port}");
+    // SAFETY: called in the single-threaded child right before exec.
+    unsafe {
+        for name in PROXY_ENV_VARS {
+            std::env::set_var(name, &url);
+        }
+        for name in NO_PROXY_ENV_VARS {
+            std::env::remove_var(name);
+        }
+    }
+}
+
+/// Applies the rlimits last so that they only constrain the command, not the
+/// proxy bridge.
+fn exec_command(command: &[String], resource_limits: &ResourceLimits) -> ! {
+    if let Err(e) = apply_rlimits(resource_limits) {
+        panic!("error applying resource limits: {e}");
     }
 
     #[expect(clippy::expect_used)]
@@ -0,0 +1,162 @@
+//! Network isolation for sandboxes that may only reach Codex's network proxy.
+//!
+//! The helper moves itself into a fresh user + network namespace, where the
+//! only interface is loopback. It then forks: the child runs the command with
+//! the proxy environment variables pointing at a loopback port, and the parent
+//! bridges connections on that port to the proxy's Unix socket (which, being a
+//! file-system path, is reachable from any network namespace).
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::Ipv4Addr;
+use std::net::Shutdown;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Moves the current (single-threaded) process into a new user and network
+/// namespace and brings up its loopback interface.
+pub(crate) fn enter_isolated_network_namespace() -> io::Result<()> {
+    let uid = unsafe { libc::geteuid() };
+    let gid = unsafe { libc::getegid() };
+    if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // Keep the same ids inside the namespace so file ownership is unchanged.
+    std::fs::write("/proc/self/setgroups", "deny")?;
+    std::fs::write("/proc/self/uid_map", format!("{uid} {uid} 1"))?;
+    std::fs::write("/proc/self/gid_map", format!("{gid} {gid} 1"))?;
+
+    bring_up_loopback()
+}
+
+fn bring_up_loopback() -> io::Result<()> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut request: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in request.ifr_name.iter_mut().zip(b"lo\0") {
+        *dst = *src as libc::c_char;
+    }
+    let result = unsafe {
+        if libc::ioctl(fd, libc::SIOCGIFFLAGS as _, &mut request) < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+            if libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &request) < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    };
+    unsafe { libc::close(fd) };
+    result
+}
+
+/// Forks the process. Only the child returns, with the loopback port the
+/// proxy is reachable on; the parent forwards connections on that port to
+/// `proxy_socket` until the child exits, then exits the same way.
+pub(crate) fn fork_proxy_bridge(proxy_socket: PathBuf) -> u16 {
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) {
+        Ok(listener) => listener,
+        Err(e) => panic!("failed to listen on loopback: {e}"),
+    };
+    let port = match listener.local_addr() {
+        Ok(addr) => addr.port(),
+        Err(e) => panic!("failed to read loopback address: {e}"),
+    };
+
+    let parent_pid = unsafe { libc::getpid() };
+    match unsafe { libc::fork() } {
+        -1 => panic!("fork failed: {}", io::Error::last_os_error()),
+        0 => {
+            drop(listener);
+            // Do not outlive the bridge: without it the command has no network
+            // anyway, and Codex only tracks the bridge process.
+            unsafe {
+                if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) == -1
+                    || libc::getppid() != parent_pid
+                {
+                    libc::_exit(1);
+                }
+            }
+            port
+        }
+        child_pid => {
+            std::thread::spawn(move || serve_bridge(listener, &proxy_socket));
+            exit_like_child(child_pid)
+        }
+    }
+}
+
+fn serve_bridge(listener: TcpListener, proxy_socket: &Path) {
+    for client in listener.incoming().flatten() {
+        let Ok(upstream) = UnixStream::connect(proxy_socket) else {
+            continue;
+        };
+        let (Ok(client_read), Ok(upstream_read)) = (client.try_clone(), upstream.try_clone())
+        else {
+            continue;
+        };
+        std::thread::spawn(move || pipe(client_read, upstream));
+        std::thread::spawn(move || pipe(upstream_read, client));
+    }
+}
+
+/// Copies `from` into `to` until EOF, then closes the write half of `to`.
+fn pipe<R, W>(mut from: R, mut to: W)
+where
+    R: Read,
+    W: Write + ShutdownWrite,
+{
+    let _ = io::copy(&mut from, &mut to);
+    to.shutdown_write();
+}
+
+trait ShutdownWrite {
+    fn shutdown_write(&self);
+}
+
+impl ShutdownWrite for TcpStream {
+    fn shutdown_write(&self) {
+        let _ = self.shutdown(Shutdown::Write);
+    }
+}
+
+impl ShutdownWrite for UnixStream {
+    fn shutdown_write(&self) {
+        let _ = self.shutdown(Shutdown::Write);
+    }
+}
+
+/// Waits for `child_pid` and terminates this process with the same exit code
+/// or signal.
+fn exit_like_child(child_pid: libc::pid_t) -> ! {
+    let mut status = 0;
+    loop {
+        if unsafe { libc::waitpid(child_pid, &mut status, 0) } != -1 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            panic!("waitpid failed: {err}");
+        }
+    }
+
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+        std::process::exit(128 + signal);
+    }
+    std::process::exit(libc::WEXITSTATUS(status));
+}
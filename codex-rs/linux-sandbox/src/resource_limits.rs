@@ -0,0 +1,98 @@
+//! Applies the [`ResourceLimits`] Codex passes via `--resource-limits`.
+
+use std::io;
+use std::path::Path;
+
+use codex_core::resource_limits::ResourceLimits;
+use codex_core::resource_limits::exec_cgroup_path;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Moves the current process into a new cgroup whose `memory.max` is
+/// `memory_max_mb` and whose `pids.max` is `max_processes`. Must run before
+/// the process leaves its user namespace, since the cgroup files belong to the
+/// user Codex runs as. The network proxy bridge, forked afterwards, counts
+/// towards `pids.max` too.
+pub(crate) fn join_exec_cgroup(limits: &ResourceLimits) -> io::Result<()> {
+    let path = exec_cgroup_path(std::process::id())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Codex is not in a cgroup v2"))?;
+    let parent = path.parent().unwrap_or(&path);
+    // Also rejects hybrid setups, where /sys/fs/cgroup is not a cgroup2 mount.
+    let controllers = std::fs::read_to_string(parent.join("cgroup.subtree_control"))?;
+    let needed = [
+        limits.memory_max_mb.map(|_| "memory"),
+        limits.max_processes.map(|_| "pids"),
+    ];
+    for controller in needed.into_iter().flatten() {
+        if !controllers.split_whitespace().any(|c| c == controller) {
+            return Err(io::Error::other(format!(
+                "the {controller} controller is not enabled in {}/cgroup.subtree_control",
+                parent.display()
+            )));
+        }
+    }
+
+    match std::fs::create_dir(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e),
+    }
+    configure_and_join(&path, limits).inspect_err(|_| {
+        let _ = std::fs::remove_dir(&path);
+    })
+}
+
+fn configure_and_join(path: &Path, limits: &ResourceLimits) -> io::Result<()> {
+    if let Some(memory_max_mb) = limits.memory_max_mb {
+        std::fs::write(
+            path.join("memory.max"),
+            memory_max_mb.saturating_mul(MIB).to_string(),
+        )?;
+        // Without swap accounting the file is absent and the cap covers RAM only.
+        match std::fs::write(path.join("memory.swap.max"), "0") {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    if let Some(count) = limits.max_processes {
+        std::fs::write(path.join("pids.max"), count.to_string())?;
+    }
+    std::fs::write(path.join("cgroup.procs"), std::process::id().to_string())
+}
+
+/// Applies the rlimits in `limits` to the current process, to be inherited
+/// by the command it execs. Limits can only be lowered.
+pub(crate) fn apply_rlimits(limits: &ResourceLimits) -> io::Result<()> {
+    if let Some(seconds) = limits.cpu_time_seconds {
+        // SIGXCPU at the soft limit, SIGKILL a second later if it is ignored.
+        set_rlimit(libc::RLIMIT_CPU, seconds, seconds.saturating_add(1))?;
+    }
+    if let Some(mb) = limits.address_space_mb {
+        let bytes = mb.saturating_mul(MIB);
+        set_rlimit(libc::RLIMIT_AS, bytes, bytes)?;
+    }
+    if let Some(count) = limits.max_open_files {
+        set_rlimit(libc::RLIMIT_NOFILE, count, count)?;
+    }
+    Ok(())
+}
+
+fn set_rlimit(resource: libc::__rlimit_resource_t, soft: u64, hard: u64) -> io::Result<()> {
+    let mut current = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Raising the hard limit needs privileges, so never go above it.
+    let rlim_max = hard.min(current.rlim_max);
+    let limit = libc::rlimit {
+        rlim_cur: soft.min(rlim_max),
+        rlim_max,
+    };
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
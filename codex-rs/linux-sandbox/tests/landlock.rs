@@ -3,12 +3,19 @@ use codex_core::config_types::ShellEnvironmentPolicy;
 use codex_core::error::CodexErr;
 use codex_core::error::SandboxErr;
 use codex_core::exec::ExecParams;
+use codex_core::exec::ExecToolCallOutput;
 use codex_core::exec::SandboxType;
+use codex_core::exec::StdoutStream;
 use codex_core::exec::process_exec_tool_call;
 use codex_core::exec_env::create_env;
+use codex_core::protocol::BackgroundEventEvent;
+use codex_core::protocol::EventMsg;
 use codex_core::protocol::SandboxPolicy;
+use codex_core::resource_limits::ResourceLimits;
 use std::collections::HashMap;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::Command;
 use tempfile::NamedTempFile;
 
 // At least on GitHub CI, the arm64 tests appear to need longer timeouts.
@@ -33,17 +40,7 @@ fn create_env_from_core_vars() -> HashMap<String, String> {
     create_env(&policy)
 }
 
-#[expect(clippy::print_stdout, clippy::expect_used, clippy::unwrap_used)]
 async fn run_cmd(cmd: &[&str], writable_roots: &[PathBuf], timeout_ms: u64) {
-    let params = ExecParams {
-        command: cmd.iter().map(|elm| elm.to_string()).collect(),
-        cwd: std::env::current_dir().expect("cwd should exist"),
-        timeout_ms: Some(timeout_ms),
-        env: create_env_from_core_vars(),
-        with_escalated_permissions: None,
-        justification: None,
-    };
-
     let sandbox_policy = SandboxPolicy::WorkspaceWrite {
         writable_roots: writable_roots.to_vec(),
         network_access: false,
@@ -52,13 +49,31 @@ async fn run_cmd(cmd: &[&str], writable_roots: &[PathBuf], timeout_ms: u64) {
         // writing to in the sandbox.
         exclude_tmpdir_env_var: true,
         exclude_slash_tmp: true,
+        readable_roots: vec![],
+        allowed_domains: vec![],
     };
+    run_cmd_with_policy(cmd, &sandbox_policy, timeout_ms).await;
+}
+
+#[expect(clippy::print_stdout, clippy::expect_used, clippy::unwrap_used)]
+async fn run_cmd_with_policy(cmd: &[&str], sandbox_policy: &SandboxPolicy, timeout_ms: u64) {
+    let params = ExecParams {
+        command: cmd.iter().map(|elm| elm.to_string()).collect(),
+        cwd: std::env::current_dir().expect("cwd should exist"),
+        timeout_ms: Some(timeout_ms),
+        env: create_env_from_core_vars(),
+        with_escalated_permissions: None,
+        justification: None,
+        resource_limits: Default::default(),
+        output_limits: Default::default(),
+    };
+
     let sandbox_program = env!("CARGO_BIN_EXE_codex-linux-sandbox");
     let codex_linux_sandbox_exe = Some(PathBuf::from(sandbox_program));
     let res = process_exec_tool_call(
         params,
         SandboxType::LinuxSeccomp,
-        &sandbox_policy,
+        sandbox_policy,
         &codex_linux_sandbox_exe,
         None,
     )
@@ -120,6 +135,123 @@ async fn test_writable_root() {
     .await;
 }
 
+#[tokio::test]
+async fn test_read_inside_readable_root() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let file_path = tmpdir.path().join("notes.txt");
+    std::fs::write(&file_path, "hello").unwrap();
+    run_cmd_with_policy(
+        &["cat", &file_path.to_string_lossy()],
+        &SandboxPolicy::ReadOnly {
+            readable_roots: vec![tmpdir.path().to_path_buf()],
+        },
+        LONG_TIMEOUT_MS,
+    )
+    .await;
+}
+
+#[tokio::test]
+#[should_panic(expected = "Sandbox(Denied")]
+async fn test_read_outside_readable_roots() {
+    let readable = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    let file_path = outside.path().join("notes.txt");
+    std::fs::write(&file_path, "hello").unwrap();
+    run_cmd_with_policy(
+        &["cat", &file_path.to_string_lossy()],
+        &SandboxPolicy::ReadOnly {
+            readable_roots: vec![readable.path().to_path_buf()],
+        },
+        LONG_TIMEOUT_MS,
+    )
+    .await;
+}
+
+/// Runs the helper directly so the test controls `--deny-read` instead of
+/// depending on the real home directory.
+#[expect(clippy::expect_used)]
+fn run_helper_with_deny_read(deny_read: &Path, cmd: &[&str]) -> std::process::Output {
+    let cwd = std::env::current_dir().expect("cwd should exist");
+    run_helper_with_policy(deny_read, &cwd, r#"{"mode":"read-only"}"#, cmd)
+}
+
+#[expect(clippy::expect_used)]
+fn run_helper_with_policy(
+    deny_read: &Path,
+    cwd: &Path,
+    sandbox_policy: &str,
+    cmd: &[&str],
+) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_codex-linux-sandbox"))
+        .arg("--deny-read")
+        .arg(deny_read)
+        .arg(cwd)
+        .arg(sandbox_policy)
+        .arg("--")
+        .args(cmd)
+        .current_dir(cwd)
+        .output()
+        .expect("failed to run codex-linux-sandbox")
+}
+
+#[test]
+fn test_deny_read_beneath_readable_root() {
+    let home = tempfile::tempdir().unwrap();
+    let secrets = home.path().join(".ssh");
+    std::fs::create_dir(&secrets).unwrap();
+    std::fs::write(secrets.join("id_ed25519"), "secret").unwrap();
+    std::fs::write(home.path().join("notes.txt"), "hello").unwrap();
+
+    let denied = run_helper_with_deny_read(
+        &secrets,
+        &["cat", &secrets.join("id_ed25519").to_string_lossy()],
+    );
+    assert!(!denied.status.success(), "denied path was readable");
+
+    let allowed = run_helper_with_deny_read(
+        &secrets,
+        &["cat", &home.path().join("notes.txt").to_string_lossy()],
+    );
+    assert!(allowed.status.success(), "{allowed:?}");
+    assert_eq!(b"hello", allowed.stdout.as_slice());
+
+    // The folders leading to a denied path stay listable.
+    let listed = run_helper_with_deny_read(&secrets, &["ls", &home.path().to_string_lossy()]);
+    assert!(listed.status.success(), "{listed:?}");
+}
+
+#[test]
+fn test_deny_read_beneath_writable_root() {
+    let home = tempfile::tempdir().unwrap();
+    let secrets = home.path().join(".ssh");
+    std::fs::create_dir(&secrets).unwrap();
+    std::fs::write(secrets.join("id_ed25519"), "secret").unwrap();
+    std::fs::write(home.path().join("notes.txt"), "hello").unwrap();
+    // The workspace is `home` itself, which contains the denied path.
+    let policy =
+        r#"{"mode":"workspace-write","exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+    let run = |cmd: &[&str]| run_helper_with_policy(&secrets, home.path(), policy, cmd);
+
+    let denied = run(&["cat", &secrets.join("id_ed25519").to_string_lossy()]);
+    assert!(!denied.status.success(), "denied path was readable");
+
+    // The top of the workspace stays writable.
+    let touched = run(&["touch", &home.path().join("new.txt").to_string_lossy()]);
+    assert!(touched.status.success(), "{touched:?}");
+    let made = run(&["mkdir", &home.path().join("build").to_string_lossy()]);
+    assert!(made.status.success(), "{made:?}");
+    let removed = run(&["rm", &home.path().join("notes.txt").to_string_lossy()]);
+    assert!(removed.status.success(), "{removed:?}");
+
+    // So are folders created there during the command.
+    let written = run(&[
+        "cp",
+        "/dev/null",
+        &home.path().join("build").join("out.txt").to_string_lossy(),
+    ]);
+    assert!(written.status.success(), "{written:?}");
+}
+
 #[tokio::test]
 #[should_panic(expected = "Sandbox(Timeout)")]
 async fn test_timeout() {
@@ -142,6 +274,8 @@ async fn assert_network_blocked(cmd: &[&str]) {
         env: create_env_from_core_vars(),
         with_escalated_permissions: None,
         justification: None,
+        resource_limits: Default::default(),
+        output_limits: Default::default(),
     };
 
     let sandbox_policy = SandboxPolicy::new_read_only_policy();
@@ -228,3 +362,203 @@ async fn sandbox_blocks_dev_tcp_redirection() {
     // all images ship bash, so we guard against 127 as well.
     assert_network_blocked(&["bash", "-c", "echo hi > /dev/tcp/127.0.0.1/80"]).await;
 }
+
+/// Serves `hello` over HTTP on loopback from a background thread and returns
+/// the port.
+#[expect(clippy::expect_used)]
+fn start_local_http_server() -> u16 {
+    use std::io::Read;
+    use std::io::Write;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind local server");
+    let port = listener.local_addr().expect("local server addr").port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+            );
+        }
+    });
+    port
+}
+
+/// Runs `cmd` under a policy that only allows `localhost` through the network
+/// proxy and returns the exit code, stdout and any background events.
+#[expect(clippy::expect_used)]
+async fn run_cmd_with_allowed_domains(cmd: &[&str]) -> (i32, String, Vec<String>) {
+    let params = ExecParams {
+        command: cmd.iter().map(|s| s.to_string()).collect(),
+        cwd: std::env::current_dir().expect("cwd should exist"),
+        timeout_ms: Some(NETWORK_TIMEOUT_MS),
+        env: create_env_from_core_vars(),
+        with_escalated_permissions: None,
+        justification: None,
+        resource_limits: Default::default(),
+        output_limits: Default::default(),
+    };
+    let sandbox_policy = SandboxPolicy::WorkspaceWrite {
+        writable_roots: vec![],
+        network_access: false,
+        exclude_tmpdir_env_var: true,
+        exclude_slash_tmp: true,
+        readable_roots: vec![],
+        allowed_domains: vec!["localhost".to_string()],
+    };
+    let (tx_event, rx_event) = async_channel::unbounded();
+    let stdout_stream = StdoutStream {
+        sub_id: "sub".to_string(),
+        call_id: "call".to_string(),
+        tx_event,
+    };
+    let sandbox_program = env!("CARGO_BIN_EXE_codex-linux-sandbox");
+    let result = process_exec_tool_call(
+        params,
+        SandboxType::LinuxSeccomp,
+        &sandbox_policy,
+        &Some(PathBuf::from(sandbox_program)),
+        Some(stdout_stream),
+    )
+    .await;
+
+    let (exit_code, stdout) = match result {
+        Ok(output) => (output.exit_code, output.stdout.text),
+        Err(CodexErr::Sandbox(SandboxErr::Denied(exit_code, stdout, _))) => (exit_code, stdout),
+        Err(e) => panic!("unexpected exec error: {e:?}"),
+    };
+    let mut background_events = Vec::new();
+    while let Ok(event) = rx_event.try_recv() {
+        if let EventMsg::BackgroundEvent(BackgroundEventEvent { message }) = event.msg {
+            background_events.push(message);
+        }
+    }
+    (exit_code, stdout, background_events)
+}
+
+#[tokio::test]
+async fn allowed_domain_is_reachable_through_proxy() {
+    let port = start_local_http_server();
+    let url = format!("http://localhost:{port}/");
+    let (exit_code, stdout, background_events) =
+        run_cmd_with_allowed_domains(&["curl", "-sSf", &url]).await;
+    if exit_code == 127 {
+        // curl is not installed.
+        return;
+    }
+    assert_eq!(0, exit_code);
+    assert_eq!("hello", stdout);
+    assert!(background_events.is_empty(), "{background_events:?}");
+}
+
+#[tokio::test]
+async fn domain_outside_allowlist_is_blocked_and_reported() {
+    let port = start_local_http_server();
+    let url = format!("http://127.0.0.1:{port}/");
+    let (exit_code, _, background_events) =
+        run_cmd_with_allowed_domains(&["curl", "-sSf", &url]).await;
+    if exit_code == 127 {
+        return;
+    }
+    assert_ne!(0, exit_code);
+    assert_eq!(
+        vec![format!(
+            "Sandbox blocked network access to 127.0.0.1:{port} (not in allowed_domains)"
+        )],
+        background_events
+    );
+}
+
+#[tokio::test]
+async fn connections_bypassing_the_proxy_are_blocked() {
+    let port = start_local_http_server();
+    let url = format!("http://localhost:{port}/");
+    let (exit_code, _, _) =
+        run_cmd_with_allowed_domains(&["curl", "-sSf", "--noproxy", "*", &url]).await;
+    assert_ne!(0, exit_code);
+}
+
+#[tokio::test]
+async fn ptrace_is_denied_with_allowed_domains() {
+    let sys_ptrace = if cfg!(target_arch = "aarch64") {
+        117
+    } else {
+        101
+    };
+    // PTRACE_TRACEME: exits 0 only if the call fails with EPERM.
+    let script = format!("exit(syscall({sys_ptrace}, 0, 0, 0, 0) == -1 && $!{{EPERM}} ? 0 : 1)");
+    let (exit_code, _, _) = run_cmd_with_allowed_domains(&["perl", "-e", &script]).await;
+    if exit_code == 127 {
+        // perl is not installed.
+        return;
+    }
+    assert_eq!(0, exit_code);
+}
+
+#[expect(clippy::unwrap_used)]
+async fn run_cmd_with_resource_limits(
+    cmd: &[&str],
+    resource_limits: ResourceLimits,
+    timeout_ms: u64,
+) -> ExecToolCallOutput {
+    let params = ExecParams {
+        command: cmd.iter().map(|s| s.to_string()).collect(),
+        cwd: std::env::current_dir().unwrap(),
+        timeout_ms: Some(timeout_ms),
+        env: create_env_from_core_vars(),
+        with_escalated_permissions: None,
+        justification: None,
+        resource_limits,
+        output_limits: Default::default(),
+    };
+    let sandbox_program = env!("CARGO_BIN_EXE_codex-linux-sandbox");
+    let codex_linux_sandbox_exe = Some(PathBuf::from(sandbox_program));
+    process_exec_tool_call(
+        params,
+        SandboxType::LinuxSeccomp,
+        &SandboxPolicy::new_read_only_policy(),
+        &codex_linux_sandbox_exe,
+        None,
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn resource_limits_apply_to_the_command() {
+    let output = run_cmd_with_resource_limits(
+        &["bash", "-c", "ulimit -n; ulimit -t"],
+        ResourceLimits {
+            cpu_time_seconds: Some(30),
+            max_open_files: Some(64),
+            ..Default::default()
+        },
+        LONG_TIMEOUT_MS,
+    )
+    .await;
+
+    assert_eq!(output.exit_code, 0, "stderr: {}", output.stderr.text);
+    assert_eq!(output.stdout.text, "64\n30\n");
+    assert_eq!(output.resource_limit_exceeded, None);
+}
+
+#[tokio::test]
+async fn cpu_time_limit_is_reported_to_the_model() {
+    let output = run_cmd_with_resource_limits(
+        &["bash", "-c", "while :; do :; done"],
+        ResourceLimits {
+            cpu_time_seconds: Some(1),
+            ..Default::default()
+        },
+        // Comfortably above the CPU time limit, so that the limit wins.
+        NETWORK_TIMEOUT_MS * 3,
+    )
+    .await;
+
+    let message = output.resource_limit_exceeded.unwrap_or_default();
+    assert!(
+        message.contains("resource_limits.cpu_time_seconds"),
+        "unexpected message: {message:?}"
+    );
+    assert_ne!(output.exit_code, 0);
+}
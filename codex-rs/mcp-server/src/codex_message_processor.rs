@@ -578,6 +578,7 @@ async fn apply_bespoke_event_handling(
             command,
             cwd,
             reason,
+            matched_rule: _,
         }) => {
             let params = ExecCommandApprovalParams {
                 conversation_id,
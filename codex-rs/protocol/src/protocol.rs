@@ -125,6 +125,14 @@ pub enum Op {
         decision: ReviewDecision,
     },
 
+    /// Forget a command pattern previously approved with
+    /// [`ReviewDecision::ApprovedForProject`], both for the running session
+    /// and in `config.toml`.
+    RevokeProjectApproval {
+        /// The pattern exactly as listed in `approved_commands`.
+        pattern: String,
+    },
+
     /// Append an entry to the persistent cross-session message history.
     ///
     /// Note the entry is not guaranteed to be logged if the user has
@@ -145,6 +153,13 @@ pub enum Op {
     /// The agent will use its existing context (either conversation history or previous response id)
     /// to generate a summary which will be returned as an AgentMessage event.
     Compact,
+
+    /// Restore the files changed by the most recent turn, including files it
+    /// created or deleted, to how they were before the turn started. The
+    /// outcome is reported as a `BackgroundEvent`, or as an `Error` when there
+    /// is no turn to undo or one of those files was edited after the turn.
+    UndoTurn,
+
     /// Request to shut down codex instance.
     Shutdown,
 }
@@ -186,9 +201,15 @@ pub enum SandboxPolicy {
     #[serde(rename = "danger-full-access")]
     DangerFullAccess,
 
-    /// Read-only access to the entire file-system.
+    /// Read-only access to the entire file-system, or to `readable_roots` if
+    /// any are specified.
     #[serde(rename = "read-only")]
-    ReadOnly,
+    ReadOnly {
+        /// When non-empty, reads are limited to these folders, the current
+        /// working directory and the system folders needed to run programs.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        readable_roots: Vec<PathBuf>,
+    },
 
     /// Same as `ReadOnly` but additionally grants write access to the current
     /// working directory ("workspace").
@@ -214,6 +235,17 @@ pub enum SandboxPolicy {
         /// writable roots on UNIX. Defaults to `false`.
         #[serde(default)]
         exclude_slash_tmp: bool,
+
+        /// When non-empty, reads are limited to these folders, the writable
+        /// roots and the system folders needed to run programs.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        readable_roots: Vec<PathBuf>,
+
+        /// When `network_access` is `false`, outbound HTTP(S) requests to
+        /// these domains (and their subdomains) are still allowed through a
+        /// proxy run by Codex.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        allowed_domains: Vec<String>,
     },
 }
 
@@ -259,7 +291,9 @@ impl FromStr for SandboxPolicy {
 impl SandboxPolicy {
     /// Returns a policy with read-only disk access and no network.
     pub fn new_read_only_policy() -> Self {
-        SandboxPolicy::ReadOnly
+        SandboxPolicy::ReadOnly {
+            readable_roots: vec![],
+        }
     }
 
     /// Returns a policy that can read the entire disk, but can only write to
@@ -271,18 +305,44 @@ impl SandboxPolicy {
             network_access: false,
             exclude_tmpdir_env_var: false,
             exclude_slash_tmp: false,
+            readable_roots: vec![],
+            allowed_domains: vec![],
         }
     }
 
-    /// Always returns `true`; restricting read access is not supported.
+    /// Returns `false` when reads are limited to `readable_roots`. Note that
+    /// on Linux a small set of credential files stays unreadable inside the
+    /// sandbox even when this returns `true`.
     pub fn has_full_disk_read_access(&self) -> bool {
-        true
+        match self {
+            SandboxPolicy::DangerFullAccess => true,
+            SandboxPolicy::ReadOnly { readable_roots }
+            | SandboxPolicy::WorkspaceWrite { readable_roots, .. } => readable_roots.is_empty(),
+        }
+    }
+
+    /// Returns the folders reads are limited to (tailored to the current
+    /// working directory), or `None` if the entire file-system is readable.
+    /// Writable roots are readable too and are not repeated here; platform
+    /// system folders are added by each sandbox implementation.
+    pub fn get_readable_roots_with_cwd(&self, cwd: &Path) -> Option<Vec<PathBuf>> {
+        match self {
+            SandboxPolicy::ReadOnly { readable_roots } if !readable_roots.is_empty() => {
+                let mut roots = readable_roots.clone();
+                roots.push(cwd.to_path_buf());
+                Some(roots)
+            }
+            SandboxPolicy::WorkspaceWrite { readable_roots, .. } if !readable_roots.is_empty() => {
+                Some(readable_roots.clone())
+            }
+            _ => None,
+        }
     }
 
     pub fn has_full_disk_write_access(&self) -> bool {
         match self {
             SandboxPolicy::DangerFullAccess => true,
-            SandboxPolicy::ReadOnly => false,
+            SandboxPolicy::ReadOnly { .. } => false,
             SandboxPolicy::WorkspaceWrite { .. } => false,
         }
     }
@@ -290,23 +350,38 @@ impl SandboxPolicy {
     pub fn has_full_network_access(&self) -> bool {
         match self {
             SandboxPolicy::DangerFullAccess => true,
-            SandboxPolicy::ReadOnly => false,
+            SandboxPolicy::ReadOnly { .. } => false,
             SandboxPolicy::WorkspaceWrite { network_access, .. } => *network_access,
         }
     }
 
+    /// Returns the domains reachable through the sandbox's network proxy, or
+    /// `None` if network access is either unrestricted or fully blocked.
+    pub fn get_proxied_domains(&self) -> Option<&[String]> {
+        match self {
+            SandboxPolicy::WorkspaceWrite {
+                network_access: false,
+                allowed_domains,
+                ..
+            } if !allowed_domains.is_empty() => Some(allowed_domains),
+            _ => None,
+        }
+    }
+
     /// Returns the list of writable roots (tailored to the current working
     /// directory) together with subpaths that should remain read‑only under
     /// each writable root.
     pub fn get_writable_roots_with_cwd(&self, cwd: &Path) -> Vec<WritableRoot> {
         match self {
             SandboxPolicy::DangerFullAccess => Vec::new(),
-            SandboxPolicy::ReadOnly => Vec::new(),
+            SandboxPolicy::ReadOnly { .. } => Vec::new(),
             SandboxPolicy::WorkspaceWrite {
                 writable_roots,
                 exclude_tmpdir_env_var,
                 exclude_slash_tmp,
                 network_access: _,
+                readable_roots: _,
+                allowed_domains: _,
             } => {
                 // Start from explicitly configured writable roots.
                 let mut roots: Vec<PathBuf> = writable_roots.clone();
@@ -403,7 +478,7 @@ pub enum EventMsg {
 
     /// Token count event, sent periodically to report the number of tokens
     /// used in the current session.
-    TokenCount(TokenUsage),
+    TokenCount(TokenCountEvent),
 
     /// Agent text output message
     AgentMessage(AgentMessageEvent),
@@ -446,6 +521,9 @@ pub enum EventMsg {
 
     BackgroundEvent(BackgroundEventEvent),
 
+    /// The background jobs still running, sent whenever one starts or exits.
+    BackgroundJobsUpdate(BackgroundJobsUpdateEvent),
+
     /// Notification that the agent is about to apply a code patch. Mirrors
     /// `ExecCommandBegin` so front‑ends can show progress indicators.
     PatchApplyBegin(PatchApplyBeginEvent),
@@ -481,6 +559,18 @@ pub struct TaskCompleteEvent {
     pub last_agent_message: Option<String>,
 }
 
+/// Usage of one model response, with the cost core charged to the session's
+/// budget for it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TokenCountEvent {
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+    /// Cost of the response in US dollars, priced for the model that served
+    /// it, which may be a fallback. `None` when its pricing is unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct TokenUsage {
     pub input_tokens: u64,
@@ -548,11 +638,16 @@ impl TokenUsage {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FinalOutput {
     pub token_usage: TokenUsage,
+    /// Cumulative cost in US dollars, when the model's pricing is known.
+    pub cost_usd: Option<f64>,
 }
 
 impl From<TokenUsage> for FinalOutput {
     fn from(token_usage: TokenUsage) -> Self {
-        Self { token_usage }
+        Self {
+            token_usage,
+            cost_usd: None,
+        }
     }
 }
 
@@ -574,7 +669,11 @@ impl fmt::Display for FinalOutput {
                 .reasoning_output_tokens
                 .map(|r| format!(" (reasoning {r})"))
                 .unwrap_or_default()
-        )
+        )?;
+        if let Some(cost_usd) = self.cost_usd {
+            write!(f, " cost=${cost_usd:.4}")?;
+        }
+        Ok(())
     }
 }
 
@@ -701,6 +800,9 @@ pub struct ExecApprovalRequestEvent {
     /// Optional human-readable reason for the approval (e.g. retry without sandbox).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reason: Option<String>,
+    /// Name of the exec policy rule that asked for this approval, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_rule: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -721,6 +823,17 @@ pub struct BackgroundEventEvent {
     pub message: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BackgroundJobsUpdateEvent {
+    pub running: Vec<RunningBackgroundJob>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct RunningBackgroundJob {
+    pub job_id: u32,
+    pub command: Vec<String>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PatchApplyBeginEvent {
     /// Identifier so this can be paired with the PatchApplyEnd event.
@@ -741,6 +854,33 @@ pub struct PatchApplyEndEvent {
     pub stderr: String,
     /// Whether the patch was applied successfully.
     pub success: bool,
+    /// What happened to each file the patch touches. A patch is applied to
+    /// all of its files or to none of them, so when it fails the files show
+    /// which change failed and which were rolled back.
+    #[serde(default)]
+    pub files: Vec<PatchApplyFileResult>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PatchApplyFileResult {
+    pub path: PathBuf,
+    pub status: PatchApplyFileStatus,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchApplyFileStatus {
+    /// The file was changed as the patch describes.
+    Applied,
+    /// The change to this file is what made the patch fail.
+    Failed,
+    /// The patch failed before this file was written.
+    NotApplied,
+    /// The file was written, then restored after a later file failed.
+    RolledBack,
+    /// The file was written and could not be restored, so the patch is
+    /// partially applied.
+    RestoreFailed,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -791,6 +931,11 @@ pub enum ReviewDecision {
     /// remainder of the session.
     ApprovedForSession,
 
+    /// User has approved this command and wants it approved automatically in
+    /// this project from now on, across sessions. The command is saved as a
+    /// pattern under `[projects."<cwd>"].approved_commands` in `config.toml`.
+    ApprovedForProject,
+
     /// User has denied this command and the agent should not execute it, but
     /// it should continue the session and try something else.
     #[default]
@@ -806,11 +951,26 @@ pub enum ReviewDecision {
 pub enum FileChange {
     Add {
         content: String,
+        #[serde(default)]
+        executable: bool,
     },
     Delete,
     Update {
         unified_diff: String,
         move_path: Option<PathBuf>,
+        /// Sets or clears the executable bit; `None` leaves it as it is.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        executable: Option<bool>,
+    },
+    /// Adds or replaces a file whose contents are not text.
+    Binary {
+        content_base64: String,
+        #[serde(default)]
+        executable: bool,
+    },
+    /// Adds or replaces a symlink.
+    Symlink {
+        target: PathBuf,
     },
 }
 
@@ -832,6 +992,8 @@ pub struct TurnAbortedEvent {
 pub enum TurnAbortReason {
     Interrupted,
     Replaced,
+    /// The session reached its `max_cost_usd` or `max_total_tokens` limit.
+    BudgetExceeded,
 }
 
 #[cfg(test)]
@@ -41,6 +41,13 @@ struct WorkflowContext {
     name: String,
     steps: Vec<WorkflowStepRuntime>,
     index: usize,
+    budget: Budget,
+    // Usage of all steps so far, with the cost core reported for each
+    spend: Spend,
+    // Validated output of finished steps that declare an output schema
+    outputs: Vec<(String, serde_json::Value)>,
+    // Correction prompts sent for the current step
+    corrections_sent: usize,
 }
 
 #[derive(Clone, Debug)]
@@ -48,12 +55,17 @@ struct WorkflowStepRuntime {
     kind: String, // agent|team
     id: String,
     prompt: Option<String>,
+    output_schema: Option<serde_json::Value>,
 }
 use crate::streaming::StreamKind;
 use crate::streaming::controller::AppEventHistorySink;
 use crate::streaming::controller::StreamController;
 use codex_core::ConversationManager;
+use codex_core::budget::Budget;
+use codex_core::budget::Spend;
 use codex_core::config::Config;
+use codex_core::output_schema::DEFAULT_OUTPUT_SCHEMA_RETRIES;
+use codex_core::output_schema::OutputSchema;
 use codex_core::protocol::Event;
 use codex_core::protocol::Op;
 use color_eyre::eyre::Result;
@@ -475,18 +487,22 @@ impl App<'_> {
             return;
         }
         let step = ctx.steps[ctx.index].clone();
+        // Hand the structured output of earlier steps to this one.
+        let initial_prompt = step
+            .prompt
+            .map(|p| codex_core::workflows::prompt_with_step_outputs(&p, &ctx.outputs));
         match step.kind.as_str() {
             "agent" => {
                 self.app_event_tx.send(AppEvent::SwitchToAgent {
                     name: step.id,
-                    initial_prompt: step.prompt,
+                    initial_prompt,
                 });
             }
             "team" => {
                 // Switch to team; initial prompt sent to first member; team context will be set.
                 self.app_event_tx.send(AppEvent::SwitchToAgent {
                     name: step.id,
-                    initial_prompt: step.prompt,
+                    initial_prompt,
                 });
             }
             _ => {
@@ -499,9 +515,78 @@ impl App<'_> {
         }
     }
 
+    /// Caps a workflow step at what is left of the workflow budget and applies
+    /// the step's output schema.
+    fn apply_workflow_step(&mut self, cfg: &mut Config) {
+        if let Some(ctx) = &mut self.workflow_context {
+            cfg.budget = cfg.budget.min(ctx.budget.remaining(&ctx.spend));
+            cfg.output_schema = ctx
+                .steps
+                .get(ctx.index)
+                .and_then(|step| step.output_schema.clone());
+        }
+    }
+
+    /// Checks the final message of the current workflow step against its
+    /// output schema. A conforming message is kept for later steps; otherwise
+    /// the agent is asked for a correction, and the workflow stops once the
+    /// retries are used up.
+    fn complete_workflow_step(&mut self, last_agent_message: Option<&str>) {
+        let Some(ctx) = &mut self.workflow_context else {
+            return;
+        };
+        let Some(step) = ctx.steps.get(ctx.index).cloned() else {
+            return;
+        };
+        let Some(schema) = step.output_schema else {
+            self.advance_workflow();
+            return;
+        };
+        let errors = match OutputSchema::new(schema) {
+            Ok(schema) => match schema.validate(last_agent_message) {
+                Ok(value) => {
+                    ctx.outputs.push((step.id, value));
+                    self.advance_workflow();
+                    return;
+                }
+                Err(errors) if ctx.corrections_sent < DEFAULT_OUTPUT_SCHEMA_RETRIES => {
+                    ctx.corrections_sent += 1;
+                    let msg = format!(
+                        "Step '{}' output does not match its output schema; asking for a correction ({}/{DEFAULT_OUTPUT_SCHEMA_RETRIES})",
+                        step.id, ctx.corrections_sent
+                    );
+                    self.pending_history_lines
+                        .extend(new_info_block(vec![msg]).display_lines());
+                    if let AppState::Chat { widget } = &self.app_state {
+                        widget.submit_op(Op::UserInput {
+                            items: vec![InputItem::Text {
+                                text: schema.correction_prompt(&errors),
+                            }],
+                        });
+                    }
+                    self.app_event_tx.send(AppEvent::RequestRedraw);
+                    return;
+                }
+                Err(errors) => errors,
+            },
+            Err(e) => vec![e.to_string()],
+        };
+        let name = ctx.name.clone();
+        self.workflow_context = None;
+        let mut lines = vec![format!(
+            "Workflow '{name}' stopped: step '{}' output does not match its output schema",
+            step.id
+        )];
+        lines.extend(errors.into_iter().map(|e| format!("- {e}")));
+        self.pending_history_lines
+            .extend(new_info_block(lines).display_lines());
+        self.app_event_tx.send(AppEvent::RequestRedraw);
+    }
+
     fn advance_workflow(&mut self) {
         if let Some(ctx) = &mut self.workflow_context {
             ctx.index += 1;
+            ctx.corrections_sent = 0;
             if ctx.index < ctx.steps.len() {
                 self.start_current_workflow_step();
             } else {
@@ -646,14 +731,35 @@ impl App<'_> {
                             // Do not trigger chaining on AgentMessage; wait for TaskComplete so the
                             // first agent fully finishes before selecting the next.
                         }
-                        codex_core::protocol::EventMsg::TaskComplete(_ev) => {
+                        codex_core::protocol::EventMsg::TokenCount(token_count) => {
+                            if let Some(ctx) = &mut self.workflow_context {
+                                ctx.spend.add(&token_count.usage, token_count.cost_usd);
+                            }
+                        }
+                        codex_core::protocol::EventMsg::TurnAborted(
+                            codex_core::protocol::TurnAbortedEvent {
+                                reason: codex_core::protocol::TurnAbortReason::BudgetExceeded,
+                            },
+                        ) => {
+                            if let Some(ctx) = self.workflow_context.take() {
+                                self.pending_history_lines.extend(
+                                    new_info_block(vec![format!(
+                                        "Workflow '{}' stopped: budget exceeded",
+                                        ctx.name
+                                    )])
+                                    .display_lines(),
+                                );
+                                self.app_event_tx.send(AppEvent::RequestRedraw);
+                            }
+                        }
+                        codex_core::protocol::EventMsg::TaskComplete(ev) => {
                             // First, forward to UI so ChatWidget marks the task complete
                             // (this hides any previous status and flushes the turn).
                             self.dispatch_codex_event(event.clone());
 
                             // Advance workflow step if active.
                             if self.workflow_context.is_some() {
-                                self.advance_workflow();
+                                self.complete_workflow_step(ev.last_agent_message.as_deref());
                             }
 
                             // Now trigger chaining; this will show the status view and
@@ -704,12 +810,17 @@ impl App<'_> {
                                                 },
                                                 id: s.id,
                                                 prompt: s.prompt,
+                                                output_schema: s.output_schema,
                                             })
                                             .collect();
                                         self.workflow_context = Some(WorkflowContext {
                                             name: wf.name,
                                             steps,
                                             index: 0,
+                                            budget: wf.budget,
+                                            spend: Spend::default(),
+                                            outputs: Vec::new(),
+                                            corrections_sent: 0,
                                         });
                                         self.start_current_workflow_step();
                                     }
@@ -1014,6 +1125,9 @@ impl App<'_> {
                                                 new_cfg.user_instructions = Some(p);
                                             }
                                             new_cfg.mcp_servers = agent_def.mcp_servers.clone();
+                                            new_cfg.budget =
+                                                new_cfg.budget.min(agent_def.config.budget());
+                                            self.apply_workflow_step(&mut new_cfg);
                                             let new_widget = Box::new(ChatWidget::new(
                                                 new_cfg,
                                                 self.server.clone(),
@@ -1090,6 +1204,8 @@ impl App<'_> {
                                         new_cfg.user_instructions = Some(prompt.clone());
                                     }
                                     new_cfg.mcp_servers = agent_def.mcp_servers.clone();
+                                    new_cfg.budget = new_cfg.budget.min(agent_def.config.budget());
+                                    self.apply_workflow_step(&mut new_cfg);
 
                                     // Spawn a fresh ChatWidget (new session) with optional initial prompt
                                     let new_widget = Box::new(ChatWidget::new(
@@ -1499,6 +1615,9 @@ max_turns = 1
                             self.app_event_tx.send(AppEvent::CodexOp(Op::Compact));
                         }
                     }
+                    SlashCommand::Undo => {
+                        self.app_event_tx.send(AppEvent::CodexOp(Op::UndoTurn));
+                    }
                     SlashCommand::Model => {
                         // Model picker not available in this build
                         let lines = new_info_block(vec![
@@ -1509,13 +1628,28 @@ max_turns = 1
                         self.app_event_tx.send(AppEvent::RequestRedraw);
                     }
                     SlashCommand::Approvals => {
-                        // Approvals picker not available in this build
-                        let lines = new_info_block(vec![
-                            "Approvals picker is not available in this build.".to_string(),
-                        ])
-                        .display_lines();
-                        self.app_event_tx.send(AppEvent::InsertHistory(lines));
-                        self.app_event_tx.send(AppEvent::RequestRedraw);
+                        if let AppState::Chat { widget } = &mut self.app_state {
+                            let message = match codex_core::config::load_project_approved_commands(
+                                &self.config.codex_home,
+                                &self.config.cwd,
+                            ) {
+                                Ok(patterns) if !patterns.is_empty() => {
+                                    widget.show_project_approvals(patterns);
+                                    None
+                                }
+                                Ok(_) => Some(format!(
+                                    "No commands are approved for {}. Choose \"Project\" when approving a command to add one.",
+                                    self.config.cwd.display()
+                                )),
+                                Err(e) => Some(format!("Failed to read project approvals: {e}")),
+                            };
+                            if let Some(message) = message {
+                                self.app_event_tx.send(AppEvent::InsertHistory(
+                                    new_info_block(vec![message]).display_lines(),
+                                ));
+                            }
+                            self.app_event_tx.send(AppEvent::RequestRedraw);
+                        }
                     }
                     SlashCommand::Quit => {
                         break;
@@ -1674,6 +1808,7 @@ max_turns = 1
                                             PathBuf::from("/tmp/test.txt"),
                                             FileChange::Add {
                                                 content: "test".to_string(),
+                                                executable: false,
                                             },
                                         ),
                                         (
@@ -1681,6 +1816,7 @@ max_turns = 1
                                             FileChange::Update {
                                                 unified_diff: "+test\n-test2".to_string(),
                                                 move_path: None,
+                                                executable: None,
                                             },
                                         ),
                                     ]),
@@ -1754,6 +1890,17 @@ max_turns = 1
         }
     }
 
+    /// Token usage and cost of the current session, printed on exit.
+    pub(crate) fn final_output(&self) -> codex_core::protocol::FinalOutput {
+        codex_core::protocol::FinalOutput {
+            token_usage: self.token_usage(),
+            cost_usd: match &self.app_state {
+                AppState::Chat { widget } => widget.total_cost_usd(),
+                AppState::Onboarding { .. } => None,
+            },
+        }
+    }
+
     fn draw_next_frame(&mut self, terminal: &mut tui::Tui) -> Result<()> {
         if matches!(self.app_state, AppState::Onboarding { .. }) {
             terminal.clear()?;
@@ -82,6 +82,7 @@ mod tests {
             id: "test".to_string(),
             command: vec!["echo".to_string(), "hi".to_string()],
             reason: None,
+            matched_rule: None,
         }
     }
 
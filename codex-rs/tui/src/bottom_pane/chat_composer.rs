@@ -68,6 +68,8 @@ pub(crate) struct ChatComposer {
     current_file_query: Option<String>,
     pending_pastes: Vec<(String, String)>,
     token_usage_info: Option<TokenUsageInfo>,
+    /// Commands of the background jobs still running, shown in the footer.
+    running_background_jobs: Vec<String>,
     has_focus: bool,
     placeholder_text: String,
 }
@@ -100,6 +102,7 @@ impl ChatComposer {
             current_file_query: None,
             pending_pastes: Vec::new(),
             token_usage_info: None,
+            running_background_jobs: Vec::new(),
             has_focus: has_input_focus,
             placeholder_text,
         }
@@ -157,6 +160,10 @@ impl ChatComposer {
         });
     }
 
+    pub(crate) fn set_running_background_jobs(&mut self, commands: Vec<String>) {
+        self.running_background_jobs = commands;
+    }
+
     /// Record the history metadata advertised by `SessionConfiguredEvent` so
     /// that the composer can navigate cross-session history.
     pub(crate) fn set_history_metadata(&mut self, log_id: u64, entry_count: usize) {
@@ -705,6 +712,19 @@ impl WidgetRef for &ChatComposer {
                     }
                 }
 
+                if !self.running_background_jobs.is_empty() {
+                    let count = self.running_background_jobs.len();
+                    let noun = if count == 1 { "job" } else { "jobs" };
+                    hint.push(Span::from("   "));
+                    hint.push(
+                        Span::from(format!(
+                            "{count} background {noun} running: {}",
+                            self.running_background_jobs.join(", ")
+                        ))
+                        .style(Style::default().fg(Color::Yellow)),
+                    );
+                }
+
                 Line::from(hint)
                     .style(Style::default().dim())
                     .render_ref(bottom_line_rect, buf);
@@ -268,6 +268,12 @@ impl BottomPane<'_> {
         self.request_redraw();
     }
 
+    /// Update the background jobs listed in the composer footer.
+    pub(crate) fn set_running_background_jobs(&mut self, commands: Vec<String>) {
+        self.composer.set_running_background_jobs(commands);
+        self.request_redraw();
+    }
+
     /// Called when the agent requests user approval.
     pub fn push_approval_request(&mut self, request: ApprovalRequest) {
         let request = if let Some(view) = self.active_view.as_mut() {
@@ -365,6 +371,7 @@ mod tests {
             id: "1".to_string(),
             command: vec!["echo".into(), "ok".into()],
             reason: None,
+            matched_rule: None,
         }
     }
 
@@ -12,6 +12,7 @@ use codex_core::protocol::AgentReasoningRawContentDeltaEvent;
 use codex_core::protocol::AgentReasoningRawContentEvent;
 use codex_core::protocol::ApplyPatchApprovalRequestEvent;
 use codex_core::protocol::BackgroundEventEvent;
+use codex_core::protocol::BackgroundJobsUpdateEvent;
 use codex_core::protocol::ErrorEvent;
 use codex_core::protocol::Event;
 use codex_core::protocol::EventMsg;
@@ -24,7 +25,10 @@ use codex_core::protocol::McpToolCallEndEvent;
 use codex_core::protocol::Op;
 use codex_core::protocol::PatchApplyBeginEvent;
 use codex_core::protocol::TaskCompleteEvent;
+use codex_core::protocol::TokenCountEvent;
 use codex_core::protocol::TokenUsage;
+use codex_core::protocol::TurnAbortReason;
+use codex_core::protocol::TurnAbortedEvent;
 use codex_core::protocol::TurnDiffEvent;
 use codex_protocol::parse_command::ParsedCommand;
 use crossterm::event::KeyEvent;
@@ -45,6 +49,8 @@ use crate::bottom_pane::BottomPane;
 use crate::bottom_pane::BottomPaneParams;
 use crate::bottom_pane::CancellationEvent;
 use crate::bottom_pane::InputResult;
+use crate::bottom_pane::SelectionItem;
+use crate::exec_command::strip_bash_lc_and_escape;
 use crate::history_cell;
 use crate::history_cell::CommandOutput;
 use crate::history_cell::ExecCell;
@@ -77,6 +83,8 @@ pub(crate) struct ChatWidget<'a> {
     initial_user_message: Option<UserMessage>,
     total_token_usage: TokenUsage,
     last_token_usage: TokenUsage,
+    // Cumulative cost, known only when the model has a configured price
+    total_cost_usd: Option<f64>,
     // Stream lifecycle controller
     stream: StreamController,
     // Track the most recently active stream kind in the current turn
@@ -208,8 +216,15 @@ impl ChatWidget<'_> {
         self.mark_needs_redraw();
     }
 
-    fn on_token_count(&mut self, token_usage: TokenUsage) {
+    fn on_token_count(&mut self, token_count: TokenCountEvent) {
+        let TokenCountEvent {
+            usage: token_usage,
+            cost_usd,
+        } = token_count;
         self.total_token_usage = add_token_usage(&self.total_token_usage, &token_usage);
+        if let Some(cost_usd) = cost_usd {
+            *self.total_cost_usd.get_or_insert(0.0) += cost_usd;
+        }
         self.last_token_usage = token_usage;
         self.bottom_pane.set_token_usage(
             self.total_token_usage.clone(),
@@ -317,6 +332,15 @@ impl ChatWidget<'_> {
     fn on_background_event(&mut self, message: String) {
         debug!("BackgroundEvent: {message}");
     }
+
+    fn on_background_jobs_update(&mut self, update: BackgroundJobsUpdateEvent) {
+        let commands = update
+            .running
+            .iter()
+            .map(|job| format!("#{} {}", job.job_id, strip_bash_lc_and_escape(&job.command)))
+            .collect();
+        self.bottom_pane.set_running_background_jobs(commands);
+    }
     /// Periodic tick to commit at most one queued line to history with a small delay,
     /// animating the output.
     pub(crate) fn on_commit_tick(&mut self) {
@@ -415,6 +439,7 @@ impl ChatWidget<'_> {
             id,
             command: ev.command,
             reason: ev.reason,
+            matched_rule: ev.matched_rule,
         };
         self.bottom_pane.push_approval_request(request);
         self.mark_needs_redraw();
@@ -535,6 +560,7 @@ impl ChatWidget<'_> {
             ),
             total_token_usage: TokenUsage::default(),
             last_token_usage: TokenUsage::default(),
+            total_cost_usd: None,
             stream: StreamController::new(config),
             last_stream_kind: None,
             running_commands: HashMap::new(),
@@ -681,8 +707,11 @@ impl ChatWidget<'_> {
             EventMsg::AgentReasoningSectionBreak(_) => self.on_reasoning_section_break(),
             EventMsg::TaskStarted => self.on_task_started(),
             EventMsg::TaskComplete(TaskCompleteEvent { .. }) => self.on_task_complete(),
-            EventMsg::TokenCount(token_usage) => self.on_token_count(token_usage),
+            EventMsg::TokenCount(token_count) => self.on_token_count(token_count),
             EventMsg::Error(ErrorEvent { message }) => self.on_error(message),
+            EventMsg::TurnAborted(TurnAbortedEvent {
+                reason: TurnAbortReason::BudgetExceeded,
+            }) => self.on_error("Turn aborted: budget exceeded".to_owned()),
             EventMsg::TurnAborted(_) => self.on_error("Turn interrupted".to_owned()),
             EventMsg::PlanUpdate(update) => self.on_plan_update(update),
             EventMsg::ExecApprovalRequest(ev) => self.on_exec_approval_request(id, ev),
@@ -700,6 +729,7 @@ impl ChatWidget<'_> {
             EventMsg::BackgroundEvent(BackgroundEventEvent { message }) => {
                 self.on_background_event(message)
             }
+            EventMsg::BackgroundJobsUpdate(update) => self.on_background_jobs_update(update),
             EventMsg::McpListToolsResponse(_) => {
                 // No-op in this build; /status path shows tools
             }
@@ -732,10 +762,37 @@ impl ChatWidget<'_> {
         self.add_to_history(&history_cell::new_status_output(
             &self.config,
             &self.total_token_usage,
+            self.total_cost_usd,
             &self.session_id,
         ));
     }
 
+    /// List the commands approved for this project; selecting one revokes it.
+    pub(crate) fn show_project_approvals(&mut self, patterns: Vec<String>) {
+        let items = patterns
+            .into_iter()
+            .map(|pattern| {
+                let revoke = pattern.clone();
+                SelectionItem {
+                    name: pattern,
+                    description: None,
+                    is_current: false,
+                    actions: vec![Box::new(move |tx: &AppEventSender| {
+                        tx.send(AppEvent::CodexOp(Op::RevokeProjectApproval {
+                            pattern: revoke.clone(),
+                        }));
+                    })],
+                }
+            })
+            .collect();
+        self.bottom_pane.show_selection_view(
+            "Project approvals".to_string(),
+            Some("Commands Codex runs without asking in this project".to_string()),
+            Some("Press Enter to revoke the selected approval, Esc to close".to_string()),
+            items,
+        );
+    }
+
     /// Forward file-search results to the bottom pane.
     pub(crate) fn apply_file_search_result(&mut self, query: String, matches: Vec<FileMatch>) {
         self.bottom_pane.on_file_search_result(query, matches);
@@ -781,8 +838,13 @@ impl ChatWidget<'_> {
         &self.total_token_usage
     }
 
+    pub(crate) fn total_cost_usd(&self) -> Option<f64> {
+        self.total_cost_usd
+    }
+
     pub(crate) fn clear_token_usage(&mut self) {
         self.total_token_usage = TokenUsage::default();
+        self.total_cost_usd = None;
         self.bottom_pane.set_token_usage(
             self.total_token_usage.clone(),
             self.last_token_usage.clone(),
@@ -136,6 +136,7 @@ fn make_chatwidget_manual() -> (
         initial_user_message: None,
         total_token_usage: TokenUsage::default(),
         last_token_usage: TokenUsage::default(),
+        total_cost_usd: None,
         stream: StreamController::new(cfg),
         last_stream_kind: None,
         running_commands: HashMap::new(),
@@ -432,6 +433,7 @@ fn apply_patch_events_emit_history_cells() {
         PathBuf::from("foo.txt"),
         FileChange::Add {
             content: "hello\n".to_string(),
+            executable: false,
         },
     );
     let ev = ApplyPatchApprovalRequestEvent {
@@ -458,6 +460,7 @@ fn apply_patch_events_emit_history_cells() {
         PathBuf::from("foo.txt"),
         FileChange::Add {
             content: "hello\n".to_string(),
+            executable: false,
         },
     );
     let begin = PatchApplyBeginEvent {
@@ -483,6 +486,7 @@ fn apply_patch_events_emit_history_cells() {
         stdout: "ok\n".into(),
         stderr: String::new(),
         success: true,
+        files: Vec::new(),
     };
     chat.handle_codex_event(Event {
         id: "s1".into(),
@@ -506,6 +510,7 @@ fn apply_patch_approval_sends_op_with_submission_id() {
         PathBuf::from("file.rs"),
         FileChange::Add {
             content: "fn main(){}\n".into(),
+            executable: false,
         },
     );
     let ev = ApplyPatchApprovalRequestEvent {
@@ -546,7 +551,10 @@ fn apply_patch_full_flow_integration_like() {
     let mut changes = HashMap::new();
     changes.insert(
         PathBuf::from("pkg.rs"),
-        FileChange::Add { content: "".into() },
+        FileChange::Add {
+            content: "".into(),
+            executable: false,
+        },
     );
     chat.handle_codex_event(Event {
         id: "sub-xyz".into(),
@@ -589,7 +597,10 @@ fn apply_patch_full_flow_integration_like() {
     let mut changes2 = HashMap::new();
     changes2.insert(
         PathBuf::from("pkg.rs"),
-        FileChange::Add { content: "".into() },
+        FileChange::Add {
+            content: "".into(),
+            executable: false,
+        },
     );
     chat.handle_codex_event(Event {
         id: "sub-xyz".into(),
@@ -606,6 +617,7 @@ fn apply_patch_full_flow_integration_like() {
             stdout: String::from("ok"),
             stderr: String::new(),
             success: true,
+            files: Vec::new(),
         }),
     });
 }
@@ -620,7 +632,10 @@ fn apply_patch_untrusted_shows_approval_modal() {
     let mut changes = HashMap::new();
     changes.insert(
         PathBuf::from("a.rs"),
-        FileChange::Add { content: "".into() },
+        FileChange::Add {
+            content: "".into(),
+            executable: false,
+        },
     );
     chat.handle_codex_event(Event {
         id: "sub-1".into(),
@@ -64,7 +64,7 @@ pub(crate) fn create_diff_summary(
     let mut files: Vec<FileSummary> = Vec::new();
     for (path, change) in changes.iter() {
         match change {
-            FileChange::Add { content } => files.push(FileSummary {
+            FileChange::Add { content, .. } => files.push(FileSummary {
                 display_path: path.display().to_string(),
                 added: content.lines().count(),
                 removed: 0,
@@ -77,9 +77,20 @@ pub(crate) fn create_diff_summary(
                     .map(|s| s.lines().count())
                     .unwrap_or(0),
             }),
+            FileChange::Binary { .. } => files.push(FileSummary {
+                display_path: path.display().to_string(),
+                added: 0,
+                removed: 0,
+            }),
+            FileChange::Symlink { target } => files.push(FileSummary {
+                display_path: format!("{} → {}", path.display(), target.display()),
+                added: 0,
+                removed: 0,
+            }),
             FileChange::Update {
                 unified_diff,
                 move_path,
+                ..
             } => {
                 let (added, removed) = count_from_unified(unified_diff);
                 let display_path = if let Some(new_path) = move_path {
@@ -184,7 +195,7 @@ fn render_patch_details(changes: &HashMap<PathBuf, FileChange>) -> Vec<RtLine<'s
             ]));
         }
         match change {
-            FileChange::Add { content } => {
+            FileChange::Add { content, .. } => {
                 for (i, raw) in content.lines().enumerate() {
                     let ln = i + 1;
                     out.extend(push_wrapped_diff_line(
@@ -207,10 +218,14 @@ fn render_patch_details(changes: &HashMap<PathBuf, FileChange>) -> Vec<RtLine<'s
                     ));
                 }
             }
-            FileChange::Update {
-                unified_diff,
-                move_path: _,
-            } => {
+            FileChange::Binary { .. } => {
+                out.push(RtLine::from(vec![
+                    RtSpan::raw("    "),
+                    RtSpan::styled("binary contents", style_dim()),
+                ]));
+            }
+            FileChange::Symlink { .. } => {}
+            FileChange::Update { unified_diff, .. } => {
                 if let Ok(patch) = diffy::Patch::from_str(unified_diff) {
                     for h in patch.hunks() {
                         let mut old_ln = h.old_range().start();
@@ -387,6 +402,7 @@ mod tests {
             PathBuf::from("README.md"),
             FileChange::Add {
                 content: "first line\nsecond line\n".to_string(),
+                executable: false,
             },
         );
 
@@ -409,6 +425,7 @@ mod tests {
             FileChange::Update {
                 unified_diff: patch,
                 move_path: Some(PathBuf::from("src/lib_new.rs")),
+                executable: None,
             },
         );
 
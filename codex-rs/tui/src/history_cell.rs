@@ -509,6 +509,7 @@ pub(crate) fn new_diff_output(message: String) -> PlainHistoryCell {
 pub(crate) fn new_status_output(
     config: &Config,
     usage: &TokenUsage,
+    cost_usd: Option<f64>,
     session_id: &Option<Uuid>,
 ) -> PlainHistoryCell {
     let mut lines: Vec<Line<'static>> = Vec::new();
@@ -540,7 +541,7 @@ pub(crate) fn new_status_output(
     // Sandbox (simplified name only)
     let sandbox_name = match &config.sandbox_policy {
         SandboxPolicy::DangerFullAccess => "danger-full-access",
-        SandboxPolicy::ReadOnly => "read-only",
+        SandboxPolicy::ReadOnly { .. } => "read-only",
         SandboxPolicy::WorkspaceWrite { .. } => "workspace-write",
     };
     lines.push(Line::from(vec![
@@ -639,6 +640,27 @@ pub(crate) fn new_status_output(
         "  • Total: ".into(),
         usage.blended_total().to_string().into(),
     ]));
+    // Cost: $<cost>
+    if let Some(cost_usd) = cost_usd {
+        lines.push(Line::from(vec![
+            "  • Cost: ".into(),
+            format!("${cost_usd:.4}").into(),
+        ]));
+    }
+    // Budget: [$<max>] [<max> tokens]
+    let budget = &config.budget;
+    if !budget.is_unlimited() {
+        let limits: Vec<String> = budget
+            .max_cost_usd
+            .map(|max| format!("${max:.2}"))
+            .into_iter()
+            .chain(budget.max_total_tokens.map(|max| format!("{max} tokens")))
+            .collect();
+        lines.push(Line::from(vec![
+            "  • Budget: ".into(),
+            limits.join(", ").into(),
+        ]));
+    }
 
     lines.push(Line::from(""));
     PlainHistoryCell { lines }
@@ -71,7 +71,7 @@ pub use cli::Cli;
 pub async fn run_main(
     cli: Cli,
     codex_linux_sandbox_exe: Option<PathBuf>,
-) -> std::io::Result<codex_core::protocol::TokenUsage> {
+) -> std::io::Result<codex_core::protocol::FinalOutput> {
     let (sandbox_mode, approval_policy) = if cli.full_auto {
         (
             Some(SandboxMode::WorkspaceWrite),
@@ -298,7 +298,7 @@ fn run_ratatui_app(
     cli: Cli,
     config: Config,
     should_show_trust_screen: bool,
-) -> color_eyre::Result<codex_core::protocol::TokenUsage> {
+) -> color_eyre::Result<codex_core::protocol::FinalOutput> {
     color_eyre::install()?;
 
     // Forward panic reports through tracing so they appear in the UI status
@@ -320,7 +320,7 @@ fn run_ratatui_app(
     let mut app = App::new(config.clone(), prompt, images, should_show_trust_screen);
 
     let app_result = app.run(&mut terminal);
-    let usage = app.token_usage();
+    let usage = app.final_output();
 
     restore();
     // Mark the end of the recorded session.
@@ -380,6 +380,8 @@ fn apply_agent_target(config: &mut Config, agent: &AgentDefinition) {
     }
     // Per-agent MCP servers (already merged if inherit flag set).
     config.mcp_servers = agent.mcp_servers.clone();
+    // Agent spend caps can only tighten the session budget.
+    config.budget = config.budget.min(agent.config.budget());
 }
 
 #[expect(
@@ -21,9 +21,9 @@ fn main() -> anyhow::Result<()> {
             .config_overrides
             .raw_overrides
             .splice(0..0, top_cli.config_overrides.raw_overrides);
-        let usage = run_main(inner, codex_linux_sandbox_exe).await?;
-        if !usage.is_zero() {
-            println!("{}", codex_core::protocol::FinalOutput::from(usage));
+        let output = run_main(inner, codex_linux_sandbox_exe).await?;
+        if !output.token_usage.is_zero() {
+            println!("{output}");
         }
         Ok(())
     })
@@ -36,6 +36,8 @@ pub(crate) enum ApprovalRequest {
         id: String,
         command: Vec<String>,
         reason: Option<String>,
+        /// Exec policy rule that asked for approval.
+        matched_rule: Option<String>,
     },
     ApplyPatch {
         id: String,
@@ -68,6 +70,12 @@ static COMMAND_SELECT_OPTIONS: LazyLock<Vec<SelectOption>> = LazyLock::new(|| {
             key: KeyCode::Char('a'),
             decision: ReviewDecision::ApprovedForSession,
         },
+        SelectOption {
+            label: Line::from(vec!["P".underlined(), "roject".into()]),
+            description: "Always approve the command in this project, across sessions",
+            key: KeyCode::Char('p'),
+            decision: ReviewDecision::ApprovedForProject,
+        },
         SelectOption {
             label: Line::from(vec!["N".underlined(), "o".into()]),
             description: "Do not run the command",
@@ -141,7 +149,10 @@ impl UserApprovalWidget<'_> {
     pub(crate) fn new(approval_request: ApprovalRequest, app_event_tx: AppEventSender) -> Self {
         let confirmation_prompt = match &approval_request {
             ApprovalRequest::Exec {
-                command, reason, ..
+                command,
+                reason,
+                matched_rule,
+                ..
             } => {
                 let cmd = strip_bash_lc_and_escape(command);
                 let mut contents: Vec<Line> = to_command_display(
@@ -155,6 +166,13 @@ impl UserApprovalWidget<'_> {
                     contents.push(Line::from(reason.clone().italic()));
                     contents.push(Line::from(""));
                 }
+                if let Some(rule) = matched_rule {
+                    contents.push(Line::from(vec![
+                        "Matched exec policy rule ".dim(),
+                        rule.clone().bold(),
+                    ]));
+                    contents.push(Line::from(""));
+                }
                 Paragraph::new(contents).wrap(Wrap { trim: false })
             }
             ApprovalRequest::ApplyPatch {
@@ -291,6 +309,18 @@ impl UserApprovalWidget<'_> {
                             vec![" every time this session".bold()],
                         ));
                     }
+                    ReviewDecision::ApprovedForProject => {
+                        lines.extend(to_command_display(
+                            vec![
+                                "✔ ".fg(Color::Green),
+                                "You ".into(),
+                                "approved".bold(),
+                                " codex to run ".into(),
+                            ],
+                            cmd,
+                            vec![" every time in this project".bold()],
+                        ));
+                    }
                     ReviewDecision::Denied => {
                         lines.extend(to_command_display(
                             vec![